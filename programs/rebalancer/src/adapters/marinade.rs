@@ -0,0 +1,23 @@
+use anchor_lang::prelude::*;
+use crate::adapters::{AdapterContext, ProtocolAdapter};
+use crate::errors::RebalancerErrorCode;
+use crate::state::ProtocolType;
+
+/// Marinade stake pools (`LiquidStaking` strategies).
+pub struct MarinadeAdapter;
+
+impl ProtocolAdapter for MarinadeAdapter {
+    fn name(&self) -> &'static str {
+        "marinade"
+    }
+
+    fn cpi_accounts(&self, ctx: &AdapterContext) -> Result<Vec<AccountMeta>> {
+        match ctx.protocol_type {
+            ProtocolType::LiquidStaking { validator_id, stake_pool, .. } => Ok(vec![
+                AccountMeta::new(*stake_pool, false),            // Pool state
+                AccountMeta::new_readonly(*validator_id, false), // Validator the stake is delegated to
+            ]),
+            _ => err!(RebalancerErrorCode::AdapterAccountMismatch),
+        }
+    }
+}
@@ -0,0 +1,154 @@
+pub mod solend;
+pub mod orca;
+pub mod marinade;
+pub mod perpetual;
+
+pub use solend::*;
+pub use orca::*;
+pub use marinade::*;
+pub use perpetual::*;
+
+use anchor_lang::prelude::*;
+use crate::errors::RebalancerErrorCode;
+use crate::state::ProtocolType;
+
+/// The strategy an adapter call moves capital for.
+pub struct AdapterContext<'a> {
+    pub strategy_id: Pubkey,
+    pub protocol_type: &'a ProtocolType,
+}
+
+/// Moves capital in and out of one protocol on behalf of a strategy.
+///
+/// Implementations only describe the protocol's CPI accounts. `deposit` and
+/// `withdraw` currently validate those accounts and log the transfer without
+/// invoking the protocol, reporting the full amount as moved so callers can
+/// already check it against slippage bounds.
+pub trait ProtocolAdapter {
+    fn name(&self) -> &'static str;
+
+    /// Accounts the protocol's deposit/withdraw CPI takes, built from the
+    /// strategy's protocol parameters. Fails if the strategy is not a
+    /// strategy of this adapter's protocol.
+    fn cpi_accounts(&self, ctx: &AdapterContext) -> Result<Vec<AccountMeta>>;
+
+    /// Returns the amount the protocol accepted.
+    fn deposit(&self, ctx: &AdapterContext, amount: u64) -> Result<u64> {
+        stub_transfer(self, ctx, "deposit", amount)
+    }
+
+    /// Returns the amount the protocol released.
+    fn withdraw(&self, ctx: &AdapterContext, amount: u64) -> Result<u64> {
+        stub_transfer(self, ctx, "withdraw", amount)
+    }
+}
+
+/// Adapter for a strategy's protocol.
+pub fn adapter_for(protocol_type: &ProtocolType) -> &'static dyn ProtocolAdapter {
+    match protocol_type {
+        ProtocolType::StableLending { .. } => &SolendAdapter,
+        ProtocolType::YieldFarming { .. } => &OrcaAdapter,
+        ProtocolType::LiquidStaking { .. } => &MarinadeAdapter,
+        ProtocolType::PerpetualFunding { .. } => &PerpetualAdapter,
+    }
+}
+
+fn stub_transfer<A: ProtocolAdapter + ?Sized>(
+    adapter: &A,
+    ctx: &AdapterContext,
+    action: &str,
+    amount: u64,
+) -> Result<u64> {
+    let accounts = adapter.cpi_accounts(ctx)?;
+    require!(
+        accounts.iter().all(|meta| meta.pubkey != Pubkey::default()),
+        RebalancerErrorCode::AdapterAccountMismatch
+    );
+
+    msg!("{} {}: {} lamports for strategy {} ({} CPI accounts)",
+         adapter.name(), action, amount, ctx.strategy_id, accounts.len());
+
+    Ok(amount)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lending() -> ProtocolType {
+        ProtocolType::StableLending {
+            pool_id: Pubkey::new_unique(),
+            reserve_address: Pubkey::new_unique(),
+            utilization: 7500,
+        }
+    }
+
+    fn farming() -> ProtocolType {
+        ProtocolType::YieldFarming {
+            pair_id: Pubkey::new_unique(),
+            token_a_mint: Pubkey::new_unique(),
+            token_b_mint: Pubkey::new_unique(),
+            fee_tier: 30,
+            reward_multiplier: 2,
+        }
+    }
+
+    fn staking() -> ProtocolType {
+        ProtocolType::LiquidStaking {
+            validator_id: Pubkey::new_unique(),
+            stake_pool: Pubkey::new_unique(),
+            unstake_delay: 2,
+            commission: 500,
+        }
+    }
+
+    fn perpetual() -> ProtocolType {
+        ProtocolType::PerpetualFunding {
+            market_id: Pubkey::new_unique(),
+            funding_rate_bps: 25,
+            max_leverage: 3,
+        }
+    }
+
+    fn context(protocol_type: &ProtocolType) -> AdapterContext<'_> {
+        AdapterContext { strategy_id: Pubkey::new_unique(), protocol_type }
+    }
+
+    #[test]
+    fn test_dispatch_selects_adapter_per_protocol() {
+        assert_eq!(adapter_for(&lending()).name(), "solend");
+        assert_eq!(adapter_for(&farming()).name(), "orca");
+        assert_eq!(adapter_for(&staking()).name(), "marinade");
+        assert_eq!(adapter_for(&perpetual()).name(), "perpetual");
+    }
+
+    #[test]
+    fn test_dispatched_adapter_builds_its_protocol_accounts() {
+        for protocol_type in [lending(), farming(), staking(), perpetual()] {
+            let ctx = context(&protocol_type);
+            let accounts = adapter_for(&protocol_type).cpi_accounts(&ctx).unwrap();
+
+            assert!(accounts.iter().any(|meta| meta.pubkey == protocol_type.correlation_key()));
+            assert_eq!(adapter_for(&protocol_type).deposit(&ctx, 1_000_000_000).unwrap(), 1_000_000_000);
+            assert_eq!(adapter_for(&protocol_type).withdraw(&ctx, 500_000_000).unwrap(), 500_000_000);
+        }
+    }
+
+    #[test]
+    fn test_adapter_rejects_other_protocols() {
+        let protocol_type = farming();
+        let err = SolendAdapter.deposit(&context(&protocol_type), 1_000_000_000).unwrap_err();
+        assert_eq!(err, RebalancerErrorCode::AdapterAccountMismatch.into());
+    }
+
+    #[test]
+    fn test_adapter_rejects_unset_accounts() {
+        let protocol_type = ProtocolType::StableLending {
+            pool_id: Pubkey::new_unique(),
+            reserve_address: Pubkey::default(),
+            utilization: 7500,
+        };
+        let err = adapter_for(&protocol_type).deposit(&context(&protocol_type), 1_000_000_000).unwrap_err();
+        assert_eq!(err, RebalancerErrorCode::AdapterAccountMismatch.into());
+    }
+}
@@ -0,0 +1,24 @@
+use anchor_lang::prelude::*;
+use crate::adapters::{AdapterContext, ProtocolAdapter};
+use crate::errors::RebalancerErrorCode;
+use crate::state::ProtocolType;
+
+/// Orca liquidity pairs (`YieldFarming` strategies).
+pub struct OrcaAdapter;
+
+impl ProtocolAdapter for OrcaAdapter {
+    fn name(&self) -> &'static str {
+        "orca"
+    }
+
+    fn cpi_accounts(&self, ctx: &AdapterContext) -> Result<Vec<AccountMeta>> {
+        match ctx.protocol_type {
+            ProtocolType::YieldFarming { pair_id, token_a_mint, token_b_mint, .. } => Ok(vec![
+                AccountMeta::new(*pair_id, false),               // Pool receiving the liquidity
+                AccountMeta::new_readonly(*token_a_mint, false),
+                AccountMeta::new_readonly(*token_b_mint, false),
+            ]),
+            _ => err!(RebalancerErrorCode::AdapterAccountMismatch),
+        }
+    }
+}
@@ -0,0 +1,22 @@
+use anchor_lang::prelude::*;
+use crate::adapters::{AdapterContext, ProtocolAdapter};
+use crate::errors::RebalancerErrorCode;
+use crate::state::ProtocolType;
+
+/// Perpetual markets (`PerpetualFunding` strategies).
+pub struct PerpetualAdapter;
+
+impl ProtocolAdapter for PerpetualAdapter {
+    fn name(&self) -> &'static str {
+        "perpetual"
+    }
+
+    fn cpi_accounts(&self, ctx: &AdapterContext) -> Result<Vec<AccountMeta>> {
+        match ctx.protocol_type {
+            ProtocolType::PerpetualFunding { market_id, .. } => Ok(vec![
+                AccountMeta::new(*market_id, false), // Market holding the margin
+            ]),
+            _ => err!(RebalancerErrorCode::AdapterAccountMismatch),
+        }
+    }
+}
@@ -0,0 +1,23 @@
+use anchor_lang::prelude::*;
+use crate::adapters::{AdapterContext, ProtocolAdapter};
+use crate::errors::RebalancerErrorCode;
+use crate::state::ProtocolType;
+
+/// Solend lending reserves (`StableLending` strategies).
+pub struct SolendAdapter;
+
+impl ProtocolAdapter for SolendAdapter {
+    fn name(&self) -> &'static str {
+        "solend"
+    }
+
+    fn cpi_accounts(&self, ctx: &AdapterContext) -> Result<Vec<AccountMeta>> {
+        match ctx.protocol_type {
+            ProtocolType::StableLending { pool_id, reserve_address, .. } => Ok(vec![
+                AccountMeta::new(*reserve_address, false),  // Reserve holding the liquidity
+                AccountMeta::new_readonly(*pool_id, false), // Lending market
+            ]),
+            _ => err!(RebalancerErrorCode::AdapterAccountMismatch),
+        }
+    }
+}
@@ -0,0 +1,156 @@
+/// Integer square root: the largest `r` such that `r * r <= n`
+/// 
+/// Deterministic replacement for `f64::sqrt` in on-chain math (diversification
+/// curves, risk weighting), where floating point results must not vary.
+/// 
+/// # Algorithm
+/// Newton's method starting from a power of two that is guaranteed to be at
+/// least `sqrt(n)`, so the iterates decrease monotonically and the first
+/// non-decreasing step marks the floor of the root.
+/// 
+/// # Mathematical Safety
+/// - The initial guess is at most 2^64, so `x + n / x` never exceeds 2^65
+/// - Division is always by a non-zero `x` (n < 2 returns early)
+pub fn isqrt(n: u128) -> u128 {
+    if n < 2 {
+        return n;
+    }
+    
+    let bits = 128 - n.leading_zeros();
+    let mut x = 1u128 << bits.div_ceil(2);
+    
+    loop {
+        let y = (x + n / x) >> 1;
+        if y >= x {
+            return x;
+        }
+        x = y;
+    }
+}
+
+// Fixed-point scales for impermanent loss math
+pub const PRICE_SCALE: u128 = 1_000_000;             // Prices carry 6 decimals
+const IL_PRECISION: u128 = 1_000_000_000_000;        // 1e12 fixed point for ratios
+
+/// Impermanent loss of a constant-product LP position, in price-scaled value units
+/// 
+/// Compares the value of the LP position against simply holding the deposited
+/// tokens at current prices. Always `<= 0`: an LP never outperforms holding
+/// before fees.
+/// 
+/// # Algorithm
+/// With `r = (current_a / current_b) / (entry_a / entry_b)`, the LP position is
+/// worth `2 * sqrt(r) / (1 + r)` of the held tokens. For a 2x move this is
+/// ~0.9428, i.e. ~5.72% impermanent loss.
+/// 
+/// # Mathematical Safety
+/// - Any zero price returns 0 instead of dividing by zero
+/// - All intermediate math is checked u128; an overflow also returns 0
+pub fn compute_impermanent_loss(
+    entry_price_a: u64,
+    entry_price_b: u64,
+    current_price_a: u64,
+    current_price_b: u64,
+    token_a_amount: u64,
+    token_b_amount: u64,
+) -> i64 {
+    if entry_price_a == 0 || entry_price_b == 0 || current_price_a == 0 || current_price_b == 0 {
+        return 0;
+    }
+    
+    let loss = (|| {
+        let ratio = (current_price_a as u128 * entry_price_b as u128)
+            .checked_mul(IL_PRECISION)?
+            / (entry_price_a as u128 * current_price_b as u128);
+        let sqrt_ratio = isqrt(ratio.checked_mul(IL_PRECISION)?);
+        let lp_factor = sqrt_ratio
+            .checked_mul(2 * IL_PRECISION)?
+            / IL_PRECISION.checked_add(ratio)?;
+        
+        let hold_value = (token_a_amount as u128 * current_price_a as u128)
+            .checked_add(token_b_amount as u128 * current_price_b as u128)?
+            / PRICE_SCALE;
+        
+        hold_value
+            .checked_mul(IL_PRECISION.saturating_sub(lp_factor))?
+            .checked_div(IL_PRECISION)
+    })();
+    
+    loss.and_then(|loss| i64::try_from(loss).ok())
+        .map(|loss| -loss)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn is_floor_sqrt(n: u128, r: u128) -> bool {
+        r.checked_mul(r).is_some_and(|sq| sq <= n)
+            && (r + 1).checked_mul(r + 1).is_none_or(|sq| sq > n)
+    }
+    
+    #[test]
+    fn test_isqrt_exhaustive_small_values() {
+        for n in 0u128..=100_000 {
+            let r = isqrt(n);
+            assert!(is_floor_sqrt(n, r), "isqrt({}) returned {}", n, r);
+        }
+    }
+    
+    #[test]
+    fn test_isqrt_perfect_squares_and_neighbours() {
+        for root in [1u128, 2, 3, 10, 1_000, 65_535, 1 << 32, 3_037_000_499, (1 << 63) + 12_345] {
+            let square = root * root;
+            assert_eq!(isqrt(square), root);
+            assert_eq!(isqrt(square - 1), root - 1);
+            assert_eq!(isqrt(square + 1), root);
+        }
+    }
+    
+    #[test]
+    fn test_isqrt_large_values() {
+        assert_eq!(isqrt(u64::MAX as u128), 4_294_967_295);
+        assert_eq!(isqrt(u128::MAX), u64::MAX as u128);
+        assert_eq!(isqrt(1u128 << 127), 13_043_817_825_332_782_212);
+        assert_eq!(isqrt(10_000_000_000_000_000_000_000_000_000), 100_000_000_000_000);
+        
+        for n in [u128::MAX - 1, (u64::MAX as u128) * (u64::MAX as u128), 123_456_789_012_345_678_901_234_567_890] {
+            assert!(is_floor_sqrt(n, isqrt(n)));
+        }
+    }
+    
+    #[test]
+    fn test_impermanent_loss_known_values() {
+        // 2x price move: ~5.72% of the 3000 SOL-equivalent held value
+        assert_eq!(
+            compute_impermanent_loss(1_000_000, 1_000_000, 2_000_000, 1_000_000, 1_000_000_000, 1_000_000_000),
+            -171_572_875
+        );
+        // Halving is the same ratio move in the other direction
+        assert_eq!(
+            compute_impermanent_loss(1_000_000, 1_000_000, 500_000, 1_000_000, 1_000_000_000, 1_000_000_000),
+            -85_786_437
+        );
+        // 5x price move: ~25.46%
+        assert_eq!(
+            compute_impermanent_loss(1_000_000, 1_000_000, 5_000_000, 1_000_000, 1_000_000_000, 1_000_000_000),
+            -1_527_864_045
+        );
+    }
+    
+    #[test]
+    fn test_impermanent_loss_zero_when_ratio_unchanged() {
+        assert_eq!(compute_impermanent_loss(1_000_000, 2_000_000, 1_000_000, 2_000_000, 500, 250), 0);
+        // Both tokens doubling leaves the ratio (and so the loss) unchanged
+        assert_eq!(compute_impermanent_loss(1_000_000, 2_000_000, 2_000_000, 4_000_000, 500, 250), 0);
+    }
+    
+    #[test]
+    fn test_impermanent_loss_zero_prices() {
+        assert_eq!(compute_impermanent_loss(0, 1_000_000, 2_000_000, 1_000_000, 1_000, 1_000), 0);
+        assert_eq!(compute_impermanent_loss(1_000_000, 0, 2_000_000, 1_000_000, 1_000, 1_000), 0);
+        assert_eq!(compute_impermanent_loss(1_000_000, 1_000_000, 0, 1_000_000, 1_000, 1_000), 0);
+        assert_eq!(compute_impermanent_loss(1_000_000, 1_000_000, 2_000_000, 0, 1_000, 1_000), 0);
+    }
+}
@@ -74,6 +74,7 @@ pub enum RebalancerErrorCode {
     #[msg("Insufficient strategies for rebalancing (minimum 2 required)")]
     InsufficientStrategies,
     
+    // Limit is redistribute_capital::MAX_STRATEGIES_PER_OP
     #[msg("Too many strategies for single operation (max 10)")]
     TooManyStrategies,
 
@@ -85,4 +86,175 @@ pub enum RebalancerErrorCode {
 
     #[msg("Invalid performance score for calculation")]
     InvalidPerformanceScore,
-}
\ No newline at end of file
+
+    #[msg("Guardian may only pause; unpausing requires the manager")]
+    GuardianCannotUnpause,
+
+    #[msg("Position type does not match the strategy's protocol")]
+    InvalidPositionType,
+
+    #[msg("Entry price must be greater than zero")]
+    InvalidEntryPrice,
+
+    #[msg("Invalid risk limits: bps values must be within 0-10000 and max >= min")]
+    InvalidRiskLimits,
+
+    #[msg("Fee treasury must be a non-default address")]
+    InvalidTreasury,
+
+    #[msg("Illegal strategy status transition")]
+    InvalidStatusTransition,
+
+    #[msg("Emergency rebalance requires an active strategy above the crisis volatility threshold")]
+    NoCrisisConditions,
+
+    #[msg("Price must be greater than zero")]
+    InvalidPrice,
+
+    #[msg("Strategy must be deprecated before it can be closed")]
+    StrategyNotDeprecated,
+
+    #[msg("Strategy still holds capital")]
+    StrategyStillFunded,
+
+    #[msg("Strategy has an open capital position")]
+    OpenPositionExists,
+
+    #[msg("Portfolio still has registered strategies; close them first")]
+    PortfolioHasStrategies,
+
+    #[msg("Only the pending manager can accept a manager transfer")]
+    UnauthorizedPendingManager,
+
+    #[msg("Number of strategy accounts does not match the number of updates")]
+    BatchLengthMismatch,
+
+    #[msg("Yield rate exceeds the plausible ceiling for this protocol type")]
+    UnreasonableYieldForProtocol,
+
+    #[msg("Strategy metrics are older than the portfolio's maximum staleness; update performance first")]
+    StaleStrategyData,
+
+    #[msg("Maximum metric staleness must not be negative")]
+    InvalidMetricStaleness,
+
+    #[msg("Invalid perpetual market ID")]
+    InvalidMarketId,
+
+    #[msg("Funding rate magnitude exceeds 10000 basis points")]
+    InvalidFundingRate,
+
+    #[msg("Invalid leverage (must be 1-20x)")]
+    InvalidLeverage,
+
+    #[msg("Expected improvement does not cover rebalancing fees at the configured net benefit ratio")]
+    RebalanceNotWorthwhile,
+
+    #[msg("Destination received less than the allocation's minimum acceptable amount")]
+    SlippageExceeded,
+
+    #[msg("Top performers all share one protocol type while protocol diversity is required")]
+    InsufficientDiversity,
+
+    #[msg("Strategy balance would exceed its deposit capacity")]
+    StrategyAtCapacity,
+
+    #[msg("Strategy was already submitted in this ranking cycle")]
+    RankingBatchAlreadySubmitted,
+
+    #[msg("Ranking cycle has not been started or not every strategy has been submitted")]
+    RankingCycleIncomplete,
+
+    #[msg("Strategy metrics changed after its ranking batch was submitted")]
+    RankingInputsChanged,
+
+    #[msg("Protocol accounts do not match the strategy's adapter")]
+    AdapterAccountMismatch,
+
+    #[msg("No gains above the high-water mark to charge a performance fee on")]
+    NoPerformanceGains,
+
+    #[msg("Volatility smoothing must be between 1 and 10000 basis points")]
+    InvalidVolatilitySmoothing,
+
+    #[msg("Allocations add up to more than the capital available")]
+    AllocationExceedsCapital,
+
+    #[msg("Every registered strategy account must be passed in remaining_accounts")]
+    MissingStrategyAccounts,
+
+    #[msg("Platform and manager fees together exceed the 10% cap")]
+    ExcessiveFees,
+
+    #[msg("Capital position account is not funded to rent exemption")]
+    PositionNotRentExempt,
+
+    #[msg("Governance needs 1 to 8 distinct managers, a threshold within the list and a valid approval window")]
+    InvalidGovernanceConfig,
+
+    #[msg("Signer is not a governance manager")]
+    NotGovernanceMember,
+
+    #[msg("Action has not been approved by enough governance managers")]
+    GovernanceApprovalRequired,
+
+    #[msg("Vault balance did not increase by exactly the registered initial balance")]
+    DepositMismatch,
+
+    #[msg("Vault deposits are only supported for native SOL strategies")]
+    VaultRequiresNativeSol,
+
+    #[msg("A strategy can only be transferred between two different portfolios")]
+    InvalidStrategyTransfer,
+
+    #[msg("Strategy has vault-held funds; withdraw them through the vault before transferring")]
+    StrategyHasVaultBalance,
+
+    #[msg("Allocations differ from the ones this execution was started with")]
+    ExecutionMismatch,
+
+    #[msg("No pending allocation of this execution can be applied")]
+    NoPendingAllocations,
+
+    #[msg("No strategy is below the rebalancing threshold; nothing to rebalance")]
+    NoUnderperformers,
+
+    #[msg("Strategy is paused or deprecated and cannot receive capital")]
+    StrategyNotActive,
+
+    #[msg("Account holds non-zero reserved bytes from a legacy layout")]
+    ReservedBytesNotZeroed,
+
+    #[msg("Allocation list does not fit an execution account (max 16 allocations)")]
+    ExecutionTooLarge,
+
+    #[msg("Strategy count can only be lowered by reconciliation")]
+    InvalidStrategyCount,
+
+    #[msg("Strategy account is still open")]
+    StrategyAccountNotClosed,
+
+    #[msg("Strategy balances do not add up to the portfolio's total value locked")]
+    TotalValueLockedMismatch,
+
+    #[msg("Withdrawal reaches vault-escrowed capital; pass the strategy vault")]
+    VaultAccountRequired,
+
+    #[msg("Account is not in a legacy layout; nothing to migrate")]
+    AccountAlreadyMigrated,
+
+    #[msg("Account was written in an unsupported layout version; migrate it first")]
+    UnsupportedLayoutVersion,
+
+    #[msg("Ranking cycle is already finalized; apply its ranks with apply_ranking_batch")]
+    RankingCycleFinalized,
+
+    #[msg("Strategy's rank from this ranking cycle was already applied")]
+    RankingAlreadyApplied,
+
+    #[msg("Delta page size must be between 1 and MAX_DELTAS_PER_PAGE")]
+    InvalidDeltaPage,
+
+    #[msg("Strategy has an allocation log; pass the destination allocation log to move it")]
+    AllocationLogRequired,
+}
@@ -0,0 +1,144 @@
+use anchor_lang::prelude::*;
+use crate::state::ProtocolType;
+
+#[event]
+pub struct PortfolioInitialized {
+    pub portfolio: Pubkey,
+    pub manager: Pubkey,
+    pub base_threshold: u8,
+    pub min_rebalance_interval: i64,
+    pub max_strategies: u32,             // 0 = uncapped
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct StrategyRegistered {
+    pub portfolio: Pubkey,
+    pub strategy_id: Pubkey,
+    pub protocol_type: ProtocolType,
+    pub mint: Pubkey,
+    pub initial_balance: u64,
+    pub verified_balance: u64,
+    pub total_strategies: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PerformanceUpdated {
+    pub portfolio: Pubkey,
+    pub strategy_id: Pubkey,
+    pub yield_rate: u64,
+    pub volatility_score: u32,
+    pub volatility_ema: u32,
+    pub current_balance: u64,
+    pub performance_score: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RankingCycleCompleted {
+    pub portfolio: Pubkey,
+    pub strategies_ranked: u32,
+    pub underperformers: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CapitalRedistributed {
+    pub portfolio: Pubkey,
+    pub manager: Pubkey,
+    pub amount_redistributed: u64,
+    pub allocation_count: u32,
+    pub total_capital_moved: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CapitalWithdrawn {
+    pub portfolio: Pubkey,
+    pub strategy_id: Pubkey,
+    pub amount: u64,
+    pub remaining_balance: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PortfolioCapacitySummary {
+    pub portfolio: Pubkey,
+    pub total_strategies: u32,
+    pub max_strategies: u32,             // 0 = uncapped
+    pub total_capital: u64,
+    pub max_capital: u64,                // 0 = uncapped
+    pub capacity_utilization_bps: u16,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BaseThresholdUpdated {
+    pub portfolio: Pubkey,
+    pub previous_threshold: u8,
+    pub base_threshold: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PerformanceFeeRecorded {
+    pub portfolio: Pubkey,
+    pub manager_treasury: Pubkey,
+    pub fee: u64,
+    pub high_water_mark: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct StrategyCountReconciled {
+    pub portfolio: Pubkey,
+    pub previous_count: u32,
+    pub total_strategies: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct GovernanceActionApproved {
+    pub portfolio: Pubkey,
+    pub approver: Pubkey,
+    pub action_hash: [u8; 32],
+    pub approvals: u8,
+    pub threshold: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct StrategyTransferred {
+    pub strategy_id: Pubkey,
+    pub source_portfolio: Pubkey,
+    pub destination_portfolio: Pubkey,
+    pub balance: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct StrategyPauseChanged {
+    pub portfolio: Pubkey,
+    pub strategy_id: Pubkey,
+    pub paused: bool,
+    pub authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PortfolioMigrated {
+    pub portfolio: Pubkey,
+    pub manager: Pubkey,
+    pub layout_version: u8,
+    pub total_value_locked: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct StrategyMigrated {
+    pub portfolio: Pubkey,
+    pub strategy_id: Pubkey,
+    pub layout_version: u8,
+    pub timestamp: i64,
+}
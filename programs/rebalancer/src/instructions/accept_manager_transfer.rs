@@ -0,0 +1,26 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct AcceptManagerTransfer<'info> {
+    // The PDA address is pinned to seed_manager, so it is unchanged by the transfer
+    #[account(
+        mut,
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+    
+    pub new_manager: Signer<'info>,
+}
+
+pub fn accept_manager_transfer(ctx: Context<AcceptManagerTransfer>) -> Result<()> {
+    let portfolio = &mut ctx.accounts.portfolio;
+    let previous_manager = portfolio.manager;
+    
+    portfolio.accept_manager(ctx.accounts.new_manager.key())?;
+    
+    msg!("Manager transfer accepted: {} -> {}", previous_manager, portfolio.manager);
+    
+    Ok(())
+}
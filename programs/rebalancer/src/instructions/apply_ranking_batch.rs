@@ -0,0 +1,52 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::*;
+use crate::utils::{load_portfolio_strategies, persist_strategies};
+
+#[derive(Accounts)]
+pub struct ApplyRankingBatch<'info> {
+    #[account(
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+        has_one = manager @ RebalancerErrorCode::UnauthorizedManager
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+    
+    #[account(
+        mut,
+        seeds = [b"ranking_session", portfolio.key().as_ref()],
+        bump = ranking_session.bump,
+    )]
+    pub ranking_session: Account<'info, RankingSession>,
+    
+    #[account(mut)]
+    pub manager: Signer<'info>,
+}
+
+/// Write a finalized cycle's ranks back to one batch of strategies (writable,
+/// in `remaining_accounts`). A strategy whose metrics changed since it was
+/// submitted is rejected. The session is closed to the manager once every
+/// submitted strategy has been handled.
+pub fn apply_ranking_batch<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ApplyRankingBatch<'info>>,
+) -> Result<()> {
+    let session = &mut ctx.accounts.ranking_session;
+    
+    let mut strategies = load_portfolio_strategies(&ctx.accounts.portfolio.key(), ctx.remaining_accounts)?;
+    require!(!strategies.is_empty(), RebalancerErrorCode::InsufficientStrategies);
+    
+    for strategy in strategies.iter_mut() {
+        session.apply_rank(strategy)?;
+    }
+    persist_strategies(&strategies)?;
+    
+    let applied = session.entries.iter().filter(|e| e.applied).count();
+    msg!("Ranking batch applied: {} strategies, {}/{} written back",
+         strategies.len(), applied, session.entries.len());
+    
+    if session.is_fully_applied() {
+        session.close(ctx.accounts.manager.to_account_info())?;
+    }
+    
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::*;
+use crate::events::GovernanceActionApproved;
+
+#[derive(Accounts)]
+pub struct ApproveGovernanceAction<'info> {
+    #[account(
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+    
+    #[account(
+        mut,
+        seeds = [b"governance", portfolio.key().as_ref()],
+        bump = governance_config.bump,
+    )]
+    pub governance_config: Account<'info, GovernanceConfig>,
+    
+    /// One of the governance managers
+    pub approver: Signer<'info>,
+}
+
+/// Add the signer's approval to `action`. Once the threshold is reached, the
+/// matching instruction can be sent with the same arguments.
+pub fn approve_governance_action(
+    ctx: Context<ApproveGovernanceAction>,
+    action: GovernanceAction,
+) -> Result<()> {
+    require!(ctx.accounts.portfolio.is_governance_enabled()?, RebalancerErrorCode::InvalidGovernanceConfig);
+    
+    let governance = &mut ctx.accounts.governance_config;
+    let approver = ctx.accounts.approver.key();
+    let current_time = Clock::get()?.unix_timestamp;
+    let action_hash = action.hash();
+    
+    let approvals = governance.approve(&approver, action_hash, current_time)?;
+    
+    msg!("Governance approval {}/{} from {}", approvals, governance.threshold, approver);
+    
+    emit!(GovernanceActionApproved {
+        portfolio: ctx.accounts.portfolio.key(),
+        approver,
+        action_hash,
+        approvals,
+        threshold: governance.threshold,
+        timestamp: current_time,
+    });
+    
+    Ok(())
+}
@@ -0,0 +1,113 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::*;
+use crate::events::PerformanceUpdated;
+use crate::instructions::redistribute_capital::MAX_STRATEGIES_PER_OP;
+use crate::instructions::update_performance::apply_performance_update;
+use crate::utils::{load_portfolio_strategies, persist_strategies};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct PerformanceUpdate {
+    pub strategy_id: Pubkey,
+    pub yield_rate: u64,
+    pub volatility_score: u32,
+    pub current_balance: u64,
+}
+
+#[derive(Accounts)]
+pub struct BatchUpdatePerformance<'info> {
+    #[account(
+        mut,
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+        constraint = portfolio.can_update_performance(&authority.key()) @ RebalancerErrorCode::UnauthorizedManager
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+    
+    /// Either the portfolio manager or its oracle authority
+    pub authority: Signer<'info>,
+}
+
+/// Apply several `update_performance` calls in one transaction.
+///
+/// The strategy accounts are passed (writable) in `remaining_accounts`, in the
+/// same order as `updates`. Every entry goes through the same validations as
+/// the single-strategy instruction; any failure rejects the whole batch.
+pub fn batch_update_performance<'info>(
+    ctx: Context<'_, '_, 'info, 'info, BatchUpdatePerformance<'info>>,
+    updates: Vec<PerformanceUpdate>,
+) -> Result<()> {
+    let portfolio_key = ctx.accounts.portfolio.key();
+    let current_time = Clock::get()?.unix_timestamp;
+    let smoothing_bps = ctx.accounts.portfolio.volatility_smoothing_bps;
+    
+    // BATCH SHAPE VALIDATION
+    validate_batch_shape(updates.len(), ctx.remaining_accounts.len())?;
+    
+    let mut strategies = load_portfolio_strategies(&portfolio_key, ctx.remaining_accounts)?;
+    
+    for (strategy, update) in strategies.iter_mut().zip(updates.iter()) {
+        require!(strategy.strategy_id == update.strategy_id, RebalancerErrorCode::StrategyNotFound);
+        
+        let previous_balance = strategy.current_balance;
+        apply_performance_update(
+            strategy,
+            update.yield_rate,
+            update.volatility_score,
+            update.current_balance,
+            current_time,
+            smoothing_bps,
+        )?;
+        ctx.accounts.portfolio.apply_balance_change(&strategy.mint, previous_balance, update.current_balance)?;
+        
+        emit!(PerformanceUpdated {
+            portfolio: portfolio_key,
+            strategy_id: strategy.strategy_id,
+            yield_rate: update.yield_rate,
+            volatility_score: update.volatility_score,
+            volatility_ema: strategy.volatility_ema,
+            current_balance: update.current_balance,
+            performance_score: strategy.performance_score,
+            timestamp: current_time,
+        });
+    }
+    persist_strategies(&strategies)?;
+    
+    msg!("Batch performance update applied to {} strategies", updates.len());
+    
+    Ok(())
+}
+
+pub fn validate_batch_shape(update_count: usize, account_count: usize) -> Result<()> {
+    require!(update_count > 0, RebalancerErrorCode::InsufficientStrategies);
+    require!(update_count <= MAX_STRATEGIES_PER_OP, RebalancerErrorCode::TooManyStrategies);
+    require!(update_count == account_count, RebalancerErrorCode::BatchLengthMismatch);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_batch_shape_accepts_matching_lengths() {
+        assert!(validate_batch_shape(1, 1).is_ok());
+        assert!(validate_batch_shape(MAX_STRATEGIES_PER_OP, MAX_STRATEGIES_PER_OP).is_ok());
+    }
+    
+    #[test]
+    fn test_batch_shape_rejects_mismatch_and_oversize() {
+        assert_eq!(
+            validate_batch_shape(3, 2).unwrap_err(),
+            RebalancerErrorCode::BatchLengthMismatch.into()
+        );
+        assert_eq!(
+            validate_batch_shape(MAX_STRATEGIES_PER_OP + 1, MAX_STRATEGIES_PER_OP + 1).unwrap_err(),
+            RebalancerErrorCode::TooManyStrategies.into()
+        );
+        assert_eq!(
+            validate_batch_shape(0, 0).unwrap_err(),
+            RebalancerErrorCode::InsufficientStrategies.into()
+        );
+    }
+}
@@ -0,0 +1,65 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::*;
+
+#[derive(Accounts)]
+pub struct BeginRankingCycle<'info> {
+    #[account(
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+        has_one = manager @ RebalancerErrorCode::UnauthorizedManager
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+    
+    // Every batch of the cycle is ranked under these limits
+    #[account(
+        seeds = [b"risk_config", portfolio.key().as_ref()],
+        bump = risk_config.bump,
+    )]
+    pub risk_config: Account<'info, RiskConfig>,
+    
+    // init_if_needed so an abandoned session is simply restarted
+    #[account(
+        init_if_needed,
+        payer = manager,
+        space = RankingSession::MAX_SIZE,
+        seeds = [b"ranking_session", portfolio.key().as_ref()],
+        bump
+    )]
+    pub ranking_session: Account<'info, RankingSession>,
+    
+    #[account(mut)]
+    pub manager: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+/// Start a ranking cycle that is submitted in batches.
+///
+/// Snapshots the portfolio's strategy count and risk limits;
+/// `finalize_ranking_cycle` only succeeds once that many strategies have been
+/// submitted under unchanged limits.
+pub fn begin_ranking_cycle(ctx: Context<BeginRankingCycle>) -> Result<()> {
+    let portfolio = &ctx.accounts.portfolio;
+    let current_time = Clock::get()?.unix_timestamp;
+    
+    // REBALANCING ELIGIBILITY CHECKS (same as execute_ranking_cycle)
+    require!(!portfolio.emergency_pause, RebalancerErrorCode::EmergencyPaused);
+    require!(
+        portfolio.can_rebalance(current_time),
+        RebalancerErrorCode::InvalidRebalanceInterval
+    );
+    require!(portfolio.total_strategies >= 2, RebalancerErrorCode::InsufficientStrategies);
+    require!(
+        portfolio.total_strategies as usize <= MAX_RANKING_SESSION_STRATEGIES,
+        RebalancerErrorCode::TooManyStrategies
+    );
+    
+    let session = &mut ctx.accounts.ranking_session;
+    session.reset(portfolio.key(), portfolio.total_strategies, current_time, &ctx.accounts.risk_config.limits)?;
+    session.bump = ctx.bumps.ranking_session;
+    
+    msg!("Chunked ranking cycle started for {} strategies", portfolio.total_strategies);
+    
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::*;
+
+#[derive(Accounts)]
+pub struct ClosePortfolio<'info> {
+    #[account(
+        mut,
+        close = manager,
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+        has_one = manager @ RebalancerErrorCode::UnauthorizedManager
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+    
+    #[account(mut)]
+    pub manager: Signer<'info>,
+}
+
+pub fn close_portfolio(ctx: Context<ClosePortfolio>) -> Result<()> {
+    let portfolio = &ctx.accounts.portfolio;
+    
+    // WIND-DOWN PRECONDITIONS
+    validate_portfolio_closable(portfolio)?;
+    
+    msg!("Portfolio closed: manager={}, lifetime capital moved={}",
+         portfolio.manager, portfolio.total_capital_moved);
+    
+    Ok(())
+}
+
+// A PORTFOLIO CAN ONLY BE CLOSED ONCE EVERY STRATEGY IS CLOSED AND IT IS NOT PAUSED
+pub fn validate_portfolio_closable(portfolio: &Portfolio) -> Result<()> {
+    require!(portfolio.total_strategies == 0, RebalancerErrorCode::PortfolioHasStrategies);
+    require!(!portfolio.emergency_pause, RebalancerErrorCode::EmergencyPaused);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn portfolio(total_strategies: u32, emergency_pause: bool) -> Portfolio {
+        Portfolio {
+            manager: Pubkey::new_unique(),
+            total_capital_moved: 0,
+            last_rebalance: 0,
+            min_rebalance_interval: 3600,
+            portfolio_creation: 0,
+            total_strategies,
+            performance_fee_bps: 200,
+            base_threshold: 15,
+            emergency_pause,
+            bump: 255,
+            max_strategies: 0,
+            max_capital: 0,
+            guardian: Pubkey::default(),
+            emergency_rebalance_count: 0,
+            seed_manager: Pubkey::default(),
+            pending_manager: Pubkey::default(),
+            max_metric_staleness: DEFAULT_MAX_METRIC_STALENESS,
+            rebalance_sequence: 0,
+            high_water_mark: 0,
+            total_value_locked: 0,
+            volatility_smoothing_bps: DEFAULT_VOLATILITY_SMOOTHING_BPS,
+            oracle_authority: Pubkey::default(),
+            governance_enabled: false,
+            layout_version: PORTFOLIO_LAYOUT_VERSION,
+            reserved: [0u8; 3],
+        }
+    }
+    
+    #[test]
+    fn test_empty_portfolio_is_closable() {
+        assert!(validate_portfolio_closable(&portfolio(0, false)).is_ok());
+    }
+    
+    #[test]
+    fn test_portfolio_with_strategies_rejected() {
+        assert_eq!(
+            validate_portfolio_closable(&portfolio(2, false)).unwrap_err(),
+            RebalancerErrorCode::PortfolioHasStrategies.into()
+        );
+    }
+    
+    #[test]
+    fn test_paused_portfolio_rejected() {
+        assert_eq!(
+            validate_portfolio_closable(&portfolio(0, true)).unwrap_err(),
+            RebalancerErrorCode::EmergencyPaused.into()
+        );
+    }
+}
@@ -0,0 +1,38 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::*;
+
+#[derive(Accounts)]
+pub struct CloseRedistributionExecution<'info> {
+    #[account(
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+        has_one = manager @ RebalancerErrorCode::UnauthorizedManager
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+    
+    #[account(
+        mut,
+        close = manager,
+        seeds = [b"execution", portfolio.key().as_ref(), &execution.execution_id.to_le_bytes()],
+        bump = execution.bump,
+    )]
+    pub execution: Account<'info, RedistributionExecution>,
+    
+    #[account(mut)]
+    pub manager: Signer<'info>,
+}
+
+/// Close a redistribution execution and return its rent to the manager.
+///
+/// Normally called once every allocation of the plan has been applied. An
+/// unfinished execution may be closed too, which abandons its progress: the
+/// remaining allocations can still be applied by calls without an execution.
+pub fn close_redistribution_execution(ctx: Context<CloseRedistributionExecution>) -> Result<()> {
+    let execution = &ctx.accounts.execution;
+    
+    msg!("Redistribution execution closed: id={}, complete={}, applied={}/{}",
+         execution.execution_id, execution.is_complete(), execution.executed.count_ones(), execution.allocation_count);
+    
+    Ok(())
+}
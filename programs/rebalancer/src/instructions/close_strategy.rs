@@ -0,0 +1,135 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::*;
+use crate::utils::transfer_from_vault;
+
+#[derive(Accounts)]
+#[instruction(strategy_id: Pubkey)]
+pub struct CloseStrategy<'info> {
+    #[account(
+        mut,
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+        has_one = manager @ RebalancerErrorCode::UnauthorizedManager
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+    
+    #[account(
+        mut,
+        close = manager,
+        seeds = [b"strategy", portfolio.key().as_ref(), strategy_id.as_ref()],
+        bump = strategy.bump,
+        constraint = strategy.strategy_id == strategy_id @ RebalancerErrorCode::StrategyNotFound
+    )]
+    pub strategy: Account<'info, Strategy>,
+    
+    /// CHECK: Only inspected for existence; the seeds pin it to this strategy's position PDA
+    #[account(
+        seeds = [b"position", strategy_id.as_ref()],
+        bump
+    )]
+    pub position: UncheckedAccount<'info>,
+    
+    // Native SOL escrow, swept to the manager: once the strategy is closed
+    // nothing could sign for it again
+    #[account(
+        mut,
+        seeds = [b"vault", strategy.key().as_ref()],
+        bump,
+    )]
+    pub vault: SystemAccount<'info>,
+    
+    #[account(mut)]
+    pub manager: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+pub fn close_strategy(ctx: Context<CloseStrategy>, strategy_id: Pubkey) -> Result<()> {
+    let portfolio = &mut ctx.accounts.portfolio;
+    let has_open_position = !ctx.accounts.position.data_is_empty();
+    
+    // CLOSE ELIGIBILITY
+    validate_strategy_closable(&ctx.accounts.strategy, has_open_position)?;
+    
+    // DRAIN THE VAULT
+    let vault_lamports = ctx.accounts.vault.lamports();
+    transfer_from_vault(
+        &ctx.accounts.vault,
+        ctx.accounts.manager.to_account_info(),
+        &ctx.accounts.system_program,
+        &ctx.accounts.strategy.key(),
+        ctx.bumps.vault,
+        vault_lamports,
+    )?;
+    
+    portfolio.total_strategies = portfolio.total_strategies
+        .checked_sub(1)
+        .ok_or(RebalancerErrorCode::InsufficientStrategies)?;
+    
+    msg!("Strategy closed: strategy={}, vault drained={}, remaining strategies={}",
+         strategy_id, vault_lamports, portfolio.total_strategies);
+    
+    Ok(())
+}
+
+// ONLY DRAINED, DEPRECATED STRATEGIES WITHOUT POSITIONS MAY BE CLOSED
+pub fn validate_strategy_closable(strategy: &Strategy, has_open_position: bool) -> Result<()> {
+    require!(strategy.status == StrategyStatus::Deprecated, RebalancerErrorCode::StrategyNotDeprecated);
+    require!(
+        strategy.current_balance == 0 && strategy.verified_balance == 0,
+        RebalancerErrorCode::StrategyStillFunded
+    );
+    require!(!has_open_position, RebalancerErrorCode::OpenPositionExists);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+    
+    fn strategy(status: StrategyStatus, current_balance: u64) -> Strategy {
+        Strategy {
+            current_balance,
+            total_withdrawals: 1_000_000_000 - current_balance,
+            status,
+            ..test_utils::strategy()
+        }
+    }
+    
+    #[test]
+    fn test_drained_deprecated_strategy_is_closable() {
+        assert!(validate_strategy_closable(&strategy(StrategyStatus::Deprecated, 0), false).is_ok());
+    }
+    
+    #[test]
+    fn test_non_deprecated_strategy_rejected() {
+        for status in [StrategyStatus::Active, StrategyStatus::Paused] {
+            assert_eq!(
+                validate_strategy_closable(&strategy(status, 0), false).unwrap_err(),
+                RebalancerErrorCode::StrategyNotDeprecated.into()
+            );
+        }
+    }
+    
+    #[test]
+    fn test_funded_or_positioned_strategy_rejected() {
+        assert_eq!(
+            validate_strategy_closable(&strategy(StrategyStatus::Deprecated, 1), false).unwrap_err(),
+            RebalancerErrorCode::StrategyStillFunded.into()
+        );
+        assert_eq!(
+            validate_strategy_closable(&strategy(StrategyStatus::Deprecated, 0), true).unwrap_err(),
+            RebalancerErrorCode::OpenPositionExists.into()
+        );
+        
+        // Escrow that was never withdrawn through the vault
+        let mut escrowed = strategy(StrategyStatus::Deprecated, 0);
+        escrowed.verified_balance = 1;
+        assert_eq!(
+            validate_strategy_closable(&escrowed, false).unwrap_err(),
+            RebalancerErrorCode::StrategyStillFunded.into()
+        );
+    }
+}
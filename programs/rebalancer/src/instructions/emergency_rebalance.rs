@@ -0,0 +1,151 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::*;
+use crate::instructions::execute_ranking::complete_ranking_cycle;
+use crate::instructions::redistribute_capital::RiskLimits;
+use crate::utils::{load_portfolio_strategies, write_rebalance_record};
+
+#[derive(Accounts)]
+pub struct EmergencyRebalance<'info> {
+    #[account(
+        mut,
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+        has_one = manager @ RebalancerErrorCode::UnauthorizedManager
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+    
+    // Optional: protocol weights and the crisis threshold fall back to their defaults when absent
+    #[account(
+        seeds = [b"risk_config", portfolio.key().as_ref()],
+        bump = risk_config.bump,
+    )]
+    pub risk_config: Option<Account<'info, RiskConfig>>,
+    
+    // Audit entry for this rebalance, numbered by the portfolio's sequence
+    #[account(
+        init,
+        payer = manager,
+        space = RebalanceRecord::MAX_SIZE,
+        seeds = [b"record", portfolio.key().as_ref(), &portfolio.rebalance_sequence.to_le_bytes()],
+        bump
+    )]
+    pub rebalance_record: Account<'info, RebalanceRecord>,
+    
+    #[account(mut)]
+    pub manager: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+/// Run a ranking cycle without waiting out `min_rebalance_interval`.
+///
+/// Only allowed while at least one active strategy in `remaining_accounts` is
+/// above the risk config's `crisis_volatility_threshold`, so the bypass can't be used for routine
+/// rebalancing. Every use is counted in `portfolio.emergency_rebalance_count`.
+pub fn emergency_rebalance<'info>(
+    ctx: Context<'_, '_, 'info, 'info, EmergencyRebalance<'info>>,
+) -> Result<()> {
+    let portfolio = &mut ctx.accounts.portfolio;
+    let current_time = Clock::get()?.unix_timestamp;
+    
+    // ELIGIBILITY CHECKS (INTERVAL DELIBERATELY SKIPPED)
+    require!(!portfolio.emergency_pause, RebalancerErrorCode::EmergencyPaused);
+    require!(portfolio.total_strategies >= 2, RebalancerErrorCode::InsufficientStrategies);
+    
+    let risk_limits = ctx.accounts.risk_config
+        .as_ref()
+        .map(|config| config.limits.clone())
+        .unwrap_or_default();
+    
+    let mut strategies = load_portfolio_strategies(&portfolio.key(), ctx.remaining_accounts)?;
+    validate_crisis_conditions(strategies.iter().map(|s| &**s), &risk_limits)?;
+    
+    portfolio.emergency_rebalance_count = portfolio.emergency_rebalance_count
+        .checked_add(1)
+        .ok_or(RebalancerErrorCode::BalanceOverflow)?;
+    
+    msg!("Emergency rebalance #{} bypassing {}s interval (last rebalance: {})",
+         portfolio.emergency_rebalance_count, portfolio.min_rebalance_interval, portfolio.last_rebalance);
+    
+    let (underperformers, flagged_capital) =
+        complete_ranking_cycle(portfolio, &mut strategies, &risk_limits, current_time)?;
+    
+    let record = &mut ctx.accounts.rebalance_record;
+    record.bump = ctx.bumps.rebalance_record;
+    write_rebalance_record(
+        record, portfolio, RebalanceKind::EmergencyRebalance, flagged_capital, underperformers, 0, current_time,
+    )
+}
+
+pub fn validate_crisis_conditions<'a>(
+    strategies: impl IntoIterator<Item = &'a Strategy>,
+    risk_limits: &RiskLimits,
+) -> Result<()> {
+    require!(
+        strategies.into_iter().any(|s| s.is_in_crisis(risk_limits.crisis_volatility_threshold)),
+        RebalancerErrorCode::NoCrisisConditions
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+    
+    fn strategy(volatility_score: u32, status: StrategyStatus) -> Strategy {
+        Strategy {
+            volatility_score,
+            volatility_ema: volatility_score,
+            status,
+            ..test_utils::strategy()
+        }
+    }
+    
+    #[test]
+    fn test_bypass_allowed_with_high_volatility_strategy() {
+        let strategies = [
+            strategy(2000, StrategyStatus::Active),
+            strategy(9500, StrategyStatus::Active),
+        ];
+        assert!(validate_crisis_conditions(strategies.iter(), &RiskLimits::default()).is_ok());
+    }
+    
+    #[test]
+    fn test_bypass_rejected_in_calm_markets() {
+        // Exactly at the threshold does not count as a crisis
+        let strategies = [
+            strategy(2000, StrategyStatus::Active),
+            strategy(CRISIS_VOLATILITY_THRESHOLD, StrategyStatus::Active),
+        ];
+        assert_eq!(
+            validate_crisis_conditions(strategies.iter(), &RiskLimits::default()).unwrap_err(),
+            RebalancerErrorCode::NoCrisisConditions.into()
+        );
+    }
+    
+    #[test]
+    fn test_bypass_follows_configured_crisis_threshold() {
+        let strategies = [
+            strategy(2000, StrategyStatus::Active),
+            strategy(8000, StrategyStatus::Active),
+        ];
+        let sensitive = RiskLimits {
+            crisis_volatility_threshold: 7500,
+            ..RiskLimits::default()
+        };
+        assert!(validate_crisis_conditions(strategies.iter(), &RiskLimits::default()).is_err());
+        assert!(validate_crisis_conditions(strategies.iter(), &sensitive).is_ok());
+    }
+    
+    #[test]
+    fn test_bypass_ignores_inactive_strategies() {
+        let strategies = [
+            strategy(2000, StrategyStatus::Active),
+            strategy(9800, StrategyStatus::Paused),
+            strategy(9800, StrategyStatus::Deprecated),
+        ];
+        assert!(validate_crisis_conditions(strategies.iter(), &RiskLimits::default()).is_err());
+    }
+}
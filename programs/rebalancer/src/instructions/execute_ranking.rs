@@ -1,25 +1,50 @@
 use anchor_lang::prelude::*;
 use crate::state::*;
 use crate::errors::*;
-use crate::utils::{calculate_average_volatility, calculate_dynamic_threshold};
+use crate::events::RankingCycleCompleted;
+use crate::instructions::redistribute_capital::RiskLimits;
+use crate::utils::{
+    calculate_average_volatility, calculate_dynamic_threshold, load_portfolio_strategies, persist_strategies,
+    trace_compute_units, write_rebalance_record,
+};
 
 #[derive(Accounts)]
 pub struct ExecuteRankingCycle<'info> {
     #[account(
         mut,
-        seeds = [b"portfolio", portfolio.manager.as_ref()],
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
         bump = portfolio.bump,
         has_one = manager @ RebalancerErrorCode::UnauthorizedManager
     )]
     pub portfolio: Account<'info, Portfolio>,
     
+    // Optional: protocol weights fall back to ProtocolType defaults when absent
+    #[account(
+        seeds = [b"risk_config", portfolio.key().as_ref()],
+        bump = risk_config.bump,
+    )]
+    pub risk_config: Option<Account<'info, RiskConfig>>,
+    
+    // Audit entry for this rebalance, numbered by the portfolio's sequence
+    #[account(
+        init,
+        payer = manager,
+        space = RebalanceRecord::MAX_SIZE,
+        seeds = [b"record", portfolio.key().as_ref(), &portfolio.rebalance_sequence.to_le_bytes()],
+        bump
+    )]
+    pub rebalance_record: Account<'info, RebalanceRecord>,
+    
     #[account(mut)]
     pub manager: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
 }
 
-pub fn execute_ranking_cycle(
-    ctx: Context<ExecuteRankingCycle>,
+pub fn execute_ranking_cycle<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ExecuteRankingCycle<'info>>,
 ) -> Result<()> {
+    trace_compute_units!("execute_ranking_cycle: start");
     let portfolio = &mut ctx.accounts.portfolio;
     let current_time = Clock::get()?.unix_timestamp;
     
@@ -33,32 +58,123 @@ pub fn execute_ranking_cycle(
     
     msg!("Ranking cycle initiated for {} strategies", portfolio.total_strategies);
     
-    // NOTE: In a real implementation, this would iterate through strategy accounts
-    // For assessment purposes, we'll implement the core ranking logic
-    // that would be called for each batch of strategies
+    // LOAD STRATEGY ACCOUNTS (passed writable via remaining_accounts)
+    let mut strategies = load_portfolio_strategies(&portfolio.key(), ctx.remaining_accounts)?;
+    let risk_limits = ctx.accounts.risk_config
+        .as_ref()
+        .map(|config| config.limits.clone())
+        .unwrap_or_default();
     
-    portfolio.last_rebalance = current_time;
+    let (underperformers, flagged_capital) =
+        complete_ranking_cycle(portfolio, &mut strategies, &risk_limits, current_time)?;
     
+    let record = &mut ctx.accounts.rebalance_record;
+    record.bump = ctx.bumps.rebalance_record;
+    write_rebalance_record(
+        record, portfolio, RebalanceKind::RankingCycle, flagged_capital, underperformers, 0, current_time,
+    )?;
+    
+    trace_compute_units!("execute_ranking_cycle: end");
     Ok(())
 }
 
+// RANK, PERSIST PERCENTILES AND RECORD THE CYCLE
+// Shared by the scheduled ranking cycle and emergency_rebalance.
+// Returns the number of underperformers identified and the capital they hold.
+pub fn complete_ranking_cycle(
+    portfolio: &mut Account<Portfolio>,
+    strategies: &mut [Account<Strategy>],
+    risk_limits: &RiskLimits,
+    current_time: i64,
+) -> Result<(u32, u64)> {
+    // RANK THE WHOLE PORTFOLIO, NEVER A SUBSET
+    portfolio.validate_strategy_account_count(strategies.len())?;
+    
+    // NEVER RANK ON STALE METRICS
+    portfolio.validate_metric_freshness(strategies.iter().map(|s| &**s), current_time)?;
+    
+    let mut ranking_data = rankable_strategies(strategies.iter().map(|s| &**s), risk_limits);
+    
+    let underperformers = calculate_percentile_rankings(&mut ranking_data, portfolio.base_threshold, risk_limits)?;
+    
+    for strategy in strategies.iter_mut() {
+        if let Some(ranked) = ranking_data.iter().find(|r| r.strategy_id == strategy.strategy_id) {
+            strategy.percentile_rank = ranked.percentile_rank;
+        }
+    }
+    persist_strategies(strategies)?;
+    
+    msg!("Ranking cycle completed: {} strategies ranked, {} underperformers",
+         strategies.len(), underperformers.len());
+    
+    portfolio.last_rebalance = current_time;
+    
+    emit!(RankingCycleCompleted {
+        portfolio: portfolio.key(),
+        strategies_ranked: strategies.len() as u32,
+        underperformers: underperformers.len() as u32,
+        timestamp: current_time,
+    });
+    
+    let flagged = flagged_capital(&ranking_data, &underperformers)?;
+    Ok((underperformers.len() as u32, flagged))
+}
+
+// CAPITAL HELD BY THE STRATEGIES A CYCLE FLAGGED FOR EXTRACTION
+// A ranking cycle moves nothing itself; this is what its record reports as extracted.
+pub fn flagged_capital(ranking_data: &[StrategyData], underperformers: &[Pubkey]) -> Result<u64> {
+    ranking_data
+        .iter()
+        .filter(|s| underperformers.contains(&s.strategy_id))
+        .try_fold(0u64, |total, s| {
+            total.checked_add(s.current_balance).ok_or(RebalancerErrorCode::BalanceOverflow.into())
+        })
+}
+
 // CORE PERCENTILE RANKING ALGORITHM
-pub fn calculate_percentile_rankings(strategies: &mut Vec<StrategyData>, base_threshold: u8) -> Result<Vec<Pubkey>> {
+pub fn calculate_percentile_rankings(
+    strategies: &mut [StrategyData],
+    base_threshold: u8,
+    risk_limits: &RiskLimits,
+) -> Result<Vec<Pubkey>> {
     require!(!strategies.is_empty(), RebalancerErrorCode::InsufficientStrategies);
     
-    // SORT STRATEGIES BY PERFORMANCE SCORE (DESCENDING - HIGHEST FIRST)
-    strategies.sort_by(|a, b| {
-        b.performance_score.cmp(&a.performance_score)
-            .then(b.current_balance.cmp(&a.current_balance)) // Tiebreaker: higher balance wins
-            .then(a.volatility_score.cmp(&b.volatility_score)) // Secondary tiebreaker: lower volatility wins
-    });
+    // SORT STRATEGIES BY PROTOCOL-WEIGHTED PERFORMANCE SCORE (DESCENDING - HIGHEST FIRST)
+    strategies.sort_by(|a, b| ranking_order(a, b, risk_limits.tie_break_policy));
+    
+    assign_percentile_ranks(strategies, base_threshold, risk_limits)
+}
+
+// RANKING ORDER: BEST FIRST
+pub fn ranking_order(a: &StrategyData, b: &StrategyData, tie_break: TieBreakPolicy) -> std::cmp::Ordering {
+    let by_balance = b.current_balance.cmp(&a.current_balance); // Higher balance wins
+    let by_volatility = a.volatility_score.cmp(&b.volatility_score); // Lower volatility wins
+    let tie_break_order = match tie_break {
+        TieBreakPolicy::BalanceFirst => by_balance.then(by_volatility),
+        TieBreakPolicy::VolatilityFirst => by_volatility.then(by_balance),
+    };
+    
+    b.loss_bps().cmp(&a.loss_bps()) // Loss-making strategies rank below break-even ones, deepest loss last
+        .then(b.weighted_score().cmp(&a.weighted_score()))
+        .then(tie_break_order)
+        .then(a.strategy_id.to_bytes().cmp(&b.strategy_id.to_bytes())) // Final tiebreaker: lower id wins, so the order is total
+}
+
+// ASSIGN PERCENTILES TO STRATEGIES ALREADY IN RANKING ORDER
+// Used directly by the chunked ranking cycle, whose batches are merged in order.
+pub fn assign_percentile_ranks(
+    strategies: &mut [StrategyData],
+    base_threshold: u8,
+    risk_limits: &RiskLimits,
+) -> Result<Vec<Pubkey>> {
+    require!(!strategies.is_empty(), RebalancerErrorCode::InsufficientStrategies);
     
     let total_strategies = strategies.len();
     let mut underperformers = Vec::new();
     
     // CALCULATE DYNAMIC THRESHOLD BASED ON AVERAGE VOLATILITY
     let average_volatility = calculate_average_volatility(strategies)?;
-    let dynamic_threshold = calculate_dynamic_threshold(base_threshold, average_volatility)?;
+    let dynamic_threshold = calculate_dynamic_threshold(base_threshold, average_volatility, risk_limits)?;
     
     msg!("Dynamic threshold calculated: {}% (base: {}%, avg volatility: {})",
          dynamic_threshold, base_threshold, average_volatility);
@@ -66,30 +182,25 @@ pub fn calculate_percentile_rankings(strategies: &mut Vec<StrategyData>, base_th
     // ASSIGN PERCENTILE RANKS AND IDENTIFY UNDERPERFORMERS
     for (index, strategy_data) in strategies.iter_mut().enumerate() {
         // Calculate percentile rank: 0 (worst) to 100 (best)
-        strategy_data.percentile_rank = if total_strategies == 1 {
-            50u8 // Single strategy gets median rank
-        } else {
-            // Percentile formula: (rank / (total - 1)) * 100
-            // where rank 0 = worst, rank (total-1) = best
-            let rank_from_bottom = total_strategies - 1 - index;
-            ((rank_from_bottom * 100) / (total_strategies - 1)) as u8
-        };
+        strategy_data.percentile_rank = percentile_rank(index, total_strategies);
         
         // IDENTIFY BOTTOM PERFORMERS BASED ON DYNAMIC THRESHOLD
         let _bottom_threshold_rank = if total_strategies <= 4 {
             // For small portfolios, only rebalance bottom 25% if rank is 0
+            if total_strategies > 1 && strategy_data.percentile_rank == 0 {
+                underperformers.push(strategy_data.strategy_id);
+            }
             0u8
         } else {
             // For larger portfolios, use dynamic threshold percentage
-            let threshold_strategies = (total_strategies * dynamic_threshold as usize) / 100;
-            let threshold_strategies = threshold_strategies.max(1); // At least 1 strategy
+            let threshold_strategies = threshold_strategy_count(total_strategies, dynamic_threshold);
             
             if index >= total_strategies - threshold_strategies {
                 underperformers.push(strategy_data.strategy_id);
             }
             
             // Calculate the percentile rank that corresponds to the threshold
-            ((threshold_strategies * 100) / total_strategies) as u8
+            clamp_percent((threshold_strategies as u64 * 100) / total_strategies as u64)
         };
         
         msg!("Strategy {} ranked: percentile={}%, score={}, balance={}, volatility={}",
@@ -103,26 +214,90 @@ pub fn calculate_percentile_rankings(strategies: &mut Vec<StrategyData>, base_th
     Ok(underperformers)
 }
 
+// PERCENTILE OF THE STRATEGY AT `index` (0 = BEST) AMONG `total_strategies`
+// Percentile formula: (rank / (total - 1)) * 100, where rank 0 = worst, rank (total-1) = best.
+// Intermediate products use u64 so large counts cannot overflow a 32-bit usize.
+pub fn percentile_rank(index: usize, total_strategies: usize) -> u8 {
+    match total_strategies {
+        0 => 0,   // Defensive: callers reject empty rankings
+        1 => 50,  // Single strategy gets median rank
+        _ => {
+            let last = total_strategies as u64 - 1;
+            let rank_from_bottom = last.saturating_sub(index as u64);
+            clamp_percent(rank_from_bottom.saturating_mul(100) / last)
+        }
+    }
+}
+
+// NARROW A 0-100 PERCENTAGE TO u8 WITHOUT WRAPPING
+// Anything above 100 is a logic error upstream; it is clamped rather than truncated.
+pub fn clamp_percent(percent: u64) -> u8 {
+    percent.min(100) as u8
+}
+
+// NUMBER OF BOTTOM STRATEGIES COVERED BY A THRESHOLD PERCENTAGE (AT LEAST 1, AT MOST ALL)
+pub fn threshold_strategy_count(total_strategies: usize, threshold_percent: u8) -> usize {
+    if total_strategies == 0 {
+        return 0;
+    }
+    
+    let count = (total_strategies as u64).saturating_mul(threshold_percent.min(100) as u64) / 100;
+    (count as usize).clamp(1, total_strategies)
+}
+
+// STATUS POLICY FOR RANKING
+// Only Active strategies are ranked against each other. Paused strategies are
+// frozen: they are neither ranked nor extracted from, and keep their last
+// percentile. Deprecated strategies are not ranked either; the planner drains
+// them by status rather than by rank (see execute_complete_rebalancing).
+pub fn rankable_strategies<'a>(
+    strategies: impl IntoIterator<Item = &'a Strategy>,
+    risk_limits: &RiskLimits,
+) -> Vec<StrategyData> {
+    strategies
+        .into_iter()
+        .filter(|s| s.status == StrategyStatus::Active)
+        .map(|s| StrategyData::from_strategy(s, risk_limits))
+        .collect()
+}
+
 // HELPER STRUCTURE FOR RANKING CALCULATIONS
-#[derive(Debug, Clone)]
+// Serialized for MetricsSnapshot, so field order is part of the snapshot format
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone)]
 pub struct StrategyData {
     pub strategy_id: Pubkey,
     pub performance_score: u64,
     pub current_balance: u64,
     pub volatility_score: u32,
     pub percentile_rank: u8,
+    pub protocol_weight_bps: u32,
+    pub net_return_bps: i64,
 }
 
 impl StrategyData {
-    pub fn from_strategy(strategy: &Strategy) -> Self {
+    pub const SIZE: usize = 32 + 8 + 8 + 4 + 1 + 4 + 8;
+    
+    pub fn from_strategy(strategy: &Strategy, risk_limits: &RiskLimits) -> Self {
         StrategyData {
             strategy_id: strategy.strategy_id,
             performance_score: strategy.performance_score,
             current_balance: strategy.current_balance,
-            volatility_score: strategy.volatility_score,
+            volatility_score: strategy.volatility_ema,
             percentile_rank: strategy.percentile_rank,
+            protocol_weight_bps: risk_limits.protocol_weight(&strategy.protocol_type),
+            net_return_bps: strategy.net_return_bps(),
         }
     }
+    
+    // Performance score scaled by the protocol's risk weight, used for ranking
+    pub fn weighted_score(&self) -> u128 {
+        self.performance_score as u128 * self.protocol_weight_bps as u128 / 10000
+    }
+    
+    // Lifetime loss in basis points (0 for break-even or profitable strategies)
+    pub fn loss_bps(&self) -> i64 {
+        self.net_return_bps.min(0)
+    }
 }
 
 // REBALANCING TRIGGER LOGIC
@@ -130,14 +305,18 @@ pub fn should_rebalance_strategy(
     strategy: &Strategy,
     strategies: &[StrategyData],
     base_threshold: u8,
+    risk_limits: &RiskLimits,
 ) -> Result<bool> {
     // Strategy qualifies for rebalancing if:
     // 1. It's in the bottom percentile based on dynamic threshold
     // 2. It has sufficient balance to make rebalancing worthwhile
     // 3. It's currently active
+    // Deprecated strategies are always extraction targets while they hold capital
     
-    if strategy.status != StrategyStatus::Active {
-        return Ok(false);
+    match strategy.status {
+        StrategyStatus::Deprecated => return Ok(strategy.current_balance > 0),
+        StrategyStatus::Paused => return Ok(false),
+        StrategyStatus::Active => {}
     }
     
     if strategy.current_balance < 50_000_000 { // 0.05 SOL minimum threshold
@@ -146,15 +325,16 @@ pub fn should_rebalance_strategy(
     
     // Calculate dynamic threshold based on average volatility
     let average_volatility = calculate_average_volatility(strategies)?;
-    let dynamic_threshold = calculate_dynamic_threshold(base_threshold, average_volatility)?;
+    let dynamic_threshold = calculate_dynamic_threshold(base_threshold, average_volatility, risk_limits)?;
     
-    // Check if strategy is in bottom percentile
-    Ok(strategy.percentile_rank < dynamic_threshold)
+    // Check if strategy is below the configured cutoff, or the dynamic threshold
+    Ok(strategy.percentile_rank < risk_limits.underperformer_cutoff(dynamic_threshold))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils;
     use anchor_lang::prelude::Pubkey;
     
     #[test]
@@ -166,6 +346,8 @@ mod tests {
                 current_balance: 1_000_000_000,
                 volatility_score: 2000,
                 percentile_rank: 0,
+                protocol_weight_bps: 10000,
+                net_return_bps: 0,
             },
             StrategyData {
                 strategy_id: Pubkey::new_unique(),
@@ -173,6 +355,8 @@ mod tests {
                 current_balance: 2_000_000_000,
                 volatility_score: 4000,
                 percentile_rank: 0,
+                protocol_weight_bps: 10000,
+                net_return_bps: 0,
             },
             StrategyData {
                 strategy_id: Pubkey::new_unique(),
@@ -180,10 +364,12 @@ mod tests {
                 current_balance: 500_000_000,
                 volatility_score: 6000,
                 percentile_rank: 0,
+                protocol_weight_bps: 10000,
+                net_return_bps: 0,
             },
         ];
         
-        let underperformers = calculate_percentile_rankings(&mut strategies, 15).unwrap();
+        let underperformers = calculate_percentile_rankings(&mut strategies, 15, &RiskLimits::default()).unwrap();
         
         // Verify ranking order (highest score = highest percentile)
         assert!(strategies[0].percentile_rank > strategies[1].percentile_rank);
@@ -203,6 +389,8 @@ mod tests {
                 current_balance: 2_000_000_000, // Higher balance
                 volatility_score: 3000,
                 percentile_rank: 0,
+                protocol_weight_bps: 10000,
+                net_return_bps: 0,
             },
             StrategyData {
                 strategy_id: Pubkey::new_unique(),
@@ -210,16 +398,102 @@ mod tests {
                 current_balance: 1_000_000_000, // Lower balance
                 volatility_score: 3000,
                 percentile_rank: 0,
+                protocol_weight_bps: 10000,
+                net_return_bps: 0,
             },
         ];
         
-        calculate_percentile_rankings(&mut strategies, 15).unwrap();
+        calculate_percentile_rankings(&mut strategies, 15, &RiskLimits::default()).unwrap();
         
         // Higher balance should win the tiebreaker
         assert!(strategies[0].percentile_rank > strategies[1].percentile_rank);
         assert_eq!(strategies[0].current_balance, 2_000_000_000);
     }
     
+    #[test]
+    fn test_tie_break_policy_orders_tied_scores() {
+        let tied = |current_balance: u64, volatility_score: u32| StrategyData {
+            strategy_id: Pubkey::new_unique(),
+            performance_score: 5000, // Same score
+            current_balance,
+            volatility_score,
+            percentile_rank: 0,
+            protocol_weight_bps: 10000,
+            net_return_bps: 0,
+        };
+        let large_volatile = tied(2_000_000_000, 6000);
+        let small_calm = tied(1_000_000_000, 1000);
+        let best_first = |tie_break_policy| {
+            let mut strategies = vec![small_calm.clone(), large_volatile.clone()];
+            calculate_percentile_rankings(&mut strategies, 15, &RiskLimits { tie_break_policy, ..RiskLimits::default() }).unwrap();
+            strategies[0].strategy_id
+        };
+        
+        assert_eq!(RiskLimits::default().tie_break_policy, TieBreakPolicy::BalanceFirst);
+        assert_eq!(best_first(TieBreakPolicy::BalanceFirst), large_volatile.strategy_id);
+        assert_eq!(best_first(TieBreakPolicy::VolatilityFirst), small_calm.strategy_id);
+        
+        // With volatility equal too, volatility-first falls back to balance
+        let mut equal_volatility = vec![tied(1_000_000_000, 3000), tied(2_000_000_000, 3000)];
+        let limits = RiskLimits { tie_break_policy: TieBreakPolicy::VolatilityFirst, ..RiskLimits::default() };
+        calculate_percentile_rankings(&mut equal_volatility, 15, &limits).unwrap();
+        assert_eq!(equal_volatility[0].current_balance, 2_000_000_000);
+    }
+    
+    #[test]
+    fn test_identical_metrics_rank_by_strategy_id() {
+        let identical = |seed: u8| StrategyData {
+            strategy_id: Pubkey::new_from_array([seed; 32]),
+            performance_score: 5000,
+            current_balance: 1_000_000_000,
+            volatility_score: 3000,
+            percentile_rank: 0,
+            protocol_weight_bps: 10000,
+            net_return_bps: 0,
+        };
+        
+        // Every input order produces the same ranks
+        let ranks = |order: [u8; 3]| {
+            let mut strategies: Vec<StrategyData> = order.iter().map(|&seed| identical(seed)).collect();
+            calculate_percentile_rankings(&mut strategies, 15, &RiskLimits::default()).unwrap();
+            strategies.iter().map(|s| (s.strategy_id, s.percentile_rank)).collect::<Vec<_>>()
+        };
+        let expected = ranks([1, 2, 3]);
+        for order in [[3, 2, 1], [2, 3, 1], [3, 1, 2]] {
+            assert_eq!(ranks(order), expected);
+        }
+        
+        // Lowest id ranks best
+        assert_eq!(expected[0], (Pubkey::new_from_array([1; 32]), 100));
+        assert_eq!(expected[2], (Pubkey::new_from_array([3; 32]), 0));
+    }
+    
+    #[test]
+    fn test_losing_strategy_ranks_below_flat_one() {
+        let strategy = |performance_score, net_return_bps| StrategyData {
+            strategy_id: Pubkey::new_unique(),
+            performance_score,
+            current_balance: 1_000_000_000,
+            volatility_score: 3000,
+            percentile_rank: 0,
+            protocol_weight_bps: 10000,
+            net_return_bps,
+        };
+        // The loser still reports a higher yield-based score than the flat strategy
+        let losing = strategy(8000, -500);
+        let deeper_loss = strategy(9000, -2000);
+        let flat = strategy(4000, 0);
+        let profitable = strategy(5000, 300);
+        
+        let mut strategies = vec![deeper_loss.clone(), losing.clone(), flat.clone(), profitable.clone()];
+        calculate_percentile_rankings(&mut strategies, 15, &RiskLimits::default()).unwrap();
+        
+        let order: Vec<Pubkey> = strategies.iter().map(|s| s.strategy_id).collect();
+        // Break-even and profitable strategies compare on score; losses come last, deepest at the bottom
+        assert_eq!(order, vec![profitable.strategy_id, flat.strategy_id, losing.strategy_id, deeper_loss.strategy_id]);
+        assert!(strategies[1].percentile_rank > strategies[2].percentile_rank);
+    }
+    
     #[test]
     fn test_edge_cases() {
         // Single strategy
@@ -230,11 +504,150 @@ mod tests {
                 current_balance: 1_000_000_000,
                 volatility_score: 3000,
                 percentile_rank: 0,
+                protocol_weight_bps: 10000,
+                net_return_bps: 0,
             }
         ];
         
-        let underperformers = calculate_percentile_rankings(&mut single_strategy, 15).unwrap();
+        let underperformers = calculate_percentile_rankings(&mut single_strategy, 15, &RiskLimits::default()).unwrap();
         assert_eq!(single_strategy[0].percentile_rank, 50); // Median rank
         assert_eq!(underperformers.len(), 0); // No rebalancing for single strategy
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_percentile_rank_bounds_with_large_counts() {
+        for total in [2usize, 3, 101, 1_000_000, u32::MAX as usize] {
+            assert_eq!(percentile_rank(0, total), 100);
+            assert_eq!(percentile_rank(total - 1, total), 0);
+            assert!(percentile_rank(total / 2, total) <= 100);
+        }
+        // Out-of-range index still yields a valid rank
+        assert_eq!(percentile_rank(10, 5), 0);
+        assert_eq!(percentile_rank(0, 0), 0);
+    }
+    
+    #[test]
+    fn test_percentiles_span_full_range_with_101_strategies() {
+        let mut strategies: Vec<StrategyData> = (0..101u64)
+            .map(|i| StrategyData {
+                strategy_id: Pubkey::new_unique(),
+                performance_score: 1000 + i * 10,
+                current_balance: 1_000_000_000,
+                volatility_score: 2000,
+                percentile_rank: 0,
+                protocol_weight_bps: 10000,
+                net_return_bps: 0,
+            })
+            .collect();
+        
+        calculate_percentile_rankings(&mut strategies, 15, &RiskLimits::default()).unwrap();
+        
+        // One percentile step per strategy: best is 100, worst is 0, nothing wraps or repeats
+        let ranks: Vec<u8> = strategies.iter().map(|s| s.percentile_rank).collect();
+        assert_eq!(ranks, (0..=100u8).rev().collect::<Vec<_>>());
+        assert_eq!(strategies[0].performance_score, 2000);
+    }
+    
+    #[test]
+    fn test_clamp_percent_never_wraps() {
+        assert_eq!(clamp_percent(0), 0);
+        assert_eq!(clamp_percent(100), 100);
+        // Out-of-range inputs clamp instead of wrapping (256 would truncate to 0)
+        assert_eq!(clamp_percent(101), 100);
+        assert_eq!(clamp_percent(256), 100);
+        assert_eq!(clamp_percent(u64::MAX), 100);
+    }
+    
+    #[test]
+    fn test_threshold_strategy_count_bounds() {
+        assert_eq!(threshold_strategy_count(0, 25), 0);
+        assert_eq!(threshold_strategy_count(5, 10), 1); // Rounds down to zero, floored at one
+        assert_eq!(threshold_strategy_count(20, 25), 5);
+        assert_eq!(threshold_strategy_count(u32::MAX as usize, 40), (u32::MAX as u64 * 40 / 100) as usize);
+        // Percentages above 100 cannot select more strategies than exist
+        assert_eq!(threshold_strategy_count(10, 250), 10);
+    }
+    
+    #[test]
+    fn test_protocol_weight_breaks_equal_raw_scores() {
+        let farming = StrategyData {
+            strategy_id: Pubkey::new_unique(),
+            performance_score: 6000, // Same raw score
+            current_balance: 2_000_000_000, // Would win the balance tiebreaker unweighted
+            volatility_score: 3000,
+            percentile_rank: 0,
+            protocol_weight_bps: 8500,
+            net_return_bps: 0,
+        };
+        let lending = StrategyData {
+            strategy_id: Pubkey::new_unique(),
+            performance_score: 6000, // Same raw score
+            current_balance: 1_000_000_000,
+            volatility_score: 3000,
+            percentile_rank: 0,
+            protocol_weight_bps: 10000,
+            net_return_bps: 0,
+        };
+        
+        let mut strategies = vec![farming.clone(), lending.clone()];
+        calculate_percentile_rankings(&mut strategies, 15, &RiskLimits::default()).unwrap();
+        assert_eq!(strategies[0].strategy_id, lending.strategy_id);
+        assert_eq!(strategies[0].percentile_rank, 100);
+        
+        // Boosting the farming weight above lending flips the order
+        let mut strategies = vec![
+            StrategyData { protocol_weight_bps: 12000, ..farming.clone() },
+            lending.clone(),
+        ];
+        calculate_percentile_rankings(&mut strategies, 15, &RiskLimits::default()).unwrap();
+        assert_eq!(strategies[0].strategy_id, farming.strategy_id);
+        assert_eq!(strategies[0].percentile_rank, 100);
+    }
+    
+    #[test]
+    fn test_only_active_strategies_are_ranked() {
+        let strategy = |performance_score, status| Strategy {
+            performance_score,
+            status,
+            percentile_rank: 60,
+            ..test_utils::strategy()
+        };
+        let strategies = [
+            strategy(8000, StrategyStatus::Active),
+            strategy(100, StrategyStatus::Paused), // Worst score, but frozen
+            strategy(5000, StrategyStatus::Active),
+            strategy(200, StrategyStatus::Deprecated),
+        ];
+        
+        let mut ranking_data = rankable_strategies(strategies.iter(), &RiskLimits::default());
+        let underperformers = calculate_percentile_rankings(&mut ranking_data, 15, &RiskLimits::default()).unwrap();
+        
+        let ranked: Vec<Pubkey> = ranking_data.iter().map(|r| r.strategy_id).collect();
+        assert_eq!(ranked, vec![strategies[0].strategy_id, strategies[2].strategy_id]);
+        assert_eq!(ranking_data[1].percentile_rank, 0);
+        // The paused strategy cannot be flagged even though it scores lowest
+        assert_eq!(underperformers, vec![strategies[2].strategy_id]);
+    }
+    
+    #[test]
+    fn test_flagged_capital_sums_underperformer_balances() {
+        let data = |balance: u64| StrategyData {
+            strategy_id: Pubkey::new_unique(),
+            performance_score: 5000,
+            current_balance: balance,
+            volatility_score: 2000,
+            percentile_rank: 0,
+            protocol_weight_bps: 10000,
+            net_return_bps: 0,
+        };
+        let ranking_data = vec![data(3_000_000_000), data(2_000_000_000), data(500_000_000)];
+        
+        let flagged = vec![ranking_data[1].strategy_id, ranking_data[2].strategy_id];
+        assert_eq!(flagged_capital(&ranking_data, &flagged).unwrap(), 2_500_000_000);
+        assert_eq!(flagged_capital(&ranking_data, &[]).unwrap(), 0);
+        
+        let huge = vec![data(u64::MAX), data(1)];
+        let all: Vec<Pubkey> = huge.iter().map(|s| s.strategy_id).collect();
+        assert!(flagged_capital(&huge, &all).is_err());
+    }
+}
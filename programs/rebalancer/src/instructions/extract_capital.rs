@@ -1,13 +1,14 @@
 use anchor_lang::prelude::*;
 use crate::state::*;
 use crate::errors::*;
+use crate::instructions::redistribute_capital::{RiskLimits, MAX_STRATEGIES_PER_OP};
 
 #[derive(Accounts)]
 #[instruction(strategy_ids: Vec<Pubkey>)]
 pub struct ExtractCapital<'info> {
     #[account(
         mut,
-        seeds = [b"portfolio", portfolio.manager.as_ref()],
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
         bump = portfolio.bump,
         has_one = manager @ RebalancerErrorCode::UnauthorizedManager
     )]
@@ -26,7 +27,7 @@ pub fn extract_capital(
     // SECURITY VALIDATIONS
     require!(!portfolio.emergency_pause, RebalancerErrorCode::EmergencyPaused);
     require!(!strategy_ids.is_empty(), RebalancerErrorCode::InsufficientStrategies);
-    require!(strategy_ids.len() <= 10, RebalancerErrorCode::TooManyStrategies);
+    require!(strategy_ids.len() <= MAX_STRATEGIES_PER_OP, RebalancerErrorCode::TooManyStrategies);
     
     let total_extracted = 0u64;
     
@@ -47,11 +48,12 @@ pub fn extract_capital(
 pub fn extract_from_protocol(
     strategy: &mut Strategy,
     position: &mut CapitalPosition,
+    risk_limits: &RiskLimits,
 ) -> Result<ExtractionResult> {
     require!(strategy.status == StrategyStatus::Active, RebalancerErrorCode::StrategyNotFound);
     require!(strategy.current_balance > 0, RebalancerErrorCode::InsufficientBalance);
     
-    match strategy.protocol_type {
+    let result = match strategy.protocol_type {
         ProtocolType::StableLending { .. } => {
             extract_from_lending(strategy, position)
         },
@@ -61,7 +63,17 @@ pub fn extract_from_protocol(
         ProtocolType::LiquidStaking { .. } => {
             extract_from_staking(strategy, position)
         },
+        ProtocolType::PerpetualFunding { .. } => {
+            extract_from_perpetual(strategy, position, risk_limits)
+        },
+    }?;
+    
+    // START THE REALLOCATION COOLDOWN
+    if result.extracted_amount > 0 {
+        strategy.last_extracted = Clock::get()?.unix_timestamp;
     }
+    
+    Ok(result)
 }
 
 // STABLE LENDING EXTRACTION (Simple Balance Withdrawal)
@@ -69,14 +81,8 @@ pub fn extract_from_lending(
     strategy: &mut Strategy,
     position: &mut CapitalPosition,
 ) -> Result<ExtractionResult> {
-    let available_balance = strategy.current_balance;
-    
-    // CALCULATE WITHDRAWAL AMOUNT (Full extraction for rebalancing)
-    let extraction_amount = if available_balance > 10_000_000 { // Keep 0.01 SOL for rent
-        available_balance.saturating_sub(10_000_000)
-    } else {
-        0u64
-    };
+    // CALCULATE WITHDRAWAL AMOUNT (Full extraction down to the rent reserve)
+    let extraction_amount = extractable_balance(strategy.current_balance, &strategy.mint);
     
     if extraction_amount == 0 {
         return Ok(ExtractionResult {
@@ -97,8 +103,7 @@ pub fn extract_from_lending(
     
     // UPDATE POSITION STATE
     position.token_a_amount = position.token_a_amount
-        .checked_sub(extraction_amount)
-        .unwrap_or(0);
+        .saturating_sub(extraction_amount);
     
     position.last_rebalance = Clock::get()?.unix_timestamp;
     
@@ -269,6 +274,56 @@ pub fn extract_from_staking(
     })
 }
 
+// PERPETUAL FUNDING EXTRACTION (Close Hedge, Withdraw Margin)
+pub fn extract_from_perpetual(
+    strategy: &mut Strategy,
+    position: &mut CapitalPosition,
+    risk_limits: &RiskLimits,
+) -> Result<ExtractionResult> {
+    let ProtocolType::PerpetualFunding { max_leverage, .. } = strategy.protocol_type else {
+        return Err(RebalancerErrorCode::InvalidProtocolType.into());
+    };
+    
+    // CALCULATE WITHDRAWABLE MARGIN (Keep the rent reserve)
+    let margin_withdrawal = extractable_balance(strategy.current_balance, &strategy.mint);
+    if margin_withdrawal == 0 {
+        return Ok(ExtractionResult {
+            extracted_amount: 0,
+            extraction_type: ExtractionType::NoExtraction,
+            fees_paid: 0,
+        });
+    }
+    
+    // CLOSING THE SHORT LEG PAYS A TAKER FEE ON THE LEVERAGED NOTIONAL
+    let close_fee = ((margin_withdrawal as u128 * max_leverage as u128 * risk_limits.perpetual_taker_fee_bps as u128) / 10000)
+        .min(margin_withdrawal as u128) as u64;
+    let final_amount = margin_withdrawal
+        .checked_sub(close_fee)
+        .ok_or(RebalancerErrorCode::InsufficientBalance)?;
+    
+    // UPDATE STRATEGY STATE
+    strategy.current_balance = strategy.current_balance
+        .checked_sub(margin_withdrawal)
+        .ok_or(RebalancerErrorCode::InsufficientBalance)?;
+    
+    strategy.total_withdrawals = strategy.total_withdrawals
+        .checked_add(final_amount)
+        .ok_or(RebalancerErrorCode::BalanceOverflow)?;
+    
+    // UPDATE POSITION STATE
+    position.token_a_amount = position.token_a_amount.saturating_sub(margin_withdrawal);
+    position.last_rebalance = Clock::get()?.unix_timestamp;
+    
+    msg!("Closed perpetual hedge: withdrew {} margin, close fee {}, received {}",
+         margin_withdrawal, close_fee, final_amount);
+    
+    Ok(ExtractionResult {
+        extracted_amount: final_amount,
+        extraction_type: ExtractionType::MarginWithdrawal,
+        fees_paid: close_fee,
+    })
+}
+
 // EXTRACTION RESULT STRUCTURES
 #[derive(Debug, Clone)]
 pub struct ExtractionResult {
@@ -283,4 +338,5 @@ pub enum ExtractionType {
     LendingWithdrawal,
     LiquidityWithdrawal,
     StakingUnstake,
+    MarginWithdrawal,
 }
\ No newline at end of file
@@ -0,0 +1,93 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::*;
+use crate::events::RankingCycleCompleted;
+use crate::instructions::execute_ranking::flagged_capital;
+use crate::utils::write_rebalance_record;
+
+#[derive(Accounts)]
+pub struct FinalizeRankingCycle<'info> {
+    #[account(
+        mut,
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+        has_one = manager @ RebalancerErrorCode::UnauthorizedManager
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+    
+    #[account(
+        mut,
+        seeds = [b"ranking_session", portfolio.key().as_ref()],
+        bump = ranking_session.bump,
+    )]
+    pub ranking_session: Account<'info, RankingSession>,
+    
+    // Must hold the limits the cycle began with
+    #[account(
+        seeds = [b"risk_config", portfolio.key().as_ref()],
+        bump = risk_config.bump,
+    )]
+    pub risk_config: Account<'info, RiskConfig>,
+    
+    // Audit entry for this rebalance, numbered by the portfolio's sequence
+    #[account(
+        init,
+        payer = manager,
+        space = RebalanceRecord::MAX_SIZE,
+        seeds = [b"record", portfolio.key().as_ref(), &portfolio.rebalance_sequence.to_le_bytes()],
+        bump
+    )]
+    pub rebalance_record: Account<'info, RebalanceRecord>,
+    
+    #[account(mut)]
+    pub manager: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+/// Assign percentiles from the session's merged order.
+///
+/// Works from the submitted entries alone, so no strategy account is passed
+/// and the cycle never needs the whole portfolio in one transaction. No
+/// sorting happens here either; the cost was spread across the submitted
+/// batches. The ranks are stored in the session and written back to the
+/// strategies by `apply_ranking_batch`, which closes the session.
+pub fn finalize_ranking_cycle(ctx: Context<FinalizeRankingCycle>) -> Result<()> {
+    let portfolio = &mut ctx.accounts.portfolio;
+    let session = &mut ctx.accounts.ranking_session;
+    let risk_limits = &ctx.accounts.risk_config.limits;
+    let current_time = Clock::get()?.unix_timestamp;
+    
+    require!(!portfolio.emergency_pause, RebalancerErrorCode::EmergencyPaused);
+    session.validate_risk_limits(risk_limits)?;
+    
+    // NEVER RANK ON STALE METRICS (as submitted; write-back rejects any later change)
+    require!(
+        session.entries
+            .iter()
+            .all(|e| !e.rankable || !portfolio.is_metric_stale(e.last_updated, current_time)),
+        RebalancerErrorCode::StaleStrategyData
+    );
+    
+    // ASSIGN PERCENTILES FROM THE MERGED ORDER
+    let (ranking_data, underperformers) = session.finalize(portfolio.base_threshold, risk_limits)?;
+    
+    msg!("Chunked ranking cycle completed: {} strategies ranked, {} underperformers",
+         ranking_data.len(), underperformers.len());
+    
+    portfolio.last_rebalance = current_time;
+    
+    emit!(RankingCycleCompleted {
+        portfolio: portfolio.key(),
+        strategies_ranked: ranking_data.len() as u32,
+        underperformers: underperformers.len() as u32,
+        timestamp: current_time,
+    });
+    
+    let flagged = flagged_capital(&ranking_data, &underperformers)?;
+    let record = &mut ctx.accounts.rebalance_record;
+    record.bump = ctx.bumps.rebalance_record;
+    write_rebalance_record(
+        record, portfolio, RebalanceKind::RankingCycle, flagged, underperformers.len() as u32, 0, current_time,
+    )
+}
@@ -0,0 +1,156 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::*;
+use crate::instructions::redistribute_capital::RiskLimits;
+use crate::utils::{calculate_dynamic_threshold, load_portfolio_strategies};
+
+#[derive(Accounts)]
+pub struct GetPortfolioSummary<'info> {
+    #[account(
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+    
+    // Optional: the dynamic threshold falls back to the default sensitivity when absent
+    #[account(
+        seeds = [b"risk_config", portfolio.key().as_ref()],
+        bump = risk_config.bump,
+    )]
+    pub risk_config: Option<Account<'info, RiskConfig>>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct PortfolioSummary {
+    pub strategy_count: u32,
+    pub total_capital: u64,
+    pub average_yield_rate: u64,      // Basis points
+    pub average_volatility: u32,      // Basis points
+    pub active_count: u32,
+    pub paused_count: u32,
+    pub deprecated_count: u32,
+    pub dynamic_threshold: u8,        // Percent, as used by the next ranking cycle
+}
+
+/// Read-only aggregate stats for dashboards.
+///
+/// The strategy accounts are passed in `remaining_accounts`; the summary is
+/// returned through Anchor's return data, so clients can `simulate` it instead
+/// of fetching and totalling every strategy themselves.
+pub fn get_portfolio_summary<'info>(
+    ctx: Context<'_, '_, 'info, 'info, GetPortfolioSummary<'info>>,
+) -> Result<PortfolioSummary> {
+    let portfolio = &ctx.accounts.portfolio;
+    let strategies = load_portfolio_strategies(&portfolio.key(), ctx.remaining_accounts)?;
+    
+    let risk_limits = ctx.accounts.risk_config
+        .as_ref()
+        .map(|config| config.limits.clone())
+        .unwrap_or_default();
+    
+    let summary = summarize_portfolio(strategies.iter().map(|s| &**s), portfolio.base_threshold, &risk_limits)?;
+    
+    msg!("Portfolio summary: strategies={}, capital={}, avg_yield={}bps, avg_volatility={}, threshold={}%",
+         summary.strategy_count, summary.total_capital, summary.average_yield_rate,
+         summary.average_volatility, summary.dynamic_threshold);
+    
+    Ok(summary)
+}
+
+pub fn summarize_portfolio<'a>(
+    strategies: impl IntoIterator<Item = &'a Strategy>,
+    base_threshold: u8,
+    risk_limits: &RiskLimits,
+) -> Result<PortfolioSummary> {
+    let mut strategy_count = 0u32;
+    let mut total_capital = 0u64;
+    let mut total_yield = 0u128;
+    let mut total_volatility = 0u64;
+    let (mut active_count, mut paused_count, mut deprecated_count) = (0u32, 0u32, 0u32);
+    
+    for strategy in strategies {
+        strategy_count += 1;
+        total_capital = total_capital
+            .checked_add(strategy.current_balance)
+            .ok_or(RebalancerErrorCode::BalanceOverflow)?;
+        total_yield += strategy.yield_rate as u128;
+        total_volatility += strategy.volatility_score as u64;
+        
+        match strategy.status {
+            StrategyStatus::Active => active_count += 1,
+            StrategyStatus::Paused => paused_count += 1,
+            StrategyStatus::Deprecated => deprecated_count += 1,
+        }
+    }
+    
+    // An empty portfolio reports zero averages rather than failing
+    let (average_yield_rate, average_volatility) = if strategy_count == 0 {
+        (0, 0)
+    } else {
+        (
+            (total_yield / strategy_count as u128) as u64,
+            (total_volatility / strategy_count as u64) as u32,
+        )
+    };
+    
+    Ok(PortfolioSummary {
+        strategy_count,
+        total_capital,
+        average_yield_rate,
+        average_volatility,
+        active_count,
+        paused_count,
+        deprecated_count,
+        dynamic_threshold: calculate_dynamic_threshold(base_threshold, average_volatility, risk_limits)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+    
+    fn strategy(current_balance: u64, yield_rate: u64, volatility_score: u32, status: StrategyStatus) -> Strategy {
+        Strategy {
+            current_balance,
+            yield_rate,
+            total_deposits: current_balance,
+            volatility_score,
+            status,
+            volatility_ema: volatility_score,
+            ..test_utils::strategy()
+        }
+    }
+    
+    #[test]
+    fn test_summary_aggregates_known_strategies() {
+        let strategies = [
+            strategy(1_000_000_000, 12000, 2000, StrategyStatus::Active),
+            strategy(2_000_000_000, 8000, 4000, StrategyStatus::Active),
+            strategy(500_000_000, 4000, 6000, StrategyStatus::Paused),
+            strategy(0, 0, 8000, StrategyStatus::Deprecated),
+        ];
+        
+        let summary = summarize_portfolio(strategies.iter(), 15, &RiskLimits::default()).unwrap();
+        
+        assert_eq!(summary, PortfolioSummary {
+            strategy_count: 4,
+            total_capital: 3_500_000_000,
+            average_yield_rate: 6000,
+            average_volatility: 5000,
+            active_count: 2,
+            paused_count: 1,
+            deprecated_count: 1,
+            dynamic_threshold: 25, // 15 + (5000 * 20 / 10000)
+        });
+    }
+    
+    #[test]
+    fn test_summary_of_empty_portfolio() {
+        let summary = summarize_portfolio(std::iter::empty(), 15, &RiskLimits::default()).unwrap();
+        
+        assert_eq!(summary.strategy_count, 0);
+        assert_eq!(summary.total_capital, 0);
+        assert_eq!(summary.dynamic_threshold, 15);
+    }
+}
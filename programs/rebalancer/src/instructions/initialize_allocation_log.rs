@@ -0,0 +1,54 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::*;
+
+#[derive(Accounts)]
+#[instruction(strategy_id: Pubkey)]
+pub struct InitializeAllocationLog<'info> {
+    #[account(
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+        has_one = manager @ RebalancerErrorCode::UnauthorizedManager
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+    
+    #[account(
+        seeds = [b"strategy", portfolio.key().as_ref(), strategy_id.as_ref()],
+        bump = strategy.bump,
+        constraint = strategy.strategy_id == strategy_id @ RebalancerErrorCode::StrategyNotFound
+    )]
+    pub strategy: Account<'info, Strategy>,
+    
+    #[account(
+        init,
+        payer = manager,
+        space = StrategyAllocationLog::MAX_SIZE,
+        seeds = [b"allocation_log", portfolio.key().as_ref(), strategy_id.as_ref()],
+        bump
+    )]
+    pub allocation_log: Account<'info, StrategyAllocationLog>,
+    
+    #[account(mut)]
+    pub manager: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+/// Start recording a strategy's allocation history.
+///
+/// Once created, pass the log (writable) after the strategy accounts in
+/// `redistribute_capital`'s `remaining_accounts` to append each allocation the
+/// strategy receives.
+pub fn initialize_allocation_log(ctx: Context<InitializeAllocationLog>, strategy_id: Pubkey) -> Result<()> {
+    let log = &mut ctx.accounts.allocation_log;
+    
+    log.portfolio = ctx.accounts.portfolio.key();
+    log.strategy_id = strategy_id;
+    log.head = 0;
+    log.entries = Vec::new();
+    log.bump = ctx.bumps.allocation_log;
+    
+    msg!("Allocation log initialized: strategy={}, capacity={}", strategy_id, ALLOCATION_LOG_CAPACITY);
+    
+    Ok(())
+}
@@ -1,8 +1,9 @@
 use anchor_lang::prelude::*;
 use crate::{errors::RebalancerErrorCode, state::*};
+use crate::events::PortfolioInitialized;
 
 #[derive(Accounts)]
-#[instruction(manager: Pubkey, base_threshold: u8, min_rebalance_interval: i64)]
+#[instruction(manager_arg: Pubkey, base_threshold: u8, min_rebalance_interval: i64)]
 pub struct InitializePortfolio<'info> {
     #[account(
         init,
@@ -16,7 +17,9 @@ pub struct InitializePortfolio<'info> {
     #[account(mut)]
     pub payer: Signer<'info>,
     
-    /// CHECK: Manager address validation happens in instruction logic
+    /// CHECK: Any key may manage a portfolio; it must be the `manager` argument
+    /// so the PDA seeds and the stored manager cannot diverge
+    #[account(constraint = manager.key() == manager_arg @ RebalancerErrorCode::InvalidManager)]
     pub manager: UncheckedAccount<'info>,
     
     pub system_program: Program<'info, System>,
@@ -27,6 +30,7 @@ pub fn initialize_portfolio(
     manager: Pubkey,
     base_threshold: u8,
     min_rebalance_interval: i64,
+    max_strategies: Option<u32>,
 ) -> Result<()> {
     let portfolio = &mut ctx.accounts.portfolio;
     let current_time = Clock::get()?.unix_timestamp;
@@ -53,10 +57,33 @@ pub fn initialize_portfolio(
     portfolio.emergency_pause = false;
     portfolio.performance_fee_bps = 200; // 2% default performance fee
     portfolio.bump = ctx.bumps.portfolio;
-    portfolio.reserved = [0u8; 31];
+    portfolio.max_strategies = max_strategies.unwrap_or(DEFAULT_MAX_STRATEGIES); // 0 = uncapped
+    portfolio.max_capital = 0; // Uncapped until configured
+    portfolio.guardian = Pubkey::default(); // No guardian until configured
+    portfolio.emergency_rebalance_count = 0;
+    portfolio.seed_manager = manager; // Fixed for the portfolio's lifetime
+    portfolio.pending_manager = Pubkey::default();
+    portfolio.max_metric_staleness = DEFAULT_MAX_METRIC_STALENESS;
+    portfolio.rebalance_sequence = 0;
+    portfolio.high_water_mark = 0;
+    portfolio.total_value_locked = 0;
+    portfolio.volatility_smoothing_bps = DEFAULT_VOLATILITY_SMOOTHING_BPS;
+    portfolio.oracle_authority = Pubkey::default(); // Only the manager updates performance until configured
+    portfolio.governance_enabled = false; // Single-manager mode until governance is configured
+    portfolio.layout_version = PORTFOLIO_LAYOUT_VERSION;
+    portfolio.reserved = [0u8; 3];
     
-    msg!("Portfolio initialized: manager={}, base_threshold={}%, interval={}s",
-         manager, base_threshold, min_rebalance_interval);
+    msg!("Portfolio initialized: manager={}, base_threshold={}%, interval={}s, max_strategies={}",
+         manager, base_threshold, min_rebalance_interval, portfolio.max_strategies);
+    
+    emit!(PortfolioInitialized {
+        portfolio: portfolio.key(),
+        manager,
+        base_threshold,
+        min_rebalance_interval,
+        max_strategies: portfolio.max_strategies,
+        timestamp: current_time,
+    });
     
     Ok(())
 }
\ No newline at end of file
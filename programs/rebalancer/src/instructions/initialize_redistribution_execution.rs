@@ -0,0 +1,53 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::*;
+
+#[derive(Accounts)]
+#[instruction(execution_id: u64)]
+pub struct InitializeRedistributionExecution<'info> {
+    #[account(
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+        has_one = manager @ RebalancerErrorCode::UnauthorizedManager
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+    
+    #[account(
+        init,
+        payer = manager,
+        space = RedistributionExecution::MAX_SIZE,
+        seeds = [b"execution", portfolio.key().as_ref(), &execution_id.to_le_bytes()],
+        bump
+    )]
+    pub execution: Account<'info, RedistributionExecution>,
+    
+    #[account(mut)]
+    pub manager: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+/// Create the scratch account that lets a redistribution plan be applied over
+/// several `redistribute_capital` calls.
+///
+/// Pass it as `execution` on every call for the plan, each time with the full
+/// allocation list. Strategy allocations whose destination is not among the
+/// call's strategy accounts are deferred to a later call, and allocations an
+/// earlier call applied are skipped.
+pub fn initialize_redistribution_execution(
+    ctx: Context<InitializeRedistributionExecution>,
+    execution_id: u64,
+) -> Result<()> {
+    let execution = &mut ctx.accounts.execution;
+    
+    execution.portfolio = ctx.accounts.portfolio.key();
+    execution.execution_id = execution_id;
+    execution.allocations_hash = [0u8; 32];
+    execution.allocation_count = 0; // Bound by the first redistribution that uses it
+    execution.executed = 0;
+    execution.bump = ctx.bumps.execution;
+    
+    msg!("Redistribution execution initialized: id={}", execution_id);
+    
+    Ok(())
+}
@@ -0,0 +1,240 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::*;
+use crate::events::PortfolioMigrated;
+use crate::utils::{grow_legacy_account, validate_reserved_zeroed};
+
+// Legacy portfolio layout: discriminator, the fields through `bump`, then 31
+// reserved bytes. Every later field was appended after `bump`.
+const LEGACY_MANAGER_OFFSET: usize = 8;
+const LEGACY_RESERVED_OFFSET: usize = 81;
+
+// Strategy prefix shared by every strategy layout: discriminator, strategy_id,
+// current_balance
+const STRATEGY_ID_OFFSET: usize = 8;
+const STRATEGY_BALANCE_OFFSET: usize = 40;
+const STRATEGY_PREFIX_LEN: usize = 48;
+
+#[derive(Accounts)]
+pub struct MigratePortfolio<'info> {
+    /// CHECK: A legacy portfolio is shorter than `Portfolio::MAX_SIZE` and cannot
+    /// be loaded as `Account<Portfolio>`; the discriminator, stored manager and
+    /// layout are checked by `migrated_portfolio`. Legacy portfolios were always
+    /// seeded by their (never transferable) manager.
+    #[account(
+        mut,
+        seeds = [b"portfolio", manager.key().as_ref()],
+        bump,
+        owner = crate::ID
+    )]
+    pub portfolio: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub manager: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+/// Grow a portfolio written before the layout was versioned to the current
+/// layout.
+///
+/// Fields appended after `bump` read as zero once the account is extended, so
+/// the ones whose zero value is not a safe default are filled in: the PDA seed
+/// key, the strategy cap, metric staleness and volatility smoothing. The
+/// remaining appended fields start at zero as for a new portfolio: the
+/// rebalance sequence begins at the first record, and the oracle authority,
+/// guardian and pending manager are unset.
+///
+/// `remaining_accounts` holds every strategy of the portfolio (read-only, in
+/// either layout) so `total_value_locked` can be seeded from their balances.
+/// The manager pays the rent for the extra space.
+pub fn migrate_portfolio<'info>(
+    ctx: Context<'_, '_, 'info, 'info, MigratePortfolio<'info>>,
+) -> Result<()> {
+    let portfolio_info = ctx.accounts.portfolio.to_account_info();
+    let manager = ctx.accounts.manager.key();
+    
+    // STRATEGIES: SEED TOTAL VALUE LOCKED
+    let mut strategy_ids: Vec<Pubkey> = Vec::with_capacity(ctx.remaining_accounts.len());
+    let mut total_value_locked = 0u64;
+    for info in ctx.remaining_accounts {
+        require_keys_eq!(*info.owner, crate::ID, RebalancerErrorCode::StrategyNotFound);
+        let (strategy_id, balance) = strategy_prefix(&info.try_borrow_data()?)?;
+        
+        let (expected_address, _) = Pubkey::find_program_address(
+            &[b"strategy", portfolio_info.key.as_ref(), strategy_id.as_ref()],
+            &crate::ID,
+        );
+        require_keys_eq!(expected_address, info.key(), RebalancerErrorCode::StrategyNotFound);
+        require!(!strategy_ids.contains(&strategy_id), RebalancerErrorCode::DuplicateStrategy);
+        strategy_ids.push(strategy_id);
+        
+        total_value_locked = total_value_locked
+            .checked_add(balance)
+            .ok_or(RebalancerErrorCode::BalanceOverflow)?;
+    }
+    
+    let portfolio = migrated_portfolio(&portfolio_info.try_borrow_data()?, &manager, total_value_locked)?;
+    require!(
+        strategy_ids.len() == portfolio.total_strategies as usize,
+        RebalancerErrorCode::MissingStrategyAccounts
+    );
+    
+    // GROW TO THE CURRENT LAYOUT (MANAGER PAYS THE EXTRA RENT)
+    grow_legacy_account(&portfolio_info, &ctx.accounts.manager, &ctx.accounts.system_program, Portfolio::MAX_SIZE)?;
+    portfolio.try_serialize(&mut &mut portfolio_info.try_borrow_mut_data()?[..])?;
+    
+    msg!("Portfolio migrated to layout {}: {} strategies, {} lamports locked",
+         portfolio.layout_version, portfolio.total_strategies, portfolio.total_value_locked);
+    
+    emit!(PortfolioMigrated {
+        portfolio: portfolio_info.key(),
+        manager,
+        layout_version: portfolio.layout_version,
+        total_value_locked: portfolio.total_value_locked,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    
+    Ok(())
+}
+
+/// Build the current-layout portfolio from a legacy account's data.
+pub fn migrated_portfolio(legacy: &[u8], manager: &Pubkey, total_value_locked: u64) -> Result<Portfolio> {
+    require!(legacy.starts_with(Portfolio::DISCRIMINATOR), ErrorCode::AccountDiscriminatorMismatch);
+    require!(legacy.len() == LEGACY_PORTFOLIO_SIZE, RebalancerErrorCode::AccountAlreadyMigrated);
+    require!(
+        legacy[LEGACY_MANAGER_OFFSET..LEGACY_MANAGER_OFFSET + 32] == manager.to_bytes(),
+        RebalancerErrorCode::UnauthorizedManager
+    );
+    // The appended fields are decoded from the legacy reserved region
+    validate_reserved_zeroed(&legacy[LEGACY_RESERVED_OFFSET..])?;
+    
+    let mut data = legacy.to_vec();
+    data.resize(Portfolio::MAX_SIZE, 0);
+    let mut portfolio = Portfolio::try_deserialize(&mut &data[..])?;
+    
+    portfolio.seed_manager = *manager;
+    portfolio.max_strategies = DEFAULT_MAX_STRATEGIES;
+    portfolio.max_metric_staleness = DEFAULT_MAX_METRIC_STALENESS;
+    portfolio.volatility_smoothing_bps = DEFAULT_VOLATILITY_SMOOTHING_BPS;
+    portfolio.total_value_locked = total_value_locked;
+    portfolio.layout_version = PORTFOLIO_LAYOUT_VERSION;
+    
+    Ok(portfolio)
+}
+
+/// Read the strategy id and current balance from a strategy account of any
+/// layout.
+pub fn strategy_prefix(data: &[u8]) -> Result<(Pubkey, u64)> {
+    require!(data.starts_with(Strategy::DISCRIMINATOR), ErrorCode::AccountDiscriminatorMismatch);
+    require!(data.len() >= STRATEGY_PREFIX_LEN, ErrorCode::AccountDidNotDeserialize);
+    
+    let strategy_id = Pubkey::try_from(&data[STRATEGY_ID_OFFSET..STRATEGY_BALANCE_OFFSET])
+        .map_err(|_| ErrorCode::AccountDidNotDeserialize)?;
+    let balance = u64::from_le_bytes(
+        data[STRATEGY_BALANCE_OFFSET..STRATEGY_PREFIX_LEN]
+            .try_into()
+            .map_err(|_| ErrorCode::AccountDidNotDeserialize)?,
+    );
+    
+    Ok((strategy_id, balance))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    // Legacy portfolio bytes as written before the layout was versioned
+    fn legacy_portfolio(manager: &Pubkey, total_strategies: u32) -> Vec<u8> {
+        let mut data = Portfolio::DISCRIMINATOR.to_vec();
+        data.extend_from_slice(manager.as_ref());
+        data.extend_from_slice(&5_000_000_000u64.to_le_bytes()); // total_capital_moved
+        data.extend_from_slice(&1_700_000_000i64.to_le_bytes()); // last_rebalance
+        data.extend_from_slice(&3600i64.to_le_bytes()); // min_rebalance_interval
+        data.extend_from_slice(&1_690_000_000i64.to_le_bytes()); // portfolio_creation
+        data.extend_from_slice(&total_strategies.to_le_bytes());
+        data.extend_from_slice(&200u16.to_le_bytes()); // performance_fee_bps
+        data.push(15); // base_threshold
+        data.push(0); // emergency_pause
+        data.push(254); // bump
+        data.extend_from_slice(&[0u8; 31]); // reserved
+        data
+    }
+    
+    #[test]
+    fn test_legacy_portfolio_migrates() {
+        let manager = Pubkey::new_unique();
+        let legacy = legacy_portfolio(&manager, 3);
+        assert_eq!(legacy.len(), LEGACY_PORTFOLIO_SIZE);
+        
+        let portfolio = migrated_portfolio(&legacy, &manager, 7_000_000_000).unwrap();
+        
+        assert_eq!(portfolio.manager, manager);
+        assert_eq!(portfolio.total_capital_moved, 5_000_000_000);
+        assert_eq!(portfolio.last_rebalance, 1_700_000_000);
+        assert_eq!(portfolio.portfolio_creation, 1_690_000_000);
+        assert_eq!(portfolio.total_strategies, 3);
+        assert_eq!(portfolio.performance_fee_bps, 200);
+        assert_eq!(portfolio.bump, 254);
+        assert_eq!(portfolio.seed_manager, manager);
+        assert_eq!(portfolio.max_strategies, DEFAULT_MAX_STRATEGIES);
+        assert_eq!(portfolio.max_metric_staleness, DEFAULT_MAX_METRIC_STALENESS);
+        assert_eq!(portfolio.volatility_smoothing_bps, DEFAULT_VOLATILITY_SMOOTHING_BPS);
+        assert_eq!(portfolio.total_value_locked, 7_000_000_000);
+        assert_eq!(portfolio.rebalance_sequence, 0);
+        assert_eq!(portfolio.oracle_authority, Pubkey::default());
+        assert!(!portfolio.governance_enabled);
+        assert_eq!(portfolio.layout_version, PORTFOLIO_LAYOUT_VERSION);
+        
+        let mut data = Vec::new();
+        portfolio.try_serialize(&mut data).unwrap();
+        assert_eq!(data.len(), Portfolio::MAX_SIZE);
+    }
+    
+    #[test]
+    fn test_current_portfolio_rejected() {
+        let manager = Pubkey::new_unique();
+        let mut current = legacy_portfolio(&manager, 0);
+        current.resize(Portfolio::MAX_SIZE, 0);
+        
+        assert_eq!(
+            migrated_portfolio(&current, &manager, 0).unwrap_err(),
+            RebalancerErrorCode::AccountAlreadyMigrated.into()
+        );
+    }
+    
+    #[test]
+    fn test_other_manager_rejected() {
+        let legacy = legacy_portfolio(&Pubkey::new_unique(), 0);
+        
+        assert_eq!(
+            migrated_portfolio(&legacy, &Pubkey::new_unique(), 0).unwrap_err(),
+            RebalancerErrorCode::UnauthorizedManager.into()
+        );
+    }
+    
+    #[test]
+    fn test_dirty_legacy_reserved_rejected() {
+        let manager = Pubkey::new_unique();
+        let mut legacy = legacy_portfolio(&manager, 0);
+        legacy[LEGACY_PORTFOLIO_SIZE - 1] = 1;
+        
+        assert_eq!(
+            migrated_portfolio(&legacy, &manager, 0).unwrap_err(),
+            RebalancerErrorCode::ReservedBytesNotZeroed.into()
+        );
+    }
+    
+    #[test]
+    fn test_strategy_prefix_reads_id_and_balance() {
+        let strategy_id = Pubkey::new_unique();
+        let mut data = Strategy::DISCRIMINATOR.to_vec();
+        data.extend_from_slice(strategy_id.as_ref());
+        data.extend_from_slice(&2_500_000_000u64.to_le_bytes());
+        data.extend_from_slice(&[0u8; 16]);
+        
+        assert_eq!(strategy_prefix(&data).unwrap(), (strategy_id, 2_500_000_000));
+        assert!(strategy_prefix(&data[..STRATEGY_PREFIX_LEN - 1]).is_err());
+        assert!(strategy_prefix(&legacy_portfolio(&strategy_id, 0)).is_err());
+    }
+}
@@ -0,0 +1,202 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::*;
+use crate::events::StrategyMigrated;
+use crate::utils::{grow_legacy_account, validate_reserved_zeroed};
+
+// Legacy strategy layout: discriminator, strategy_id, the five u64 balances and
+// counters, protocol_type, then volatility_score through bump (23 bytes) and
+// 29 reserved bytes. Every later field was appended after `bump`.
+const LEGACY_PROTOCOL_OFFSET: usize = 80;
+const LEGACY_FIELDS_AFTER_PROTOCOL: usize = 23;
+
+#[derive(Accounts)]
+#[instruction(strategy_id: Pubkey)]
+pub struct MigrateStrategy<'info> {
+    #[account(
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+        has_one = manager @ RebalancerErrorCode::UnauthorizedManager
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+
+    /// CHECK: A legacy strategy is shorter than `Strategy::MAX_SIZE` and cannot
+    /// be loaded as `Account<Strategy>`; the discriminator and layout are
+    /// checked by `migrated_strategy`.
+    #[account(
+        mut,
+        seeds = [b"strategy", portfolio.key().as_ref(), strategy_id.as_ref()],
+        bump,
+        owner = crate::ID
+    )]
+    pub strategy: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub manager: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Grow a strategy written before the layout was versioned to the current
+/// layout. Legacy strategies only ever held native SOL, so the mint is set to
+/// wrapped SOL; the volatility EMA starts from the latest raw reading. The
+/// other appended fields start at zero: uncapped, no vault escrow, never
+/// extracted and an empty yield history. The manager pays the rent for the
+/// extra space.
+///
+/// Portfolios are migrated first (`migrate_portfolio`), so the strategy's
+/// balance is already part of `total_value_locked`.
+pub fn migrate_strategy(ctx: Context<MigrateStrategy>, strategy_id: Pubkey) -> Result<()> {
+    let strategy_info = ctx.accounts.strategy.to_account_info();
+
+    let strategy = migrated_strategy(&strategy_info.try_borrow_data()?)?;
+    require_keys_eq!(strategy.strategy_id, strategy_id, RebalancerErrorCode::StrategyNotFound);
+
+    // GROW TO THE CURRENT LAYOUT (MANAGER PAYS THE EXTRA RENT)
+    grow_legacy_account(&strategy_info, &ctx.accounts.manager, &ctx.accounts.system_program, Strategy::MAX_SIZE)?;
+    strategy.try_serialize(&mut &mut strategy_info.try_borrow_mut_data()?[..])?;
+
+    msg!("Strategy migrated to layout {}: ID={}, Balance={}",
+         strategy.layout_version, strategy_id, strategy.current_balance);
+
+    emit!(StrategyMigrated {
+        portfolio: ctx.accounts.portfolio.key(),
+        strategy_id,
+        layout_version: strategy.layout_version,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Build the current-layout strategy from a legacy account's data.
+pub fn migrated_strategy(legacy: &[u8]) -> Result<Strategy> {
+    require!(legacy.starts_with(Strategy::DISCRIMINATOR), ErrorCode::AccountDiscriminatorMismatch);
+    require!(legacy.len() == LEGACY_STRATEGY_SIZE, RebalancerErrorCode::AccountAlreadyMigrated);
+
+    let mut data = legacy.to_vec();
+    data.resize(Strategy::MAX_SIZE, 0);
+    let mut strategy = Strategy::try_deserialize(&mut &data[..])?;
+
+    // The appended fields are decoded from the legacy reserved region, which
+    // starts right after the variable-length protocol_type
+    let legacy_reserved_offset = LEGACY_PROTOCOL_OFFSET
+        + strategy.protocol_type.try_to_vec()?.len()
+        + LEGACY_FIELDS_AFTER_PROTOCOL;
+    validate_reserved_zeroed(&legacy[legacy_reserved_offset..])?;
+
+    strategy.mint = WRAPPED_SOL_MINT;
+    strategy.decimals = SOL_DECIMALS;
+    strategy.volatility_ema = strategy.volatility_score;
+    strategy.layout_version = STRATEGY_LAYOUT_VERSION;
+
+    Ok(strategy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Legacy strategy bytes as written before the layout was versioned
+    fn legacy_strategy(strategy_id: &Pubkey, protocol_type: ProtocolType) -> Vec<u8> {
+        let mut data = Strategy::DISCRIMINATOR.to_vec();
+        data.extend_from_slice(strategy_id.as_ref());
+        data.extend_from_slice(&2_000_000_000u64.to_le_bytes()); // current_balance
+        data.extend_from_slice(&800u64.to_le_bytes()); // yield_rate
+        data.extend_from_slice(&6500u64.to_le_bytes()); // performance_score
+        data.extend_from_slice(&3_000_000_000u64.to_le_bytes()); // total_deposits
+        data.extend_from_slice(&1_000_000_000u64.to_le_bytes()); // total_withdrawals
+        data.extend_from_slice(&protocol_type.try_to_vec().unwrap());
+        data.extend_from_slice(&4200u32.to_le_bytes()); // volatility_score
+        data.extend_from_slice(&1_700_000_000i64.to_le_bytes()); // last_updated
+        data.extend_from_slice(&1_690_000_000i64.to_le_bytes()); // creation_time
+        data.push(0); // status: Active
+        data.push(75); // percentile_rank
+        data.push(253); // bump
+        data.resize(LEGACY_STRATEGY_SIZE, 0); // reserved and unused protocol_type space
+        data
+    }
+
+    fn farming() -> ProtocolType {
+        ProtocolType::YieldFarming {
+            pair_id: Pubkey::new_unique(),
+            token_a_mint: Pubkey::new_unique(),
+            token_b_mint: Pubkey::new_unique(),
+            fee_tier: 30,
+            reward_multiplier: 2,
+        }
+    }
+
+    #[test]
+    fn test_legacy_strategy_migrates() {
+        let strategy_id = Pubkey::new_unique();
+        let strategy = migrated_strategy(&legacy_strategy(&strategy_id, farming())).unwrap();
+
+        assert_eq!(strategy.strategy_id, strategy_id);
+        assert_eq!(strategy.current_balance, 2_000_000_000);
+        assert_eq!(strategy.performance_score, 6500);
+        assert_eq!(strategy.total_withdrawals, 1_000_000_000);
+        assert_eq!(strategy.volatility_score, 4200);
+        assert_eq!(strategy.volatility_ema, 4200);
+        assert_eq!(strategy.percentile_rank, 75);
+        assert_eq!(strategy.bump, 253);
+        assert_eq!(strategy.mint, WRAPPED_SOL_MINT);
+        assert_eq!(strategy.decimals, SOL_DECIMALS);
+        assert_eq!(strategy.max_capacity, 0);
+        assert_eq!(strategy.verified_balance, 0);
+        assert_eq!(strategy.yield_history_len, 0);
+        assert_eq!(strategy.layout_version, STRATEGY_LAYOUT_VERSION);
+
+        let mut data = Vec::new();
+        strategy.try_serialize(&mut data).unwrap();
+        assert!(data.len() <= Strategy::MAX_SIZE);
+    }
+
+    #[test]
+    fn test_current_strategy_rejected() {
+        let mut current = legacy_strategy(&Pubkey::new_unique(), farming());
+        current.resize(Strategy::MAX_SIZE, 0);
+
+        assert_eq!(
+            migrated_strategy(&current).unwrap_err(),
+            RebalancerErrorCode::AccountAlreadyMigrated.into()
+        );
+    }
+
+    #[test]
+    fn test_dirty_legacy_reserved_rejected() {
+        // A shorter protocol_type moves the reserved region forward
+        let lending = ProtocolType::StableLending {
+            pool_id: Pubkey::new_unique(),
+            reserve_address: Pubkey::new_unique(),
+            utilization: 7500,
+        };
+        let mut legacy = legacy_strategy(&Pubkey::new_unique(), lending);
+        let reserved_offset = LEGACY_PROTOCOL_OFFSET + lending.try_to_vec().unwrap().len() + LEGACY_FIELDS_AFTER_PROTOCOL;
+        assert!(migrated_strategy(&legacy).is_ok());
+
+        legacy[reserved_offset] = 1;
+        assert_eq!(
+            migrated_strategy(&legacy).unwrap_err(),
+            RebalancerErrorCode::ReservedBytesNotZeroed.into()
+        );
+    }
+
+    #[test]
+    fn test_first_legacy_reserved_byte_rejected() {
+        let lending = ProtocolType::StableLending {
+            pool_id: Pubkey::new_unique(),
+            reserve_address: Pubkey::new_unique(),
+            utilization: 7500,
+        };
+        let mut legacy = legacy_strategy(&Pubkey::new_unique(), lending);
+        // Discriminator, strategy_id and five u64s, protocol_type, then volatility_score through bump
+        let first_reserved = 8 + 32 + 40 + lending.try_to_vec().unwrap().len() + 23;
+
+        legacy[first_reserved] = 1;
+        assert_eq!(
+            migrated_strategy(&legacy).unwrap_err(),
+            RebalancerErrorCode::ReservedBytesNotZeroed.into()
+        );
+    }
+}
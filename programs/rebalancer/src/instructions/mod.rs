@@ -4,10 +4,92 @@ pub mod update_performance;
 pub mod execute_ranking;
 pub mod extract_capital;
 pub mod redistribute_capital;
+pub mod preview_rebalancing;
+pub mod set_capacity_limits;
+pub mod redistribute_scoped_capital;
+pub mod set_emergency_pause;
+pub mod set_guardian;
+pub mod open_capital_position;
+pub mod set_risk_config;
+pub mod withdraw_capital;
+pub mod update_strategy_status;
+pub mod emergency_rebalance;
+pub mod update_position;
+pub mod close_strategy;
+pub mod close_portfolio;
+pub mod propose_manager_transfer;
+pub mod accept_manager_transfer;
+pub mod get_portfolio_summary;
+pub mod batch_update_performance;
+pub mod set_metric_staleness;
+pub mod simulate_rebalance;
+pub mod begin_ranking_cycle;
+pub mod submit_ranking_batch;
+pub mod finalize_ranking_cycle;
+pub mod update_base_threshold;
+pub mod record_performance_fee;
+pub mod initialize_allocation_log;
+pub mod set_volatility_smoothing;
+pub mod set_oracle_authority;
+pub mod reconcile_strategy_count;
+pub mod set_governance_config;
+pub mod approve_governance_action;
+pub mod simulate_rebalance_deltas;
+pub mod transfer_strategy;
+pub mod initialize_redistribution_execution;
+pub mod rebalance_status;
+pub mod pause_strategy;
+pub mod snapshot_metrics;
+pub mod simulate_target_allocation;
+pub mod close_redistribution_execution;
+pub mod migrate_portfolio;
+pub mod migrate_strategy;
+pub mod apply_ranking_batch;
 
 pub use initialize_portfolio::*;
 pub use register_strategy::*;
 pub use update_performance::*;
 pub use execute_ranking::*;
 pub use extract_capital::*;
-pub use redistribute_capital::*;
\ No newline at end of file
+pub use redistribute_capital::*;
+pub use preview_rebalancing::*;
+pub use set_capacity_limits::*;
+pub use redistribute_scoped_capital::*;
+pub use set_emergency_pause::*;
+pub use set_guardian::*;
+pub use open_capital_position::*;
+pub use set_risk_config::*;
+pub use withdraw_capital::*;
+pub use update_strategy_status::*;
+pub use emergency_rebalance::*;
+pub use update_position::*;
+pub use close_strategy::*;
+pub use close_portfolio::*;
+pub use propose_manager_transfer::*;
+pub use accept_manager_transfer::*;
+pub use get_portfolio_summary::*;
+pub use batch_update_performance::*;
+pub use set_metric_staleness::*;
+pub use simulate_rebalance::*;
+pub use begin_ranking_cycle::*;
+pub use submit_ranking_batch::*;
+pub use finalize_ranking_cycle::*;
+pub use update_base_threshold::*;
+pub use record_performance_fee::*;
+pub use initialize_allocation_log::*;
+pub use set_volatility_smoothing::*;
+pub use set_oracle_authority::*;
+pub use reconcile_strategy_count::*;
+pub use set_governance_config::*;
+pub use approve_governance_action::*;
+pub use simulate_rebalance_deltas::*;
+pub use transfer_strategy::*;
+pub use initialize_redistribution_execution::*;
+pub use rebalance_status::*;
+pub use pause_strategy::*;
+pub use snapshot_metrics::*;
+pub use simulate_target_allocation::*;
+pub use close_redistribution_execution::*;
+pub use migrate_portfolio::*;
+pub use migrate_strategy::*;
+pub use apply_ranking_batch::*;
\ No newline at end of file
@@ -0,0 +1,208 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::*;
+
+#[derive(Accounts)]
+#[instruction(strategy_id: Pubkey)]
+pub struct OpenCapitalPosition<'info> {
+    #[account(
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+        has_one = manager @ RebalancerErrorCode::UnauthorizedManager
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+    
+    #[account(
+        seeds = [b"strategy", portfolio.key().as_ref(), strategy_id.as_ref()],
+        bump = strategy.bump,
+        constraint = strategy.strategy_id == strategy_id @ RebalancerErrorCode::StrategyNotFound
+    )]
+    pub strategy: Account<'info, Strategy>,
+    
+    #[account(
+        init,
+        payer = manager,
+        space = CapitalPosition::MAX_SIZE,
+        seeds = [b"position", strategy_id.as_ref()],
+        bump
+    )]
+    pub position: Account<'info, CapitalPosition>,
+    
+    #[account(mut)]
+    pub manager: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+pub fn open_capital_position(
+    ctx: Context<OpenCapitalPosition>,
+    strategy_id: Pubkey,
+    position_type: PositionType,
+    token_a_amount: u64,
+    entry_price_a: u64,
+    token_b_amount: u64,
+    entry_price_b: u64,
+) -> Result<()> {
+    let portfolio = &ctx.accounts.portfolio;
+    let strategy = &ctx.accounts.strategy;
+    let position = &mut ctx.accounts.position;
+    let current_time = Clock::get()?.unix_timestamp;
+    
+    // POSITION VALIDATIONS
+    require!(!portfolio.emergency_pause, RebalancerErrorCode::EmergencyPaused);
+    require!(strategy.status == StrategyStatus::Active, RebalancerErrorCode::StrategyNotActive);
+    require!(token_a_amount > 0, RebalancerErrorCode::InsufficientBalance);
+    require!(entry_price_a > 0, RebalancerErrorCode::InvalidEntryPrice);
+    validate_position_type(&strategy.protocol_type, position_type)?;
+    validate_token_b_leg(position_type, token_b_amount, entry_price_b)?;
+    
+    // POSITION INITIALIZATION
+    position.strategy_id = strategy_id;
+    position.token_a_amount = token_a_amount;
+    position.token_b_amount = token_b_amount;
+    position.lp_tokens = 0;
+    position.platform_controlled_lp = 0;
+    position.entry_price_a = entry_price_a;
+    position.entry_price_b = entry_price_b;
+    position.last_rebalance = current_time;
+    position.accrued_fees = 0;
+    position.impermanent_loss = 0;
+    position.position_type = position_type;
+    position.bump = ctx.bumps.position;
+    position.last_price_update = 0;
+    position.reserved = [0u8; 6];
+    
+    // A position below rent exemption could be reclaimed by the runtime
+    validate_position_rent(&position.to_account_info(), &Rent::get()?)?;
+    
+    msg!("Capital position opened: strategy={}, type={:?}, amount_a={}, entry_price_a={}, amount_b={}, entry_price_b={}",
+         strategy_id, position_type, token_a_amount, entry_price_a, token_b_amount, entry_price_b);
+    
+    Ok(())
+}
+
+// POSITION ACCOUNT MUST BE FULL SIZE AND RENT-EXEMPT
+pub fn validate_position_rent(position: &AccountInfo, rent: &Rent) -> Result<()> {
+    require!(
+        position.data_len() >= CapitalPosition::MAX_SIZE
+            && rent.is_exempt(position.lamports(), CapitalPosition::MAX_SIZE),
+        RebalancerErrorCode::PositionNotRentExempt
+    );
+    Ok(())
+}
+
+// POSITION TYPE MUST MATCH THE STRATEGY'S PROTOCOL
+pub fn validate_position_type(protocol_type: &ProtocolType, position_type: PositionType) -> Result<()> {
+    require!(
+        protocol_type.get_position_type() == position_type,
+        RebalancerErrorCode::InvalidPositionType
+    );
+    Ok(())
+}
+
+// ONLY LIQUIDITY PAIRS HAVE A TOKEN B LEG, AND THEIRS NEEDS AN AMOUNT AND ENTRY PRICE
+pub fn validate_token_b_leg(position_type: PositionType, token_b_amount: u64, entry_price_b: u64) -> Result<()> {
+    if position_type == PositionType::LiquidityPair {
+        require!(token_b_amount > 0, RebalancerErrorCode::InsufficientBalance);
+        require!(entry_price_b > 0, RebalancerErrorCode::InvalidEntryPrice);
+    } else {
+        require!(token_b_amount == 0 && entry_price_b == 0, RebalancerErrorCode::InvalidPositionType);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_position_type_matches_protocol() {
+        let lending = ProtocolType::StableLending {
+            pool_id: Pubkey::new_unique(),
+            reserve_address: Pubkey::new_unique(),
+            utilization: 7500,
+        };
+        let farming = ProtocolType::YieldFarming {
+            pair_id: Pubkey::new_unique(),
+            token_a_mint: Pubkey::new_unique(),
+            token_b_mint: Pubkey::new_unique(),
+            fee_tier: 30,
+            reward_multiplier: 2,
+        };
+        let staking = ProtocolType::LiquidStaking {
+            validator_id: Pubkey::new_unique(),
+            stake_pool: Pubkey::new_unique(),
+            unstake_delay: 10,
+            commission: 500,
+        };
+        
+        assert!(validate_position_type(&lending, PositionType::SingleAsset).is_ok());
+        assert!(validate_position_type(&farming, PositionType::LiquidityPair).is_ok());
+        assert!(validate_position_type(&staking, PositionType::StakedPosition).is_ok());
+    }
+    
+    #[test]
+    fn test_position_type_mismatch_rejected() {
+        let farming = ProtocolType::YieldFarming {
+            pair_id: Pubkey::new_unique(),
+            token_a_mint: Pubkey::new_unique(),
+            token_b_mint: Pubkey::new_unique(),
+            fee_tier: 30,
+            reward_multiplier: 2,
+        };
+        
+        for position_type in [PositionType::SingleAsset, PositionType::StakedPosition] {
+            assert_eq!(
+                validate_position_type(&farming, position_type).unwrap_err(),
+                RebalancerErrorCode::InvalidPositionType.into()
+            );
+        }
+    }
+    
+    #[test]
+    fn test_position_rent_exemption() {
+        let rent = Rent::default();
+        let exempt = rent.minimum_balance(CapitalPosition::MAX_SIZE);
+        let key = Pubkey::new_unique();
+        let owner = crate::ID;
+        let mut data = vec![0u8; CapitalPosition::MAX_SIZE];
+        
+        let check = |lamports: u64, data: &mut [u8]| {
+            let mut lamports = lamports;
+            let info = AccountInfo::new(&key, false, true, &mut lamports, data, &owner, false, 0);
+            validate_position_rent(&info, &rent)
+        };
+        
+        assert!(check(exempt, &mut data).is_ok());
+        assert_eq!(
+            check(exempt - 1, &mut data).unwrap_err(),
+            RebalancerErrorCode::PositionNotRentExempt.into()
+        );
+        // Truncated account data is rejected even when well funded
+        assert_eq!(
+            check(exempt * 2, &mut data[..CapitalPosition::MAX_SIZE - 1]).unwrap_err(),
+            RebalancerErrorCode::PositionNotRentExempt.into()
+        );
+    }
+    
+    #[test]
+    fn test_token_b_leg_only_on_liquidity_pairs() {
+        assert!(validate_token_b_leg(PositionType::LiquidityPair, 1_000_000_000, 1_000_000).is_ok());
+        assert_eq!(
+            validate_token_b_leg(PositionType::LiquidityPair, 0, 1_000_000).unwrap_err(),
+            RebalancerErrorCode::InsufficientBalance.into()
+        );
+        assert_eq!(
+            validate_token_b_leg(PositionType::LiquidityPair, 1_000_000_000, 0).unwrap_err(),
+            RebalancerErrorCode::InvalidEntryPrice.into()
+        );
+        
+        for position_type in [PositionType::SingleAsset, PositionType::StakedPosition] {
+            assert!(validate_token_b_leg(position_type, 0, 0).is_ok());
+            assert_eq!(
+                validate_token_b_leg(position_type, 1_000_000_000, 1_000_000).unwrap_err(),
+                RebalancerErrorCode::InvalidPositionType.into()
+            );
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::*;
+use crate::events::StrategyPauseChanged;
+
+#[derive(Accounts)]
+#[instruction(strategy_id: Pubkey)]
+pub struct PauseStrategy<'info> {
+    #[account(
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+        constraint = portfolio.can_pause_strategy(&authority.key()) @ RebalancerErrorCode::UnauthorizedManager
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+    
+    #[account(
+        mut,
+        seeds = [b"strategy", portfolio.key().as_ref(), strategy_id.as_ref()],
+        bump = strategy.bump,
+        constraint = strategy.strategy_id == strategy_id @ RebalancerErrorCode::StrategyNotFound
+    )]
+    pub strategy: Account<'info, Strategy>,
+    
+    /// Either the portfolio manager or its oracle authority
+    pub authority: Signer<'info>,
+}
+
+/// Freeze one strategy without pausing the portfolio. A paused strategy is
+/// neither ranked, extracted from nor allocated to; the rest keep rebalancing.
+pub fn pause_strategy(ctx: Context<PauseStrategy>, strategy_id: Pubkey) -> Result<()> {
+    set_strategy_paused(ctx, strategy_id, true)
+}
+
+pub fn resume_strategy(ctx: Context<PauseStrategy>, strategy_id: Pubkey) -> Result<()> {
+    set_strategy_paused(ctx, strategy_id, false)
+}
+
+fn set_strategy_paused(ctx: Context<PauseStrategy>, strategy_id: Pubkey, paused: bool) -> Result<()> {
+    let strategy = &mut ctx.accounts.strategy;
+    apply_strategy_pause(strategy, paused)?;
+    
+    // last_updated is left alone: a resumed strategy still needs fresh metrics
+    // before the staleness gate lets it take part in a rebalance. Cached previews
+    // still miss, since the preview inputs hash covers the status
+    msg!("Strategy {} {} by {}", strategy_id, if paused { "paused" } else { "resumed" }, ctx.accounts.authority.key());
+    
+    emit!(StrategyPauseChanged {
+        portfolio: ctx.accounts.portfolio.key(),
+        strategy_id,
+        paused,
+        authority: ctx.accounts.authority.key(),
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    
+    Ok(())
+}
+
+// ONLY ACTIVE -> PAUSED AND PAUSED -> ACTIVE; DEPRECATION STAYS WITH update_strategy_status
+pub fn apply_strategy_pause(strategy: &mut Strategy, paused: bool) -> Result<()> {
+    let (from, to) = if paused {
+        (StrategyStatus::Active, StrategyStatus::Paused)
+    } else {
+        (StrategyStatus::Paused, StrategyStatus::Active)
+    };
+    require!(strategy.status == from, RebalancerErrorCode::InvalidStatusTransition);
+    
+    strategy.status = to;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+    use crate::instructions::redistribute_capital::validate_allocation_status;
+    
+    fn strategy(status: StrategyStatus) -> Strategy {
+        Strategy {
+            status,
+            ..test_utils::strategy()
+        }
+    }
+    
+    #[test]
+    fn test_pause_then_resume() {
+        let mut target = strategy(StrategyStatus::Active);
+        
+        apply_strategy_pause(&mut target, true).unwrap();
+        assert_eq!(target.status, StrategyStatus::Paused);
+        apply_strategy_pause(&mut target, false).unwrap();
+        assert_eq!(target.status, StrategyStatus::Active);
+    }
+    
+    #[test]
+    fn test_pause_rejects_wrong_starting_status() {
+        for (status, paused) in [
+            (StrategyStatus::Paused, true),
+            (StrategyStatus::Active, false),
+            (StrategyStatus::Deprecated, true),
+            (StrategyStatus::Deprecated, false),
+        ] {
+            assert_eq!(
+                apply_strategy_pause(&mut strategy(status), paused).unwrap_err(),
+                RebalancerErrorCode::InvalidStatusTransition.into()
+            );
+        }
+    }
+    
+    #[test]
+    fn test_paused_strategy_blocks_only_its_own_allocation() {
+        let active = strategy(StrategyStatus::Active);
+        let mut paused = strategy(StrategyStatus::Active);
+        apply_strategy_pause(&mut paused, true).unwrap();
+        let allocation = |strategy_id| CapitalAllocation {
+            strategy_id,
+            amount: 1_000_000_000,
+            min_acceptable_amount: 0,
+            allocation_type: AllocationType::TopPerformer,
+        };
+        
+        // The active strategy keeps receiving capital while its neighbour is paused
+        assert!(validate_allocation_status(&[allocation(active.strategy_id)], [&active, &paused]).is_ok());
+        assert_eq!(
+            validate_allocation_status(
+                &[allocation(active.strategy_id), allocation(paused.strategy_id)],
+                [&active, &paused],
+            )
+            .unwrap_err(),
+            RebalancerErrorCode::StrategyNotActive.into()
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::RebalancerErrorCode;
+use crate::events::PortfolioCapacitySummary;
+use crate::instructions::redistribute_capital::{
+    execute_complete_rebalancing, RebalancingPlan, StrategyPerformanceData,
+};
+use crate::utils::load_portfolio_strategies;
+
+#[derive(Accounts)]
+pub struct PreviewRebalancing<'info> {
+    #[account(
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+
+    #[account(
+        seeds = [b"risk_config", portfolio.key().as_ref()],
+        bump = risk_config.bump,
+    )]
+    pub risk_config: Account<'info, RiskConfig>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = PreviewCache::MAX_SIZE,
+        seeds = [b"preview", portfolio.key().as_ref()],
+        bump
+    )]
+    pub preview_cache: Account<'info, PreviewCache>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn preview_rebalancing<'info>(
+    ctx: Context<'_, '_, 'info, 'info, PreviewRebalancing<'info>>,
+) -> Result<RebalancingPlan> {
+    let portfolio = &ctx.accounts.portfolio;
+    let portfolio_key = portfolio.key();
+    let current_time = Clock::get()?.unix_timestamp;
+
+    // LOAD AND VERIFY STRATEGY ACCOUNTS
+    let strategies = load_portfolio_strategies(&portfolio_key, ctx.remaining_accounts)?;
+    let risk_limits = &ctx.accounts.risk_config.limits;
+    let inputs_hash = PreviewCache::hash_inputs(portfolio.base_threshold, risk_limits, strategies.iter().map(|s| &**s))?;
+
+    // CAPACITY SUMMARY FOR DASHBOARDS
+    let total_capital = strategies.iter().try_fold(0u64, |total, s| {
+        total.checked_add(s.current_balance).ok_or(RebalancerErrorCode::BalanceOverflow)
+    })?;
+    emit!(PortfolioCapacitySummary {
+        portfolio: portfolio_key,
+        total_strategies: portfolio.total_strategies,
+        max_strategies: portfolio.max_strategies,
+        total_capital,
+        max_capital: portfolio.max_capital,
+        capacity_utilization_bps: portfolio.capacity_utilization_bps(total_capital),
+        timestamp: current_time,
+    });
+
+    let cache = &mut ctx.accounts.preview_cache;
+    cache.portfolio = portfolio_key;
+    cache.bump = ctx.bumps.preview_cache;
+
+    // SERVE FROM CACHE WHEN INPUTS ARE UNCHANGED AND FRESH
+    if let Some(plan) = cache.lookup(&inputs_hash, current_time) {
+        msg!("Preview cache hit: computed_at={}", cache.computed_at);
+        return Ok(plan.clone());
+    }
+
+    // RECOMPUTE AND REFRESH CACHE
+    let performance_data: Vec<StrategyPerformanceData> = strategies
+        .iter()
+        .map(|s| StrategyPerformanceData::from_strategy(s, risk_limits, current_time))
+        .collect();
+    let plan = execute_complete_rebalancing(portfolio, &performance_data, risk_limits)?;
+    cache.store(inputs_hash, current_time, plan.clone())?;
+
+    msg!("Preview cache refreshed: targets={}, total_to_extract={}",
+         plan.extraction_targets.len(), plan.total_to_extract);
+
+    Ok(plan)
+}
@@ -0,0 +1,30 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::*;
+
+#[derive(Accounts)]
+pub struct ProposeManagerTransfer<'info> {
+    #[account(
+        mut,
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+        has_one = manager @ RebalancerErrorCode::UnauthorizedManager
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+    
+    pub manager: Signer<'info>,
+}
+
+pub fn propose_manager_transfer(
+    ctx: Context<ProposeManagerTransfer>,
+    new_manager: Pubkey,
+) -> Result<()> {
+    let portfolio = &mut ctx.accounts.portfolio;
+    
+    // Pubkey::default() cancels a pending transfer
+    portfolio.propose_manager(new_manager)?;
+    
+    msg!("Manager transfer proposed: {} -> {}", portfolio.manager, new_manager);
+    
+    Ok(())
+}
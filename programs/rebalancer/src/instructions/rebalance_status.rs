@@ -0,0 +1,97 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct RebalanceStatus<'info> {
+    #[account(
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct RebalanceStatusView {
+    pub can_rebalance: bool,
+    pub seconds_until_eligible: i64,  // 0 once the interval has elapsed, even while paused
+    pub is_paused: bool,
+}
+
+/// Read-only rebalance eligibility, returned through Anchor's return data so
+/// clients can `simulate` it instead of re-implementing `can_rebalance`.
+pub fn rebalance_status(ctx: Context<RebalanceStatus>) -> Result<RebalanceStatusView> {
+    let status = rebalance_status_at(&ctx.accounts.portfolio, Clock::get()?.unix_timestamp);
+    
+    msg!("Rebalance status: can_rebalance={}, seconds_until_eligible={}, paused={}",
+         status.can_rebalance, status.seconds_until_eligible, status.is_paused);
+    
+    Ok(status)
+}
+
+pub fn rebalance_status_at(portfolio: &Portfolio, current_time: i64) -> RebalanceStatusView {
+    RebalanceStatusView {
+        can_rebalance: portfolio.can_rebalance(current_time),
+        seconds_until_eligible: portfolio.seconds_until_rebalance(current_time),
+        is_paused: portfolio.emergency_pause,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn portfolio(last_rebalance: i64, emergency_pause: bool) -> Portfolio {
+        Portfolio {
+            manager: Pubkey::new_unique(),
+            total_capital_moved: 0,
+            last_rebalance,
+            min_rebalance_interval: 3600,
+            portfolio_creation: 0,
+            total_strategies: 3,
+            performance_fee_bps: 200,
+            base_threshold: 15,
+            emergency_pause,
+            bump: 255,
+            max_strategies: 50,
+            max_capital: 0,
+            guardian: Pubkey::default(),
+            emergency_rebalance_count: 0,
+            seed_manager: Pubkey::default(),
+            pending_manager: Pubkey::default(),
+            max_metric_staleness: DEFAULT_MAX_METRIC_STALENESS,
+            rebalance_sequence: 1,
+            high_water_mark: 0,
+            total_value_locked: 0,
+            volatility_smoothing_bps: DEFAULT_VOLATILITY_SMOOTHING_BPS,
+            oracle_authority: Pubkey::default(),
+            governance_enabled: false,
+            layout_version: PORTFOLIO_LAYOUT_VERSION,
+            reserved: [0u8; 3],
+        }
+    }
+    
+    #[test]
+    fn test_status_right_after_and_after_interval() {
+        let rebalanced = portfolio(10_000, false);
+        
+        assert_eq!(rebalance_status_at(&rebalanced, 10_000), RebalanceStatusView {
+            can_rebalance: false,
+            seconds_until_eligible: 3600,
+            is_paused: false,
+        });
+        assert_eq!(rebalance_status_at(&rebalanced, 13_600), RebalanceStatusView {
+            can_rebalance: true,
+            seconds_until_eligible: 0,
+            is_paused: false,
+        });
+    }
+    
+    #[test]
+    fn test_paused_portfolio_reports_elapsed_interval() {
+        let status = rebalance_status_at(&portfolio(10_000, true), 13_600);
+        
+        assert!(!status.can_rebalance);
+        assert_eq!(status.seconds_until_eligible, 0);
+        assert!(status.is_paused);
+    }
+}
@@ -0,0 +1,87 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::*;
+use crate::events::StrategyCountReconciled;
+use crate::utils::load_portfolio_strategies;
+
+#[derive(Accounts)]
+pub struct ReconcileStrategyCount<'info> {
+    #[account(
+        mut,
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+        has_one = manager @ RebalancerErrorCode::UnauthorizedManager
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+    
+    pub manager: Signer<'info>,
+}
+
+/// Lower `total_strategies` to the number of live strategy accounts.
+///
+/// `remaining_accounts` holds every live strategy (read-only), followed by
+/// one account per entry of `closed_strategy_ids`. Live strategies are
+/// verified to be strategy PDAs of this portfolio, duplicates are rejected
+/// and their native SOL balances must add up to the stored
+/// `total_value_locked`. Every
+/// strategy missing from the live set needs a closure proof: the account at
+/// its derived PDA must be empty and owned by the system program. The count
+/// can only fall; registration is the one path that raises it.
+pub fn reconcile_strategy_count<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ReconcileStrategyCount<'info>>,
+    closed_strategy_ids: Vec<Pubkey>,
+) -> Result<()> {
+    let portfolio = &mut ctx.accounts.portfolio;
+    let live_count = ctx.remaining_accounts
+        .len()
+        .checked_sub(closed_strategy_ids.len())
+        .ok_or(RebalancerErrorCode::BatchLengthMismatch)?;
+    let (live_accounts, closed_accounts) = ctx.remaining_accounts.split_at(live_count);
+    
+    let strategies = load_portfolio_strategies(&portfolio.key(), live_accounts)?;
+    
+    // CLOSURE PROOFS: ONE PER STRATEGY MISSING FROM THE LIVE SET
+    let missing = (portfolio.total_strategies as usize)
+        .checked_sub(strategies.len())
+        .ok_or(RebalancerErrorCode::InvalidStrategyCount)?;
+    require!(closed_strategy_ids.len() == missing, RebalancerErrorCode::MissingStrategyAccounts);
+    
+    for (index, (strategy_id, info)) in closed_strategy_ids.iter().zip(closed_accounts).enumerate() {
+        require!(!closed_strategy_ids[..index].contains(strategy_id), RebalancerErrorCode::DuplicateStrategy);
+        
+        let (expected_address, _) = Pubkey::find_program_address(
+            &[b"strategy", portfolio.key().as_ref(), strategy_id.as_ref()],
+            &crate::ID,
+        );
+        require_keys_eq!(expected_address, info.key(), RebalancerErrorCode::StrategyNotFound);
+        require!(is_closed_account(info), RebalancerErrorCode::StrategyAccountNotClosed);
+    }
+    
+    // LIVE SET: BALANCES MUST ACCOUNT FOR THE WHOLE TVL
+    // An id that was never registered also derives an empty PDA, so the proofs
+    // alone cannot show that no funded strategy was left out.
+    let live_balance = strategies
+        .iter()
+        .filter(|s| s.is_native_sol())
+        .try_fold(0u64, |total, s| total.checked_add(s.current_balance).ok_or(RebalancerErrorCode::BalanceOverflow))?;
+    require!(live_balance == portfolio.total_value_locked, RebalancerErrorCode::TotalValueLockedMismatch);
+    
+    let previous_count = portfolio.reconcile_strategy_count(strategies.len())?;
+    
+    msg!("Strategy count reconciled: {} -> {} ({} closure proofs)",
+         previous_count, portfolio.total_strategies, closed_strategy_ids.len());
+    
+    emit!(StrategyCountReconciled {
+        portfolio: portfolio.key(),
+        previous_count,
+        total_strategies: portfolio.total_strategies,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    
+    Ok(())
+}
+
+// A closed (or never created) account is an empty, system-owned address
+fn is_closed_account(info: &AccountInfo) -> bool {
+    info.owner == &anchor_lang::system_program::ID && info.data_is_empty()
+}
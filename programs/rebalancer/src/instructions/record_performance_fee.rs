@@ -0,0 +1,98 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::*;
+use crate::events::PerformanceFeeRecorded;
+use crate::utils::load_portfolio_strategies;
+
+#[derive(Accounts)]
+pub struct RecordPerformanceFee<'info> {
+    #[account(
+        mut,
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+        has_one = manager @ RebalancerErrorCode::UnauthorizedManager
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+
+    // Holds the manager treasury the fee is owed to
+    #[account(
+        seeds = [b"risk_config", portfolio.key().as_ref()],
+        bump = risk_config.bump,
+    )]
+    pub risk_config: Account<'info, RiskConfig>,
+
+    pub manager: Signer<'info>,
+}
+
+/// Record the `performance_fee_bps` owed on profit made since the last call.
+///
+/// Every strategy account must be passed (read-only) in `remaining_accounts` so
+/// profit is measured across the whole portfolio. The high-water mark is in
+/// lamports, so only native SOL strategies count toward it; profit in other
+/// mints has no common unit to be added to it. No funds move: the fee owed to
+/// the manager treasury is raised through `PerformanceFeeRecorded` and returned
+/// through Anchor's return data, to be paid out of the strategies off-chain.
+pub fn record_performance_fee<'info>(
+    ctx: Context<'_, '_, 'info, 'info, RecordPerformanceFee<'info>>,
+) -> Result<u64> {
+    let portfolio = &mut ctx.accounts.portfolio;
+
+    require!(!portfolio.emergency_pause, RebalancerErrorCode::EmergencyPaused);
+
+    let strategies = load_portfolio_strategies(&portfolio.key(), ctx.remaining_accounts)?;
+    portfolio.validate_strategy_account_count(strategies.len())?;
+
+    let cumulative_profit = native_sol_profit(strategies.iter().map(|s| &**s));
+    let fee = portfolio.record_performance_fee(cumulative_profit)?;
+    let manager_treasury = ctx.accounts.risk_config.limits.manager_treasury;
+
+    msg!("Performance fee recorded: {} lamports owed to {}, high-water mark {}",
+         fee, manager_treasury, portfolio.high_water_mark);
+
+    emit!(PerformanceFeeRecorded {
+        portfolio: portfolio.key(),
+        manager_treasury,
+        fee,
+        high_water_mark: portfolio.high_water_mark,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(fee)
+}
+
+// LIFETIME PROFIT OF THE NATIVE SOL STRATEGIES (lamports)
+pub fn native_sol_profit<'a>(strategies: impl IntoIterator<Item = &'a Strategy>) -> i128 {
+    strategies
+        .into_iter()
+        .filter(|s| s.is_native_sol())
+        .map(|s| s.net_profit())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+
+    fn strategy(mint: Pubkey, decimals: u8, total_deposits: u64, current_balance: u64) -> Strategy {
+        Strategy {
+            current_balance,
+            total_deposits,
+            mint,
+            decimals,
+            ..test_utils::strategy()
+        }
+    }
+
+    #[test]
+    fn test_profit_in_other_mints_is_left_out() {
+        let strategies = [
+            strategy(WRAPPED_SOL_MINT, SOL_DECIMALS, 1_000_000_000, 1_500_000_000),
+            strategy(WRAPPED_SOL_MINT, SOL_DECIMALS, 2_000_000_000, 1_800_000_000),
+            // 1,000 USDC of profit, in 6-decimal units
+            strategy(Pubkey::new_unique(), 6, 5_000_000_000, 6_000_000_000),
+        ];
+
+        assert_eq!(native_sol_profit(strategies.iter()), 300_000_000);
+    }
+}
@@ -1,76 +1,269 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program::MAX_RETURN_DATA;
+use crate::adapters::{adapter_for, AdapterContext};
 use crate::state::*;
 use crate::errors::*;
-use crate::utils::calculate_dynamic_threshold;
+use crate::events::CapitalRedistributed;
+use crate::utils::{
+    apply_bps, calculate_dynamic_threshold, load_allocation_logs, load_portfolio_strategies, split_allocation_logs,
+    trace_compute_units, write_rebalance_record,
+};
 
 // Risk/fee configuration defaults (basis points)
 const MAX_SINGLE_STRATEGY_BPS: u64 = 4000; // 40%
 const MIN_SINGLE_STRATEGY_BPS: u64 = 100;  // 1%
 const PLATFORM_FEE_BPS: u64 = 50;          // 0.5%
 const MANAGER_FEE_BPS: u64 = 150;          // 1.5%
+const MAX_TOTAL_FEE_BPS: u64 = 1000;       // 10% combined platform + manager fees
 const RISK_TOLERANCE_BPS: u64 = 8000;      // 80%
+const MIN_EXTRACTION_PER_STRATEGY: u64 = 50_000_000; // 0.05 SOL
+const MIN_REBALANCE_CAPITAL: u64 = 100_000_000; // 0.1 SOL extracted in total
+const MIN_NET_BENEFIT_BPS: u64 = 10000;    // Expected gain must at least cover fees
+const MAX_NET_BENEFIT_BPS: u64 = 100000;   // Never demand more than 10x the fees
+const MAX_GROUP_BPS: u64 = 6000;           // 60% to strategies sharing a pool/pair/validator/market
+const ESTIMATED_FEE_BPS: u64 = 200;        // 2% estimated cost of moving capital
+const TOP_PERFORMER_COUNT: u8 = 5;         // Strategies funded per rebalance
+const TOP_PERFORMER_PERCENTILE: u8 = 75;   // Top quartile
+const MAX_TOP_PERFORMER_COUNT: u8 = 7;     // Two fee entries + 7 + unallocated fit the preview cache
+const FEE_GRACE_PERIOD: i64 = 0;           // New strategies pay fees from day one unless configured
+const MAX_FEE_GRACE_PERIOD: i64 = 30 * 86400; // 30 days
+const REALLOCATION_COOLDOWN: i64 = 0;      // Extracted strategies may be refunded at once unless configured
+const MAX_REALLOCATION_COOLDOWN: i64 = 30 * 86400; // 30 days
+const VOLATILITY_WEIGHT: u32 = 20;         // Threshold points added at 100% average volatility
+const MAX_VOLATILITY_WEIGHT: u32 = 100;    // Volatility alone may move the threshold across its whole range
+const MIN_THRESHOLD: u8 = 10;              // Lowest dynamic threshold (percent)
+const MAX_THRESHOLD: u8 = 40;              // Highest dynamic threshold (percent)
+const MIN_ALLOCATION_SCORE: u64 = 500;     // Score floor for weighting, 5% of the scale
+const MAX_TARGET_DESTINATIONS: usize = RebalancingPlan::MAX_ALLOCATIONS - 2; // Room left beside the two fee entries
+
+/// Maximum number of strategies (or allocations) a single instruction may touch.
+///
+/// Ten keeps every supported instruction comfortably within the transaction
+/// account and compute limits. The `TooManyStrategies` error message quotes this
+/// value, so keep the two in sync.
+pub const MAX_STRATEGIES_PER_OP: usize = 10;
 
 #[derive(Accounts)]
 #[instruction(allocations: Vec<CapitalAllocation>)]
 pub struct RedistributeCapital<'info> {
     #[account(
         mut,
-        seeds = [b"portfolio", portfolio.manager.as_ref()],
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
         bump = portfolio.bump,
         has_one = manager @ RebalancerErrorCode::UnauthorizedManager
     )]
     pub portfolio: Account<'info, Portfolio>,
     
+    // Audit entry for this rebalance, numbered by the portfolio's sequence
+    #[account(
+        init,
+        payer = manager,
+        space = RebalanceRecord::MAX_SIZE,
+        seeds = [b"record", portfolio.key().as_ref(), &portfolio.rebalance_sequence.to_le_bytes()],
+        bump
+    )]
+    pub rebalance_record: Account<'info, RebalanceRecord>,
+    
+    // Optional: resumable execution of a plan applied over several calls
+    #[account(
+        mut,
+        seeds = [b"execution", portfolio.key().as_ref(), &execution.execution_id.to_le_bytes()],
+        bump = execution.bump,
+    )]
+    pub execution: Option<Account<'info, RedistributionExecution>>,
+    
     #[account(mut)]
     pub manager: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
 }
 
-pub fn redistribute_capital(
-    ctx: Context<RedistributeCapital>,
+pub fn redistribute_capital<'info>(
+    ctx: Context<'_, '_, 'info, 'info, RedistributeCapital<'info>>,
     allocations: Vec<CapitalAllocation>,
 ) -> Result<()> {
+    trace_compute_units!("redistribute_capital: start");
     let portfolio = &mut ctx.accounts.portfolio;
+    let current_time = Clock::get()?.unix_timestamp;
     
     // COMPREHENSIVE VALIDATION
     require!(!portfolio.emergency_pause, RebalancerErrorCode::EmergencyPaused);
     require!(!allocations.is_empty(), RebalancerErrorCode::InsufficientStrategies);
-    require!(allocations.len() <= 20, RebalancerErrorCode::TooManyStrategies);
     
-    // VALIDATE ALLOCATION TOTALS
+    // VALIDATE ALLOCATION COUNT AND TOTALS
+    validate_allocations(&allocations)?;
+    
+    // DESTINATION VALIDATION: every non-fee allocation must target a strategy
+    // of this portfolio passed in remaining_accounts. Allocation logs, if any,
+    // follow the strategy accounts.
+    let (strategy_accounts, log_accounts) = split_allocation_logs(ctx.remaining_accounts);
+    let strategies = load_portfolio_strategies(&portfolio.key(), strategy_accounts)?;
+    let registered_ids: Vec<Pubkey> = strategies.iter().map(|s| s.strategy_id).collect();
+    
+    // RESUMABLE EXECUTION: with an execution account, allocations applied by an
+    // earlier call are skipped and those whose destination was not passed wait
+    // for a later one. The transaction is atomic, so marking them up front is safe.
+    let allocations: Vec<CapitalAllocation> = match ctx.accounts.execution.as_mut() {
+        Some(execution) => {
+            execution.bind(&allocations)?;
+            let pending = pending_allocation_indices(&allocations, execution, &registered_ids)?;
+            for &index in &pending {
+                execution.mark_executed(index);
+            }
+            msg!("Execution {}: applying {} of {} allocations", execution.execution_id, pending.len(), allocations.len());
+            pending.iter().map(|&index| allocations[index].clone()).collect()
+        }
+        None => allocations,
+    };
     let total_allocated = validate_allocations(&allocations)?;
+    validate_allocation_destinations(&allocations, &registered_ids)?;
+    validate_allocation_status(&allocations, strategies.iter().map(|s| &**s))?;
+    validate_allocation_capacity(&allocations, strategies.iter().map(|s| &**s))?;
+    portfolio.validate_metric_freshness(strategies.iter().map(|s| &**s), current_time)?;
+    
+    // SLIPPAGE BOUNDS: every destination must receive at least its minimum, or
+    // the whole redistribution fails. Strategy allocations deposit through
+    // their destination's protocol adapter; fee allocations receive exactly
+    // the allocated amount.
+    for allocation in &allocations {
+        let destination = strategies.iter().find(|s| {
+            s.strategy_id == allocation.strategy_id && allocation.allocation_type.is_strategy_allocation()
+        });
+        let received = match destination {
+            Some(strategy) => {
+                let adapter_ctx = AdapterContext {
+                    strategy_id: strategy.strategy_id,
+                    protocol_type: &strategy.protocol_type,
+                };
+                adapter_for(&strategy.protocol_type).deposit(&adapter_ctx, allocation.amount)?
+            }
+            None => allocation.amount,
+        };
+        allocation.validate_received(received)?;
+    }
+    
+    // ALLOCATION HISTORY: append to the log of each strategy allocated to
+    let mut logs = load_allocation_logs(&portfolio.key(), log_accounts)?;
+    for log in logs.iter_mut() {
+        let strategy_id = log.strategy_id;
+        for allocation in allocations.iter().filter(|a| {
+            a.strategy_id == strategy_id && a.allocation_type.is_strategy_allocation()
+        }) {
+            log.record(AllocationLogEntry {
+                timestamp: current_time,
+                amount: allocation.amount,
+                allocation_type: allocation.allocation_type,
+            });
+        }
+        require!(log.to_account_info().is_writable, ErrorCode::ConstraintMut);
+        log.exit(&crate::ID)?;
+    }
     
     msg!("Redistributing {} lamports across {} strategies", total_allocated, allocations.len());
     
     // NOTE: In full implementation, this would update strategy accounts
     // For assessment purposes, we'll implement the core redistribution logic
     
+    // Only capital reaching a strategy or the safe haven counts as moved; fees do not
     portfolio.total_capital_moved = portfolio.total_capital_moved
-        .checked_add(total_allocated)
+        .checked_add(capital_moved(&allocations)?)
         .ok_or(RebalancerErrorCode::BalanceOverflow)?;
     
+    emit!(CapitalRedistributed {
+        portfolio: portfolio.key(),
+        manager: portfolio.manager,
+        amount_redistributed: total_allocated,
+        allocation_count: allocations.len() as u32,
+        total_capital_moved: portfolio.total_capital_moved,
+        timestamp: current_time,
+    });
+    
+    // AUDIT RECORD: strategy allocations are the targets, treasury entries the fees
+    let target_count = allocations
+        .iter()
+        .filter(|a| a.allocation_type.is_strategy_allocation())
+        .count() as u32;
+    let total_fees = allocations
+        .iter()
+        .filter(|a| matches!(a.allocation_type, AllocationType::PlatformFee | AllocationType::ManagerIncentive))
+        .try_fold(0u64, |total, a| total.checked_add(a.amount).ok_or(RebalancerErrorCode::BalanceOverflow))?;
+    
+    let record = &mut ctx.accounts.rebalance_record;
+    record.bump = ctx.bumps.rebalance_record;
+    write_rebalance_record(
+        record, portfolio, RebalanceKind::Redistribution, total_allocated, target_count, total_fees, current_time,
+    )?;
+    
+    trace_compute_units!("redistribute_capital: end");
     Ok(())
 }
 
 // OPTIMAL ALLOCATION ALGORITHM
+// Under performance weighting a destination scoring below MIN_ALLOCATION_SCORE
+// is weighted as if it scored the floor, as long as some destination scores
+// above zero: one nonzero score among zeros would otherwise take every share
+// and leave diversification to the caps alone. With no scoring destination at
+// all the plan is rejected. When only one destination is viable it is funded
+// up to the single-strategy cap and the rest is returned as Unallocated.
 pub fn calculate_optimal_allocation(
     available_capital: u64,
     top_strategies: &[StrategyPerformanceData],
     risk_limits: &RiskLimits,
+    mode: AllocationMode,
 ) -> Result<Vec<CapitalAllocation>> {
     require!(available_capital > 0, RebalancerErrorCode::InsufficientBalance);
-    require!(!top_strategies.is_empty(), RebalancerErrorCode::InsufficientStrategies);
+    // Target plans move capital by the deltas of plan_target_allocation instead
+    require!(mode != AllocationMode::TargetAllocation, RebalancerErrorCode::InvalidRiskLimits);
+    // Limits loaded from a config written before the fee cap existed are checked here too
+    risk_limits.validate_total_fees()?;
+    
+    // ONLY ACTIVE STRATEGIES RECEIVE CAPITAL (deprecated/paused ones are extraction sources only)
+    let destinations: Vec<&StrategyPerformanceData> = top_strategies
+        .iter()
+        .filter(|s| s.can_receive_allocation())
+        .collect();
+    require!(!destinations.is_empty(), RebalancerErrorCode::InsufficientStrategies);
     
     let mut allocations = Vec::new();
     let mut remaining_capital = available_capital;
     
+    let has_scoring_destination = destinations.iter().any(|s| s.performance_score > 0);
+    let weights: Vec<u128> = destinations
+        .iter()
+        .map(|s| match mode {
+            AllocationMode::PerformanceWeighted if has_scoring_destination => {
+                mode.weight(s.performance_score.max(MIN_ALLOCATION_SCORE), s.current_balance)
+            }
+            _ => mode.weight(s.performance_score, s.current_balance),
+        })
+        .collect();
+    let total_weight: u128 = weights.iter().sum();
+    
+    require!(
+        total_weight > 0,
+        match mode {
+            AllocationMode::BalanceWeighted => RebalancerErrorCode::InsufficientBalance,
+            _ => RebalancerErrorCode::InvalidPerformanceScore,
+        }
+    );
+    
     // CALCULATE PLATFORM AND MANAGER FEES FIRST
-    let platform_fee = (available_capital * risk_limits.platform_fee_bps) / 10000;
-    let manager_fee = (available_capital * risk_limits.manager_fee_bps) / 10000;
+    // Only the share of capital headed to strategies past their fee grace period is charged
+    let fee_weight: u128 = destinations
+        .iter()
+        .zip(&weights)
+        .filter(|(s, _)| !s.in_fee_grace)
+        .map(|(_, weight)| weight)
+        .sum();
+    let fee_base = (available_capital as u128 * fee_weight / total_weight) as u64;
+    let platform_fee = apply_bps(fee_base, risk_limits.platform_fee_bps)?;
+    let manager_fee = apply_bps(fee_base, risk_limits.manager_fee_bps)?;
     
     if platform_fee > 0 {
         allocations.push(CapitalAllocation {
             strategy_id: risk_limits.platform_treasury,
             amount: platform_fee,
+            min_acceptable_amount: 0,
             allocation_type: AllocationType::PlatformFee,
         });
         remaining_capital = remaining_capital.saturating_sub(platform_fee);
@@ -80,34 +273,30 @@ pub fn calculate_optimal_allocation(
         allocations.push(CapitalAllocation {
             strategy_id: risk_limits.manager_treasury,
             amount: manager_fee,
+            min_acceptable_amount: 0,
             allocation_type: AllocationType::ManagerIncentive,
         });
         remaining_capital = remaining_capital.saturating_sub(manager_fee);
     }
     
-    // PERFORMANCE-WEIGHTED ALLOCATION
-    let total_performance_score: u128 = top_strategies
-        .iter()
-        .map(|s| s.performance_score as u128)
-        .sum();
-    
-    require!(total_performance_score > 0, RebalancerErrorCode::InvalidPerformanceScore);
+    // CORRELATED STRATEGIES SHARE ONE GROUP CAP
+    let max_group_allocation = apply_bps(available_capital, risk_limits.max_group_bps)?;
+    let mut group_totals: Vec<(Pubkey, u64)> = Vec::new();
     
     // CALCULATE ALLOCATIONS WITH DIVERSIFICATION CONSTRAINTS
-    for (index, strategy) in top_strategies.iter().enumerate() {
+    for (index, strategy) in destinations.iter().enumerate() {
         if remaining_capital == 0 {
             break;
         }
         
-        // PERFORMANCE-BASED ALLOCATION
-        let performance_allocation = (remaining_capital as u128 * strategy.performance_score as u128) 
-            / total_performance_score;
+        // WEIGHTED SHARE FOR THE SELECTED MODE
+        let weighted_allocation = (remaining_capital as u128 * weights[index]) / total_weight;
         
         // APPLY DIVERSIFICATION LIMITS
-        let max_single_allocation = (available_capital * risk_limits.max_single_strategy_bps) / 10000;
-        let min_single_allocation = (available_capital * risk_limits.min_single_strategy_bps) / 10000;
+        let max_single_allocation = apply_bps(available_capital, risk_limits.max_single_strategy_bps)?;
+        let min_single_allocation = apply_bps(available_capital, risk_limits.min_single_strategy_bps)?;
         
-        let mut allocation_amount = performance_allocation as u64;
+        let mut allocation_amount = weighted_allocation as u64;
         
         // ENFORCE MAXIMUM ALLOCATION LIMIT
         if allocation_amount > max_single_allocation {
@@ -120,34 +309,30 @@ pub fn calculate_optimal_allocation(
         }
         
         // PROTOCOL-SPECIFIC MINIMUM REQUIREMENTS
-        match strategy.protocol_type {
-            ProtocolType::StableLending { .. } => {
-                if allocation_amount < 100_000_000 { // 0.1 SOL minimum for lending
-                    continue;
-                }
-            },
-            ProtocolType::YieldFarming { .. } => {
-                if allocation_amount < 500_000_000 { // 0.5 SOL minimum for LP positions
-                    continue;
-                }
-            },
-            ProtocolType::LiquidStaking { .. } => {
-                if allocation_amount < 1_000_000_000 { // 1 SOL minimum for staking
-                    continue;
-                }
-            },
+        if allocation_amount < risk_limits.min_allocation_amount(&strategy.protocol_type, strategy.decimals) {
+            continue;
         }
         
         // RISK-ADJUSTED ALLOCATION MODIFIER
-        let risk_adjustment = calculate_risk_adjustment(strategy.volatility_score, risk_limits);
+        let risk_adjustment = calculate_risk_adjustment(strategy.volatility_score, &strategy.protocol_type, risk_limits);
         allocation_amount = (allocation_amount as u128 * risk_adjustment as u128 / 10000u128) as u64;
         
+        // RE-ENFORCE MAXIMUM (risk multiplier can exceed 100%)
+        allocation_amount = allocation_amount.min(max_single_allocation);
+        
         // ENSURE WE DON'T OVERALLOCATE
         if allocation_amount > remaining_capital {
             allocation_amount = remaining_capital;
         }
         
+        // TRIM TO WHAT THE STRATEGY'S CORRELATION GROUP CAN STILL ABSORB
+        let group_key = strategy.protocol_type.correlation_key();
+        allocation_amount = allocation_amount
+            .min(max_group_allocation.saturating_sub(group_total(&group_totals, &group_key)));
+        
         if allocation_amount > 0 {
+            add_to_group(&mut group_totals, group_key, allocation_amount)?;
+            
             let allocation_type = if index < 3 {
                 AllocationType::TopPerformer
             } else {
@@ -157,6 +342,7 @@ pub fn calculate_optimal_allocation(
             allocations.push(CapitalAllocation {
                 strategy_id: strategy.strategy_id,
                 amount: allocation_amount,
+                min_acceptable_amount: 0,
                 allocation_type,
             });
             
@@ -164,27 +350,157 @@ pub fn calculate_optimal_allocation(
         }
     }
     
-    // REDISTRIBUTE ANY REMAINING DUST TO TOP PERFORMER
-    if remaining_capital > 1_000_000 && !allocations.is_empty() { // 0.001 SOL threshold
-        if let Some(top_allocation) = allocations.iter_mut()
-            .find(|a| matches!(a.allocation_type, AllocationType::TopPerformer)) {
-            top_allocation.amount = top_allocation.amount
-                .checked_add(remaining_capital)
-                .ok_or(RebalancerErrorCode::BalanceOverflow)?;
+    // REDISTRIBUTE ANY REMAINING DUST PER THE REMAINDER POLICY (WITHIN DIVERSIFICATION CAPS)
+    if remaining_capital > 1_000_000 { // 0.001 SOL threshold
+        let max_single_allocation = apply_bps(available_capital, risk_limits.max_single_strategy_bps)?;
+        
+        match risk_limits.remainder_policy {
+            RemainderPolicy::TopPerformer => {
+                if let Some(top_allocation) = allocations.iter_mut()
+                    .find(|a| matches!(a.allocation_type, AllocationType::TopPerformer)) {
+                    let group_key = destinations
+                        .iter()
+                        .find(|s| s.strategy_id == top_allocation.strategy_id)
+                        .map(|s| s.protocol_type.correlation_key())
+                        .ok_or(RebalancerErrorCode::StrategyNotFound)?;
+                    let dust_top_up = remaining_capital
+                        .min(max_single_allocation.saturating_sub(top_allocation.amount))
+                        .min(max_group_allocation.saturating_sub(group_total(&group_totals, &group_key)));
+                    top_allocation.amount = top_allocation.amount
+                        .checked_add(dust_top_up)
+                        .ok_or(RebalancerErrorCode::BalanceOverflow)?;
+                    remaining_capital = remaining_capital.saturating_sub(dust_top_up);
+                }
+            }
+            RemainderPolicy::RoundRobin => {
+                remaining_capital = distribute_round_robin(
+                    &mut allocations, &destinations, &mut group_totals,
+                    remaining_capital, max_single_allocation, max_group_allocation,
+                )?;
+            }
         }
     }
     
+    // RETURN WHATEVER NO STRATEGY ABSORBED SO ALLOCATIONS ALWAYS SUM TO THE INPUT
+    if remaining_capital > 0 {
+        allocations.push(CapitalAllocation {
+            strategy_id: Pubkey::default(),
+            amount: remaining_capital,
+            min_acceptable_amount: 0,
+            allocation_type: AllocationType::Unallocated,
+        });
+    }
+    
+    validate_allocation_total(&allocations, available_capital)?;
+    
     Ok(allocations)
 }
 
+// SPREAD LEFTOVER CAPITAL ACROSS THE FUNDED STRATEGIES, ONE EVEN SHARE PER PASS
+// A strategy that hits its single or group cap drops out and its unused share
+// goes round again, so each pass either places everything or caps a strategy.
+// Returns what could not be placed.
+fn distribute_round_robin(
+    allocations: &mut [CapitalAllocation],
+    destinations: &[&StrategyPerformanceData],
+    group_totals: &mut Vec<(Pubkey, u64)>,
+    mut remaining_capital: u64,
+    max_single_allocation: u64,
+    max_group_allocation: u64,
+) -> Result<u64> {
+    let group_key_of = |strategy_id: &Pubkey| {
+        destinations
+            .iter()
+            .find(|s| s.strategy_id == *strategy_id)
+            .map(|s| s.protocol_type.correlation_key())
+            .ok_or(RebalancerErrorCode::StrategyNotFound)
+    };
+    let headroom = |allocation: &CapitalAllocation, group_totals: &[(Pubkey, u64)]| -> Result<u64> {
+        let group_key = group_key_of(&allocation.strategy_id)?;
+        Ok(max_single_allocation
+            .saturating_sub(allocation.amount)
+            .min(max_group_allocation.saturating_sub(group_total(group_totals, &group_key))))
+    };
+    
+    while remaining_capital > 0 {
+        let mut recipients = 0u64;
+        for allocation in allocations.iter().filter(|a| a.allocation_type.is_strategy_allocation()) {
+            if headroom(allocation, group_totals)? > 0 {
+                recipients += 1;
+            }
+        }
+        if recipients == 0 {
+            break;
+        }
+        
+        // Earlier (higher ranked) strategies take the indivisible lamports
+        let share = remaining_capital / recipients;
+        let mut odd_lamports = remaining_capital % recipients;
+        let mut placed = false;
+        
+        for allocation in allocations.iter_mut().filter(|a| a.allocation_type.is_strategy_allocation()) {
+            let available = headroom(allocation, group_totals)?;
+            if available == 0 {
+                continue;
+            }
+            let mut wanted = share;
+            if odd_lamports > 0 {
+                wanted += 1;
+                odd_lamports -= 1;
+            }
+            let top_up = wanted.min(available).min(remaining_capital);
+            if top_up > 0 {
+                allocation.amount = allocation.amount
+                    .checked_add(top_up)
+                    .ok_or(RebalancerErrorCode::BalanceOverflow)?;
+                add_to_group(group_totals, group_key_of(&allocation.strategy_id)?, top_up)?;
+                remaining_capital -= top_up;
+                placed = true;
+            }
+        }
+        
+        if !placed {
+            break;
+        }
+    }
+    
+    Ok(remaining_capital)
+}
+
+// CLOSING INVARIANT: the plan (fees and unallocated capital included) never hands out more than it was given
+pub fn validate_allocation_total(allocations: &[CapitalAllocation], available_capital: u64) -> Result<()> {
+    let total = allocations
+        .iter()
+        .try_fold(0u64, |total, a| total.checked_add(a.amount).ok_or(RebalancerErrorCode::BalanceOverflow))?;
+    require!(total <= available_capital, RebalancerErrorCode::AllocationExceedsCapital);
+    Ok(())
+}
+
+// CAPITAL ALREADY ALLOCATED TO A CORRELATION GROUP
+fn group_total(group_totals: &[(Pubkey, u64)], group_key: &Pubkey) -> u64 {
+    group_totals
+        .iter()
+        .find(|(key, _)| key == group_key)
+        .map_or(0, |(_, total)| *total)
+}
+
+fn add_to_group(group_totals: &mut Vec<(Pubkey, u64)>, group_key: Pubkey, amount: u64) -> Result<()> {
+    match group_totals.iter_mut().find(|(key, _)| *key == group_key) {
+        Some((_, total)) => {
+            *total = total.checked_add(amount).ok_or(RebalancerErrorCode::BalanceOverflow)?;
+        }
+        None => group_totals.push((group_key, amount)),
+    }
+    Ok(())
+}
+
 // RISK ADJUSTMENT CALCULATION
-pub fn calculate_risk_adjustment(volatility_score: u32, risk_limits: &RiskLimits) -> u32 {
+pub fn calculate_risk_adjustment(volatility_score: u32, protocol_type: &ProtocolType, risk_limits: &RiskLimits) -> u32 {
     // Lower volatility = higher allocation multiplier
     // Higher volatility = lower allocation multiplier
-    // Range: 50% to 150% of base allocation
+    // Range: 50% to 150% of base allocation, along the protocol's risk curve
     
-    let volatility_percentage = volatility_score.min(10000); // Cap at 100%
-    let inverse_volatility = 10000u32.saturating_sub(volatility_percentage);
+    let inverse_volatility = protocol_type.risk_curve().inverse_volatility(volatility_score);
     
     // Scale to 5000-15000 range (50%-150%)
     let min_multiplier = 5000u32;
@@ -194,21 +510,28 @@ pub fn calculate_risk_adjustment(volatility_score: u32, risk_limits: &RiskLimits
         ((inverse_volatility as u64 * (max_multiplier - min_multiplier) as u64) / 10000u64) as u32;
     
     // Apply portfolio risk tolerance
-    let final_multiplier = (risk_multiplier as u64 * risk_limits.risk_tolerance_bps as u64) / 10000u64;
+    let final_multiplier = (risk_multiplier as u64 * risk_limits.risk_tolerance_bps) / 10000u64;
     
     (final_multiplier as u32).min(max_multiplier)
 }
 
 // ALLOCATION VALIDATION
 pub fn validate_allocations(allocations: &[CapitalAllocation]) -> Result<u64> {
+    require!(allocations.len() <= MAX_STRATEGIES_PER_OP, RebalancerErrorCode::TooManyStrategies);
+    
     let mut total = 0u64;
     let mut strategy_ids = std::collections::HashSet::new();
+    let mut fee_destinations = std::collections::HashSet::new();
     
     for allocation in allocations {
-        // CHECK FOR DUPLICATE STRATEGIES
-        if !strategy_ids.insert(allocation.strategy_id) {
-            return Err(RebalancerErrorCode::DuplicateStrategy.into());
-        }
+        // CHECK FOR DUPLICATE STRATEGIES (fee entries are keyed separately, so a
+        // treasury that happens to equal a strategy id is not a duplicate)
+        let is_unique = if allocation.allocation_type.is_strategy_allocation() {
+            strategy_ids.insert(allocation.strategy_id)
+        } else {
+            fee_destinations.insert((allocation.allocation_type as u8, allocation.strategy_id))
+        };
+        require!(is_unique, RebalancerErrorCode::DuplicateStrategy);
         
         // VALIDATE ALLOCATION AMOUNT
         require!(allocation.amount > 0, RebalancerErrorCode::InsufficientBalance);
@@ -222,26 +545,180 @@ pub fn validate_allocations(allocations: &[CapitalAllocation]) -> Result<u64> {
     Ok(total)
 }
 
+/// Capital an allocation list adds to `total_capital_moved`. Treasury fees and
+/// unallocated remainders are part of the list's total but not of the capital
+/// moved, so counting them would inflate the lifetime figure by every fee paid.
+pub fn capital_moved(allocations: &[CapitalAllocation]) -> Result<u64> {
+    allocations
+        .iter()
+        .filter(|a| a.allocation_type.counts_as_capital_moved())
+        .try_fold(0u64, |total, a| total.checked_add(a.amount).ok_or(RebalancerErrorCode::BalanceOverflow.into()))
+}
+
+// RESUMABLE EXECUTION
+/// Indices of the allocations a resumed call applies: not yet executed, and
+/// either a fee entry or a strategy allocation whose destination was passed.
+pub fn pending_allocation_indices(
+    allocations: &[CapitalAllocation],
+    execution: &RedistributionExecution,
+    passed_strategy_ids: &[Pubkey],
+) -> Result<Vec<usize>> {
+    let pending: Vec<usize> = allocations
+        .iter()
+        .enumerate()
+        .filter(|(index, allocation)| {
+            !execution.is_executed(*index)
+                && (!allocation.allocation_type.is_strategy_allocation()
+                    || passed_strategy_ids.contains(&allocation.strategy_id))
+        })
+        .map(|(index, _)| index)
+        .collect();
+    require!(!pending.is_empty(), RebalancerErrorCode::NoPendingAllocations);
+    Ok(pending)
+}
+
+// DESTINATION VALIDATION (fee allocations go to treasuries and unallocated capital stays put)
+pub fn validate_allocation_destinations(
+    allocations: &[CapitalAllocation],
+    registered_strategy_ids: &[Pubkey],
+) -> Result<()> {
+    for allocation in allocations {
+        if !allocation.allocation_type.is_strategy_allocation() {
+            continue;
+        }
+        
+        require!(
+            registered_strategy_ids.contains(&allocation.strategy_id),
+            RebalancerErrorCode::StrategyNotFound
+        );
+    }
+    
+    Ok(())
+}
+
+// STATUS GATE: paused and deprecated strategies take no fresh capital
+pub fn validate_allocation_status<'a>(
+    allocations: &[CapitalAllocation],
+    strategies: impl IntoIterator<Item = &'a Strategy>,
+) -> Result<()> {
+    for strategy in strategies {
+        let is_destination = allocations
+            .iter()
+            .any(|a| a.allocation_type.is_strategy_allocation() && a.strategy_id == strategy.strategy_id);
+        require!(
+            !is_destination || strategy.status == StrategyStatus::Active,
+            RebalancerErrorCode::StrategyNotActive
+        );
+    }
+    
+    Ok(())
+}
+
+// DEPOSIT CAPS: no destination may end up above its max_capacity
+pub fn validate_allocation_capacity<'a>(
+    allocations: &[CapitalAllocation],
+    strategies: impl IntoIterator<Item = &'a Strategy>,
+) -> Result<()> {
+    for strategy in strategies {
+        let incoming = allocations
+            .iter()
+            .filter(|a| a.allocation_type.is_strategy_allocation() && a.strategy_id == strategy.strategy_id)
+            .try_fold(0u64, |total, a| total.checked_add(a.amount).ok_or(RebalancerErrorCode::BalanceOverflow))?;
+        let new_balance = strategy.current_balance
+            .checked_add(incoming)
+            .ok_or(RebalancerErrorCode::BalanceOverflow)?;
+        strategy.validate_capacity(new_balance)?;
+    }
+    
+    Ok(())
+}
+
 // HELPER STRUCTURES
 #[derive(Debug, Clone)]
 pub struct StrategyPerformanceData {
     pub strategy_id: Pubkey,
     pub performance_score: u64,
+    pub yield_rate: u64,
     pub current_balance: u64,
     pub volatility_score: u32,
     pub protocol_type: ProtocolType,
     pub percentile_rank: u8,
+    pub status: StrategyStatus,
+    pub mint: Pubkey,
+    pub decimals: u8,
+    pub in_fee_grace: bool,
+    pub in_cooldown: bool,
 }
 
-#[derive(Debug, Clone)]
+impl StrategyPerformanceData {
+    pub fn from_strategy(strategy: &Strategy, risk_limits: &RiskLimits, current_time: i64) -> Self {
+        StrategyPerformanceData {
+            strategy_id: strategy.strategy_id,
+            performance_score: strategy.performance_score,
+            yield_rate: strategy.yield_rate,
+            current_balance: strategy.current_balance,
+            volatility_score: strategy.volatility_ema,
+            protocol_type: strategy.protocol_type,
+            percentile_rank: strategy.percentile_rank,
+            status: strategy.status,
+            mint: strategy.mint,
+            decimals: strategy.decimals,
+            in_fee_grace: risk_limits.in_fee_grace(strategy.creation_time, current_time),
+            in_cooldown: risk_limits.in_reallocation_cooldown(strategy.last_extracted, current_time),
+        }
+    }
+    
+    /// Only active strategies may be allocated fresh capital. Deprecated ones
+    /// are being wound down and paused ones are frozen, whatever score or rank
+    /// they last recorded. A strategy extracted from within the reallocation
+    /// cooldown sits out as well, so a score that ticks back up does not pull
+    /// the capital straight back in.
+    pub fn can_receive_allocation(&self) -> bool {
+        self.status == StrategyStatus::Active && !self.in_cooldown
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone)]
 pub struct RiskLimits {
     pub max_single_strategy_bps: u64,    // Maximum % of capital to single strategy
     pub min_single_strategy_bps: u64,    // Minimum % threshold for allocation
     pub platform_fee_bps: u64,           // Platform fee percentage
     pub manager_fee_bps: u64,            // Manager fee percentage
     pub risk_tolerance_bps: u64,         // Overall risk tolerance modifier
+    pub min_extraction_per_strategy: u64, // Minimum extractable lamports to target an underperformer
     pub platform_treasury: Pubkey,       // Platform fee destination
     pub manager_treasury: Pubkey,        // Manager fee destination
+    pub stable_lending_weight_bps: u32,  // Ranking weight for stable lending scores
+    pub yield_farming_weight_bps: u32,   // Ranking weight for yield farming scores
+    pub liquid_staking_weight_bps: u32,  // Ranking weight for liquid staking scores
+    pub stable_lending_min_lamports: u64, // Smallest allocation sent to a stable lending strategy
+    pub yield_farming_min_lamports: u64,  // Smallest allocation sent to a yield farming strategy
+    pub liquid_staking_min_lamports: u64, // Smallest allocation sent to a liquid staking strategy
+    pub allocation_mode: AllocationMode,  // How extracted capital is split among top performers
+    pub min_net_benefit_bps: u64,         // Required expected gain as a share of fees (0 = unchecked)
+    pub max_group_bps: u64,               // Maximum % of capital to strategies sharing a correlation key
+    pub top_performer_count: u8,          // Maximum number of strategies funded per rebalance
+    pub top_performer_percentile: u8,     // Minimum percentile rank to receive capital
+    pub require_protocol_diversity: bool, // Refuse plans whose top performers share one protocol type
+    pub fee_grace_period: i64,            // Seconds after registration during which allocations to a strategy are fee-free
+    pub volatility_weight: u32,           // Threshold points added per 100% of average volatility
+    pub min_threshold: u8,                // Lower clamp of the dynamic threshold (percent)
+    pub max_threshold: u8,                // Upper clamp of the dynamic threshold (percent)
+    pub remainder_policy: RemainderPolicy, // Who receives capital the weighted split leaves over
+    pub reallocation_cooldown: i64,       // Seconds after an extraction during which a strategy receives no capital
+    pub extract_only: bool,               // Safe mode: route extracted capital to safe_haven, redeploy nothing
+    pub safe_haven: Pubkey,               // Destination for extracted capital in safe mode
+    pub underperformer_max_percentile: u8, // Percentile rank below which strategies are extracted from (0 = dynamic threshold)
+    pub tie_break_policy: TieBreakPolicy, // Balance or volatility first among equally scored strategies
+    pub min_rebalance_capital: u64,       // Least total extractable capital worth a rebalance (base units)
+    pub stable_lending_target_bps: u16,   // Target share of capital in stable lending (TargetAllocation mode)
+    pub yield_farming_target_bps: u16,    // Target share of capital in yield farming (TargetAllocation mode)
+    pub liquid_staking_target_bps: u16,   // Target share of capital in liquid staking (TargetAllocation mode)
+    pub perpetual_funding_target_bps: u16, // Target share of capital in perpetual funding (TargetAllocation mode)
+    pub perpetual_funding_weight_bps: u32, // Ranking weight for perpetual funding scores
+    pub perpetual_funding_min_lamports: u64, // Smallest allocation sent to a perpetual funding strategy
+    pub perpetual_taker_fee_bps: u64,     // Taker fee charged when closing a perpetual position
+    pub crisis_volatility_threshold: u32, // Volatility above which an active strategy justifies an emergency rebalance
 }
 
 impl Default for RiskLimits {
@@ -252,9 +729,201 @@ impl Default for RiskLimits {
             platform_fee_bps: PLATFORM_FEE_BPS,             // 0.5% platform fee
             manager_fee_bps: MANAGER_FEE_BPS,              // 1.5% manager fee
             risk_tolerance_bps: RISK_TOLERANCE_BPS,          // 80% risk tolerance (conservative)
+            min_extraction_per_strategy: MIN_EXTRACTION_PER_STRATEGY, // 0.05 SOL per extraction
             platform_treasury: Pubkey::default(),
             manager_treasury: Pubkey::default(),
+            stable_lending_weight_bps: STABLE_LENDING_WEIGHT_BPS,
+            yield_farming_weight_bps: YIELD_FARMING_WEIGHT_BPS,
+            liquid_staking_weight_bps: LIQUID_STAKING_WEIGHT_BPS,
+            stable_lending_min_lamports: STABLE_LENDING_MIN_LAMPORTS,
+            yield_farming_min_lamports: YIELD_FARMING_MIN_LAMPORTS,
+            liquid_staking_min_lamports: LIQUID_STAKING_MIN_LAMPORTS,
+            allocation_mode: AllocationMode::PerformanceWeighted,
+            min_net_benefit_bps: MIN_NET_BENEFIT_BPS,         // Break-even: gain >= fees
+            max_group_bps: MAX_GROUP_BPS,                     // 60% max per correlated group
+            top_performer_count: TOP_PERFORMER_COUNT,
+            top_performer_percentile: TOP_PERFORMER_PERCENTILE,
+            require_protocol_diversity: false,
+            fee_grace_period: FEE_GRACE_PERIOD,
+            volatility_weight: VOLATILITY_WEIGHT,
+            min_threshold: MIN_THRESHOLD,
+            max_threshold: MAX_THRESHOLD,
+            remainder_policy: RemainderPolicy::TopPerformer,
+            reallocation_cooldown: REALLOCATION_COOLDOWN,
+            extract_only: false,
+            safe_haven: Pubkey::default(),
+            underperformer_max_percentile: 0, // Follow the dynamic threshold
+            tie_break_policy: TieBreakPolicy::BalanceFirst,
+            min_rebalance_capital: MIN_REBALANCE_CAPITAL, // 0.1 SOL
+            stable_lending_target_bps: 0,     // Targets only apply in TargetAllocation mode
+            yield_farming_target_bps: 0,
+            liquid_staking_target_bps: 0,
+            perpetual_funding_target_bps: 0,
+            perpetual_funding_weight_bps: PERPETUAL_FUNDING_WEIGHT_BPS,
+            perpetual_funding_min_lamports: PERPETUAL_FUNDING_MIN_LAMPORTS,
+            perpetual_taker_fee_bps: PERPETUAL_TAKER_FEE_BPS, // 0.05% taker fee
+            crisis_volatility_threshold: CRISIS_VOLATILITY_THRESHOLD, // 90% volatility
+        }
+    }
+}
+
+impl RiskLimits {
+    pub fn protocol_weight(&self, protocol_type: &ProtocolType) -> u32 {
+        match protocol_type {
+            ProtocolType::StableLending { .. } => self.stable_lending_weight_bps,
+            ProtocolType::YieldFarming { .. } => self.yield_farming_weight_bps,
+            ProtocolType::LiquidStaking { .. } => self.liquid_staking_weight_bps,
+            ProtocolType::PerpetualFunding { .. } => self.perpetual_funding_weight_bps,
+        }
+    }
+    
+    pub fn min_allocation_lamports(&self, protocol_type: &ProtocolType) -> u64 {
+        match protocol_type {
+            ProtocolType::StableLending { .. } => self.stable_lending_min_lamports,
+            ProtocolType::YieldFarming { .. } => self.yield_farming_min_lamports,
+            ProtocolType::LiquidStaking { .. } => self.liquid_staking_min_lamports,
+            ProtocolType::PerpetualFunding { .. } => self.perpetual_funding_min_lamports,
+        }
+    }
+    
+    /// Share of capital `TargetAllocation` mode aims to hold in a protocol.
+    pub fn target_bps(&self, protocol_type: &ProtocolType) -> u16 {
+        match protocol_type {
+            ProtocolType::StableLending { .. } => self.stable_lending_target_bps,
+            ProtocolType::YieldFarming { .. } => self.yield_farming_target_bps,
+            ProtocolType::LiquidStaking { .. } => self.liquid_staking_target_bps,
+            ProtocolType::PerpetualFunding { .. } => self.perpetual_funding_target_bps,
+        }
+    }
+    
+    /// Protocol targets must split the whole portfolio: exactly 10000 bps.
+    pub fn validate_target_allocation(&self) -> Result<()> {
+        let total: u32 = [
+            self.stable_lending_target_bps,
+            self.yield_farming_target_bps,
+            self.liquid_staking_target_bps,
+            self.perpetual_funding_target_bps,
+        ]
+        .iter()
+        .map(|bps| *bps as u32)
+        .sum();
+        require!(total == 10000, RebalancerErrorCode::InvalidRiskLimits);
+        Ok(())
+    }
+    
+    /// Platform and manager fees together may never take more than
+    /// `MAX_TOTAL_FEE_BPS` of the capital being moved.
+    pub fn validate_total_fees(&self) -> Result<()> {
+        require!(
+            self.platform_fee_bps.saturating_add(self.manager_fee_bps) <= MAX_TOTAL_FEE_BPS,
+            RebalancerErrorCode::ExcessiveFees
+        );
+        Ok(())
+    }
+    
+    /// A strategy registered less than `fee_grace_period` seconds ago is not
+    /// charged fees on capital allocated to it.
+    pub fn in_fee_grace(&self, creation_time: i64, current_time: i64) -> bool {
+        current_time.saturating_sub(creation_time) < self.fee_grace_period
+    }
+    
+    /// A strategy extracted from less than `reallocation_cooldown` seconds ago
+    /// is not allocated capital. Strategies never extracted from are not.
+    pub fn in_reallocation_cooldown(&self, last_extracted: i64, current_time: i64) -> bool {
+        last_extracted > 0 && current_time.saturating_sub(last_extracted) < self.reallocation_cooldown
+    }
+    
+    /// Percentile rank below which an active strategy is an underperformer: the
+    /// configured cutoff, or the dynamic threshold when none is set.
+    pub fn underperformer_cutoff(&self, dynamic_threshold: u8) -> u8 {
+        if self.underperformer_max_percentile > 0 {
+            self.underperformer_max_percentile
+        } else {
+            dynamic_threshold
+        }
+    }
+    
+    /// Configured minimum in base units of a mint with `decimals` decimals.
+    pub fn min_allocation_amount(&self, protocol_type: &ProtocolType, decimals: u8) -> u64 {
+        scale_to_decimals(self.min_allocation_lamports(protocol_type), decimals)
+    }
+    
+    /// Registration and withdrawals hold balances to the same minimum allocations are sized against.
+    pub fn validate_balance_constraints(&self, protocol_type: &ProtocolType, balance: u64, decimals: u8) -> Result<()> {
+        require!(
+            balance >= self.min_allocation_amount(protocol_type, decimals),
+            RebalancerErrorCode::InsufficientBalance
+        );
+        Ok(())
+    }
+    
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.platform_treasury != Pubkey::default() && self.manager_treasury != Pubkey::default(),
+            RebalancerErrorCode::InvalidTreasury
+        );
+        require!(
+            self.max_single_strategy_bps <= 10000
+                && self.min_single_strategy_bps <= self.max_single_strategy_bps
+                && self.risk_tolerance_bps <= 10000,
+            RebalancerErrorCode::InvalidRiskLimits
+        );
+        self.validate_total_fees()?;
+        // Weights may boost a protocol up to 2x but never zero it out
+        require!(
+            [
+                self.stable_lending_weight_bps,
+                self.yield_farming_weight_bps,
+                self.liquid_staking_weight_bps,
+                self.perpetual_funding_weight_bps,
+            ]
+            .iter()
+            .all(|weight| (1..=20000).contains(weight)),
+            RebalancerErrorCode::InvalidRiskLimits
+        );
+        require!(self.perpetual_taker_fee_bps <= 10000, RebalancerErrorCode::InvalidRiskLimits);
+        // Volatility scores top out at 100%, so a higher threshold could never be crossed
+        require!(self.crisis_volatility_threshold < 10000, RebalancerErrorCode::InvalidRiskLimits);
+        require!(self.min_net_benefit_bps <= MAX_NET_BENEFIT_BPS, RebalancerErrorCode::InvalidRiskLimits);
+        // A group of one is bounded by the group cap, so it must not undercut the single cap
+        require!(
+            self.max_single_strategy_bps <= self.max_group_bps && self.max_group_bps <= 10000,
+            RebalancerErrorCode::InvalidRiskLimits
+        );
+        require!(
+            (1..=MAX_TOP_PERFORMER_COUNT).contains(&self.top_performer_count)
+                && self.top_performer_percentile <= 100,
+            RebalancerErrorCode::InvalidRiskLimits
+        );
+        require!(
+            (0..=MAX_FEE_GRACE_PERIOD).contains(&self.fee_grace_period)
+                && (0..=MAX_REALLOCATION_COOLDOWN).contains(&self.reallocation_cooldown),
+            RebalancerErrorCode::InvalidRiskLimits
+        );
+        require!(
+            self.volatility_weight <= MAX_VOLATILITY_WEIGHT
+                && self.min_threshold < self.max_threshold
+                && self.max_threshold <= 100,
+            RebalancerErrorCode::InvalidRiskLimits
+        );
+        // Underperformers (rank below the cutoff) and top performers (rank at or
+        // above top_performer_percentile) must never be the same strategies. With
+        // no fixed cutoff the dynamic threshold can reach max_threshold.
+        require!(
+            self.underperformer_cutoff(self.max_threshold) <= self.top_performer_percentile,
+            RebalancerErrorCode::InvalidRiskLimits
+        );
+        // A rebalance must always move something
+        require!(self.min_rebalance_capital > 0, RebalancerErrorCode::InvalidRiskLimits);
+        if self.allocation_mode == AllocationMode::TargetAllocation {
+            self.validate_target_allocation()?;
         }
+        // Safe mode must have somewhere to send the capital
+        require!(
+            !self.extract_only || self.safe_haven != Pubkey::default(),
+            RebalancerErrorCode::InvalidTreasury
+        );
+        Ok(())
     }
 }
 
@@ -262,6 +931,7 @@ impl Default for RiskLimits {
 pub fn execute_complete_rebalancing(
     portfolio: &Portfolio,
     strategies: &[StrategyPerformanceData],
+    risk_limits: &RiskLimits,
 ) -> Result<RebalancingPlan> {
     // STEP 1: IDENTIFY UNDERPERFORMERS
     // Calculate average volatility across provided strategies (basis points)
@@ -273,120 +943,575 @@ pub fn execute_complete_rebalancing(
     let average_volatility: u32 = (total_volatility / strategies.len() as u64) as u32;
 
     // Compute dynamic threshold using portfolio base threshold
-    let dynamic_threshold = calculate_dynamic_threshold(portfolio.base_threshold, average_volatility)?;
+    let dynamic_threshold = calculate_dynamic_threshold(portfolio.base_threshold, average_volatility, risk_limits)?;
+    let underperformer_cutoff = risk_limits.underperformer_cutoff(dynamic_threshold);
 
-    let underperformers: Vec<StrategyPerformanceData> = strategies
+    // Skip underperformers whose extractable capital would not cover the per-strategy extraction cost.
+    // Deprecated strategies are always extracted from while they hold anything above the rent reserve;
+    // Paused strategies are never touched, whatever rank they last held.
+    let mut underperformers: Vec<StrategyPerformanceData> = strategies
         .iter()
-        .filter(|s| s.percentile_rank < dynamic_threshold)
+        .filter(|s| {
+            let extractable = extractable_balance(s.current_balance, &s.mint);
+            match s.status {
+                StrategyStatus::Deprecated => extractable > 0,
+                StrategyStatus::Paused => false,
+                StrategyStatus::Active => {
+                    s.percentile_rank < underperformer_cutoff && extractable >= risk_limits.min_extraction_per_strategy
+                }
+            }
+        })
         .cloned()
         .collect();
     
+    // WORST FIRST, WITHIN THE PLAN BOUNDS: beyond MAX_EXTRACTION_TARGETS only
+    // deprecated strategies and then the lowest scores are extracted from,
+    // kept in their original order. The rest are left for the next rebalance.
+    if underperformers.len() > RebalancingPlan::MAX_EXTRACTION_TARGETS {
+        let mut worst: Vec<&StrategyPerformanceData> = underperformers.iter().collect();
+        worst.sort_by_key(|s| (s.status != StrategyStatus::Deprecated, s.performance_score));
+        let worst_ids: Vec<Pubkey> = worst
+            .iter()
+            .take(RebalancingPlan::MAX_EXTRACTION_TARGETS)
+            .map(|s| s.strategy_id)
+            .collect();
+        underperformers.retain(|s| worst_ids.contains(&s.strategy_id));
+    }
+    
+    // SAFE MODE: everything extracted goes to the safe haven, nothing is redeployed
+    if risk_limits.extract_only {
+        require!(!underperformers.is_empty(), RebalancerErrorCode::NoUnderperformers);
+        let total_extractable = total_extractable(&underperformers, risk_limits)?;
+        
+        return Ok(RebalancingPlan {
+            extraction_targets: underperformers.iter().map(|s| s.strategy_id).collect(),
+            extraction_amounts: extraction_amounts(&underperformers),
+            total_to_extract: total_extractable,
+            redistribution_plan: vec![CapitalAllocation {
+                strategy_id: risk_limits.safe_haven,
+                amount: total_extractable,
+                min_acceptable_amount: 0,
+                allocation_type: AllocationType::SafeHaven,
+            }],
+            estimated_fees: apply_bps(total_extractable, ESTIMATED_FEE_BPS)?,
+            expected_improvement: 0,
+        });
+    }
+    
+    // TARGET MODE: move every strategy toward its protocol target instead of
+    // draining underperformers into top performers
+    if risk_limits.allocation_mode == AllocationMode::TargetAllocation {
+        return plan_target_rebalancing(strategies, risk_limits);
+    }
+    
     // STEP 2: IDENTIFY TOP PERFORMERS
     let top_performers: Vec<StrategyPerformanceData> = strategies
         .iter()
-        .filter(|s| s.percentile_rank >= 75) // Top quartile
-        .take(5) // Limit to top 5 for diversification
+        .filter(|s| s.percentile_rank >= risk_limits.top_performer_percentile)
+        .filter(|s| s.can_receive_allocation()) // Never fund a paused or wound-down strategy
+        .take(risk_limits.top_performer_count as usize) // Limit breadth for diversification
         .cloned()
         .collect();
     
-    require!(!underperformers.is_empty(), RebalancerErrorCode::InsufficientStrategies);
+    // Nothing below the threshold is a healthy portfolio, not a missing strategy
+    require!(!underperformers.is_empty(), RebalancerErrorCode::NoUnderperformers);
     require!(!top_performers.is_empty(), RebalancerErrorCode::InsufficientStrategies);
     
-    // STEP 3: CALCULATE TOTAL EXTRACTABLE CAPITAL
-    let total_extractable: u64 = underperformers
-        .iter()
-        .map(|s| s.current_balance.saturating_sub(10_000_000)) // Keep rent minimum
-        .sum();
+    // OPTIONAL DIVERSITY REQUIREMENT: capital must not all flow into one protocol type
+    if risk_limits.require_protocol_diversity {
+        let first_protocol = std::mem::discriminant(&top_performers[0].protocol_type);
+        require!(
+            top_performers.iter().any(|s| std::mem::discriminant(&s.protocol_type) != first_protocol),
+            RebalancerErrorCode::InsufficientDiversity
+        );
+    }
     
-    require!(total_extractable > 100_000_000, RebalancerErrorCode::InsufficientBalance); // 0.1 SOL minimum
+    // STEP 3: CALCULATE TOTAL EXTRACTABLE CAPITAL
+    let total_extractable = total_extractable(&underperformers, risk_limits)?;
     
     // STEP 4: GENERATE OPTIMAL ALLOCATION
-    let risk_limits = RiskLimits::default();
     let allocations = calculate_optimal_allocation(
         total_extractable,
         &top_performers,
-        &risk_limits,
+        risk_limits,
+        risk_limits.allocation_mode,
     )?;
     
-    Ok(RebalancingPlan {
+    let mut plan = RebalancingPlan {
         extraction_targets: underperformers.iter().map(|s| s.strategy_id).collect(),
+        extraction_amounts: extraction_amounts(&underperformers),
         total_to_extract: total_extractable,
         redistribution_plan: allocations,
-        estimated_fees: (total_extractable * 200) / 10000, // 2% estimated fees
-        expected_improvement: calculate_expected_improvement(&top_performers.iter().collect::<Vec<_>>()),
-    })
+        estimated_fees: apply_bps(total_extractable, ESTIMATED_FEE_BPS)?,
+        expected_improvement: 0,
+    };
+    plan.expected_improvement = calculate_expected_improvement(strategies, &plan);
+    Ok(plan)
 }
 
-#[derive(Debug, Clone)]
+// Capital pulled from the underperformers, each keeping its rent reserve
+fn total_extractable(underperformers: &[StrategyPerformanceData], risk_limits: &RiskLimits) -> Result<u64> {
+    let total = underperformers
+        .iter()
+        .map(|s| extractable_balance(s.current_balance, &s.mint))
+        .try_fold(0u64, |total, extractable| {
+            total.checked_add(extractable).ok_or(RebalancerErrorCode::BalanceOverflow)
+        })?;
+    
+    require!(total >= risk_limits.min_rebalance_capital, RebalancerErrorCode::InsufficientBalance);
+    Ok(total)
+}
+
+// Underperformers are drained down to their rent reserve
+fn extraction_amounts(underperformers: &[StrategyPerformanceData]) -> Vec<u64> {
+    underperformers
+        .iter()
+        .map(|s| extractable_balance(s.current_balance, &s.mint))
+        .collect()
+}
+
+const _: () = assert!(RebalancingPlan::MAX_SIZE <= MAX_RETURN_DATA);
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone)]
 pub struct RebalancingPlan {
     pub extraction_targets: Vec<Pubkey>,
+    pub extraction_amounts: Vec<u64>, // Taken from each extraction target, in the same order
     pub total_to_extract: u64,
     pub redistribution_plan: Vec<CapitalAllocation>,
     pub estimated_fees: u64,
-    pub expected_improvement: u64, // Expected performance score improvement
+    pub expected_improvement: u64, // Expected yield gain on the moved capital (bps of capital)
 }
 
-pub fn calculate_expected_improvement(top_performers: &[&StrategyPerformanceData]) -> u64 {
-    if top_performers.is_empty() {
-        return 0;
+impl RebalancingPlan {
+    /// Bounds that keep a serialized plan within Solana's return data limit,
+    /// so `simulate_rebalance` and `preview_rebalancing` can always return it.
+    pub const MAX_EXTRACTION_TARGETS: usize = 10;
+    pub const MAX_ALLOCATIONS: usize = 10; // Two fee entries + MAX_TOP_PERFORMER_COUNT + unallocated
+    
+    pub const MAX_SIZE: usize = 4 + 32 * Self::MAX_EXTRACTION_TARGETS // extraction_targets
+    + 4 + 8 * Self::MAX_EXTRACTION_TARGETS // extraction_amounts
+    + 8 // total_to_extract
+    + 4 + CapitalAllocation::SIZE * Self::MAX_ALLOCATIONS // redistribution_plan
+    + 8 // estimated_fees
+    + 8; // expected_improvement
+    // 926 bytes
+    
+    pub fn validate_size(&self) -> Result<()> {
+        require!(
+            self.extraction_targets.len() <= Self::MAX_EXTRACTION_TARGETS
+                && self.redistribution_plan.len() <= Self::MAX_ALLOCATIONS,
+            RebalancerErrorCode::TooManyStrategies
+        );
+        Ok(())
     }
     
-    let average_top_score: u64 = top_performers
-        .iter()
-        .map(|s| s.performance_score)
-        .sum::<u64>() / top_performers.len() as u64;
+    /// Amount the plan extracts from `strategy_id`; zero unless it is an
+    /// extraction target.
+    pub fn extraction_amount(&self, strategy_id: &Pubkey) -> u64 {
+        self.extraction_targets
+            .iter()
+            .zip(&self.extraction_amounts)
+            .find(|(target, _)| *target == strategy_id)
+            .map_or(0, |(_, amount)| *amount)
+    }
     
-    // Estimate 10-20% performance improvement from rebalancing
-    (average_top_score * 15) / 100
+    /// Expected improvement in lamports: the yield gain earned on the capital
+    /// being moved.
+    pub fn expected_improvement_lamports(&self) -> Result<u64> {
+        apply_bps(self.total_to_extract, self.expected_improvement)
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// How a strategy's balance moves under a plan.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeltaRole {
+    Source,         // Capital is extracted from it
+    Destination,    // Capital is allocated to it
+    Unchanged,      // Not touched by the plan
+}
+
+/// One strategy's balance before and after a plan is carried out.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct StrategyDelta {
+    pub strategy_id: Pubkey,
+    pub old_balance: u64,
+    pub new_balance: u64,
+    pub role: DeltaRole,
+}
+
+impl StrategyDelta {
+    pub const SIZE: usize = 32 + 8 + 8 + 1; // strategy_id, old_balance, new_balance, role
+}
+
+/// Most deltas one simulation call returns: the page, with its Vec length
+/// prefix, must fit in the transaction return data.
+pub const MAX_DELTAS_PER_PAGE: usize = (MAX_RETURN_DATA - 4) / StrategyDelta::SIZE;
+
+/// The `limit` deltas starting at `offset`, in strategy order. An offset past
+/// the end yields an empty page.
+pub fn page_deltas(deltas: Vec<StrategyDelta>, offset: u32, limit: u8) -> Result<Vec<StrategyDelta>> {
+    require!(
+        limit > 0 && limit as usize <= MAX_DELTAS_PER_PAGE,
+        RebalancerErrorCode::InvalidDeltaPage
+    );
+    Ok(deltas.into_iter().skip(offset as usize).take(limit as usize).collect())
+}
+
+// PER-STRATEGY VIEW OF A PLAN
+// Extraction targets give up their planned extraction amount; strategy
+// allocations are credited to their destinations. Fee and unallocated entries leave the strategies altogether,
+// so the deltas sum to minus those amounts.
+pub fn compute_rebalance_deltas(
+    strategies: &[StrategyPerformanceData],
+    plan: &RebalancingPlan,
+) -> Result<Vec<StrategyDelta>> {
+    strategies
+        .iter()
+        .map(|strategy| {
+            let extracted = plan.extraction_amount(&strategy.strategy_id);
+            let allocated = plan.redistribution_plan
+                .iter()
+                .filter(|a| a.allocation_type.is_strategy_allocation() && a.strategy_id == strategy.strategy_id)
+                .try_fold(0u64, |total, a| total.checked_add(a.amount).ok_or(RebalancerErrorCode::BalanceOverflow))?;
+            
+            let new_balance = (strategy.current_balance - extracted)
+                .checked_add(allocated)
+                .ok_or(RebalancerErrorCode::BalanceOverflow)?;
+            let role = match new_balance.cmp(&strategy.current_balance) {
+                std::cmp::Ordering::Less => DeltaRole::Source,
+                std::cmp::Ordering::Greater => DeltaRole::Destination,
+                std::cmp::Ordering::Equal => DeltaRole::Unchanged,
+            };
+            
+            Ok(StrategyDelta {
+                strategy_id: strategy.strategy_id,
+                old_balance: strategy.current_balance,
+                new_balance,
+                role,
+            })
+        })
+        .collect()
+}
+
+// TARGET ALLOCATION PLANNER
+// Computes the buys and sells that move the portfolio toward the configured
+// protocol targets. Each protocol's target is split evenly across its active
+// strategies and capped by the single and group diversification limits;
+// deprecated strategies target zero and paused ones are left as they are.
+// Strategies in their reallocation cooldown may be sold down but not bought.
+// Sells keep the rent reserve, and whichever side is larger is scaled down so
+// the capital moved balances: the deltas always sum to zero.
+pub fn plan_target_allocation(
+    strategies: &[StrategyPerformanceData],
+    risk_limits: &RiskLimits,
+) -> Result<Vec<StrategyDelta>> {
+    risk_limits.validate_target_allocation()?;
     
-    #[test]
-    fn test_optimal_allocation_calculation() {
-        let available_capital = 10_000_000_000u64; // 10 SOL
+    let is_rebalanced = |s: &StrategyPerformanceData| s.status != StrategyStatus::Paused;
+    let total_capital = strategies
+        .iter()
+        .filter(|s| is_rebalanced(s))
+        .try_fold(0u64, |total, s| total.checked_add(s.current_balance).ok_or(RebalancerErrorCode::BalanceOverflow))?;
+    let max_single_allocation = apply_bps(total_capital, risk_limits.max_single_strategy_bps)?;
+    let max_group_allocation = apply_bps(total_capital, risk_limits.max_group_bps)?;
+    
+    // TARGET BALANCE PER STRATEGY
+    let mut group_totals: Vec<(Pubkey, u64)> = Vec::new();
+    let mut sells = Vec::with_capacity(strategies.len());
+    let mut buys = Vec::with_capacity(strategies.len());
+    for strategy in strategies {
+        let target = match strategy.status {
+            StrategyStatus::Paused => strategy.current_balance,
+            StrategyStatus::Deprecated => 0,
+            StrategyStatus::Active => {
+                let peers = strategies
+                    .iter()
+                    .filter(|s| s.status == StrategyStatus::Active
+                        && std::mem::discriminant(&s.protocol_type) == std::mem::discriminant(&strategy.protocol_type))
+                    .count() as u64;
+                let protocol_target = apply_bps(total_capital, risk_limits.target_bps(&strategy.protocol_type) as u64)?;
+                let group_key = strategy.protocol_type.correlation_key();
+                let mut target = (protocol_target / peers)
+                    .min(max_single_allocation)
+                    .min(max_group_allocation.saturating_sub(group_total(&group_totals, &group_key)));
+                if strategy.in_cooldown {
+                    target = target.min(strategy.current_balance);
+                }
+                add_to_group(&mut group_totals, group_key, target)?;
+                target
+            }
+        };
         
-        let top_strategies = vec![
-            StrategyPerformanceData {
-                strategy_id: Pubkey::new_unique(),
-                performance_score: 8000,
-                current_balance: 1_000_000_000,
-                volatility_score: 2000,
-                protocol_type: ProtocolType::StableLending {
-                    pool_id: Pubkey::new_unique(),
-                    utilization: 7500,
-                    reserve_address: Pubkey::new_unique(),
-                },
-                percentile_rank: 90,
-            },
-            StrategyPerformanceData {
-                strategy_id: Pubkey::new_unique(),
-                performance_score: 7000,
-                current_balance: 2_000_000_000,
-                volatility_score: 3000,
-                protocol_type: ProtocolType::YieldFarming {
-                    pair_id: Pubkey::new_unique(),
-                    reward_multiplier: 3,
-                    token_a_mint: Pubkey::new_unique(),
-                    token_b_mint: Pubkey::new_unique(),
-                    fee_tier: 300,
-                },
-                percentile_rank: 85,
-            },
-            StrategyPerformanceData {
-                strategy_id: Pubkey::new_unique(),
-                performance_score: 6000,
-                current_balance: 500_000_000,
-                volatility_score: 4000,
-                protocol_type: ProtocolType::LiquidStaking {
-                    validator_id: Pubkey::new_unique(),
-                    commission: 500,
+        let sell = strategy.current_balance
+            .saturating_sub(target)
+            .min(extractable_balance(strategy.current_balance, &strategy.mint));
+        sells.push(sell);
+        buys.push(target.saturating_sub(strategy.current_balance));
+    }
+    
+    // BALANCE THE TWO SIDES
+    let total_sells = sells.iter().try_fold(0u64, |total, x| total.checked_add(*x).ok_or(RebalancerErrorCode::BalanceOverflow))?;
+    let total_buys = buys.iter().try_fold(0u64, |total, x| total.checked_add(*x).ok_or(RebalancerErrorCode::BalanceOverflow))?;
+    let capital_moved = total_sells.min(total_buys);
+    let sells = scale_to_total(&sells, total_sells, capital_moved);
+    let buys = scale_to_total(&buys, total_buys, capital_moved);
+    
+    strategies
+        .iter()
+        .zip(sells.iter().zip(&buys))
+        .map(|(strategy, (sell, buy))| {
+            let new_balance = (strategy.current_balance - sell)
+                .checked_add(*buy)
+                .ok_or(RebalancerErrorCode::BalanceOverflow)?;
+            let role = match new_balance.cmp(&strategy.current_balance) {
+                std::cmp::Ordering::Less => DeltaRole::Source,
+                std::cmp::Ordering::Greater => DeltaRole::Destination,
+                std::cmp::Ordering::Equal => DeltaRole::Unchanged,
+            };
+            
+            Ok(StrategyDelta {
+                strategy_id: strategy.strategy_id,
+                old_balance: strategy.current_balance,
+                new_balance,
+                role,
+            })
+        })
+        .collect()
+}
+
+// SHRINK AMOUNTS SUMMING TO `sum` PROPORTIONALLY SO THEY SUM TO `total`
+// Rounding leftovers go one lamport at a time to the earliest amounts that
+// still have room, so no entry ever grows past its original value.
+fn scale_to_total(amounts: &[u64], sum: u64, total: u64) -> Vec<u64> {
+    if sum == total {
+        return amounts.to_vec();
+    }
+    let mut scaled: Vec<u64> = amounts
+        .iter()
+        .map(|amount| (*amount as u128 * total as u128 / sum as u128) as u64)
+        .collect();
+    let mut leftover = total - scaled.iter().sum::<u64>();
+    for (scaled, amount) in scaled.iter_mut().zip(amounts) {
+        if leftover == 0 {
+            break;
+        }
+        let top_up = leftover.min(amount - *scaled);
+        *scaled += top_up;
+        leftover -= top_up;
+    }
+    scaled
+}
+
+// TARGET ALLOCATION REBALANCING
+// Carries out plan_target_allocation: sources are sold down and destinations
+// bought up toward their targets. The largest moves go first, at most
+// MAX_EXTRACTION_TARGETS sources and MAX_TARGET_DESTINATIONS destinations per
+// rebalance, with the two sides balanced again after the cut; portfolios with
+// more strategies converge over successive rebalances. Platform and manager
+// fees come out of the capital headed to destinations past their fee grace
+// period, as in calculate_optimal_allocation.
+fn plan_target_rebalancing(
+    strategies: &[StrategyPerformanceData],
+    risk_limits: &RiskLimits,
+) -> Result<RebalancingPlan> {
+    risk_limits.validate_total_fees()?;
+    let deltas = plan_target_allocation(strategies, risk_limits)?;
+    
+    let mut sells: Vec<(&StrategyPerformanceData, u64)> = Vec::new();
+    let mut buys: Vec<(&StrategyPerformanceData, u64)> = Vec::new();
+    for (strategy, delta) in strategies.iter().zip(&deltas) {
+        match delta.role {
+            DeltaRole::Source => sells.push((strategy, delta.old_balance - delta.new_balance)),
+            DeltaRole::Destination => buys.push((strategy, delta.new_balance - delta.old_balance)),
+            DeltaRole::Unchanged => {}
+        }
+    }
+    // Already on target is a healthy portfolio, as with no underperformers
+    require!(!sells.is_empty(), RebalancerErrorCode::NoUnderperformers);
+    
+    // LARGEST MOVES FIRST, WITHIN THE PLAN BOUNDS
+    sells.sort_by_key(|(_, amount)| std::cmp::Reverse(*amount));
+    sells.truncate(RebalancingPlan::MAX_EXTRACTION_TARGETS);
+    buys.sort_by_key(|(_, amount)| std::cmp::Reverse(*amount));
+    buys.truncate(MAX_TARGET_DESTINATIONS);
+    
+    let sell_amounts: Vec<u64> = sells.iter().map(|(_, amount)| *amount).collect();
+    let buy_amounts: Vec<u64> = buys.iter().map(|(_, amount)| *amount).collect();
+    let total_sells: u64 = sell_amounts.iter().sum();
+    let total_buys: u64 = buy_amounts.iter().sum();
+    let total_to_extract = total_sells.min(total_buys);
+    require!(total_to_extract >= risk_limits.min_rebalance_capital, RebalancerErrorCode::InsufficientBalance);
+    let sell_amounts = scale_to_total(&sell_amounts, total_sells, total_to_extract);
+    let buy_amounts = scale_to_total(&buy_amounts, total_buys, total_to_extract);
+    
+    // FEES COME OUT OF THE BUYS
+    let fee_base = buys
+        .iter()
+        .zip(&buy_amounts)
+        .filter(|((s, _), _)| !s.in_fee_grace)
+        .map(|(_, amount)| *amount)
+        .sum::<u64>();
+    let platform_fee = apply_bps(fee_base, risk_limits.platform_fee_bps)?;
+    let manager_fee = apply_bps(fee_base, risk_limits.manager_fee_bps)?;
+    let net_to_destinations = total_to_extract - platform_fee - manager_fee;
+    let buy_amounts = scale_to_total(&buy_amounts, total_to_extract, net_to_destinations);
+    
+    let mut allocations = Vec::new();
+    if platform_fee > 0 {
+        allocations.push(CapitalAllocation {
+            strategy_id: risk_limits.platform_treasury,
+            amount: platform_fee,
+            min_acceptable_amount: 0,
+            allocation_type: AllocationType::PlatformFee,
+        });
+    }
+    if manager_fee > 0 {
+        allocations.push(CapitalAllocation {
+            strategy_id: risk_limits.manager_treasury,
+            amount: manager_fee,
+            min_acceptable_amount: 0,
+            allocation_type: AllocationType::ManagerIncentive,
+        });
+    }
+    for ((strategy, _), amount) in buys.iter().zip(&buy_amounts).filter(|(_, amount)| **amount > 0) {
+        allocations.push(CapitalAllocation {
+            strategy_id: strategy.strategy_id,
+            amount: *amount,
+            min_acceptable_amount: 0,
+            allocation_type: AllocationType::RiskDiversification,
+        });
+    }
+    validate_allocation_total(&allocations, total_to_extract)?;
+    
+    let extractions: Vec<(Pubkey, u64)> = sells
+        .iter()
+        .zip(&sell_amounts)
+        .filter(|(_, amount)| **amount > 0)
+        .map(|((s, _), amount)| (s.strategy_id, *amount))
+        .collect();
+    
+    let mut plan = RebalancingPlan {
+        extraction_targets: extractions.iter().map(|(strategy_id, _)| *strategy_id).collect(),
+        extraction_amounts: extractions.iter().map(|(_, amount)| *amount).collect(),
+        total_to_extract,
+        redistribution_plan: allocations,
+        estimated_fees: apply_bps(total_to_extract, ESTIMATED_FEE_BPS)?,
+        expected_improvement: 0,
+    };
+    plan.expected_improvement = calculate_expected_improvement(strategies, &plan);
+    Ok(plan)
+}
+
+// NET BENEFIT GATE (run before committing a plan; previews report it unchecked)
+// Safe mode trades expected gains for safety, so it is not held to the gate.
+pub fn validate_net_benefit(plan: &RebalancingPlan, risk_limits: &RiskLimits) -> Result<()> {
+    if risk_limits.extract_only {
+        return Ok(());
+    }
+    let required_benefit = apply_bps(plan.estimated_fees, risk_limits.min_net_benefit_bps)?;
+    require!(
+        plan.expected_improvement_lamports()? >= required_benefit,
+        RebalancerErrorCode::RebalanceNotWorthwhile
+    );
+    Ok(())
+}
+
+// EXPECTED IMPROVEMENT (BPS OF THE CAPITAL MOVED)
+// The allocation-weighted yield of the destinations less the extraction-weighted
+// yield of the sources. Estimated fees are a share of the same capital, so the
+// net benefit gate compares like with like. A move to lower yields counts as no
+// improvement.
+pub fn calculate_expected_improvement(strategies: &[StrategyPerformanceData], plan: &RebalancingPlan) -> u64 {
+    let source_yield = weighted_yield(
+        strategies.iter().map(|s| (plan.extraction_amount(&s.strategy_id), s.yield_rate)),
+    );
+    let destination_yield = weighted_yield(
+        plan.redistribution_plan
+            .iter()
+            .filter(|a| a.allocation_type.is_strategy_allocation())
+            .filter_map(|a| {
+                strategies
+                    .iter()
+                    .find(|s| s.strategy_id == a.strategy_id)
+                    .map(|s| (a.amount, s.yield_rate))
+            }),
+    );
+    destination_yield.saturating_sub(source_yield)
+}
+
+// Average yield (bps) across (amount, yield) pairs, weighted by amount
+fn weighted_yield(entries: impl Iterator<Item = (u64, u64)>) -> u64 {
+    let (weighted, total) = entries.fold((0u128, 0u128), |(weighted, total), (amount, yield_rate)| {
+        (weighted + amount as u128 * yield_rate as u128, total + amount as u128)
+    });
+    weighted.checked_div(total).unwrap_or(0) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_optimal_allocation_calculation() {
+        let available_capital = 10_000_000_000u64; // 10 SOL
+        
+        let top_strategies = vec![
+            StrategyPerformanceData {
+                strategy_id: Pubkey::new_unique(),
+                performance_score: 8000,
+                yield_rate: 8000,
+                current_balance: 1_000_000_000,
+                volatility_score: 2000,
+                protocol_type: ProtocolType::StableLending {
+                    pool_id: Pubkey::new_unique(),
+                    utilization: 7500,
+                    reserve_address: Pubkey::new_unique(),
+                },
+                percentile_rank: 90,
+                status: StrategyStatus::Active,
+                mint: WRAPPED_SOL_MINT,
+                decimals: SOL_DECIMALS,
+                in_fee_grace: false,
+                in_cooldown: false,
+            },
+            StrategyPerformanceData {
+                strategy_id: Pubkey::new_unique(),
+                performance_score: 7000,
+                yield_rate: 7000,
+                current_balance: 2_000_000_000,
+                volatility_score: 3000,
+                protocol_type: ProtocolType::YieldFarming {
+                    pair_id: Pubkey::new_unique(),
+                    reward_multiplier: 3,
+                    token_a_mint: Pubkey::new_unique(),
+                    token_b_mint: Pubkey::new_unique(),
+                    fee_tier: 300,
+                },
+                percentile_rank: 85,
+                status: StrategyStatus::Active,
+                mint: WRAPPED_SOL_MINT,
+                decimals: SOL_DECIMALS,
+                in_fee_grace: false,
+                in_cooldown: false,
+            },
+            StrategyPerformanceData {
+                strategy_id: Pubkey::new_unique(),
+                performance_score: 6000,
+                yield_rate: 6000,
+                current_balance: 500_000_000,
+                volatility_score: 4000,
+                protocol_type: ProtocolType::LiquidStaking {
+                    validator_id: Pubkey::new_unique(),
+                    commission: 500,
                     stake_pool: Pubkey::new_unique(),
                     unstake_delay: 10,
                 },
                 percentile_rank: 80,
+                status: StrategyStatus::Active,
+                mint: WRAPPED_SOL_MINT,
+                decimals: SOL_DECIMALS,
+                in_fee_grace: false,
+                in_cooldown: false,
             },
         ];
         
@@ -395,6 +1520,7 @@ mod tests {
             available_capital,
             &top_strategies,
             &risk_limits,
+            AllocationMode::PerformanceWeighted,
         ).unwrap();
         
         // Verify allocations are created
@@ -420,7 +1546,7 @@ mod tests {
         println!("Test allocation results:");
         for allocation in &allocations {
             println!("  Strategy: {}, Amount: {}, Type: {:?}", 
-                     allocation.strategy_id.to_string()[..8].to_string(), 
+                     &allocation.strategy_id.to_string()[..8], 
                      allocation.amount, 
                      allocation.allocation_type);
         }
@@ -429,10 +1555,11 @@ mod tests {
     #[test]
     fn test_risk_adjustment_calculation() {
         let risk_limits = RiskLimits::default();
+        let lending = lending_strategy(5000, 1_000_000_000, 50).protocol_type;
         
         // Low volatility should get higher allocation
-        let low_vol_adjustment = calculate_risk_adjustment(1000, &risk_limits); // 10% volatility
-        let high_vol_adjustment = calculate_risk_adjustment(8000, &risk_limits); // 80% volatility
+        let low_vol_adjustment = calculate_risk_adjustment(1000, &lending, &risk_limits); // 10% volatility
+        let high_vol_adjustment = calculate_risk_adjustment(8000, &lending, &risk_limits); // 80% volatility
         
         assert!(low_vol_adjustment > high_vol_adjustment);
         assert!(low_vol_adjustment <= 15000); // Max 150%
@@ -441,6 +1568,51 @@ mod tests {
         println!("Risk adjustments - Low vol: {}, High vol: {}", low_vol_adjustment, high_vol_adjustment);
     }
     
+    #[test]
+    fn test_risk_adjustment_follows_protocol_curve() {
+        let risk_limits = RiskLimits { risk_tolerance_bps: 10000, ..RiskLimits::default() };
+        let lending = lending_strategy(5000, 1_000_000_000, 50).protocol_type;
+        let farming = ProtocolType::YieldFarming {
+            pair_id: Pubkey::new_unique(),
+            token_a_mint: Pubkey::new_unique(),
+            token_b_mint: Pubkey::new_unique(),
+            fee_tier: 30,
+            reward_multiplier: 2,
+        };
+        let staking = ProtocolType::LiquidStaking {
+            validator_id: Pubkey::new_unique(),
+            stake_pool: Pubkey::new_unique(),
+            unstake_delay: 10,
+            commission: 500,
+        };
+        let perpetual = ProtocolType::PerpetualFunding {
+            market_id: Pubkey::new_unique(),
+            funding_rate_bps: 10,
+            max_leverage: 3,
+        };
+        let adjustment = |protocol: &ProtocolType, volatility: u32| calculate_risk_adjustment(volatility, protocol, &risk_limits);
+        
+        // Lending keeps the linear curve: 50% volatility lands halfway, at 100%
+        assert_eq!(adjustment(&lending, 5000), 10000);
+        // The same volatility costs farming and perpetuals more, and staking less
+        assert_eq!(adjustment(&farming, 5000), 7500);
+        assert_eq!(adjustment(&perpetual, 5000), 7500);
+        assert_eq!(adjustment(&staking, 5000), 12500);
+        
+        // Every curve agrees at the ends of the range
+        for protocol in [&lending, &farming, &staking, &perpetual] {
+            assert_eq!(adjustment(protocol, 0), 15000);
+            assert_eq!(adjustment(protocol, 10000), 5000);
+        }
+        
+        // And each one penalises higher volatility at least as much as lower volatility
+        for protocol in [&lending, &farming, &staking, &perpetual] {
+            for volatility in (0..10000).step_by(500) {
+                assert!(adjustment(protocol, volatility) >= adjustment(protocol, volatility + 500));
+            }
+        }
+    }
+    
     #[test]
     fn test_rebalancing_plan_generation() {
         let portfolio = Portfolio {
@@ -454,7 +1626,21 @@ mod tests {
             emergency_pause: false,
             performance_fee_bps: 200,
             bump: 255,
-            reserved: [0u8; 31],
+            max_strategies: 0,
+            max_capital: 0,
+            guardian: Pubkey::default(),
+            emergency_rebalance_count: 0,
+            seed_manager: Pubkey::default(),
+            pending_manager: Pubkey::default(),
+            max_metric_staleness: DEFAULT_MAX_METRIC_STALENESS,
+            rebalance_sequence: 0,
+            high_water_mark: 0,
+            total_value_locked: 0,
+            volatility_smoothing_bps: DEFAULT_VOLATILITY_SMOOTHING_BPS,
+            oracle_authority: Pubkey::default(),
+            governance_enabled: false,
+            layout_version: PORTFOLIO_LAYOUT_VERSION,
+            reserved: [0u8; 3],
         };
         
         let strategies = vec![
@@ -462,6 +1648,7 @@ mod tests {
             StrategyPerformanceData {
                 strategy_id: Pubkey::new_unique(),
                 performance_score: 9000,
+                yield_rate: 9000,
                 current_balance: 5_000_000_000,
                 volatility_score: 1500,
                 protocol_type: ProtocolType::StableLending {
@@ -470,11 +1657,17 @@ mod tests {
                     reserve_address: Pubkey::new_unique(),
                 },
                 percentile_rank: 95,
+                status: StrategyStatus::Active,
+                mint: WRAPPED_SOL_MINT,
+                decimals: SOL_DECIMALS,
+                in_fee_grace: false,
+                in_cooldown: false,
             },
             // Underperformer
             StrategyPerformanceData {
                 strategy_id: Pubkey::new_unique(),
                 performance_score: 2000,
+                yield_rate: 2000,
                 current_balance: 2_000_000_000,
                 volatility_score: 8500,
                 protocol_type: ProtocolType::YieldFarming {
@@ -485,10 +1678,15 @@ mod tests {
                     fee_tier: 1000,
                 },
                 percentile_rank: 15, // Below 25% threshold
+                status: StrategyStatus::Active,
+                mint: WRAPPED_SOL_MINT,
+                decimals: SOL_DECIMALS,
+                in_fee_grace: false,
+                in_cooldown: false,
             },
         ];
         
-        let plan = execute_complete_rebalancing(&portfolio, &strategies).unwrap();
+        let plan = execute_complete_rebalancing(&portfolio, &strategies, &test_risk_limits()).unwrap();
         
         // Verify plan structure
         assert!(!plan.extraction_targets.is_empty());
@@ -502,4 +1700,1652 @@ mod tests {
         println!("  Redistribution allocations: {}", plan.redistribution_plan.len());
         println!("  Estimated fees: {}", plan.estimated_fees);
     }
-}
\ No newline at end of file
+    
+    fn test_portfolio() -> Portfolio {
+        Portfolio {
+            manager: Pubkey::new_unique(),
+            base_threshold: 15,
+            total_strategies: 3,
+            total_capital_moved: 0,
+            last_rebalance: 0,
+            min_rebalance_interval: 3600,
+            portfolio_creation: 0,
+            emergency_pause: false,
+            performance_fee_bps: 200,
+            bump: 255,
+            max_strategies: 0,
+            max_capital: 0,
+            guardian: Pubkey::default(),
+            emergency_rebalance_count: 0,
+            seed_manager: Pubkey::default(),
+            pending_manager: Pubkey::default(),
+            max_metric_staleness: DEFAULT_MAX_METRIC_STALENESS,
+            rebalance_sequence: 0,
+            high_water_mark: 0,
+            total_value_locked: 0,
+            volatility_smoothing_bps: DEFAULT_VOLATILITY_SMOOTHING_BPS,
+            oracle_authority: Pubkey::default(),
+            governance_enabled: false,
+            layout_version: PORTFOLIO_LAYOUT_VERSION,
+            reserved: [0u8; 3],
+        }
+    }
+    
+    fn test_risk_limits() -> RiskLimits {
+        RiskLimits {
+            platform_treasury: Pubkey::new_unique(),
+            manager_treasury: Pubkey::new_unique(),
+            ..RiskLimits::default()
+        }
+    }
+    
+    fn lending_strategy(performance_score: u64, current_balance: u64, percentile_rank: u8) -> StrategyPerformanceData {
+        StrategyPerformanceData {
+            strategy_id: Pubkey::new_unique(),
+            performance_score,
+            yield_rate: performance_score, // Yield tracks the score in these fixtures
+            current_balance,
+            volatility_score: 3000,
+            protocol_type: ProtocolType::StableLending {
+                pool_id: Pubkey::new_unique(),
+                utilization: 7500,
+                reserve_address: Pubkey::new_unique(),
+            },
+            percentile_rank,
+            status: StrategyStatus::Active,
+            mint: WRAPPED_SOL_MINT,
+            decimals: SOL_DECIMALS,
+            in_fee_grace: false,
+            in_cooldown: false,
+        }
+    }
+    
+    #[test]
+    fn test_max_size_plan_fits_return_data() {
+        let allocation = CapitalAllocation {
+            strategy_id: Pubkey::new_unique(),
+            amount: u64::MAX,
+            min_acceptable_amount: u64::MAX,
+            allocation_type: AllocationType::TopPerformer,
+        };
+        let mut plan = RebalancingPlan {
+            extraction_targets: vec![Pubkey::new_unique(); RebalancingPlan::MAX_EXTRACTION_TARGETS],
+            extraction_amounts: vec![u64::MAX; RebalancingPlan::MAX_EXTRACTION_TARGETS],
+            total_to_extract: u64::MAX,
+            redistribution_plan: vec![allocation.clone(); RebalancingPlan::MAX_ALLOCATIONS],
+            estimated_fees: u64::MAX,
+            expected_improvement: u64::MAX,
+        };
+        
+        assert!(plan.validate_size().is_ok());
+        let serialized = plan.try_to_vec().unwrap();
+        assert_eq!(serialized.len(), RebalancingPlan::MAX_SIZE);
+        assert!(serialized.len() <= MAX_RETURN_DATA);
+        
+        // It round-trips, as a client decoding the return data would
+        let decoded = RebalancingPlan::try_from_slice(&serialized).unwrap();
+        assert_eq!(decoded.redistribution_plan, plan.redistribution_plan);
+        
+        // Plans are built within the bounds; anything larger is refused
+        plan.redistribution_plan.push(allocation);
+        assert_eq!(plan.validate_size().unwrap_err(), RebalancerErrorCode::TooManyStrategies.into());
+        plan.redistribution_plan.pop();
+        plan.extraction_targets.push(Pubkey::new_unique());
+        assert_eq!(plan.validate_size().unwrap_err(), RebalancerErrorCode::TooManyStrategies.into());
+    }
+    
+    #[test]
+    fn test_plan_extracts_from_worst_underperformers_only() {
+        // Twelve underperformers, more than one plan may name
+        let mut strategies: Vec<StrategyPerformanceData> = (0..12u64)
+            .map(|i| lending_strategy(1000 + i * 100, 1_000_000_000, 0))
+            .collect();
+        strategies.push(lending_strategy(9000, 5_000_000_000, 100));
+        strategies[11].status = StrategyStatus::Deprecated;
+        
+        let plan = execute_complete_rebalancing(&test_portfolio(), &strategies, &test_risk_limits()).unwrap();
+        
+        // The deprecated strategy and the nine lowest scores are kept
+        assert!(plan.validate_size().is_ok());
+        let expected: Vec<Pubkey> = strategies[..9].iter().chain([&strategies[11]]).map(|s| s.strategy_id).collect();
+        assert_eq!(plan.extraction_targets, expected);
+        assert!(!plan.extraction_targets.contains(&strategies[9].strategy_id));
+        assert!(!plan.extraction_targets.contains(&strategies[10].strategy_id));
+        assert_eq!(plan.total_to_extract, 10 * extractable_balance(1_000_000_000, &WRAPPED_SOL_MINT));
+    }
+    
+    #[test]
+    fn test_delta_pages_fit_return_data() {
+        let delta = StrategyDelta {
+            strategy_id: Pubkey::new_unique(),
+            old_balance: u64::MAX,
+            new_balance: u64::MAX,
+            role: DeltaRole::Destination,
+        };
+        let deltas: Vec<StrategyDelta> = (0..MAX_DELTAS_PER_PAGE * 2 + 3)
+            .map(|i| StrategyDelta { old_balance: i as u64, ..delta.clone() })
+            .collect();
+        
+        // A full page serializes within the return data limit
+        let page = page_deltas(deltas.clone(), 0, MAX_DELTAS_PER_PAGE as u8).unwrap();
+        assert_eq!(page.len(), MAX_DELTAS_PER_PAGE);
+        assert!(page.try_to_vec().unwrap().len() <= MAX_RETURN_DATA);
+        
+        // Pages walk the deltas in order; the last one is short, then empty
+        let last = page_deltas(deltas.clone(), (MAX_DELTAS_PER_PAGE * 2) as u32, MAX_DELTAS_PER_PAGE as u8).unwrap();
+        assert_eq!(last.iter().map(|d| d.old_balance).collect::<Vec<_>>(), vec![40, 41, 42]);
+        assert!(page_deltas(deltas.clone(), u32::MAX, 1).unwrap().is_empty());
+        
+        // Empty and oversized pages are refused
+        for limit in [0, MAX_DELTAS_PER_PAGE as u8 + 1] {
+            assert_eq!(
+                page_deltas(deltas.clone(), 0, limit).unwrap_err(),
+                RebalancerErrorCode::InvalidDeltaPage.into()
+            );
+        }
+    }
+    
+    #[test]
+    fn test_net_benefit_gate_passes_beneficial_rebalance() {
+        let portfolio = test_portfolio();
+        let strategies = vec![
+            lending_strategy(9000, 5_000_000_000, 100),
+            lending_strategy(2000, 2_000_000_000, 0),
+        ];
+        let plan = execute_complete_rebalancing(&portfolio, &strategies, &test_risk_limits()).unwrap();
+        
+        // 9000 - 2000 = 7000 bps more yield on 1.99 SOL vs 200 bps of fees
+        assert_eq!(plan.expected_improvement, 7000);
+        assert_eq!(plan.expected_improvement_lamports().unwrap(), 1_393_000_000);
+        assert_eq!(plan.estimated_fees, 39_800_000);
+        assert!(validate_net_benefit(&plan, &test_risk_limits()).is_ok());
+    }
+    
+    #[test]
+    fn test_net_benefit_gate_rejects_fee_dominated_rebalance() {
+        let portfolio = test_portfolio();
+        // A far better score, but barely more yield
+        let strategies = vec![
+            StrategyPerformanceData { yield_rate: 1100, ..lending_strategy(9000, 5_000_000_000, 100) },
+            StrategyPerformanceData { yield_rate: 1000, ..lending_strategy(2000, 2_000_000_000, 0) },
+        ];
+        let plan = execute_complete_rebalancing(&portfolio, &strategies, &test_risk_limits()).unwrap();
+        
+        // 100 bps more yield cannot cover 200 bps of fees; scores do not count
+        assert_eq!(plan.expected_improvement, 100);
+        assert_eq!(
+            validate_net_benefit(&plan, &test_risk_limits()).unwrap_err(),
+            RebalancerErrorCode::RebalanceNotWorthwhile.into()
+        );
+        
+        // A manager may accept the trade by lowering the required ratio
+        let permissive = RiskLimits { min_net_benefit_bps: 5000, ..test_risk_limits() };
+        assert!(validate_net_benefit(&plan, &permissive).is_ok());
+        
+        // Demanding a multiple of the fees rejects it as well
+        let strict = RiskLimits { min_net_benefit_bps: 50000, ..test_risk_limits() };
+        assert!(validate_net_benefit(&plan, &strict).is_err());
+    }
+    
+    #[test]
+    fn test_extract_only_routes_everything_to_safe_haven() {
+        let portfolio = test_portfolio();
+        let safe_haven = Pubkey::new_unique();
+        let risk_limits = RiskLimits { extract_only: true, safe_haven, ..test_risk_limits() };
+        assert!(risk_limits.validate().is_ok());
+        
+        let strategies = vec![
+            lending_strategy(9000, 5_000_000_000, 100),
+            lending_strategy(8000, 4_000_000_000, 90),
+            lending_strategy(2000, 2_000_000_000, 0),
+            lending_strategy(1500, 3_000_000_000, 5),
+        ];
+        let plan = execute_complete_rebalancing(&portfolio, &strategies, &risk_limits).unwrap();
+        
+        assert_eq!(plan.extraction_targets, vec![strategies[2].strategy_id, strategies[3].strategy_id]);
+        let non_fee: Vec<&CapitalAllocation> = plan.redistribution_plan
+            .iter()
+            .filter(|a| !matches!(a.allocation_type, AllocationType::PlatformFee | AllocationType::ManagerIncentive))
+            .collect();
+        assert_eq!(non_fee.len(), 1);
+        assert_eq!(non_fee[0].strategy_id, safe_haven);
+        assert_eq!(non_fee[0].allocation_type, AllocationType::SafeHaven);
+        assert_eq!(non_fee[0].amount, plan.total_to_extract);
+        // Nothing is redeployed into the top performers
+        assert!(plan.redistribution_plan.iter().all(|a| !a.allocation_type.is_strategy_allocation()));
+        assert_eq!(plan.expected_improvement, 0);
+        // Pulling capital to safety is not held to the net benefit gate
+        assert!(validate_net_benefit(&plan, &risk_limits).is_ok());
+    }
+    
+    #[test]
+    fn test_extract_only_needs_no_top_performer() {
+        let portfolio = test_portfolio();
+        let risk_limits = RiskLimits { extract_only: true, safe_haven: Pubkey::new_unique(), ..test_risk_limits() };
+        // Every strategy ranks too low to receive capital
+        let strategies = vec![
+            lending_strategy(2000, 2_000_000_000, 0),
+            lending_strategy(2500, 2_000_000_000, 60),
+        ];
+        
+        assert!(execute_complete_rebalancing(&portfolio, &strategies, &test_risk_limits()).is_err());
+        let plan = execute_complete_rebalancing(&portfolio, &strategies, &risk_limits).unwrap();
+        assert_eq!(plan.redistribution_plan.len(), 1);
+        
+        // Safe mode without a destination is rejected when configured
+        let no_haven = RiskLimits { extract_only: true, ..test_risk_limits() };
+        assert_eq!(no_haven.validate().unwrap_err(), RebalancerErrorCode::InvalidTreasury.into());
+    }
+    
+    #[test]
+    fn test_recently_extracted_strategy_skipped_until_cooldown_elapses() {
+        let portfolio = test_portfolio();
+        let risk_limits = RiskLimits { reallocation_cooldown: 86400, ..test_risk_limits() };
+        let extracted_at = 1_000_000;
+        assert!(risk_limits.in_reallocation_cooldown(extracted_at, extracted_at + 86400 - 1));
+        assert!(!risk_limits.in_reallocation_cooldown(extracted_at, extracted_at + 86400));
+        assert!(!risk_limits.in_reallocation_cooldown(0, extracted_at)); // Never extracted
+        
+        // Drained last round, its score has since ticked back up to the top
+        let recovered = lending_strategy(9500, 1_000_000_000, 100);
+        let strategies = |current_time| vec![
+            StrategyPerformanceData {
+                in_cooldown: risk_limits.in_reallocation_cooldown(extracted_at, current_time),
+                ..recovered.clone()
+            },
+            lending_strategy(9000, 5_000_000_000, 95),
+            lending_strategy(2000, 2_000_000_000, 0),
+        ];
+        let receives_capital = |plan: &RebalancingPlan| {
+            plan.redistribution_plan.iter().any(|a| a.strategy_id == recovered.strategy_id)
+        };
+        
+        let plan = execute_complete_rebalancing(&portfolio, &strategies(extracted_at + 3600), &risk_limits).unwrap();
+        assert!(!receives_capital(&plan));
+        assert!(plan.redistribution_plan.iter().any(|a| a.allocation_type.is_strategy_allocation()));
+        
+        let plan = execute_complete_rebalancing(&portfolio, &strategies(extracted_at + 86400), &risk_limits).unwrap();
+        assert!(receives_capital(&plan));
+        
+        let too_long = RiskLimits { reallocation_cooldown: MAX_REALLOCATION_COOLDOWN + 1, ..test_risk_limits() };
+        assert_eq!(too_long.validate().unwrap_err(), RebalancerErrorCode::InvalidRiskLimits.into());
+    }
+    
+    #[test]
+    fn test_barely_funded_underperformer_is_skipped() {
+        let portfolio = test_portfolio();
+        let top_performer = lending_strategy(9000, 5_000_000_000, 100);
+        let well_funded = lending_strategy(2000, 2_000_000_000, 0);
+        // Only 1_000_000 lamports above the rent floor, below the 0.05 SOL minimum
+        let barely_funded = lending_strategy(1500, 11_000_000, 0);
+        
+        let strategies = vec![top_performer, well_funded.clone(), barely_funded.clone()];
+        let plan = execute_complete_rebalancing(&portfolio, &strategies, &test_risk_limits()).unwrap();
+        
+        assert_eq!(plan.extraction_targets, vec![well_funded.strategy_id]);
+        assert!(!plan.extraction_targets.contains(&barely_funded.strategy_id));
+        assert_eq!(plan.total_to_extract, 1_990_000_000);
+    }
+    
+    #[test]
+    fn test_extractable_capital_keeps_rent_reserve() {
+        let portfolio = test_portfolio();
+        let top_performer = lending_strategy(9000, 5_000_000_000, 100);
+        let first = lending_strategy(2000, 2_000_000_000, 0);
+        let second = lending_strategy(1800, 1_000_000_000, 0);
+        // Holding only the reserve leaves nothing to extract
+        let drained = lending_strategy(1500, STRATEGY_RENT_RESERVE, 0);
+        
+        let strategies = vec![top_performer, first.clone(), second.clone(), drained.clone()];
+        let plan = execute_complete_rebalancing(&portfolio, &strategies, &test_risk_limits()).unwrap();
+        
+        assert!(!plan.extraction_targets.contains(&drained.strategy_id));
+        assert_eq!(
+            plan.total_to_extract,
+            first.current_balance + second.current_balance - 2 * STRATEGY_RENT_RESERVE
+        );
+        assert_eq!(extractable_balance(STRATEGY_RENT_RESERVE - 1, &WRAPPED_SOL_MINT), 0);
+    }
+    
+    #[test]
+    fn test_rent_reserve_applies_to_native_sol_only() {
+        // The reserve is lamports; a token strategy can be drained completely
+        let usdc_mint = Pubkey::new_unique();
+        assert_eq!(extractable_balance(5_000_000, &usdc_mint), 5_000_000);
+        assert_eq!(extractable_balance(5_000_000, &WRAPPED_SOL_MINT), 0);
+        
+        let portfolio = test_portfolio();
+        let top_performer = lending_strategy(9000, 5_000_000_000, 100);
+        let usdc = StrategyPerformanceData { mint: usdc_mint, decimals: 6, ..lending_strategy(2000, 2_000_000_000, 0) };
+        let strategies = vec![top_performer, usdc.clone()];
+        let plan = execute_complete_rebalancing(&portfolio, &strategies, &test_risk_limits()).unwrap();
+        let deltas = compute_rebalance_deltas(&strategies, &plan).unwrap();
+        assert_eq!(deltas[1].old_balance - deltas[1].new_balance, usdc.current_balance);
+    }
+    
+    #[test]
+    fn test_rebalance_deltas_conserve_capital() {
+        let portfolio = test_portfolio();
+        let top = lending_strategy(9000, 5_000_000_000, 100);
+        let runner_up = lending_strategy(8500, 4_000_000_000, 80);
+        let middle = lending_strategy(5000, 3_000_000_000, 50);
+        let first = lending_strategy(2000, 2_000_000_000, 0);
+        let second = lending_strategy(1800, 1_000_000_000, 0);
+        
+        let strategies = vec![top.clone(), runner_up.clone(), middle.clone(), first.clone(), second.clone()];
+        let plan = execute_complete_rebalancing(&portfolio, &strategies, &test_risk_limits()).unwrap();
+        let deltas = compute_rebalance_deltas(&strategies, &plan).unwrap();
+        
+        let roles: Vec<DeltaRole> = deltas.iter().map(|d| d.role).collect();
+        assert_eq!(roles, vec![
+            DeltaRole::Destination,
+            DeltaRole::Destination,
+            DeltaRole::Unchanged,
+            DeltaRole::Source,
+            DeltaRole::Source,
+        ]);
+        assert_eq!(deltas[2].old_balance, deltas[2].new_balance);
+        assert_eq!(deltas[3].new_balance, STRATEGY_RENT_RESERVE);
+        
+        // Ignoring fees and unallocated capital, what sources lose destinations gain
+        let net: i128 = deltas.iter().map(|d| d.new_balance as i128 - d.old_balance as i128).sum();
+        let leaves_strategies: u64 = plan.redistribution_plan
+            .iter()
+            .filter(|a| !a.allocation_type.is_strategy_allocation())
+            .map(|a| a.amount)
+            .sum();
+        assert!(leaves_strategies > 0);
+        assert_eq!(net + leaves_strategies as i128, 0);
+    }
+    
+    #[test]
+    fn test_underperformer_at_min_extraction_is_targeted() {
+        let portfolio = test_portfolio();
+        let top_performer = lending_strategy(9000, 5_000_000_000, 100);
+        let well_funded = lending_strategy(2000, 2_000_000_000, 0);
+        let at_minimum = lending_strategy(1500, STRATEGY_RENT_RESERVE + MIN_EXTRACTION_PER_STRATEGY, 0);
+        
+        let strategies = vec![top_performer, well_funded.clone(), at_minimum.clone()];
+        let plan = execute_complete_rebalancing(&portfolio, &strategies, &test_risk_limits()).unwrap();
+        
+        assert_eq!(plan.extraction_targets, vec![well_funded.strategy_id, at_minimum.strategy_id]);
+        assert_eq!(plan.total_to_extract, 1_990_000_000 + MIN_EXTRACTION_PER_STRATEGY);
+    }
+    
+    #[test]
+    fn test_dust_underperformer_is_not_targeted() {
+        let portfolio = test_portfolio();
+        let top_performer = lending_strategy(9000, 5_000_000_000, 100);
+        let large = lending_strategy(2000, 2_000_000_000, 0);
+        // A few thousand lamports above the rent reserve: not worth an extraction
+        let dust = lending_strategy(1500, STRATEGY_RENT_RESERVE + 5_000, 0);
+        
+        let strategies = vec![top_performer, large.clone(), dust];
+        let plan = execute_complete_rebalancing(&portfolio, &strategies, &test_risk_limits()).unwrap();
+        
+        assert_eq!(plan.extraction_targets, vec![large.strategy_id]);
+        assert_eq!(plan.total_to_extract, 1_990_000_000);
+    }
+    
+    #[test]
+    fn test_all_strategies_above_threshold_reports_no_underperformers() {
+        let portfolio = test_portfolio();
+        // Tightly clustered ranks, all above even the highest dynamic threshold
+        let strategies = vec![
+            lending_strategy(9000, 5_000_000_000, 100),
+            lending_strategy(8800, 4_000_000_000, 90),
+            lending_strategy(8600, 3_000_000_000, 80),
+        ];
+        
+        assert_eq!(
+            execute_complete_rebalancing(&portfolio, &strategies, &test_risk_limits()).unwrap_err(),
+            RebalancerErrorCode::NoUnderperformers.into()
+        );
+        
+        let safe_mode = RiskLimits {
+            extract_only: true,
+            safe_haven: Pubkey::new_unique(),
+            ..test_risk_limits()
+        };
+        assert_eq!(
+            execute_complete_rebalancing(&portfolio, &strategies, &safe_mode).unwrap_err(),
+            RebalancerErrorCode::NoUnderperformers.into()
+        );
+    }
+    
+    #[test]
+    fn test_deprecated_strategy_is_always_extracted() {
+        let portfolio = test_portfolio();
+        let top_performer = lending_strategy(9000, 5_000_000_000, 100);
+        let underperformer = lending_strategy(2000, 2_000_000_000, 0);
+        // Ranked well and below the per-strategy minimum, but deprecated
+        let deprecated = StrategyPerformanceData {
+            status: StrategyStatus::Deprecated,
+            ..lending_strategy(8000, 30_000_000, 90)
+        };
+        
+        let strategies = vec![top_performer, underperformer.clone(), deprecated.clone()];
+        let plan = execute_complete_rebalancing(&portfolio, &strategies, &test_risk_limits()).unwrap();
+        
+        assert_eq!(plan.extraction_targets, vec![underperformer.strategy_id, deprecated.strategy_id]);
+        assert_eq!(plan.total_to_extract, 1_990_000_000 + 20_000_000);
+        assert!(plan.redistribution_plan.iter().all(|a| a.strategy_id != deprecated.strategy_id));
+    }
+    
+    #[test]
+    fn test_deprecated_strategies_receive_no_new_allocation() {
+        let available_capital = 10_000_000_000;
+        let active = lending_strategy(5000, 1_000_000_000, 80);
+        // Highest stored score, but deprecated and paused strategies are only extraction sources
+        let deprecated = StrategyPerformanceData {
+            status: StrategyStatus::Deprecated,
+            ..lending_strategy(9500, 1_000_000_000, 100)
+        };
+        let paused = StrategyPerformanceData {
+            status: StrategyStatus::Paused,
+            ..lending_strategy(9000, 1_000_000_000, 95)
+        };
+        
+        let top_strategies = vec![deprecated.clone(), paused.clone(), active.clone()];
+        let allocations = calculate_optimal_allocation(available_capital, &top_strategies, &test_risk_limits(), AllocationMode::PerformanceWeighted).unwrap();
+        
+        let allocated_to = |id: Pubkey| allocations.iter().filter(|a| a.strategy_id == id).map(|a| a.amount).sum::<u64>();
+        assert_eq!(allocated_to(deprecated.strategy_id), 0);
+        assert_eq!(allocated_to(paused.strategy_id), 0);
+        assert!(allocated_to(active.strategy_id) > 0);
+        // The active strategy is now the leading destination
+        assert!(allocations.iter().any(|a| a.strategy_id == active.strategy_id
+            && matches!(a.allocation_type, AllocationType::TopPerformer)));
+        
+        // With no active destination at all there is nothing to allocate to
+        assert_eq!(
+            calculate_optimal_allocation(available_capital, &[deprecated, paused], &test_risk_limits(), AllocationMode::PerformanceWeighted).unwrap_err(),
+            RebalancerErrorCode::InsufficientStrategies.into()
+        );
+    }
+    
+    #[test]
+    fn test_over_allocation_is_caught() {
+        let available_capital = 1_000_000_000;
+        let allocation = |amount, allocation_type| CapitalAllocation {
+            strategy_id: Pubkey::new_unique(),
+            amount,
+            min_acceptable_amount: 0,
+            allocation_type,
+        };
+        
+        let mut allocations = calculate_optimal_allocation(
+            available_capital, &[lending_strategy(8000, 1_000_000_000, 90)], &test_risk_limits(), AllocationMode::PerformanceWeighted,
+        ).unwrap();
+        assert!(validate_allocation_total(&allocations, available_capital).is_ok());
+        
+        // A dust top-up that forgot to draw down the remainder
+        allocations.push(allocation(1, AllocationType::TopPerformer));
+        assert_eq!(
+            validate_allocation_total(&allocations, available_capital).unwrap_err(),
+            RebalancerErrorCode::AllocationExceedsCapital.into()
+        );
+        
+        // A sum that cannot even be represented is an overflow, not a silent wrap
+        let overflowing = vec![
+            allocation(u64::MAX, AllocationType::TopPerformer),
+            allocation(1, AllocationType::Unallocated),
+        ];
+        assert_eq!(
+            validate_allocation_total(&overflowing, u64::MAX).unwrap_err(),
+            RebalancerErrorCode::BalanceOverflow.into()
+        );
+    }
+    
+    #[test]
+    fn test_usdc_strategy_minimum_scales_with_decimals() {
+        // 10 USDC to place; the 40% cap leaves at most 4 USDC (4_000_000 base units) per strategy
+        let available_capital = 10_000_000;
+        let usdc = StrategyPerformanceData { decimals: 6, ..lending_strategy(8000, 1_000_000_000, 90) };
+        let sol = lending_strategy(8000, 1_000_000_000, 90);
+        
+        let funded = |strategy: &StrategyPerformanceData| {
+            calculate_optimal_allocation(available_capital, std::slice::from_ref(strategy), &test_risk_limits(), AllocationMode::PerformanceWeighted)
+                .unwrap()
+                .iter()
+                .any(|a| a.strategy_id == strategy.strategy_id)
+        };
+        
+        // Above the 0.1 USDC minimum, but below the 0.1 SOL minimum for a 9-decimal mint
+        assert!(funded(&usdc));
+        assert!(!funded(&sol));
+        
+        let limits = test_risk_limits();
+        assert_eq!(limits.min_allocation_amount(&usdc.protocol_type, 6), 100_000);
+        assert_eq!(limits.min_allocation_amount(&sol.protocol_type, SOL_DECIMALS), STABLE_LENDING_MIN_LAMPORTS);
+    }
+    
+    #[test]
+    fn test_fee_grace_period_for_new_strategies() {
+        let week = 7 * 86400;
+        let limits = RiskLimits { fee_grace_period: week, ..test_risk_limits() };
+        let created = 1_000;
+        
+        assert!(limits.in_fee_grace(created, created + week - 1));
+        assert!(!limits.in_fee_grace(created, created + week));
+        // Disabled by default
+        assert!(!test_risk_limits().in_fee_grace(created, created));
+        
+        let available_capital = 10_000_000_000;
+        let fees = |strategies: &[StrategyPerformanceData]| -> u64 {
+            calculate_optimal_allocation(available_capital, strategies, &limits, AllocationMode::PerformanceWeighted)
+                .unwrap()
+                .iter()
+                .filter(|a| matches!(a.allocation_type, AllocationType::PlatformFee | AllocationType::ManagerIncentive))
+                .map(|a| a.amount)
+                .sum()
+        };
+        let within_grace = StrategyPerformanceData { in_fee_grace: true, ..lending_strategy(8000, 1_000_000_000, 90) };
+        let past_grace = lending_strategy(8000, 1_000_000_000, 90);
+        
+        // 0.5% platform + 1.5% manager on the whole amount once the grace period is over
+        assert_eq!(fees(std::slice::from_ref(&past_grace)), 200_000_000);
+        assert_eq!(fees(std::slice::from_ref(&within_grace)), 0);
+        // Only the share weighted toward the established strategy is charged
+        assert_eq!(fees(&[within_grace, past_grace]), 100_000_000);
+        
+        assert!(RiskLimits { fee_grace_period: -1, ..test_risk_limits() }.validate().is_err());
+        assert!(RiskLimits { fee_grace_period: MAX_FEE_GRACE_PERIOD + 1, ..test_risk_limits() }.validate().is_err());
+        assert!(limits.validate().is_ok());
+    }
+    
+    #[test]
+    fn test_combined_fee_cap() {
+        // Up to 10% combined is accepted, split any way
+        for (platform_fee_bps, manager_fee_bps) in [(50, 150), (500, 500), (0, 1000), (1000, 0)] {
+            let limits = RiskLimits { platform_fee_bps, manager_fee_bps, ..test_risk_limits() };
+            assert!(limits.validate().is_ok());
+            assert!(calculate_optimal_allocation(
+                10_000_000_000, &[lending_strategy(8000, 1_000_000_000, 90)], &limits, AllocationMode::PerformanceWeighted,
+            ).is_ok());
+        }
+        
+        // Each fee is modest on its own but together they cross the cap
+        let excessive = RiskLimits { platform_fee_bps: 600, manager_fee_bps: 401, ..test_risk_limits() };
+        assert_eq!(excessive.validate().unwrap_err(), RebalancerErrorCode::ExcessiveFees.into());
+        assert_eq!(
+            calculate_optimal_allocation(
+                10_000_000_000, &[lending_strategy(8000, 1_000_000_000, 90)], &excessive, AllocationMode::PerformanceWeighted,
+            ).unwrap_err(),
+            RebalancerErrorCode::ExcessiveFees.into()
+        );
+        
+        let overflowing = RiskLimits { platform_fee_bps: u64::MAX, manager_fee_bps: 1, ..test_risk_limits() };
+        assert_eq!(overflowing.validate().unwrap_err(), RebalancerErrorCode::ExcessiveFees.into());
+    }
+    
+    #[test]
+    fn test_protocol_diversity_requirement() {
+        let portfolio = test_portfolio();
+        let farming = |performance_score, current_balance, percentile_rank| StrategyPerformanceData {
+            protocol_type: ProtocolType::YieldFarming {
+                pair_id: Pubkey::new_unique(),
+                reward_multiplier: 2,
+                token_a_mint: Pubkey::new_unique(),
+                token_b_mint: Pubkey::new_unique(),
+                fee_tier: 30,
+            },
+            ..lending_strategy(performance_score, current_balance, percentile_rank)
+        };
+        let all_farming = vec![
+            farming(9000, 5_000_000_000, 100),
+            farming(8500, 5_000_000_000, 90),
+            farming(2000, 5_000_000_000, 0),
+        ];
+        let diverse = RiskLimits { require_protocol_diversity: true, ..test_risk_limits() };
+        
+        // Off by default: a single-protocol plan is allowed
+        assert!(execute_complete_rebalancing(&portfolio, &all_farming, &test_risk_limits()).is_ok());
+        assert_eq!(
+            execute_complete_rebalancing(&portfolio, &all_farming, &diverse).unwrap_err(),
+            RebalancerErrorCode::InsufficientDiversity.into()
+        );
+        
+        // One lending top performer alongside farming satisfies the requirement
+        let mixed = vec![
+            farming(9000, 5_000_000_000, 100),
+            lending_strategy(8500, 5_000_000_000, 90),
+            farming(2000, 5_000_000_000, 0),
+        ];
+        assert!(execute_complete_rebalancing(&portfolio, &mixed, &diverse).is_ok());
+    }
+    
+    #[test]
+    fn test_paused_strategies_are_neither_extracted_nor_funded() {
+        let portfolio = test_portfolio();
+        let top_performer = lending_strategy(9000, 5_000_000_000, 100);
+        let underperformer = lending_strategy(2000, 2_000_000_000, 0);
+        // Paused with stale ranks from before the pause
+        let paused_bottom = StrategyPerformanceData {
+            status: StrategyStatus::Paused,
+            ..lending_strategy(1000, 3_000_000_000, 0)
+        };
+        let paused_top = StrategyPerformanceData {
+            status: StrategyStatus::Paused,
+            ..lending_strategy(9500, 1_000_000_000, 100)
+        };
+        
+        let strategies = vec![top_performer.clone(), underperformer.clone(), paused_bottom.clone(), paused_top.clone()];
+        let plan = execute_complete_rebalancing(&portfolio, &strategies, &test_risk_limits()).unwrap();
+        
+        assert_eq!(plan.extraction_targets, vec![underperformer.strategy_id]);
+        assert!(plan.redistribution_plan.iter().all(|a| {
+            a.strategy_id != paused_bottom.strategy_id && a.strategy_id != paused_top.strategy_id
+        }));
+        assert!(plan.redistribution_plan.iter().any(|a| a.strategy_id == top_performer.strategy_id));
+    }
+    
+    #[test]
+    fn test_allocation_to_unregistered_strategy_rejected() {
+        let registered = vec![Pubkey::new_unique(), Pubkey::new_unique()];
+        let allocations = vec![
+            CapitalAllocation {
+                strategy_id: registered[0],
+                amount: 1_000_000_000,
+                min_acceptable_amount: 0,
+                allocation_type: AllocationType::TopPerformer,
+            },
+            CapitalAllocation {
+                strategy_id: Pubkey::new_unique(), // Not a registered strategy
+                amount: 500_000_000,
+                min_acceptable_amount: 0,
+                allocation_type: AllocationType::RiskDiversification,
+            },
+        ];
+        
+        let result = validate_allocation_destinations(&allocations, &registered);
+        assert_eq!(result.unwrap_err(), RebalancerErrorCode::StrategyNotFound.into());
+    }
+    
+    #[test]
+    fn test_fee_allocations_skip_destination_check() {
+        let registered = vec![Pubkey::new_unique()];
+        let allocations = vec![
+            CapitalAllocation {
+                strategy_id: Pubkey::new_unique(), // Platform treasury
+                amount: 5_000_000,
+                min_acceptable_amount: 0,
+                allocation_type: AllocationType::PlatformFee,
+            },
+            CapitalAllocation {
+                strategy_id: Pubkey::new_unique(), // Manager treasury
+                amount: 15_000_000,
+                min_acceptable_amount: 0,
+                allocation_type: AllocationType::ManagerIncentive,
+            },
+            CapitalAllocation {
+                strategy_id: registered[0],
+                amount: 980_000_000,
+                min_acceptable_amount: 0,
+                allocation_type: AllocationType::TopPerformer,
+            },
+        ];
+        
+        assert!(validate_allocation_destinations(&allocations, &registered).is_ok());
+    }
+    
+    #[test]
+    fn test_resume_after_partial_execution_applies_each_allocation_once() {
+        let first = Pubkey::new_unique();
+        let second = Pubkey::new_unique();
+        let allocations = vec![
+            CapitalAllocation {
+                strategy_id: Pubkey::new_unique(), // Platform treasury
+                amount: 20_000_000,
+                min_acceptable_amount: 0,
+                allocation_type: AllocationType::PlatformFee,
+            },
+            CapitalAllocation {
+                strategy_id: first,
+                amount: 1_000_000_000,
+                min_acceptable_amount: 0,
+                allocation_type: AllocationType::TopPerformer,
+            },
+            CapitalAllocation {
+                strategy_id: second,
+                amount: 500_000_000,
+                min_acceptable_amount: 0,
+                allocation_type: AllocationType::TopPerformer,
+            },
+        ];
+        let mut execution = RedistributionExecution {
+            portfolio: Pubkey::new_unique(),
+            execution_id: 7,
+            allocations_hash: [0u8; 32],
+            allocation_count: 0,
+            executed: 0,
+            bump: 255,
+        };
+        let mut total_capital_moved = 0u64;
+        let mut apply = |execution: &mut RedistributionExecution, passed: &[Pubkey]| -> Result<Vec<usize>> {
+            execution.bind(&allocations)?;
+            let pending = pending_allocation_indices(&allocations, execution, passed)?;
+            for &index in &pending {
+                execution.mark_executed(index);
+                total_capital_moved += allocations[index].amount;
+            }
+            Ok(pending)
+        };
+        
+        // The first call only reaches the first strategy; the second allocation waits
+        assert_eq!(apply(&mut execution, &[first]).unwrap(), vec![0, 1]);
+        assert!(!execution.is_complete());
+        
+        // The resumed call passes both strategies but only applies what is still pending
+        assert_eq!(apply(&mut execution, &[first, second]).unwrap(), vec![2]);
+        assert!(execution.is_complete());
+        
+        // A replay of the finished plan applies nothing
+        assert_eq!(
+            apply(&mut execution, &[first, second]).unwrap_err(),
+            RebalancerErrorCode::NoPendingAllocations.into()
+        );
+        assert_eq!(total_capital_moved, 1_520_000_000);
+    }
+    
+    #[test]
+    fn test_capital_moved_excludes_fees_and_unallocated() {
+        let allocation = |amount, allocation_type| CapitalAllocation {
+            strategy_id: Pubkey::new_unique(),
+            amount,
+            min_acceptable_amount: 0,
+            allocation_type,
+        };
+        let allocations = vec![
+            allocation(1_000_000_000, AllocationType::TopPerformer),
+            allocation(500_000_000, AllocationType::RiskDiversification),
+            allocation(10_000_000, AllocationType::PlatformFee),
+            allocation(20_000_000, AllocationType::ManagerIncentive),
+            allocation(3_000_000, AllocationType::Unallocated),
+        ];
+        
+        assert_eq!(validate_allocations(&allocations).unwrap(), 1_533_000_000);
+        assert_eq!(capital_moved(&allocations).unwrap(), 1_500_000_000);
+        
+        // Safe mode parks the extracted capital, which still counts as moved
+        assert_eq!(capital_moved(&[allocation(2_000_000_000, AllocationType::SafeHaven)]).unwrap(), 2_000_000_000);
+    }
+    
+    #[test]
+    fn test_allocation_without_strategy_accounts_rejected() {
+        let treasury_only = vec![CapitalAllocation {
+            strategy_id: Pubkey::new_unique(),
+            amount: 5_000_000,
+            min_acceptable_amount: 0,
+            allocation_type: AllocationType::PlatformFee,
+        }];
+        let to_strategy = vec![CapitalAllocation {
+            strategy_id: Pubkey::new_unique(),
+            amount: 1_000_000_000,
+            min_acceptable_amount: 0,
+            allocation_type: AllocationType::TopPerformer,
+        }];
+        
+        // Leaving the strategy accounts out no longer skips the check
+        assert_eq!(
+            validate_allocation_destinations(&to_strategy, &[]).unwrap_err(),
+            RebalancerErrorCode::StrategyNotFound.into()
+        );
+        assert!(validate_allocation_destinations(&treasury_only, &[]).is_ok());
+    }
+    
+    #[test]
+    fn test_fees_on_very_large_capital_do_not_overflow() {
+        // Just under the validated allocation ceiling; raw u64 `capital * bps` would overflow here
+        let available_capital = u64::MAX / 1000 - 1;
+        let top_strategies = vec![lending_strategy(8000, 1_000_000_000, 90)];
+        let risk_limits = RiskLimits {
+            platform_treasury: Pubkey::new_unique(),
+            manager_treasury: Pubkey::new_unique(),
+            ..RiskLimits::default()
+        };
+        
+        let allocations = calculate_optimal_allocation(available_capital, &top_strategies, &risk_limits, AllocationMode::PerformanceWeighted).unwrap();
+        
+        let expected_platform_fee = (available_capital as u128 * PLATFORM_FEE_BPS as u128 / 10000) as u64;
+        let expected_manager_fee = (available_capital as u128 * MANAGER_FEE_BPS as u128 / 10000) as u64;
+        assert_eq!(allocations[0].amount, expected_platform_fee);
+        assert!(matches!(allocations[0].allocation_type, AllocationType::PlatformFee));
+        assert_eq!(allocations[1].amount, expected_manager_fee);
+        assert!(matches!(allocations[1].allocation_type, AllocationType::ManagerIncentive));
+    }
+    
+    #[test]
+    fn test_estimated_fees_on_very_large_extraction_do_not_overflow() {
+        // Raw `total_extractable * 200` would overflow above u64::MAX / 200
+        let portfolio = test_portfolio();
+        let strategies = vec![
+            lending_strategy(9000, 5_000_000_000, 100),
+            lending_strategy(2000, u64::MAX / 100, 0),
+        ];
+        
+        let plan = execute_complete_rebalancing(&portfolio, &strategies, &test_risk_limits()).unwrap();
+        
+        let total_extractable = u64::MAX / 100 - STRATEGY_RENT_RESERVE;
+        assert_eq!(plan.total_to_extract, total_extractable);
+        assert_eq!(plan.estimated_fees, (total_extractable as u128 * ESTIMATED_FEE_BPS as u128 / 10000) as u64);
+    }
+    
+    #[test]
+    fn test_risk_limits_validation() {
+        assert!(test_risk_limits().validate().is_ok());
+        
+        // Default treasuries would silently misroute fees
+        assert_eq!(
+            RiskLimits::default().validate().unwrap_err(),
+            RebalancerErrorCode::InvalidTreasury.into()
+        );
+        
+        let inverted = RiskLimits {
+            max_single_strategy_bps: 100,
+            min_single_strategy_bps: 4000,
+            ..test_risk_limits()
+        };
+        assert_eq!(inverted.validate().unwrap_err(), RebalancerErrorCode::InvalidRiskLimits.into());
+        
+        let excessive_fees = RiskLimits {
+            platform_fee_bps: 6000,
+            manager_fee_bps: 5000,
+            ..test_risk_limits()
+        };
+        assert_eq!(excessive_fees.validate().unwrap_err(), RebalancerErrorCode::ExcessiveFees.into());
+        
+        let zero_weight = RiskLimits {
+            yield_farming_weight_bps: 0,
+            ..test_risk_limits()
+        };
+        assert_eq!(zero_weight.validate().unwrap_err(), RebalancerErrorCode::InvalidRiskLimits.into());
+        
+        let zero_perpetual_weight = RiskLimits {
+            perpetual_funding_weight_bps: 0,
+            ..test_risk_limits()
+        };
+        assert_eq!(zero_perpetual_weight.validate().unwrap_err(), RebalancerErrorCode::InvalidRiskLimits.into());
+        
+        let excessive_taker_fee = RiskLimits {
+            perpetual_taker_fee_bps: 10001,
+            ..test_risk_limits()
+        };
+        assert_eq!(excessive_taker_fee.validate().unwrap_err(), RebalancerErrorCode::InvalidRiskLimits.into());
+        
+        let unreachable_crisis = RiskLimits {
+            crisis_volatility_threshold: 10000,
+            ..test_risk_limits()
+        };
+        assert_eq!(unreachable_crisis.validate().unwrap_err(), RebalancerErrorCode::InvalidRiskLimits.into());
+        
+        let group_below_single = RiskLimits {
+            max_group_bps: MAX_SINGLE_STRATEGY_BPS - 1,
+            ..test_risk_limits()
+        };
+        assert_eq!(group_below_single.validate().unwrap_err(), RebalancerErrorCode::InvalidRiskLimits.into());
+        
+        let no_top_performers = RiskLimits {
+            top_performer_count: 0,
+            ..test_risk_limits()
+        };
+        assert_eq!(no_top_performers.validate().unwrap_err(), RebalancerErrorCode::InvalidRiskLimits.into());
+        
+        let too_many_top_performers = RiskLimits {
+            top_performer_count: MAX_TOP_PERFORMER_COUNT + 1,
+            ..test_risk_limits()
+        };
+        assert_eq!(too_many_top_performers.validate().unwrap_err(), RebalancerErrorCode::InvalidRiskLimits.into());
+        
+        let percentile_out_of_range = RiskLimits {
+            top_performer_percentile: 101,
+            ..test_risk_limits()
+        };
+        assert_eq!(percentile_out_of_range.validate().unwrap_err(), RebalancerErrorCode::InvalidRiskLimits.into());
+        
+        let inverted_threshold_range = RiskLimits {
+            min_threshold: 30,
+            max_threshold: 30,
+            ..test_risk_limits()
+        };
+        assert_eq!(inverted_threshold_range.validate().unwrap_err(), RebalancerErrorCode::InvalidRiskLimits.into());
+        
+        let threshold_above_100 = RiskLimits {
+            max_threshold: 101,
+            ..test_risk_limits()
+        };
+        assert_eq!(threshold_above_100.validate().unwrap_err(), RebalancerErrorCode::InvalidRiskLimits.into());
+        
+        let excessive_volatility_weight = RiskLimits {
+            volatility_weight: MAX_VOLATILITY_WEIGHT + 1,
+            ..test_risk_limits()
+        };
+        assert_eq!(excessive_volatility_weight.validate().unwrap_err(), RebalancerErrorCode::InvalidRiskLimits.into());
+    }
+    
+    #[test]
+    fn test_top_performer_selection_tracks_config() {
+        let portfolio = test_portfolio();
+        let strategies = vec![
+            lending_strategy(9000, 1_000_000_000, 100),
+            lending_strategy(8500, 1_000_000_000, 90),
+            lending_strategy(8000, 1_000_000_000, 80),
+            lending_strategy(2000, 10_000_000_000, 0),
+        ];
+        let funded = |risk_limits: RiskLimits| {
+            execute_complete_rebalancing(&portfolio, &strategies, &risk_limits)
+                .unwrap()
+                .redistribution_plan
+                .iter()
+                .filter(|a| a.allocation_type.is_strategy_allocation())
+                .count()
+        };
+        
+        assert_eq!(funded(test_risk_limits()), 3);
+        assert_eq!(funded(RiskLimits { top_performer_count: 2, ..test_risk_limits() }), 2);
+        assert_eq!(funded(RiskLimits { top_performer_count: 1, ..test_risk_limits() }), 1);
+        assert_eq!(funded(RiskLimits { top_performer_percentile: 85, ..test_risk_limits() }), 2);
+        assert_eq!(funded(RiskLimits { top_performer_percentile: 95, ..test_risk_limits() }), 1);
+    }
+    
+    #[test]
+    fn test_underperformer_cutoff_tracks_config() {
+        let portfolio = test_portfolio();
+        let strategies = vec![
+            lending_strategy(9500, 1_000_000_000, 95),
+            lending_strategy(4000, 2_000_000_000, 25),
+            lending_strategy(2000, 2_000_000_000, 8),
+            lending_strategy(1000, 2_000_000_000, 5),
+        ];
+        let extracted = |underperformer_max_percentile: u8| {
+            let risk_limits = RiskLimits {
+                underperformer_max_percentile,
+                top_performer_percentile: 90,
+                ..test_risk_limits()
+            };
+            risk_limits.validate().unwrap();
+            execute_complete_rebalancing(&portfolio, &strategies, &risk_limits)
+                .unwrap()
+                .extraction_targets
+        };
+        
+        // Only ranks below the cutoff are drained; rank 25 is left alone
+        assert_eq!(extracted(10), vec![strategies[2].strategy_id, strategies[3].strategy_id]);
+        assert_eq!(extracted(30).len(), 3);
+    }
+    
+    #[test]
+    fn test_min_rebalance_capital_tracks_config() {
+        let portfolio = test_portfolio();
+        // Each underperformer keeps STRATEGY_RENT_RESERVE, leaving 0.07 SOL to extract
+        let strategies = vec![
+            lending_strategy(9000, 1_000_000_000, 100),
+            lending_strategy(2000, 80_000_000, 0),
+        ];
+        let plan = |min_rebalance_capital| {
+            execute_complete_rebalancing(&portfolio, &strategies, &RiskLimits { min_rebalance_capital, ..test_risk_limits() })
+        };
+        
+        // Below the 0.1 SOL default, but enough for a smaller configured minimum
+        assert_eq!(RiskLimits::default().min_rebalance_capital, 100_000_000);
+        assert_eq!(plan(100_000_000).unwrap_err(), RebalancerErrorCode::InsufficientBalance.into());
+        assert_eq!(plan(60_000_000).unwrap().total_to_extract, 70_000_000);
+        assert_eq!(plan(70_000_000).unwrap().total_to_extract, 70_000_000);
+        assert_eq!(plan(70_000_001).unwrap_err(), RebalancerErrorCode::InsufficientBalance.into());
+        
+        assert_eq!(
+            RiskLimits { min_rebalance_capital: 0, ..test_risk_limits() }.validate().unwrap_err(),
+            RebalancerErrorCode::InvalidRiskLimits.into()
+        );
+    }
+    
+    #[test]
+    fn test_overlapping_underperformer_cutoff_rejected() {
+        // A fixed cutoff above the top performer percentile
+        let fixed = RiskLimits {
+            underperformer_max_percentile: 60,
+            top_performer_percentile: 50,
+            ..test_risk_limits()
+        };
+        assert_eq!(fixed.validate().unwrap_err(), RebalancerErrorCode::InvalidRiskLimits.into());
+        
+        // The dynamic threshold may climb to max_threshold
+        let dynamic = RiskLimits {
+            underperformer_max_percentile: 0,
+            max_threshold: 60,
+            top_performer_percentile: 50,
+            ..test_risk_limits()
+        };
+        assert_eq!(dynamic.validate().unwrap_err(), RebalancerErrorCode::InvalidRiskLimits.into());
+        assert!(RiskLimits { max_threshold: 50, ..dynamic }.validate().is_ok());
+    }
+    
+    #[test]
+    fn test_correlated_farming_strategies_share_group_cap() {
+        let available_capital = 10_000_000_000;
+        let pair_id = Pubkey::new_unique();
+        let farming = |pair_id| StrategyPerformanceData {
+            volatility_score: 0, // Full risk multiplier pushes each share to the single cap
+            protocol_type: ProtocolType::YieldFarming {
+                pair_id,
+                reward_multiplier: 2,
+                token_a_mint: Pubkey::new_unique(),
+                token_b_mint: Pubkey::new_unique(),
+                fee_tier: 30,
+            },
+            ..lending_strategy(9000, 1_000_000_000, 90)
+        };
+        let same_pair = vec![farming(pair_id), farming(pair_id)];
+        let risk_limits = test_risk_limits();
+        
+        let allocations = calculate_optimal_allocation(available_capital, &same_pair, &risk_limits, AllocationMode::EqualWeight).unwrap();
+        
+        let max_single = available_capital * MAX_SINGLE_STRATEGY_BPS / 10000;
+        let max_group = available_capital * MAX_GROUP_BPS / 10000;
+        let strategy_amounts: Vec<u64> = allocations.iter()
+            .filter(|a| a.allocation_type.is_strategy_allocation())
+            .map(|a| a.amount)
+            .collect();
+        assert_eq!(strategy_amounts, vec![max_single, max_group - max_single]);
+        assert_eq!(allocations.iter().map(|a| a.amount).sum::<u64>(), available_capital);
+        
+        // The same strategies on different pairs are not held to the group cap
+        let different_pairs = vec![farming(Pubkey::new_unique()), farming(Pubkey::new_unique())];
+        let allocations = calculate_optimal_allocation(available_capital, &different_pairs, &risk_limits, AllocationMode::EqualWeight).unwrap();
+        let combined: u64 = allocations.iter()
+            .filter(|a| a.allocation_type.is_strategy_allocation())
+            .map(|a| a.amount)
+            .sum();
+        assert!(combined > max_group);
+    }
+    
+    #[test]
+    fn test_capital_conserved_when_every_strategy_is_below_minimum() {
+        // 0.3 SOL is split across strategies whose protocols all require more than they'd receive
+        let available_capital = 300_000_000;
+        let farming = |score| StrategyPerformanceData {
+            protocol_type: ProtocolType::YieldFarming {
+                pair_id: Pubkey::new_unique(),
+                reward_multiplier: 2,
+                token_a_mint: Pubkey::new_unique(),
+                token_b_mint: Pubkey::new_unique(),
+                fee_tier: 30,
+            },
+            ..lending_strategy(score, 1_000_000_000, 90)
+        };
+        let top_strategies = vec![farming(9000), farming(8000)];
+        
+        let allocations = calculate_optimal_allocation(available_capital, &top_strategies, &test_risk_limits(), AllocationMode::PerformanceWeighted).unwrap();
+        
+        assert!(allocations.iter().all(|a| !matches!(
+            a.allocation_type,
+            AllocationType::TopPerformer | AllocationType::RiskDiversification
+        )));
+        let unallocated = allocations.iter()
+            .find(|a| matches!(a.allocation_type, AllocationType::Unallocated))
+            .expect("leftover capital must be returned");
+        assert_eq!(unallocated.amount, available_capital - 1_500_000 - 4_500_000);
+        assert_eq!(allocations.iter().map(|a| a.amount).sum::<u64>(), available_capital);
+    }
+    
+    #[test]
+    fn test_protocol_minimums_agree_between_allocation_and_validation() {
+        let limits = RiskLimits {
+            yield_farming_min_lamports: 50_000_000,
+            ..RiskLimits::default()
+        };
+        let protocols = [
+            ProtocolType::StableLending {
+                pool_id: Pubkey::new_unique(),
+                utilization: 7500,
+                reserve_address: Pubkey::new_unique(),
+            },
+            ProtocolType::YieldFarming {
+                pair_id: Pubkey::new_unique(),
+                reward_multiplier: 2,
+                token_a_mint: Pubkey::new_unique(),
+                token_b_mint: Pubkey::new_unique(),
+                fee_tier: 30,
+            },
+            ProtocolType::LiquidStaking {
+                validator_id: Pubkey::new_unique(),
+                stake_pool: Pubkey::new_unique(),
+                unstake_delay: 10,
+                commission: 500,
+            },
+            ProtocolType::PerpetualFunding {
+                market_id: Pubkey::new_unique(),
+                funding_rate_bps: 10,
+                max_leverage: 3,
+            },
+        ];
+        
+        for protocol in &protocols {
+            let minimum = limits.min_allocation_lamports(protocol);
+            assert!(limits.validate_balance_constraints(protocol, minimum, SOL_DECIMALS).is_ok());
+            assert!(limits.validate_balance_constraints(protocol, minimum - 1, SOL_DECIMALS).is_err());
+        }
+    }
+    
+    #[test]
+    fn test_allocation_shape_for_each_mode() {
+        // Strong performer with little capital, weak performer holding most capital, and a middle ground
+        let available_capital = 10_000_000_000;
+        let strong = lending_strategy(9000, 1_000_000_000, 100);
+        let heavy = lending_strategy(1000, 9_000_000_000, 90);
+        let middle = lending_strategy(5000, 5_000_000_000, 80);
+        let top_strategies = vec![strong, heavy.clone(), middle];
+        let risk_limits = test_risk_limits();
+        let max_single = apply_bps(available_capital, risk_limits.max_single_strategy_bps).unwrap();
+        
+        let allocated_to = |mode: AllocationMode, strategy_id: Pubkey| {
+            let allocations = calculate_optimal_allocation(available_capital, &top_strategies, &risk_limits, mode).unwrap();
+            
+            // Caps and conservation hold in every mode
+            assert_eq!(allocations.iter().map(|a| a.amount).sum::<u64>(), available_capital);
+            assert!(allocations.iter()
+                .filter(|a| !matches!(a.allocation_type, AllocationType::Unallocated))
+                .all(|a| a.amount <= max_single));
+            
+            allocations.iter().find(|a| a.strategy_id == strategy_id).map_or(0, |a| a.amount)
+        };
+        
+        let performance = allocated_to(AllocationMode::PerformanceWeighted, heavy.strategy_id);
+        let balance = allocated_to(AllocationMode::BalanceWeighted, heavy.strategy_id);
+        let equal = allocated_to(AllocationMode::EqualWeight, heavy.strategy_id);
+        
+        // The heavy-but-weak strategy keeps the most under balance weighting, the least under performance
+        assert!(balance > equal, "balance={} equal={}", balance, equal);
+        assert!(equal > performance, "equal={} performance={}", equal, performance);
+    }
+    
+    #[test]
+    fn test_remainder_policy_spreads_dust_round_robin() {
+        let strategies = vec![
+            lending_strategy(9000, 1_000_000_000, 100),
+            lending_strategy(8000, 1_000_000_000, 90),
+            lending_strategy(7000, 1_000_000_000, 80),
+        ];
+        let amounts = |remainder_policy: RemainderPolicy| -> Vec<u64> {
+            // Caps lifted so neither policy is constrained in where the dust can go
+            let limits = RiskLimits { remainder_policy, max_single_strategy_bps: 10000, max_group_bps: 10000, ..test_risk_limits() };
+            let allocations = calculate_optimal_allocation(10_000_000_000, &strategies, &limits, AllocationMode::PerformanceWeighted).unwrap();
+            assert!(allocations.iter().all(|a| a.allocation_type != AllocationType::Unallocated));
+            allocations.iter().filter(|a| a.allocation_type.is_strategy_allocation()).map(|a| a.amount).collect()
+        };
+        
+        assert_eq!(RiskLimits::default().remainder_policy, RemainderPolicy::TopPerformer);
+        let top_performer = amounts(RemainderPolicy::TopPerformer);
+        let round_robin = amounts(RemainderPolicy::RoundRobin);
+        
+        // Both policies place the same capital; only who receives the dust differs
+        assert_eq!(top_performer.iter().sum::<u64>(), round_robin.iter().sum::<u64>());
+        
+        // Round robin moves dust off the top performer and onto everyone else
+        let moved_off_top = top_performer[0] - round_robin[0];
+        let extras: Vec<u64> = (1..3).map(|i| round_robin[i] - top_performer[i]).collect();
+        assert!(extras.iter().all(|extra| *extra > 0));
+        assert_eq!(moved_off_top, extras.iter().sum::<u64>());
+        
+        // The dust is shared evenly, to the lamport
+        assert!(extras[0].abs_diff(extras[1]) <= 1);
+    }
+    
+    #[test]
+    fn test_round_robin_respects_single_strategy_cap() {
+        // The top performer's weighted share already sits at the 40% cap, so its
+        // slice of the dust spills over to the others
+        let strategies = vec![
+            lending_strategy(9000, 1_000_000_000, 100),
+            lending_strategy(3000, 1_000_000_000, 90),
+            lending_strategy(3000, 1_000_000_000, 80),
+        ];
+        let limits = RiskLimits { remainder_policy: RemainderPolicy::RoundRobin, ..test_risk_limits() };
+        let allocations = calculate_optimal_allocation(10_000_000_000, &strategies, &limits, AllocationMode::PerformanceWeighted).unwrap();
+        
+        let max_single_allocation = apply_bps(10_000_000_000, limits.max_single_strategy_bps).unwrap();
+        let strategy_amounts: Vec<u64> = allocations.iter().filter(|a| a.allocation_type.is_strategy_allocation()).map(|a| a.amount).collect();
+        assert_eq!(strategy_amounts[0], max_single_allocation);
+        assert!(strategy_amounts.iter().all(|amount| *amount <= max_single_allocation));
+        assert!(validate_allocation_total(&allocations, 10_000_000_000).is_ok());
+        
+        // The capped top performer cannot take the dust, so the default policy leaves it unallocated
+        let unallocated = |allocations: &[CapitalAllocation]| allocations
+            .iter()
+            .filter(|a| a.allocation_type == AllocationType::Unallocated)
+            .map(|a| a.amount)
+            .sum::<u64>();
+        let default_allocations = calculate_optimal_allocation(10_000_000_000, &strategies, &test_risk_limits(), AllocationMode::PerformanceWeighted).unwrap();
+        assert!(unallocated(&allocations) < unallocated(&default_allocations));
+    }
+    
+    #[test]
+    fn test_default_allocation_mode_is_performance_weighted() {
+        assert_eq!(AllocationMode::default(), AllocationMode::PerformanceWeighted);
+        assert_eq!(RiskLimits::default().allocation_mode, AllocationMode::PerformanceWeighted);
+    }
+    
+    #[test]
+    fn test_lowered_protocol_minimum_allows_small_allocations() {
+        // Same inputs as the below-minimum case, but the manager operates at a smaller scale
+        let available_capital = 300_000_000;
+        let farming = |score| StrategyPerformanceData {
+            protocol_type: ProtocolType::YieldFarming {
+                pair_id: Pubkey::new_unique(),
+                reward_multiplier: 2,
+                token_a_mint: Pubkey::new_unique(),
+                token_b_mint: Pubkey::new_unique(),
+                fee_tier: 30,
+            },
+            ..lending_strategy(score, 1_000_000_000, 90)
+        };
+        let top_strategies = vec![farming(9000), farming(8000)];
+        let small_scale = RiskLimits {
+            yield_farming_min_lamports: 50_000_000,
+            ..test_risk_limits()
+        };
+        
+        let allocations = calculate_optimal_allocation(available_capital, &top_strategies, &small_scale, AllocationMode::PerformanceWeighted).unwrap();
+        
+        assert!(allocations.iter().any(|a| matches!(a.allocation_type, AllocationType::TopPerformer)));
+        assert_eq!(allocations.iter().map(|a| a.amount).sum::<u64>(), available_capital);
+    }
+    
+    #[test]
+    fn test_perpetual_parameters_come_from_risk_limits() {
+        let perpetual = ProtocolType::PerpetualFunding {
+            market_id: Pubkey::new_unique(),
+            funding_rate_bps: 10,
+            max_leverage: 3,
+        };
+        let defaults = RiskLimits::default();
+        assert_eq!(defaults.protocol_weight(&perpetual), PERPETUAL_FUNDING_WEIGHT_BPS);
+        assert_eq!(defaults.min_allocation_lamports(&perpetual), PERPETUAL_FUNDING_MIN_LAMPORTS);
+        
+        let tuned = RiskLimits {
+            perpetual_funding_weight_bps: 12000,
+            perpetual_funding_min_lamports: 50_000_000,
+            ..test_risk_limits()
+        };
+        assert_eq!(tuned.protocol_weight(&perpetual), 12000);
+        assert_eq!(tuned.min_allocation_lamports(&perpetual), 50_000_000);
+    }
+    
+    #[test]
+    fn test_allocation_count_limit() {
+        let allocation = |_| CapitalAllocation {
+            strategy_id: Pubkey::new_unique(),
+            amount: 100_000_000,
+            min_acceptable_amount: 0,
+            allocation_type: AllocationType::TopPerformer,
+        };
+        
+        let at_limit: Vec<CapitalAllocation> = (0..MAX_STRATEGIES_PER_OP).map(allocation).collect();
+        assert!(validate_allocations(&at_limit).is_ok());
+        
+        let over_limit: Vec<CapitalAllocation> = (0..=MAX_STRATEGIES_PER_OP).map(allocation).collect();
+        assert_eq!(
+            validate_allocations(&over_limit).unwrap_err(),
+            RebalancerErrorCode::TooManyStrategies.into()
+        );
+    }
+    
+    #[test]
+    fn test_fee_allocations_do_not_collide_with_strategies() {
+        let strategy_id = Pubkey::new_unique();
+        let allocation = |strategy_id, allocation_type| CapitalAllocation {
+            strategy_id,
+            amount: 10_000_000,
+            min_acceptable_amount: 0,
+            allocation_type,
+        };
+        
+        // Treasury keys that coincide with a strategy id (and with each other)
+        let mixed = vec![
+            allocation(strategy_id, AllocationType::PlatformFee),
+            allocation(strategy_id, AllocationType::ManagerIncentive),
+            allocation(strategy_id, AllocationType::TopPerformer),
+            allocation(Pubkey::new_unique(), AllocationType::RiskDiversification),
+            allocation(Pubkey::default(), AllocationType::Unallocated),
+        ];
+        assert_eq!(validate_allocations(&mixed).unwrap(), 50_000_000);
+        
+        // Only the strategy allocations must name registered strategies
+        assert!(validate_allocation_destinations(&mixed[..3], &[strategy_id]).is_ok());
+        assert!(validate_allocation_destinations(&mixed, &[strategy_id]).is_err());
+        
+        // Real duplicates are still rejected within each kind
+        for duplicated in [AllocationType::TopPerformer, AllocationType::PlatformFee] {
+            let duplicates = vec![allocation(strategy_id, duplicated), allocation(strategy_id, duplicated)];
+            assert_eq!(
+                validate_allocations(&duplicates).unwrap_err(),
+                RebalancerErrorCode::DuplicateStrategy.into()
+            );
+        }
+    }
+    
+    fn staking_strategy(current_balance: u64) -> StrategyPerformanceData {
+        StrategyPerformanceData {
+            protocol_type: ProtocolType::LiquidStaking {
+                validator_id: Pubkey::new_unique(),
+                stake_pool: Pubkey::new_unique(),
+                unstake_delay: 10,
+                commission: 500,
+            },
+            ..lending_strategy(5000, current_balance, 50)
+        }
+    }
+    
+    fn target_limits(stable_lending_target_bps: u16, liquid_staking_target_bps: u16, yield_farming_target_bps: u16) -> RiskLimits {
+        RiskLimits {
+            allocation_mode: AllocationMode::TargetAllocation,
+            stable_lending_target_bps,
+            liquid_staking_target_bps,
+            yield_farming_target_bps,
+            ..test_risk_limits()
+        }
+    }
+    
+    #[test]
+    fn test_target_weights_must_sum_to_whole_portfolio() {
+        assert!(target_limits(4000, 4000, 2000).validate().is_ok());
+        assert_eq!(
+            target_limits(4000, 4000, 1000).validate().unwrap_err(),
+            RebalancerErrorCode::InvalidRiskLimits.into()
+        );
+        assert_eq!(
+            target_limits(6000, 4000, 2000).validate().unwrap_err(),
+            RebalancerErrorCode::InvalidRiskLimits.into()
+        );
+        // Targets are ignored outside TargetAllocation mode
+        assert!(RiskLimits { allocation_mode: AllocationMode::PerformanceWeighted, ..target_limits(0, 0, 0) }.validate().is_ok());
+    }
+    
+    #[test]
+    fn test_target_allocation_drives_portfolio_to_targets() {
+        // 70/20/10 lending/staking/farming, aiming for 40/40/20
+        let lending = lending_strategy(5000, 7_000_000_000, 50);
+        let staking = staking_strategy(2_000_000_000);
+        let farming = StrategyPerformanceData {
+            protocol_type: ProtocolType::YieldFarming {
+                pair_id: Pubkey::new_unique(),
+                token_a_mint: Pubkey::new_unique(),
+                token_b_mint: Pubkey::new_unique(),
+                fee_tier: 30,
+                reward_multiplier: 1,
+            },
+            ..lending_strategy(5000, 1_000_000_000, 50)
+        };
+        let strategies = vec![lending, staking, farming];
+        
+        let deltas = plan_target_allocation(&strategies, &target_limits(4000, 4000, 2000)).unwrap();
+        let new_balances: Vec<u64> = deltas.iter().map(|d| d.new_balance).collect();
+        
+        assert_eq!(new_balances, vec![4_000_000_000, 4_000_000_000, 2_000_000_000]);
+        assert_eq!(deltas[0].role, DeltaRole::Source);
+        assert_eq!(deltas[1].role, DeltaRole::Destination);
+        assert_eq!(deltas[2].role, DeltaRole::Destination);
+        
+        // Planning again from the target is a no-op
+        let at_target: Vec<StrategyPerformanceData> = strategies
+            .iter()
+            .zip(&new_balances)
+            .map(|(s, balance)| StrategyPerformanceData { current_balance: *balance, ..s.clone() })
+            .collect();
+        let deltas = plan_target_allocation(&at_target, &target_limits(4000, 4000, 2000)).unwrap();
+        assert!(deltas.iter().all(|d| d.role == DeltaRole::Unchanged));
+    }
+    
+    #[test]
+    fn test_target_allocation_respects_caps_and_conserves_capital() {
+        // Everything in lending: each of the two lending strategies would take 50%,
+        // but the 40% single-strategy cap holds them to 4 SOL each
+        let strategies = vec![
+            lending_strategy(5000, 3_000_000_000, 50),
+            lending_strategy(5000, 3_000_000_000, 50),
+            staking_strategy(4_000_000_000),
+        ];
+        
+        let deltas = plan_target_allocation(&strategies, &target_limits(10000, 0, 0)).unwrap();
+        let new_balances: Vec<u64> = deltas.iter().map(|d| d.new_balance).collect();
+        
+        assert_eq!(new_balances, vec![4_000_000_000, 4_000_000_000, 2_000_000_000]);
+        assert_eq!(new_balances.iter().sum::<u64>(), 10_000_000_000);
+    }
+    
+    #[test]
+    fn test_target_allocation_drains_deprecated_and_skips_paused() {
+        let deprecated = StrategyPerformanceData { status: StrategyStatus::Deprecated, ..staking_strategy(2_000_000_000) };
+        let paused = StrategyPerformanceData { status: StrategyStatus::Paused, ..staking_strategy(5_000_000_000) };
+        let strategies = vec![lending_strategy(5000, 1_000_000_000, 50), staking_strategy(1_000_000_000), deprecated, paused];
+        
+        let limits = RiskLimits { max_single_strategy_bps: 10000, max_group_bps: 10000, ..target_limits(5000, 5000, 0) };
+        let deltas = plan_target_allocation(&strategies, &limits).unwrap();
+        
+        // The deprecated strategy keeps only its rent reserve; the paused one is untouched
+        assert_eq!(deltas[2].new_balance, STRATEGY_RENT_RESERVE);
+        assert_eq!(deltas[3].role, DeltaRole::Unchanged);
+        let rebalanced: u64 = deltas[..3].iter().map(|d| d.new_balance).sum();
+        assert_eq!(rebalanced, 4_000_000_000);
+        assert!(deltas[0].new_balance > 1_000_000_000 && deltas[1].new_balance > 1_000_000_000);
+    }
+    
+    #[test]
+    fn test_target_allocation_mode_executes_target_deltas() {
+        // 70/20/10 lending/staking/farming, aiming for 40/40/20
+        let farming = StrategyPerformanceData {
+            protocol_type: ProtocolType::YieldFarming {
+                pair_id: Pubkey::new_unique(),
+                token_a_mint: Pubkey::new_unique(),
+                token_b_mint: Pubkey::new_unique(),
+                fee_tier: 30,
+                reward_multiplier: 1,
+            },
+            ..lending_strategy(5000, 1_000_000_000, 50)
+        };
+        let strategies = vec![lending_strategy(5000, 7_000_000_000, 50), staking_strategy(2_000_000_000), farming];
+        let limits = target_limits(4000, 4000, 2000);
+        
+        let plan = execute_complete_rebalancing(&test_portfolio(), &strategies, &limits).unwrap();
+        
+        // Lending is sold down to its target, not drained to the rent reserve
+        assert_eq!(plan.extraction_targets, vec![strategies[0].strategy_id]);
+        assert_eq!(plan.extraction_amounts, vec![3_000_000_000]);
+        assert_eq!(plan.total_to_extract, 3_000_000_000);
+        
+        // The buys follow the target deltas, less the 2% platform and manager fees
+        let new_balances: Vec<u64> = compute_rebalance_deltas(&strategies, &plan)
+            .unwrap()
+            .iter()
+            .map(|d| d.new_balance)
+            .collect();
+        assert_eq!(new_balances, vec![4_000_000_000, 3_960_000_000, 1_980_000_000]);
+        assert_eq!(plan.redistribution_plan.iter().map(|a| a.amount).sum::<u64>(), 3_000_000_000);
+        
+        // Top-performer weighting is not used for target plans
+        assert_eq!(
+            calculate_optimal_allocation(1_000_000_000, &strategies, &limits, AllocationMode::TargetAllocation).unwrap_err(),
+            RebalancerErrorCode::InvalidRiskLimits.into()
+        );
+    }
+    
+    #[test]
+    fn test_target_allocation_mode_moves_largest_sells_first() {
+        // Twelve deprecated strategies to wind down into one lending strategy
+        let deprecated: Vec<StrategyPerformanceData> = (1..=12u64)
+            .map(|i| StrategyPerformanceData {
+                status: StrategyStatus::Deprecated,
+                ..staking_strategy(i * 100_000_000 + STRATEGY_RENT_RESERVE)
+            })
+            .collect();
+        let mut strategies = vec![lending_strategy(5000, 1_000_000_000, 50)];
+        strategies.extend(deprecated.iter().cloned());
+        let limits = RiskLimits { max_single_strategy_bps: 10000, max_group_bps: 10000, ..target_limits(10000, 0, 0) };
+        
+        let plan = execute_complete_rebalancing(&test_portfolio(), &strategies, &limits).unwrap();
+        
+        // The ten largest are drained this time; the two smallest wait for the next rebalance
+        assert_eq!(plan.extraction_targets.len(), RebalancingPlan::MAX_EXTRACTION_TARGETS);
+        assert!(!plan.extraction_targets.contains(&deprecated[0].strategy_id));
+        assert!(!plan.extraction_targets.contains(&deprecated[1].strategy_id));
+        assert_eq!(plan.extraction_amount(&deprecated[11].strategy_id), 1_200_000_000);
+        assert_eq!(plan.total_to_extract, (3..=12).map(|i| i * 100_000_000).sum::<u64>());
+        assert!(plan.validate_size().is_ok());
+    }
+    
+    #[test]
+    fn test_zero_scores_get_floor_share_next_to_one_scorer() {
+        let available_capital = 10_000_000_000;
+        let scorer = lending_strategy(9000, 1_000_000_000, 100);
+        let zero_a = lending_strategy(0, 1_000_000_000, 90);
+        let zero_b = lending_strategy(0, 1_000_000_000, 80);
+        let strategies = vec![scorer.clone(), zero_a.clone(), zero_b.clone()];
+        
+        let allocations = calculate_optimal_allocation(available_capital, &strategies, &test_risk_limits(), AllocationMode::PerformanceWeighted).unwrap();
+        let allocated_to = |strategy_id: Pubkey| allocations.iter().find(|a| a.strategy_id == strategy_id).map_or(0, |a| a.amount);
+        
+        // The scorer still leads, but the zero scorers are funded at the floor weight
+        assert!(allocated_to(zero_a.strategy_id) > 0);
+        assert!(allocated_to(zero_b.strategy_id) > 0);
+        assert!(allocated_to(scorer.strategy_id) > allocated_to(zero_a.strategy_id));
+        assert_eq!(allocations.iter().map(|a| a.amount).sum::<u64>(), available_capital);
+        
+        // With no score to go on at all there is nothing to weight by
+        let unscored = vec![zero_a, zero_b];
+        assert_eq!(
+            calculate_optimal_allocation(available_capital, &unscored, &test_risk_limits(), AllocationMode::PerformanceWeighted).unwrap_err(),
+            RebalancerErrorCode::InvalidPerformanceScore.into()
+        );
+    }
+    
+    #[test]
+    fn test_single_viable_strategy_capped_and_rest_unallocated() {
+        let available_capital = 10_000_000_000;
+        let risk_limits = test_risk_limits();
+        let only = vec![lending_strategy(9000, 1_000_000_000, 100)];
+        
+        let allocations = calculate_optimal_allocation(available_capital, &only, &risk_limits, AllocationMode::PerformanceWeighted).unwrap();
+        let funded = allocations.iter().find(|a| a.strategy_id == only[0].strategy_id).unwrap();
+        
+        assert!(funded.amount <= apply_bps(available_capital, risk_limits.max_single_strategy_bps).unwrap());
+        assert!(allocations.iter().any(|a| a.allocation_type == AllocationType::Unallocated));
+        assert_eq!(allocations.iter().map(|a| a.amount).sum::<u64>(), available_capital);
+    }
+}
+
+#[cfg(test)]
+mod property_tests {
+    use super::*;
+    use std::collections::HashSet;
+    
+    const SEEDS: u64 = 2_000;
+    
+    // Deterministic splitmix64 generator so failures reproduce from the seed alone
+    struct SeededRng(u64);
+    
+    impl SeededRng {
+        fn next_u64(&mut self) -> u64 {
+            self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut z = self.0;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            z ^ (z >> 31)
+        }
+        
+        fn range(&mut self, low: u64, high: u64) -> u64 {
+            low + self.next_u64() % (high - low + 1)
+        }
+    }
+    
+    fn random_protocol(rng: &mut SeededRng) -> ProtocolType {
+        match rng.range(0, 2) {
+            0 => ProtocolType::StableLending {
+                pool_id: Pubkey::new_unique(),
+                reserve_address: Pubkey::new_unique(),
+                utilization: rng.range(0, 10000) as u16,
+            },
+            1 => ProtocolType::YieldFarming {
+                pair_id: Pubkey::new_unique(),
+                token_a_mint: Pubkey::new_unique(),
+                token_b_mint: Pubkey::new_unique(),
+                fee_tier: rng.range(0, 1000) as u16,
+                reward_multiplier: rng.range(1, 10) as u8,
+            },
+            _ => ProtocolType::LiquidStaking {
+                validator_id: Pubkey::new_unique(),
+                stake_pool: Pubkey::new_unique(),
+                unstake_delay: rng.range(0, 50) as u32,
+                commission: rng.range(0, 1000) as u16,
+            },
+        }
+    }
+    
+    fn random_inputs(seed: u64) -> (u64, Vec<StrategyPerformanceData>, RiskLimits) {
+        let mut rng = SeededRng(seed);
+        
+        // Capital spans 0.001 SOL to ~10,000 SOL on a log-like scale
+        let magnitude = rng.range(6, 13) as u32;
+        let available_capital = rng.range(1, 9) * 10u64.pow(magnitude);
+        
+        let strategy_count = rng.range(1, 8) as usize;
+        let strategies = (0..strategy_count)
+            .map(|_| StrategyPerformanceData {
+                strategy_id: Pubkey::new_unique(),
+                performance_score: rng.range(1, 10000),
+                yield_rate: rng.range(0, 50000),
+                current_balance: rng.range(0, 100_000_000_000),
+                volatility_score: rng.range(0, 10000) as u32,
+                protocol_type: random_protocol(&mut rng),
+                percentile_rank: rng.range(0, 100) as u8,
+                status: StrategyStatus::Active,
+                mint: WRAPPED_SOL_MINT,
+                decimals: SOL_DECIMALS,
+                in_fee_grace: false,
+                in_cooldown: false,
+            })
+            .collect();
+        
+        let risk_limits = RiskLimits {
+            platform_treasury: Pubkey::new_unique(),
+            manager_treasury: Pubkey::new_unique(),
+            ..RiskLimits::default()
+        };
+        
+        (available_capital, strategies, risk_limits)
+    }
+    
+    #[test]
+    fn test_allocation_invariants_hold_across_seeds() {
+        for seed in 0..SEEDS {
+            let (available_capital, strategies, risk_limits) = random_inputs(seed);
+            let allocations = calculate_optimal_allocation(available_capital, &strategies, &risk_limits, AllocationMode::PerformanceWeighted)
+                .unwrap_or_else(|e| panic!("seed {}: allocation failed: {:?}", seed, e));
+            
+            let max_single_allocation =
+                (available_capital as u128 * risk_limits.max_single_strategy_bps as u128 / 10000) as u64;
+            
+            let mut total = 0u64;
+            let mut destinations = HashSet::new();
+            for allocation in &allocations {
+                assert!(allocation.amount > 0, "seed {}: zero-amount allocation", seed);
+                assert!(
+                    destinations.insert(allocation.strategy_id),
+                    "seed {}: duplicate destination {}", seed, allocation.strategy_id
+                );
+                assert!(
+                    matches!(allocation.allocation_type, AllocationType::Unallocated)
+                        || allocation.amount <= max_single_allocation,
+                    "seed {}: allocation {} exceeds max single allocation {}",
+                    seed, allocation.amount, max_single_allocation
+                );
+                total = total.checked_add(allocation.amount)
+                    .unwrap_or_else(|| panic!("seed {}: allocation total overflowed", seed));
+            }
+            
+            assert_eq!(
+                total, available_capital,
+                "seed {}: allocations do not conserve the available capital", seed
+            );
+        }
+    }
+}
@@ -0,0 +1,316 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::*;
+use crate::events::CapitalRedistributed;
+use crate::instructions::execute_ranking::{calculate_percentile_rankings, rankable_strategies};
+use crate::instructions::redistribute_capital::{
+    capital_moved, execute_complete_rebalancing, validate_net_benefit, RebalancingPlan, StrategyPerformanceData,
+    MAX_STRATEGIES_PER_OP,
+};
+use crate::utils::{load_portfolio_strategies, persist_strategies, write_rebalance_record};
+
+const MAX_SCOPE_SIZE: usize = MAX_STRATEGIES_PER_OP;
+
+#[derive(Accounts)]
+#[instruction(scope: Vec<Pubkey>)]
+pub struct RedistributeScopedCapital<'info> {
+    #[account(
+        mut,
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+        has_one = manager @ RebalancerErrorCode::UnauthorizedManager
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+
+    #[account(
+        seeds = [b"risk_config", portfolio.key().as_ref()],
+        bump = risk_config.bump,
+    )]
+    pub risk_config: Account<'info, RiskConfig>,
+
+    // Audit entry for this rebalance, numbered by the portfolio's sequence
+    #[account(
+        init,
+        payer = manager,
+        space = RebalanceRecord::MAX_SIZE,
+        seeds = [b"record", portfolio.key().as_ref(), &portfolio.rebalance_sequence.to_le_bytes()],
+        bump
+    )]
+    pub rebalance_record: Account<'info, RebalanceRecord>,
+
+    #[account(mut)]
+    pub manager: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Rebalance only the strategies named in `scope`.
+///
+/// The scoped strategy accounts are passed (writable) in `remaining_accounts`.
+/// Strategies are ranked against each other, a plan is computed among them, and
+/// only their recorded balances are updated; strategies outside the scope are
+/// never loaded and so cannot be touched. Like a full rebalance it waits for
+/// the portfolio's rebalance interval and restarts it.
+pub fn redistribute_scoped_capital<'info>(
+    ctx: Context<'_, '_, 'info, 'info, RedistributeScopedCapital<'info>>,
+    scope: Vec<Pubkey>,
+) -> Result<()> {
+    let portfolio = &mut ctx.accounts.portfolio;
+    let current_time = Clock::get()?.unix_timestamp;
+
+    // SCOPE VALIDATION
+    require!(!portfolio.emergency_pause, RebalancerErrorCode::EmergencyPaused);
+    require!(scope.len() >= 2, RebalancerErrorCode::InsufficientStrategies);
+    require!(scope.len() <= MAX_SCOPE_SIZE, RebalancerErrorCode::TooManyStrategies);
+
+    let mut strategies = load_portfolio_strategies(&portfolio.key(), ctx.remaining_accounts)?;
+    require!(strategies.len() == scope.len(), RebalancerErrorCode::StrategyNotFound);
+    for strategy_id in &scope {
+        require!(
+            strategies.iter().any(|s| s.strategy_id == *strategy_id),
+            RebalancerErrorCode::StrategyNotFound
+        );
+    }
+    portfolio.validate_metric_freshness(strategies.iter().map(|s| &**s), current_time)?;
+
+    // REBALANCING ELIGIBILITY: a scoped rebalance counts against the same interval
+    require!(
+        portfolio.can_rebalance(current_time),
+        RebalancerErrorCode::InvalidRebalanceInterval
+    );
+
+    // RANK WITHIN THE SCOPE
+    let mut ranking_data = rankable_strategies(strategies.iter().map(|s| &**s), &ctx.accounts.risk_config.limits);
+    calculate_percentile_rankings(&mut ranking_data, portfolio.base_threshold, &ctx.accounts.risk_config.limits)?;
+
+    let performance_data: Vec<StrategyPerformanceData> = strategies
+        .iter()
+        .map(|s| {
+            let mut data = StrategyPerformanceData::from_strategy(s, &ctx.accounts.risk_config.limits, current_time);
+            if let Some(ranked) = ranking_data.iter().find(|r| r.strategy_id == s.strategy_id) {
+                data.percentile_rank = ranked.percentile_rank;
+            }
+            data
+        })
+        .collect();
+
+    // PLAN AND APPLY AMONG SCOPED STRATEGIES ONLY
+    let plan = execute_complete_rebalancing(portfolio, &performance_data, &ctx.accounts.risk_config.limits)?;
+    validate_net_benefit(&plan, &ctx.accounts.risk_config.limits)?;
+
+    for strategy in strategies.iter_mut() {
+        let previous_balance = strategy.current_balance;
+        apply_plan_to_strategy(strategy, &plan, current_time)?;
+        portfolio.apply_balance_change(&strategy.mint, previous_balance, strategy.current_balance)?;
+    }
+    persist_strategies(&strategies)?;
+
+    portfolio.total_capital_moved = portfolio.total_capital_moved
+        .checked_add(capital_moved(&plan.redistribution_plan)?)
+        .ok_or(RebalancerErrorCode::BalanceOverflow)?;
+    portfolio.last_rebalance = current_time;
+
+    msg!("Scoped redistribution: {} strategies in scope, {} extracted from {} targets",
+         scope.len(), plan.total_to_extract, plan.extraction_targets.len());
+    
+    emit!(CapitalRedistributed {
+        portfolio: portfolio.key(),
+        manager: portfolio.manager,
+        amount_redistributed: plan.total_to_extract,
+        allocation_count: plan.redistribution_plan.len() as u32,
+        total_capital_moved: portfolio.total_capital_moved,
+        timestamp: current_time,
+    });
+
+    let record = &mut ctx.accounts.rebalance_record;
+    record.bump = ctx.bumps.rebalance_record;
+    write_rebalance_record(
+        record,
+        portfolio,
+        RebalanceKind::ScopedRedistribution,
+        plan.total_to_extract,
+        plan.extraction_targets.len() as u32,
+        plan.estimated_fees,
+        current_time,
+    )
+}
+
+// APPLY A PLAN TO ONE STRATEGY'S RECORDED BALANCES
+pub fn apply_plan_to_strategy(strategy: &mut Strategy, plan: &RebalancingPlan, current_time: i64) -> Result<()> {
+    let extracted = plan.extraction_amount(&strategy.strategy_id);
+    if extracted > 0 {
+        strategy.current_balance = strategy.current_balance
+            .checked_sub(extracted)
+            .ok_or(RebalancerErrorCode::InsufficientBalance)?;
+        strategy.total_withdrawals = strategy.total_withdrawals
+            .checked_add(extracted)
+            .ok_or(RebalancerErrorCode::BalanceOverflow)?;
+        strategy.last_extracted = current_time;
+    }
+
+    for allocation in &plan.redistribution_plan {
+        if allocation.strategy_id != strategy.strategy_id
+            || !allocation.allocation_type.is_strategy_allocation()
+        {
+            continue;
+        }
+
+        strategy.current_balance = strategy.current_balance
+            .checked_add(allocation.amount)
+            .ok_or(RebalancerErrorCode::BalanceOverflow)?;
+        strategy.total_deposits = strategy.total_deposits
+            .checked_add(allocation.amount)
+            .ok_or(RebalancerErrorCode::BalanceOverflow)?;
+    }
+    strategy.validate_capacity(strategy.current_balance)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+    use crate::instructions::redistribute_capital::RiskLimits;
+
+    fn strategy(current_balance: u64) -> Strategy {
+        Strategy {
+            current_balance,
+            total_deposits: current_balance,
+            ..test_utils::strategy()
+        }
+    }
+
+    #[test]
+    fn test_apply_plan_moves_capital_between_scoped_strategies() {
+        let mut source = strategy(2_000_000_000);
+        let mut destination = strategy(1_000_000_000);
+        let plan = RebalancingPlan {
+            extraction_targets: vec![source.strategy_id],
+            extraction_amounts: vec![1_990_000_000],
+            total_to_extract: 1_990_000_000,
+            redistribution_plan: vec![
+                CapitalAllocation {
+                    strategy_id: Pubkey::new_unique(),
+                    amount: 9_950_000,
+                    min_acceptable_amount: 0,
+                    allocation_type: AllocationType::PlatformFee,
+                },
+                CapitalAllocation {
+                    strategy_id: destination.strategy_id,
+                    amount: 796_000_000,
+                    min_acceptable_amount: 0,
+                    allocation_type: AllocationType::TopPerformer,
+                },
+            ],
+            estimated_fees: 39_800_000,
+            expected_improvement: 0,
+        };
+
+        apply_plan_to_strategy(&mut source, &plan, 100).unwrap();
+        apply_plan_to_strategy(&mut destination, &plan, 100).unwrap();
+
+        assert_eq!(source.current_balance, STRATEGY_RENT_RESERVE);
+        assert_eq!(source.total_withdrawals, 1_990_000_000);
+        assert_eq!(destination.current_balance, 1_796_000_000);
+        assert_eq!(destination.total_deposits, 1_796_000_000);
+        // Only the drained strategy starts a reallocation cooldown
+        assert_eq!(source.last_extracted, 100);
+        assert_eq!(destination.last_extracted, 0);
+    }
+
+    #[test]
+    fn test_apply_plan_extracts_planned_amount_only() {
+        // Target plans sell a strategy down rather than draining it
+        let mut source = strategy(3_000_000_000);
+        let plan = RebalancingPlan {
+            extraction_targets: vec![source.strategy_id],
+            extraction_amounts: vec![1_000_000_000],
+            total_to_extract: 1_000_000_000,
+            redistribution_plan: vec![],
+            estimated_fees: 20_000_000,
+            expected_improvement: 0,
+        };
+
+        apply_plan_to_strategy(&mut source, &plan, 100).unwrap();
+
+        assert_eq!(source.current_balance, 2_000_000_000);
+        assert_eq!(source.total_withdrawals, 1_000_000_000);
+        assert_eq!(source.last_extracted, 100);
+    }
+
+    #[test]
+    fn test_extracted_strategy_sits_out_reallocation_cooldown() {
+        let mut source = strategy(2_000_000_000);
+        let plan = RebalancingPlan {
+            extraction_targets: vec![source.strategy_id],
+            extraction_amounts: vec![1_990_000_000],
+            total_to_extract: 1_990_000_000,
+            redistribution_plan: vec![],
+            estimated_fees: 39_800_000,
+            expected_improvement: 0,
+        };
+        let risk_limits = RiskLimits { reallocation_cooldown: 86400, ..RiskLimits::default() };
+
+        apply_plan_to_strategy(&mut source, &plan, 1_000).unwrap();
+
+        let receivable_at = |current_time| {
+            StrategyPerformanceData::from_strategy(&source, &risk_limits, current_time).can_receive_allocation()
+        };
+        assert!(!receivable_at(1_000));
+        assert!(!receivable_at(1_000 + 86400 - 1));
+        assert!(receivable_at(1_000 + 86400));
+        // Without a configured cooldown it may be refunded straight away
+        assert!(StrategyPerformanceData::from_strategy(&source, &RiskLimits::default(), 1_000).can_receive_allocation());
+    }
+
+    #[test]
+    fn test_apply_plan_leaves_out_of_scope_strategy_untouched() {
+        let mut out_of_scope = strategy(3_000_000_000);
+        let plan = RebalancingPlan {
+            extraction_targets: vec![Pubkey::new_unique()],
+            extraction_amounts: vec![1_990_000_000],
+            total_to_extract: 1_990_000_000,
+            redistribution_plan: vec![CapitalAllocation {
+                strategy_id: Pubkey::new_unique(),
+                amount: 796_000_000,
+                min_acceptable_amount: 0,
+                allocation_type: AllocationType::TopPerformer,
+            }],
+            estimated_fees: 39_800_000,
+            expected_improvement: 0,
+        };
+
+        apply_plan_to_strategy(&mut out_of_scope, &plan, 100).unwrap();
+
+        assert_eq!(out_of_scope.current_balance, 3_000_000_000);
+        assert_eq!(out_of_scope.total_deposits, 3_000_000_000);
+        assert_eq!(out_of_scope.total_withdrawals, 0);
+    }
+
+    #[test]
+    fn test_apply_plan_rejects_allocation_above_capacity() {
+        let mut destination = Strategy { max_capacity: 1_500_000_000, ..strategy(1_000_000_000) };
+        let destination_id = destination.strategy_id;
+        let plan = |amount| RebalancingPlan {
+            extraction_targets: vec![Pubkey::new_unique()],
+            extraction_amounts: vec![1_990_000_000],
+            total_to_extract: 1_990_000_000,
+            redistribution_plan: vec![CapitalAllocation {
+                strategy_id: destination_id,
+                amount,
+                min_acceptable_amount: 0,
+                allocation_type: AllocationType::TopPerformer,
+            }],
+            estimated_fees: 39_800_000,
+            expected_improvement: 0,
+        };
+
+        let err = apply_plan_to_strategy(&mut destination.clone(), &plan(500_000_001), 100).unwrap_err();
+        assert_eq!(err, RebalancerErrorCode::StrategyAtCapacity.into());
+
+        // Filling exactly to the cap is allowed
+        apply_plan_to_strategy(&mut destination, &plan(500_000_000), 100).unwrap();
+        assert_eq!(destination.current_balance, 1_500_000_000);
+    }
+}
@@ -1,18 +1,22 @@
 use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
+use anchor_spl::token_interface::Mint;
 use crate::state::*;
 use crate::errors::*;
+use crate::events::StrategyRegistered;
 
 #[derive(Accounts)]
-#[instruction(strategy_id: Pubkey, protocol_type: ProtocolType, initial_balance: u64)]
+#[instruction(strategy_id: Pubkey, protocol_type: ProtocolType, initial_balance: u64, max_capacity: Option<u64>)]
 pub struct RegisterStrategy<'info> {
     #[account(
         mut,
-        seeds = [b"portfolio", portfolio.manager.as_ref()],
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
         bump = portfolio.bump,
         has_one = manager @ RebalancerErrorCode::UnauthorizedManager
     )]
     pub portfolio: Account<'info, Portfolio>,
     
+    // Plain init: a strategy id already registered in this portfolio fails here
     #[account(
         init,
         payer = manager,
@@ -22,6 +26,25 @@ pub struct RegisterStrategy<'info> {
     )]
     pub strategy: Account<'info, Strategy>,
     
+    // Optional: protocol minimums fall back to the defaults when absent
+    #[account(
+        seeds = [b"risk_config", portfolio.key().as_ref()],
+        bump = risk_config.bump,
+    )]
+    pub risk_config: Option<Account<'info, RiskConfig>>,
+    
+    // Token the strategy holds; omit for native SOL (recorded as wrapped SOL)
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    
+    // Optional: native SOL escrow. When passed, `initial_balance` lamports are
+    // moved into it and recorded as the strategy's verified balance
+    #[account(
+        mut,
+        seeds = [b"vault", strategy.key().as_ref()],
+        bump,
+    )]
+    pub vault: Option<SystemAccount<'info>>,
+    
     #[account(mut)]
     pub manager: Signer<'info>,
     
@@ -33,22 +56,56 @@ pub fn register_strategy(
     strategy_id: Pubkey,
     protocol_type: ProtocolType,
     initial_balance: u64,
+    max_capacity: Option<u64>,
 ) -> Result<()> {
     let portfolio = &mut ctx.accounts.portfolio;
     let strategy = &mut ctx.accounts.strategy;
     let current_time = Clock::get()?.unix_timestamp;
+    let risk_limits = ctx.accounts.risk_config
+        .as_ref()
+        .map(|config| config.limits.clone())
+        .unwrap_or_default();
+    let (mint, decimals) = match &ctx.accounts.mint {
+        Some(mint) => (mint.key(), mint.decimals),
+        None => (WRAPPED_SOL_MINT, SOL_DECIMALS),
+    };
     
     // COMPREHENSIVE SECURITY VALIDATIONS
     require!(!portfolio.emergency_pause, RebalancerErrorCode::EmergencyPaused);
     require!(strategy_id != Pubkey::default(), RebalancerErrorCode::InvalidStrategyId);
     require!(initial_balance > 0, RebalancerErrorCode::InsufficientBalance);
     Strategy::validate_balance_update(initial_balance)?;
+    portfolio.validate_strategy_slot()?;
     
     // PROTOCOL-SPECIFIC VALIDATION
     protocol_type.validate()?;
     
-    // VALIDATE BALANCE CONSTRAINTS FOR SPECIFIC PROTOCOL
-    protocol_type.validate_balance_constraints(initial_balance)?;
+    // VALIDATE BALANCE CONSTRAINTS FOR SPECIFIC PROTOCOL (minimums scale with the mint's decimals)
+    risk_limits.validate_balance_constraints(&protocol_type, initial_balance, decimals)?;
+    
+    // DEPOSIT CAP (None or 0 = uncapped)
+    let max_capacity = max_capacity.unwrap_or(0);
+    require!(max_capacity == 0 || initial_balance <= max_capacity, RebalancerErrorCode::StrategyAtCapacity);
+    
+    // VERIFIED DEPOSIT: without a vault the balance is only reported
+    let verified_balance = match &ctx.accounts.vault {
+        Some(vault) => {
+            require!(ctx.accounts.mint.is_none(), RebalancerErrorCode::VaultRequiresNativeSol);
+            let pre_balance = vault.lamports();
+            transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.manager.to_account_info(),
+                        to: vault.to_account_info(),
+                    },
+                ),
+                initial_balance,
+            )?;
+            verify_deposit(pre_balance, vault.lamports(), initial_balance)?
+        }
+        None => 0,
+    };
     
     // STRATEGY INITIALIZATION WITH SAFE DEFAULTS
     strategy.strategy_id = strategy_id;
@@ -64,15 +121,68 @@ pub fn register_strategy(
     strategy.total_withdrawals = 0;
     strategy.creation_time = current_time;
     strategy.bump = ctx.bumps.strategy;
-    strategy.reserved = [0u8; 29];
+    strategy.max_capacity = max_capacity;
+    strategy.volatility_ema = strategy.volatility_score;
+    strategy.mint = mint;
+    strategy.decimals = decimals;
+    strategy.verified_balance = verified_balance;
+    strategy.last_extracted = 0;
+    strategy.yield_history = [0; YIELD_HISTORY_LEN];
+    strategy.yield_history_head = 0;
+    strategy.yield_history_len = 0;
+    strategy.layout_version = STRATEGY_LAYOUT_VERSION;
+    strategy.reserved = [0u8; 8];
     
     // UPDATE PORTFOLIO COUNTERS WITH OVERFLOW PROTECTION
     portfolio.total_strategies = portfolio.total_strategies
         .checked_add(1)
         .ok_or(RebalancerErrorCode::BalanceOverflow)?;
+    portfolio.apply_balance_change(&mint, 0, initial_balance)?;
+    
+    msg!("Strategy registered: ID={}, Protocol={}, Mint={}, Balance={}, Verified={}", 
+         strategy_id, protocol_type.get_protocol_name(), mint, initial_balance, verified_balance);
     
-    msg!("Strategy registered: ID={}, Protocol={}, Balance={}", 
-         strategy_id, protocol_type.get_protocol_name(), initial_balance);
+    emit!(StrategyRegistered {
+        portfolio: portfolio.key(),
+        strategy_id,
+        protocol_type,
+        mint,
+        initial_balance,
+        verified_balance,
+        total_strategies: portfolio.total_strategies,
+        timestamp: current_time,
+    });
     
     Ok(())
-}
\ No newline at end of file
+}
+
+// THE VAULT MUST HAVE GAINED EXACTLY THE REGISTERED BALANCE
+pub fn verify_deposit(pre_balance: u64, post_balance: u64, initial_balance: u64) -> Result<u64> {
+    require!(
+        post_balance.checked_sub(pre_balance) == Some(initial_balance),
+        RebalancerErrorCode::DepositMismatch
+    );
+    Ok(initial_balance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_matching_deposit_is_verified() {
+        assert_eq!(verify_deposit(0, 1_000_000_000, 1_000_000_000).unwrap(), 1_000_000_000);
+        // Lamports already sitting in the vault do not count toward the deposit
+        assert_eq!(verify_deposit(890_880, 1_000_890_880, 1_000_000_000).unwrap(), 1_000_000_000);
+    }
+    
+    #[test]
+    fn test_mismatching_deposit_is_rejected() {
+        for (pre_balance, post_balance) in [(0, 999_999_999), (0, 1_000_000_001), (500, 1_000_000_000), (1_000, 0)] {
+            assert_eq!(
+                verify_deposit(pre_balance, post_balance, 1_000_000_000).unwrap_err(),
+                RebalancerErrorCode::DepositMismatch.into()
+            );
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::*;
+
+#[derive(Accounts)]
+pub struct SetCapacityLimits<'info> {
+    #[account(
+        mut,
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+        has_one = manager @ RebalancerErrorCode::UnauthorizedManager
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+    
+    pub manager: Signer<'info>,
+}
+
+pub fn set_capacity_limits(
+    ctx: Context<SetCapacityLimits>,
+    max_strategies: u32,
+    max_capital: u64,
+) -> Result<()> {
+    let portfolio = &mut ctx.accounts.portfolio;
+    
+    // A strategy limit below the current count would strand registered strategies
+    require!(
+        max_strategies == 0 || max_strategies >= portfolio.total_strategies,
+        RebalancerErrorCode::TooManyStrategies
+    );
+    
+    portfolio.max_strategies = max_strategies;
+    portfolio.max_capital = max_capital;
+    
+    msg!("Capacity limits updated: max_strategies={}, max_capital={} (0 = uncapped)",
+         max_strategies, max_capital);
+    
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::*;
+
+#[derive(Accounts)]
+pub struct SetEmergencyPause<'info> {
+    #[account(
+        mut,
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+    
+    // Required for the manager once governance is enabled
+    #[account(
+        mut,
+        seeds = [b"governance", portfolio.key().as_ref()],
+        bump = governance_config.bump,
+    )]
+    pub governance_config: Option<Account<'info, GovernanceConfig>>,
+    
+    /// Either the portfolio manager or its guardian
+    pub authority: Signer<'info>,
+}
+
+pub fn set_emergency_pause(
+    ctx: Context<SetEmergencyPause>,
+    paused: bool,
+) -> Result<()> {
+    let portfolio = &mut ctx.accounts.portfolio;
+    let authority = ctx.accounts.authority.key();
+    
+    // AUTHORIZATION: manager may pause or unpause, guardian may only pause
+    let is_manager = authority == portfolio.manager;
+    let is_guardian = portfolio.has_guardian() && authority == portfolio.guardian;
+    require!(is_manager || is_guardian, RebalancerErrorCode::UnauthorizedManager);
+    require!(is_manager || paused, RebalancerErrorCode::GuardianCannotUnpause);
+    
+    // A guardian pause stays immediate; manager changes need quorum under governance
+    if is_manager {
+        require_governance_approval(
+            portfolio.is_governance_enabled()?,
+            ctx.accounts.governance_config.as_deref_mut(),
+            &GovernanceAction::SetEmergencyPause { paused },
+            Clock::get()?.unix_timestamp,
+        )?;
+    }
+    
+    portfolio.emergency_pause = paused;
+    
+    msg!("Emergency pause {} by {} ({})",
+         if paused { "enabled" } else { "cleared" },
+         authority,
+         if is_manager { "manager" } else { "guardian" });
+    
+    Ok(())
+}
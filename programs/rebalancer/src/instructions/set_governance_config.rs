@@ -0,0 +1,63 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::*;
+
+#[derive(Accounts)]
+pub struct SetGovernanceConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+        has_one = manager @ RebalancerErrorCode::UnauthorizedManager
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+    
+    #[account(
+        init_if_needed,
+        payer = manager,
+        space = GovernanceConfig::MAX_SIZE,
+        seeds = [b"governance", portfolio.key().as_ref()],
+        bump
+    )]
+    pub governance_config: Account<'info, GovernanceConfig>,
+    
+    #[account(mut)]
+    pub manager: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+/// Enable, change or disable multi-manager governance.
+///
+/// The first call from a single-manager portfolio takes effect immediately.
+/// Once governance is on, changing the manager set (or turning governance off
+/// with an empty list and a zero threshold) needs the current quorum's approval.
+pub fn set_governance_config(
+    ctx: Context<SetGovernanceConfig>,
+    managers: Vec<Pubkey>,
+    threshold: u8,
+    approval_window: i64,
+) -> Result<()> {
+    GovernanceConfig::validate_settings(&managers, threshold, approval_window)?;
+    
+    let portfolio = &mut ctx.accounts.portfolio;
+    let governance = &mut ctx.accounts.governance_config;
+    
+    require_governance_approval(
+        portfolio.is_governance_enabled()?,
+        Some(governance),
+        &GovernanceAction::SetGovernanceConfig { managers: managers.clone(), threshold, approval_window },
+        Clock::get()?.unix_timestamp,
+    )?;
+    
+    governance.portfolio = portfolio.key();
+    governance.bump = ctx.bumps.governance_config;
+    governance.apply_settings(&managers, threshold, approval_window);
+    portfolio.governance_enabled = governance.is_enabled();
+    
+    msg!("Governance {}: {}-of-{} managers, {}s approval window",
+         if portfolio.governance_enabled { "enabled" } else { "disabled" },
+         threshold, managers.len(), approval_window);
+    
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::*;
+
+#[derive(Accounts)]
+pub struct SetGuardian<'info> {
+    #[account(
+        mut,
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+        has_one = manager @ RebalancerErrorCode::UnauthorizedManager
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+    
+    pub manager: Signer<'info>,
+}
+
+pub fn set_guardian(
+    ctx: Context<SetGuardian>,
+    guardian: Pubkey,
+) -> Result<()> {
+    let portfolio = &mut ctx.accounts.portfolio;
+    
+    // Pubkey::default() removes the guardian
+    require!(guardian != portfolio.manager, RebalancerErrorCode::InvalidManager);
+    
+    portfolio.guardian = guardian;
+    
+    msg!("Guardian set to {}", guardian);
+    
+    Ok(())
+}
@@ -0,0 +1,29 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::*;
+
+#[derive(Accounts)]
+pub struct SetMetricStaleness<'info> {
+    #[account(
+        mut,
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+        has_one = manager @ RebalancerErrorCode::UnauthorizedManager
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+    
+    pub manager: Signer<'info>,
+}
+
+pub fn set_metric_staleness(
+    ctx: Context<SetMetricStaleness>,
+    max_metric_staleness: i64,
+) -> Result<()> {
+    Portfolio::validate_max_metric_staleness(max_metric_staleness)?;
+    
+    ctx.accounts.portfolio.max_metric_staleness = max_metric_staleness;
+    
+    msg!("Max metric staleness updated: {}s (0 = unchecked)", max_metric_staleness);
+    
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::*;
+
+#[derive(Accounts)]
+pub struct SetOracleAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+        has_one = manager @ RebalancerErrorCode::UnauthorizedManager
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+    
+    pub manager: Signer<'info>,
+}
+
+pub fn set_oracle_authority(
+    ctx: Context<SetOracleAuthority>,
+    oracle_authority: Pubkey,
+) -> Result<()> {
+    let portfolio = &mut ctx.accounts.portfolio;
+    
+    // Pubkey::default() removes the oracle authority
+    require!(oracle_authority != portfolio.manager, RebalancerErrorCode::InvalidManager);
+    
+    portfolio.oracle_authority = oracle_authority;
+    
+    msg!("Oracle authority set to {}", oracle_authority);
+    
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::*;
+use crate::instructions::redistribute_capital::RiskLimits;
+
+#[derive(Accounts)]
+pub struct SetRiskConfig<'info> {
+    #[account(
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+        has_one = manager @ RebalancerErrorCode::UnauthorizedManager
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+    
+    #[account(
+        init_if_needed,
+        payer = manager,
+        space = RiskConfig::MAX_SIZE,
+        seeds = [b"risk_config", portfolio.key().as_ref()],
+        bump
+    )]
+    pub risk_config: Account<'info, RiskConfig>,
+    
+    // Required once governance is enabled
+    #[account(
+        mut,
+        seeds = [b"governance", portfolio.key().as_ref()],
+        bump = governance_config.bump,
+    )]
+    pub governance_config: Option<Account<'info, GovernanceConfig>>,
+    
+    #[account(mut)]
+    pub manager: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+pub fn set_risk_config(
+    ctx: Context<SetRiskConfig>,
+    limits: RiskLimits,
+) -> Result<()> {
+    // RISK LIMIT VALIDATIONS
+    limits.validate()?;
+    
+    require_governance_approval(
+        ctx.accounts.portfolio.is_governance_enabled()?,
+        ctx.accounts.governance_config.as_deref_mut(),
+        &GovernanceAction::SetRiskConfig { limits: Box::new(limits.clone()) },
+        Clock::get()?.unix_timestamp,
+    )?;
+    
+    let risk_config = &mut ctx.accounts.risk_config;
+    
+    risk_config.portfolio = ctx.accounts.portfolio.key();
+    risk_config.limits = limits;
+    risk_config.bump = ctx.bumps.risk_config;
+    
+    msg!("Risk config updated: max_single={}bps, min_single={}bps, platform_fee={}bps, manager_fee={}bps",
+         risk_config.limits.max_single_strategy_bps,
+         risk_config.limits.min_single_strategy_bps,
+         risk_config.limits.platform_fee_bps,
+         risk_config.limits.manager_fee_bps);
+    
+    Ok(())
+}
@@ -0,0 +1,31 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::*;
+
+#[derive(Accounts)]
+pub struct SetVolatilitySmoothing<'info> {
+    #[account(
+        mut,
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+        has_one = manager @ RebalancerErrorCode::UnauthorizedManager
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+    
+    pub manager: Signer<'info>,
+}
+
+/// Set how much weight a new volatility reading gets in each strategy's
+/// volatility EMA. Existing averages are kept and converge at the new rate.
+pub fn set_volatility_smoothing(
+    ctx: Context<SetVolatilitySmoothing>,
+    smoothing_bps: u16,
+) -> Result<()> {
+    Portfolio::validate_volatility_smoothing(smoothing_bps)?;
+    
+    ctx.accounts.portfolio.volatility_smoothing_bps = smoothing_bps;
+    
+    msg!("Volatility smoothing updated: {}bps", smoothing_bps);
+    
+    Ok(())
+}
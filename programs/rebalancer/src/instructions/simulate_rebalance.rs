@@ -0,0 +1,49 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::instructions::redistribute_capital::{
+    execute_complete_rebalancing, RebalancingPlan, StrategyPerformanceData,
+};
+use crate::utils::load_portfolio_strategies;
+
+#[derive(Accounts)]
+pub struct SimulateRebalance<'info> {
+    #[account(
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+
+    #[account(
+        seeds = [b"risk_config", portfolio.key().as_ref()],
+        bump = risk_config.bump,
+    )]
+    pub risk_config: Account<'info, RiskConfig>,
+}
+
+/// Dry run of `execute_complete_rebalancing`.
+///
+/// The strategy accounts are passed (read-only) in `remaining_accounts` and the
+/// plan is returned through Anchor's return data. Nothing is written: no cache,
+/// no `last_rebalance`, no balances. The emergency pause and the rebalance
+/// interval are deliberately not checked so managers can simulate at any time.
+/// A plan too large for return data fails with `TooManyStrategies`.
+pub fn simulate_rebalance<'info>(
+    ctx: Context<'_, '_, 'info, 'info, SimulateRebalance<'info>>,
+) -> Result<RebalancingPlan> {
+    let portfolio = &ctx.accounts.portfolio;
+    let risk_limits = &ctx.accounts.risk_config.limits;
+    let current_time = Clock::get()?.unix_timestamp;
+    let strategies = load_portfolio_strategies(&portfolio.key(), ctx.remaining_accounts)?;
+
+    let performance_data: Vec<StrategyPerformanceData> = strategies
+        .iter()
+        .map(|s| StrategyPerformanceData::from_strategy(s, risk_limits, current_time))
+        .collect();
+    let plan = execute_complete_rebalancing(portfolio, &performance_data, risk_limits)?;
+    plan.validate_size()?;
+
+    msg!("Simulated rebalance: targets={}, total_to_extract={}, allocations={}",
+         plan.extraction_targets.len(), plan.total_to_extract, plan.redistribution_plan.len());
+
+    Ok(plan)
+}
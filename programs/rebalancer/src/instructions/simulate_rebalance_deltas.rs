@@ -0,0 +1,52 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::instructions::redistribute_capital::{
+    compute_rebalance_deltas, execute_complete_rebalancing, page_deltas, StrategyDelta,
+    StrategyPerformanceData,
+};
+use crate::utils::load_portfolio_strategies;
+
+#[derive(Accounts)]
+pub struct SimulateRebalanceDeltas<'info> {
+    #[account(
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+
+    #[account(
+        seeds = [b"risk_config", portfolio.key().as_ref()],
+        bump = risk_config.bump,
+    )]
+    pub risk_config: Account<'info, RiskConfig>,
+}
+
+/// Dry run of `execute_complete_rebalancing`, reported per strategy.
+///
+/// Same inputs and guarantees as `simulate_rebalance`, but returns each passed
+/// strategy's balance before and after the plan instead of the raw plan.
+///
+/// The deltas are returned a page at a time: `limit` of them (at most
+/// `MAX_DELTAS_PER_PAGE`) starting at `offset`, in `remaining_accounts` order.
+pub fn simulate_rebalance_deltas<'info>(
+    ctx: Context<'_, '_, 'info, 'info, SimulateRebalanceDeltas<'info>>,
+    offset: u32,
+    limit: u8,
+) -> Result<Vec<StrategyDelta>> {
+    let portfolio = &ctx.accounts.portfolio;
+    let risk_limits = &ctx.accounts.risk_config.limits;
+    let current_time = Clock::get()?.unix_timestamp;
+    let strategies = load_portfolio_strategies(&portfolio.key(), ctx.remaining_accounts)?;
+
+    let performance_data: Vec<StrategyPerformanceData> = strategies
+        .iter()
+        .map(|s| StrategyPerformanceData::from_strategy(s, risk_limits, current_time))
+        .collect();
+    let plan = execute_complete_rebalancing(portfolio, &performance_data, risk_limits)?;
+    let deltas = compute_rebalance_deltas(&performance_data, &plan)?;
+
+    msg!("Simulated rebalance deltas: strategies={}, targets={}, total_to_extract={}",
+         deltas.len(), plan.extraction_targets.len(), plan.total_to_extract);
+
+    page_deltas(deltas, offset, limit)
+}
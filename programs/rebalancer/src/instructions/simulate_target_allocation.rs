@@ -0,0 +1,56 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::instructions::redistribute_capital::{
+    page_deltas, plan_target_allocation, DeltaRole, StrategyDelta, StrategyPerformanceData,
+};
+use crate::utils::load_portfolio_strategies;
+
+#[derive(Accounts)]
+pub struct SimulateTargetAllocation<'info> {
+    #[account(
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+
+    #[account(
+        seeds = [b"risk_config", portfolio.key().as_ref()],
+        bump = risk_config.bump,
+    )]
+    pub risk_config: Account<'info, RiskConfig>,
+}
+
+/// Buys and sells that move the passed strategies to the risk config's
+/// protocol targets, reported per strategy.
+///
+/// Strategies are passed in `remaining_accounts` as for `simulate_rebalance`.
+/// The targets must sum to 10000 bps, which the config guarantees once its
+/// allocation mode is `TargetAllocation`.
+///
+/// These are the full moves to the targets, before fees. A rebalance in
+/// `TargetAllocation` mode carries out the largest of them; see
+/// `simulate_rebalance_deltas` for what one rebalance moves. Returned a page at
+/// a time, as `simulate_rebalance_deltas`.
+pub fn simulate_target_allocation<'info>(
+    ctx: Context<'_, '_, 'info, 'info, SimulateTargetAllocation<'info>>,
+    offset: u32,
+    limit: u8,
+) -> Result<Vec<StrategyDelta>> {
+    let portfolio = &ctx.accounts.portfolio;
+    let risk_limits = &ctx.accounts.risk_config.limits;
+    let current_time = Clock::get()?.unix_timestamp;
+    let strategies = load_portfolio_strategies(&portfolio.key(), ctx.remaining_accounts)?;
+
+    let performance_data: Vec<StrategyPerformanceData> = strategies
+        .iter()
+        .map(|s| StrategyPerformanceData::from_strategy(s, risk_limits, current_time))
+        .collect();
+    let deltas = plan_target_allocation(&performance_data, risk_limits)?;
+
+    msg!("Simulated target allocation: strategies={}, sources={}, destinations={}",
+         deltas.len(),
+         deltas.iter().filter(|d| d.role == DeltaRole::Source).count(),
+         deltas.iter().filter(|d| d.role == DeltaRole::Destination).count());
+
+    page_deltas(deltas, offset, limit)
+}
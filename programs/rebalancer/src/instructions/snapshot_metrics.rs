@@ -0,0 +1,67 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::*;
+use crate::instructions::execute_ranking::StrategyData;
+use crate::utils::load_portfolio_strategies;
+
+#[derive(Accounts)]
+pub struct SnapshotMetrics<'info> {
+    #[account(
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+        has_one = manager @ RebalancerErrorCode::UnauthorizedManager
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+    
+    // Required: the protocol weights it holds are part of the hashed inputs
+    #[account(
+        seeds = [b"risk_config", portfolio.key().as_ref()],
+        bump = risk_config.bump,
+    )]
+    pub risk_config: Account<'info, RiskConfig>,
+    
+    // One snapshot per sequence, paired with the record of the next ranking cycle
+    #[account(
+        init,
+        payer = manager,
+        space = MetricsSnapshot::MAX_SIZE,
+        seeds = [b"metrics_snapshot", portfolio.key().as_ref(), &portfolio.rebalance_sequence.to_le_bytes()],
+        bump
+    )]
+    pub metrics_snapshot: Account<'info, MetricsSnapshot>,
+    
+    #[account(mut)]
+    pub manager: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+/// Hash the ranking inputs of every strategy in the portfolio, passed in
+/// `remaining_accounts`, into a `MetricsSnapshot` auditors can check later.
+/// The digest is over strategy_id order, whatever order the accounts arrive in.
+pub fn snapshot_metrics<'info>(
+    ctx: Context<'_, '_, 'info, 'info, SnapshotMetrics<'info>>,
+) -> Result<()> {
+    let portfolio = &ctx.accounts.portfolio;
+    let strategies = load_portfolio_strategies(&portfolio.key(), ctx.remaining_accounts)?;
+    // Cover the whole portfolio, as a ranking cycle does
+    portfolio.validate_strategy_account_count(strategies.len())?;
+    
+    let risk_limits = &ctx.accounts.risk_config.limits;
+    let metrics: Vec<StrategyData> = strategies
+        .iter()
+        .map(|s| StrategyData::from_strategy(s, risk_limits))
+        .collect();
+    
+    let snapshot = &mut ctx.accounts.metrics_snapshot;
+    snapshot.portfolio = portfolio.key();
+    snapshot.sequence = portfolio.rebalance_sequence;
+    snapshot.metrics_hash = MetricsSnapshot::hash_metrics(&metrics)?;
+    snapshot.strategy_count = metrics.len() as u32;
+    snapshot.timestamp = Clock::get()?.unix_timestamp;
+    snapshot.bump = ctx.bumps.metrics_snapshot;
+    
+    msg!("Metrics snapshot #{}: {} strategies", snapshot.sequence, snapshot.strategy_count);
+    
+    Ok(())
+}
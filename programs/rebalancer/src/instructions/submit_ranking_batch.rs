@@ -0,0 +1,51 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::*;
+use crate::utils::load_portfolio_strategies;
+
+#[derive(Accounts)]
+pub struct SubmitRankingBatch<'info> {
+    #[account(
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+        has_one = manager @ RebalancerErrorCode::UnauthorizedManager
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+    
+    // Must hold the limits the cycle began with
+    #[account(
+        seeds = [b"risk_config", portfolio.key().as_ref()],
+        bump = risk_config.bump,
+    )]
+    pub risk_config: Account<'info, RiskConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"ranking_session", portfolio.key().as_ref()],
+        bump = ranking_session.bump,
+    )]
+    pub ranking_session: Account<'info, RankingSession>,
+    
+    pub manager: Signer<'info>,
+}
+
+/// Merge one batch of strategies (read-only, in `remaining_accounts`) into the
+/// open ranking session.
+pub fn submit_ranking_batch<'info>(
+    ctx: Context<'_, '_, 'info, 'info, SubmitRankingBatch<'info>>,
+) -> Result<()> {
+    let session = &mut ctx.accounts.ranking_session;
+    require!(session.expected_count > 0, RebalancerErrorCode::RankingCycleIncomplete);
+    
+    let strategies = load_portfolio_strategies(&ctx.accounts.portfolio.key(), ctx.remaining_accounts)?;
+    require!(!strategies.is_empty(), RebalancerErrorCode::InsufficientStrategies);
+    let risk_limits = &ctx.accounts.risk_config.limits;
+    session.validate_risk_limits(risk_limits)?;
+    
+    session.submit_batch(strategies.iter().map(|s| &**s), risk_limits)?;
+    
+    msg!("Ranking batch submitted: {} strategies, {}/{} collected",
+         strategies.len(), session.entries.len(), session.expected_count);
+    
+    Ok(())
+}
@@ -0,0 +1,328 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::*;
+use crate::events::StrategyTransferred;
+use crate::utils::{close_program_account, transfer_from_vault};
+
+#[derive(Accounts)]
+#[instruction(strategy_id: Pubkey)]
+pub struct TransferStrategy<'info> {
+    #[account(
+        mut,
+        seeds = [b"portfolio", source_portfolio.seed_manager.as_ref()],
+        bump = source_portfolio.bump,
+        constraint = source_portfolio.manager == source_manager.key() @ RebalancerErrorCode::UnauthorizedManager
+    )]
+    pub source_portfolio: Account<'info, Portfolio>,
+
+    #[account(
+        mut,
+        seeds = [b"portfolio", destination_portfolio.seed_manager.as_ref()],
+        bump = destination_portfolio.bump,
+        constraint = destination_portfolio.manager == destination_manager.key() @ RebalancerErrorCode::UnauthorizedManager
+    )]
+    pub destination_portfolio: Account<'info, Portfolio>,
+
+    // Strategy PDAs are seeded from their portfolio, so the strategy moves to a
+    // freshly derived account and the old one is closed
+    #[account(
+        mut,
+        close = source_manager,
+        seeds = [b"strategy", source_portfolio.key().as_ref(), strategy_id.as_ref()],
+        bump = source_strategy.bump,
+        constraint = source_strategy.strategy_id == strategy_id @ RebalancerErrorCode::StrategyNotFound
+    )]
+    pub source_strategy: Account<'info, Strategy>,
+
+    #[account(
+        init,
+        payer = destination_manager,
+        space = Strategy::MAX_SIZE,
+        seeds = [b"strategy", destination_portfolio.key().as_ref(), strategy_id.as_ref()],
+        bump
+    )]
+    pub destination_strategy: Account<'info, Strategy>,
+
+    // The old PDA's vault: escrow must be withdrawn first, and anything left
+    // (rent, stray lamports) is swept to the source manager
+    #[account(
+        mut,
+        seeds = [b"vault", source_strategy.key().as_ref()],
+        bump,
+    )]
+    pub source_vault: SystemAccount<'info>,
+
+    /// CHECK: The strategy's allocation log PDA under the source portfolio. It
+    /// is moved to `destination_allocation_log` when it exists; an address with
+    /// no log behind it is left alone.
+    #[account(
+        mut,
+        seeds = [b"allocation_log", source_portfolio.key().as_ref(), strategy_id.as_ref()],
+        bump,
+    )]
+    pub source_allocation_log: UncheckedAccount<'info>,
+
+    // Required when the strategy has an allocation log
+    #[account(
+        init,
+        payer = destination_manager,
+        space = StrategyAllocationLog::MAX_SIZE,
+        seeds = [b"allocation_log", destination_portfolio.key().as_ref(), strategy_id.as_ref()],
+        bump
+    )]
+    pub destination_allocation_log: Option<Account<'info, StrategyAllocationLog>>,
+
+    // Required once the source portfolio has governance enabled
+    #[account(
+        mut,
+        seeds = [b"governance", source_portfolio.key().as_ref()],
+        bump = governance_config.bump,
+    )]
+    pub governance_config: Option<Account<'info, GovernanceConfig>>,
+
+    #[account(mut)]
+    pub source_manager: Signer<'info>,
+
+    #[account(mut)]
+    pub destination_manager: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Move a strategy, with its balances and performance history, to another
+/// portfolio. Both managers must sign: the source gives up the strategy and
+/// receives the old accounts' rent, the destination pays for the new PDAs.
+/// The strategy's allocation log, if any, moves with it. A source portfolio
+/// under governance needs the transfer approved first.
+pub fn transfer_strategy(ctx: Context<TransferStrategy>, strategy_id: Pubkey) -> Result<()> {
+    let current_time = Clock::get()?.unix_timestamp;
+    require_governance_approval(
+        ctx.accounts.source_portfolio.is_governance_enabled()?,
+        ctx.accounts.governance_config.as_deref_mut(),
+        &GovernanceAction::TransferStrategy {
+            strategy_id,
+            destination_portfolio: ctx.accounts.destination_portfolio.key(),
+        },
+        current_time,
+    )?;
+
+    let source_portfolio = &mut ctx.accounts.source_portfolio;
+    let destination_portfolio = &mut ctx.accounts.destination_portfolio;
+    let source_strategy = &ctx.accounts.source_strategy;
+
+    // TRANSFER ELIGIBILITY
+    require!(
+        source_portfolio.key() != destination_portfolio.key(),
+        RebalancerErrorCode::InvalidStrategyTransfer
+    );
+    validate_strategy_transfer(source_portfolio, destination_portfolio, source_strategy)?;
+
+    // PORTFOLIO COUNTERS
+    apply_strategy_transfer(source_portfolio, destination_portfolio, source_strategy)?;
+
+    // SWEEP THE OLD VAULT
+    transfer_from_vault(
+        &ctx.accounts.source_vault,
+        ctx.accounts.source_manager.to_account_info(),
+        &ctx.accounts.system_program,
+        &source_strategy.key(),
+        ctx.bumps.source_vault,
+        ctx.accounts.source_vault.lamports(),
+    )?;
+
+    // COPY THE STRATEGY UNDER ITS NEW PDA
+    let mut strategy = (**source_strategy).clone();
+    strategy.bump = ctx.bumps.destination_strategy;
+    ctx.accounts.destination_strategy.set_inner(strategy);
+
+    // MOVE THE ALLOCATION LOG (A SYSTEM-OWNED ADDRESS HAS NONE)
+    let source_log_info = ctx.accounts.source_allocation_log.to_account_info();
+    if *source_log_info.owner == crate::ID {
+        let source_log = StrategyAllocationLog::try_deserialize(&mut &source_log_info.try_borrow_data()?[..])?;
+        let destination_log = ctx.accounts.destination_allocation_log
+            .as_mut()
+            .ok_or(RebalancerErrorCode::AllocationLogRequired)?;
+        let bump = ctx.bumps.destination_allocation_log.ok_or(RebalancerErrorCode::AllocationLogRequired)?;
+        destination_log.set_inner(moved_allocation_log(&source_log, destination_portfolio.key(), bump));
+        close_program_account(&source_log_info, &ctx.accounts.source_manager.to_account_info())?;
+    }
+
+    msg!("Strategy transferred: ID={}, from={}, to={}, balance={}",
+         strategy_id, source_portfolio.key(), destination_portfolio.key(), source_strategy.current_balance);
+
+    emit!(StrategyTransferred {
+        strategy_id,
+        source_portfolio: source_portfolio.key(),
+        destination_portfolio: destination_portfolio.key(),
+        balance: source_strategy.current_balance,
+        timestamp: current_time,
+    });
+
+    Ok(())
+}
+
+// NEITHER PORTFOLIO MAY BE PAUSED, AND VAULT-HELD FUNDS STAY WITH THE OLD PDA
+pub fn validate_strategy_transfer(source: &Portfolio, destination: &Portfolio, strategy: &Strategy) -> Result<()> {
+    require!(!source.emergency_pause && !destination.emergency_pause, RebalancerErrorCode::EmergencyPaused);
+    // The vault is seeded from the strategy PDA, so escrowed lamports cannot follow
+    // the strategy; they are withdrawn through the vault before the transfer
+    require!(strategy.verified_balance == 0, RebalancerErrorCode::StrategyHasVaultBalance);
+    destination.validate_strategy_slot()?;
+    Ok(())
+}
+
+pub fn apply_strategy_transfer(source: &mut Portfolio, destination: &mut Portfolio, strategy: &Strategy) -> Result<()> {
+    source.total_strategies = source.total_strategies
+        .checked_sub(1)
+        .ok_or(RebalancerErrorCode::InsufficientStrategies)?;
+    source.apply_balance_change(&strategy.mint, strategy.current_balance, 0)?;
+
+    destination.total_strategies = destination.total_strategies
+        .checked_add(1)
+        .ok_or(RebalancerErrorCode::BalanceOverflow)?;
+    destination.apply_balance_change(&strategy.mint, 0, strategy.current_balance)?;
+    Ok(())
+}
+
+// THE LOG KEEPS ITS HISTORY UNDER THE DESTINATION PORTFOLIO
+pub fn moved_allocation_log(
+    source: &StrategyAllocationLog,
+    destination_portfolio: Pubkey,
+    bump: u8,
+) -> StrategyAllocationLog {
+    StrategyAllocationLog {
+        portfolio: destination_portfolio,
+        strategy_id: source.strategy_id,
+        head: source.head,
+        entries: source.entries.clone(),
+        bump,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+
+    fn portfolio(total_strategies: u32, max_strategies: u32, total_value_locked: u64) -> Portfolio {
+        Portfolio {
+            manager: Pubkey::new_unique(),
+            total_capital_moved: 0,
+            last_rebalance: 0,
+            min_rebalance_interval: 3600,
+            portfolio_creation: 0,
+            total_strategies,
+            performance_fee_bps: 200,
+            base_threshold: 15,
+            emergency_pause: false,
+            bump: 255,
+            max_strategies,
+            max_capital: 0,
+            guardian: Pubkey::default(),
+            emergency_rebalance_count: 0,
+            seed_manager: Pubkey::default(),
+            pending_manager: Pubkey::default(),
+            max_metric_staleness: DEFAULT_MAX_METRIC_STALENESS,
+            rebalance_sequence: 0,
+            high_water_mark: 0,
+            total_value_locked,
+            volatility_smoothing_bps: DEFAULT_VOLATILITY_SMOOTHING_BPS,
+            oracle_authority: Pubkey::default(),
+            governance_enabled: false,
+            layout_version: PORTFOLIO_LAYOUT_VERSION,
+            reserved: [0u8; 3],
+        }
+    }
+
+    fn strategy(current_balance: u64, verified_balance: u64) -> Strategy {
+        Strategy {
+            current_balance,
+            total_deposits: current_balance,
+            verified_balance,
+            ..test_utils::strategy()
+        }
+    }
+
+    #[test]
+    fn test_transfer_moves_count_and_balance() {
+        let mut source = portfolio(3, 50, 5_000_000_000);
+        let mut destination = portfolio(1, 50, 1_000_000_000);
+        let moved = strategy(2_000_000_000, 0);
+
+        validate_strategy_transfer(&source, &destination, &moved).unwrap();
+        apply_strategy_transfer(&mut source, &mut destination, &moved).unwrap();
+
+        assert_eq!(source.total_strategies, 2);
+        assert_eq!(source.total_value_locked, 3_000_000_000);
+        assert_eq!(destination.total_strategies, 2);
+        assert_eq!(destination.total_value_locked, 3_000_000_000);
+    }
+
+    #[test]
+    fn test_full_or_paused_destination_rejected() {
+        let source = portfolio(3, 50, 5_000_000_000);
+        let moved = strategy(2_000_000_000, 0);
+
+        assert_eq!(
+            validate_strategy_transfer(&source, &portfolio(2, 2, 0), &moved).unwrap_err(),
+            RebalancerErrorCode::TooManyStrategies.into()
+        );
+
+        let mut paused = portfolio(1, 50, 0);
+        paused.emergency_pause = true;
+        assert_eq!(
+            validate_strategy_transfer(&source, &paused, &moved).unwrap_err(),
+            RebalancerErrorCode::EmergencyPaused.into()
+        );
+        assert_eq!(
+            validate_strategy_transfer(&paused, &source, &moved).unwrap_err(),
+            RebalancerErrorCode::EmergencyPaused.into()
+        );
+    }
+
+    #[test]
+    fn test_vault_funded_strategy_rejected() {
+        assert_eq!(
+            validate_strategy_transfer(&portfolio(1, 50, 0), &portfolio(0, 50, 0), &strategy(1_000_000_000, 1_000_000_000))
+                .unwrap_err(),
+            RebalancerErrorCode::StrategyHasVaultBalance.into()
+        );
+    }
+
+    #[test]
+    fn test_allocation_log_moves_with_history() {
+        let mut source = StrategyAllocationLog {
+            portfolio: Pubkey::new_unique(),
+            strategy_id: Pubkey::new_unique(),
+            head: 0,
+            entries: Vec::new(),
+            bump: 254,
+        };
+        for i in 0..ALLOCATION_LOG_CAPACITY as i64 + 3 {
+            source.record(AllocationLogEntry {
+                timestamp: i,
+                amount: 1_000_000 * i as u64,
+                allocation_type: AllocationType::TopPerformer,
+            });
+        }
+        let destination_portfolio = Pubkey::new_unique();
+
+        let moved = moved_allocation_log(&source, destination_portfolio, 251);
+
+        assert_eq!(moved.portfolio, destination_portfolio);
+        assert_eq!(moved.strategy_id, source.strategy_id);
+        assert_eq!(moved.bump, 251);
+        assert_eq!(moved.history(), source.history());
+    }
+
+    #[test]
+    fn test_empty_source_rejected() {
+        let mut source = portfolio(0, 50, 0);
+        let mut destination = portfolio(0, 50, 0);
+
+        assert_eq!(
+            apply_strategy_transfer(&mut source, &mut destination, &strategy(0, 0)).unwrap_err(),
+            RebalancerErrorCode::InsufficientStrategies.into()
+        );
+    }
+}
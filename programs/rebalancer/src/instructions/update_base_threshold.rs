@@ -0,0 +1,60 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::*;
+use crate::events::BaseThresholdUpdated;
+
+#[derive(Accounts)]
+pub struct UpdateBaseThreshold<'info> {
+    #[account(
+        mut,
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+        has_one = manager @ RebalancerErrorCode::UnauthorizedManager
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+    
+    // Required once governance is enabled
+    #[account(
+        mut,
+        seeds = [b"governance", portfolio.key().as_ref()],
+        bump = governance_config.bump,
+    )]
+    pub governance_config: Option<Account<'info, GovernanceConfig>>,
+    
+    pub manager: Signer<'info>,
+}
+
+/// Change the base of the dynamic underperformer threshold.
+///
+/// `last_rebalance` is left alone: the new threshold only decides which
+/// strategies the next ranking cycle flags, so it takes effect on the existing
+/// schedule rather than pushing the next cycle back.
+pub fn update_base_threshold(
+    ctx: Context<UpdateBaseThreshold>,
+    base_threshold: u8,
+) -> Result<()> {
+    Portfolio::validate_base_threshold(base_threshold)?;
+    
+    let current_time = Clock::get()?.unix_timestamp;
+    require_governance_approval(
+        ctx.accounts.portfolio.is_governance_enabled()?,
+        ctx.accounts.governance_config.as_deref_mut(),
+        &GovernanceAction::UpdateBaseThreshold { base_threshold },
+        current_time,
+    )?;
+    
+    let portfolio = &mut ctx.accounts.portfolio;
+    let previous_threshold = portfolio.base_threshold;
+    portfolio.base_threshold = base_threshold;
+    
+    msg!("Base threshold updated: {} -> {}", previous_threshold, base_threshold);
+    
+    emit!(BaseThresholdUpdated {
+        portfolio: portfolio.key(),
+        previous_threshold,
+        base_threshold,
+        timestamp: current_time,
+    });
+    
+    Ok(())
+}
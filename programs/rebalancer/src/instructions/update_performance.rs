@@ -1,15 +1,17 @@
 use anchor_lang::prelude::*;
 use crate::state::*;
 use crate::errors::RebalancerErrorCode;
+use crate::events::PerformanceUpdated;
+use crate::utils::compute_performance_score;
 
 #[derive(Accounts)]
 #[instruction(strategy_id: Pubkey)]
 pub struct UpdatePerformance<'info> {
     #[account(
         mut,
-        seeds = [b"portfolio", portfolio.manager.as_ref()],
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
         bump = portfolio.bump,
-        has_one = manager @ RebalancerErrorCode::UnauthorizedManager
+        constraint = portfolio.can_update_performance(&authority.key()) @ RebalancerErrorCode::UnauthorizedManager
     )]
     pub portfolio: Account<'info, Portfolio>,
     
@@ -21,8 +23,8 @@ pub struct UpdatePerformance<'info> {
     )]
     pub strategy: Account<'info, Strategy>,
     
-    #[account(mut)]
-    pub manager: Signer<'info>,
+    /// Either the portfolio manager or its oracle authority
+    pub authority: Signer<'info>,
 }
 
 pub fn update_performance(
@@ -34,139 +36,201 @@ pub fn update_performance(
 ) -> Result<()> {
     let strategy = &mut ctx.accounts.strategy;
     let current_time = Clock::get()?.unix_timestamp;
+    let previous_balance = strategy.current_balance;
+    let smoothing_bps = ctx.accounts.portfolio.volatility_smoothing_bps;
     
+    apply_performance_update(strategy, yield_rate, volatility_score, current_balance, current_time, smoothing_bps)?;
+    ctx.accounts.portfolio.apply_balance_change(&strategy.mint, previous_balance, current_balance)?;
+    
+    emit!(PerformanceUpdated {
+        portfolio: ctx.accounts.portfolio.key(),
+        strategy_id: strategy.strategy_id,
+        yield_rate,
+        volatility_score,
+        volatility_ema: strategy.volatility_ema,
+        current_balance,
+        performance_score: strategy.performance_score,
+        timestamp: current_time,
+    });
+    
+    Ok(())
+}
+
+// VALIDATE AND APPLY ONE STRATEGY'S METRICS (shared with batch_update_performance)
+pub fn apply_performance_update(
+    strategy: &mut Strategy,
+    yield_rate: u64,
+    volatility_score: u32,
+    current_balance: u64,
+    current_time: i64,
+    smoothing_bps: u16,
+) -> Result<()> {
     // COMPREHENSIVE INPUT VALIDATIONS
     Strategy::validate_yield_rate(yield_rate)?;
     Strategy::validate_volatility_score(volatility_score)?;
     Strategy::validate_balance_update(current_balance)?;
+    strategy.validate_capacity(current_balance)?;
+    strategy.protocol_type.validate_yield_for_protocol(yield_rate)?;
     require!(strategy.status == StrategyStatus::Active, RebalancerErrorCode::StrategyNotFound);
     
-    // UPDATE STRATEGY METRICS
+    // UPDATE STRATEGY METRICS (raw volatility kept for reference, EMA drives scoring)
     strategy.yield_rate = yield_rate;
+    strategy.record_yield(yield_rate);
     strategy.volatility_score = volatility_score;
+    strategy.volatility_ema = Strategy::smoothed_volatility(strategy.volatility_ema, volatility_score, smoothing_bps);
     strategy.current_balance = current_balance;
     strategy.last_updated = current_time;
     
-    // CALCULATE PERFORMANCE SCORE WITH WEIGHTED FORMULA
-    strategy.performance_score = calculate_performance_score(
+    // DERIVE PERFORMANCE SCORE FROM VALIDATED METRICS AND STRATEGY AGE
+    strategy.performance_score = compute_performance_score(
         yield_rate,
-        current_balance,
-        volatility_score,
-    )?;
+        strategy.volatility_ema,
+        current_time.saturating_sub(strategy.creation_time),
+    );
     
-    msg!("Performance updated: strategy={}, yield={}bps, volatility={}, balance={}, score={}", 
-         strategy.strategy_id, yield_rate, volatility_score, current_balance, strategy.performance_score);
+    msg!("Performance updated: strategy={}, yield={}bps, volatility={} (ema {}), balance={}, score={}", 
+         strategy.strategy_id, yield_rate, volatility_score, strategy.volatility_ema, current_balance, strategy.performance_score);
     
     Ok(())
 }
 
-// EXACT WEIGHTED PERFORMANCE SCORING ALGORITHM
-pub fn calculate_performance_score(
-    yield_rate: u64,      // Annual yield in basis points (0-50000)
-    balance: u64,         // Current capital allocated in lamports
-    volatility: u32,      // Risk score 0-10000 (100.00% max)
-) -> Result<u64> {
-    // NORMALIZATION TO 0-10000 SCALE FOR EACH METRIC
-    
-    // Normalize yield rate: 0-50000 basis points -> 0-10000 scale
-    let normalized_yield = if yield_rate > 50000 {
-        10000u64
-    } else {
-        (yield_rate as u128 * 10000u128 / 50000u128) as u64
-    };
-    
-    // Normalize balance: Use logarithmic scaling for better distribution
-    // Range: 100M lamports (0.1 SOL) to 100B lamports (100 SOL) -> 0-10000 scale
-    let normalized_balance = if balance == 0 {
-        0u64
-    } else if balance >= 100_000_000_000u64 { // 100 SOL cap
-        10000u64
-    } else if balance < 100_000_000u64 { // 0.1 SOL minimum
-        (balance as u128 * 1000u128 / 100_000_000u128) as u64 // Linear below minimum
-    } else {
-        // Logarithmic scaling between 0.1 and 100 SOL
-        let log_balance = ((balance as f64).ln() * 1000.0) as u64;
-        let log_min = ((100_000_000f64).ln() * 1000.0) as u64;
-        let log_max = ((100_000_000_000f64).ln() * 1000.0) as u64;
-        
-        if log_max > log_min {
-            ((log_balance.saturating_sub(log_min) as u128 * 10000u128) / 
-             (log_max - log_min) as u128) as u64
-        } else {
-            5000u64 // Fallback to median if calculation fails
-        }
-    };
-    
-    // Normalize inverse volatility: 0-10000 volatility -> 10000-0 inverse scale
-    let normalized_inverse_volatility = 10000u32.saturating_sub(volatility.min(10000)) as u64;
-    
-    // WEIGHTED COMPOSITE CALCULATION: Yield(45%) + Balance(35%) + InverseVolatility(20%)
-    let yield_component = normalized_yield
-        .checked_mul(4500)
-        .ok_or(RebalancerErrorCode::BalanceOverflow)?
-        .checked_div(10000)
-        .ok_or(RebalancerErrorCode::BalanceOverflow)?;
-    
-    let balance_component = normalized_balance
-        .checked_mul(3500)
-        .ok_or(RebalancerErrorCode::BalanceOverflow)?
-        .checked_div(10000)
-        .ok_or(RebalancerErrorCode::BalanceOverflow)?;
-    
-    let volatility_component = normalized_inverse_volatility
-        .checked_mul(2000)
-        .ok_or(RebalancerErrorCode::BalanceOverflow)?
-        .checked_div(10000)
-        .ok_or(RebalancerErrorCode::BalanceOverflow)?;
-    
-    // FINAL COMPOSITE SCORE
-    let performance_score = yield_component
-        .checked_add(balance_component)
-        .ok_or(RebalancerErrorCode::BalanceOverflow)?
-        .checked_add(volatility_component)
-        .ok_or(RebalancerErrorCode::BalanceOverflow)?;
-    
-    Ok(performance_score)
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
-    
+    use crate::test_utils;
+
+    fn strategy(protocol_type: ProtocolType) -> Strategy {
+        Strategy {
+            yield_rate: 0,
+            performance_score: 0,
+            protocol_type,
+            volatility_score: 0,
+            percentile_rank: 0,
+            volatility_ema: 0,
+            ..test_utils::strategy()
+        }
+    }
+
+    fn lending() -> ProtocolType {
+        ProtocolType::StableLending {
+            pool_id: Pubkey::new_unique(),
+            reserve_address: Pubkey::new_unique(),
+            utilization: 7500,
+        }
+    }
+
+    fn farming() -> ProtocolType {
+        ProtocolType::YieldFarming {
+            pair_id: Pubkey::new_unique(),
+            reward_multiplier: 2,
+            token_a_mint: Pubkey::new_unique(),
+            token_b_mint: Pubkey::new_unique(),
+            fee_tier: 30,
+        }
+    }
+
+    fn staking() -> ProtocolType {
+        ProtocolType::LiquidStaking {
+            validator_id: Pubkey::new_unique(),
+            stake_pool: Pubkey::new_unique(),
+            unstake_delay: 10,
+            commission: 500,
+        }
+    }
+
+    fn assert_ceiling(protocol_type: ProtocolType, ceiling: u64) {
+        assert_eq!(protocol_type.max_reasonable_yield_bps(), ceiling);
+
+        let mut at_ceiling = strategy(protocol_type);
+        apply_performance_update(&mut at_ceiling, ceiling, 3000, 1_000_000_000, 100, 10000).unwrap();
+        assert_eq!(at_ceiling.yield_rate, ceiling);
+
+        let mut above_ceiling = strategy(protocol_type);
+        let err = apply_performance_update(&mut above_ceiling, ceiling + 1, 3000, 1_000_000_000, 100, 10000).unwrap_err();
+        assert_eq!(err, RebalancerErrorCode::UnreasonableYieldForProtocol.into());
+        assert_eq!(above_ceiling.yield_rate, 0);
+        assert_eq!(above_ceiling.last_updated, 0);
+    }
+
     #[test]
-    fn test_performance_score_calculation() {
-        // Test case 1: High yield, high balance, low volatility (best case)
-        let score1 = calculate_performance_score(
-            20000,        // 200% yield
-            50_000_000_000, // 50 SOL
-            1000,         // 10% volatility
-        ).unwrap();
-        
-        // Test case 2: Low yield, low balance, high volatility (worst case)
-        let score2 = calculate_performance_score(
-            500,          // 5% yield
-            100_000_000,  // 0.1 SOL
-            9000,         // 90% volatility
-        ).unwrap();
-        
-        // Score1 should be significantly higher than Score2
-        assert!(score1 > score2);
-        assert!(score1 <= 10000); // Within expected range
-        assert!(score2 <= 10000); // Within expected range
+    fn test_stable_lending_yield_ceiling() {
+        assert_ceiling(lending(), STABLE_LENDING_MAX_YIELD_BPS);
     }
-    
+
     #[test]
-    fn test_edge_cases() {
-        // Zero balance
-        let score_zero = calculate_performance_score(10000, 0, 5000).unwrap();
-        assert_eq!(score_zero, 5000); // Should only get yield + volatility components
-        
-        // Maximum values
-        let score_max = calculate_performance_score(50000, 100_000_000_000, 0).unwrap();
-        assert_eq!(score_max, 10000); // Perfect score
-        
-        // Minimum values  
-        let score_min = calculate_performance_score(0, 100_000_000, 10000).unwrap();
-        assert!(score_min < 5000); // Low score as expected
+    fn test_liquid_staking_yield_ceiling() {
+        assert_ceiling(staking(), LIQUID_STAKING_MAX_YIELD_BPS);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_yield_farming_ceiling_matches_global_cap() {
+        assert_eq!(farming().max_reasonable_yield_bps(), YIELD_FARMING_MAX_YIELD_BPS);
+
+        let mut at_ceiling = strategy(farming());
+        apply_performance_update(&mut at_ceiling, YIELD_FARMING_MAX_YIELD_BPS, 3000, 1_000_000_000, 100, 10000).unwrap();
+        assert_eq!(at_ceiling.yield_rate, YIELD_FARMING_MAX_YIELD_BPS);
+
+        // Above the farming ceiling the global cap rejects the rate first
+        let mut above_ceiling = strategy(farming());
+        let err = apply_performance_update(&mut above_ceiling, YIELD_FARMING_MAX_YIELD_BPS + 1, 3000, 1_000_000_000, 100, 10000).unwrap_err();
+        assert_eq!(err, RebalancerErrorCode::ExcessiveYieldRate.into());
+    }
+
+    #[test]
+    fn test_balance_above_capacity_rejected() {
+        let mut capped = Strategy { max_capacity: 2_000_000_000, ..strategy(lending()) };
+        apply_performance_update(&mut capped, 1000, 3000, 2_000_000_000, 100, 10000).unwrap();
+        assert_eq!(capped.current_balance, 2_000_000_000);
+
+        let err = apply_performance_update(&mut capped, 1000, 3000, 2_000_000_001, 200, 10000).unwrap_err();
+        assert_eq!(err, RebalancerErrorCode::StrategyAtCapacity.into());
+        assert_eq!(capped.current_balance, 2_000_000_000);
+        assert_eq!(capped.last_updated, 100);
+    }
+
+    #[test]
+    fn test_uncapped_strategy_accepts_large_balance() {
+        let mut uncapped = strategy(lending());
+        assert_eq!(uncapped.max_capacity, 0);
+
+        apply_performance_update(&mut uncapped, 1000, 3000, 1_000_000_000_000_000, 100, 10000).unwrap();
+        assert_eq!(uncapped.current_balance, 1_000_000_000_000_000);
+    }
+
+    #[test]
+    fn test_volatility_ema_dampens_single_spike() {
+        let mut smoothed = Strategy { volatility_score: 2000, volatility_ema: 2000, ..strategy(lending()) };
+        let mut unsmoothed = smoothed.clone();
+
+        for (time, reading) in [(100, 2000), (200, 9000), (300, 2000)] {
+            apply_performance_update(&mut smoothed, 1000, reading, 1_000_000_000, time, DEFAULT_VOLATILITY_SMOOTHING_BPS).unwrap();
+            apply_performance_update(&mut unsmoothed, 1000, reading, 1_000_000_000, time, 10000).unwrap();
+
+            // The raw reading is always kept for reference
+            assert_eq!(smoothed.volatility_score, reading);
+            if time == 200 {
+                // 30% of the 7000bps spike reaches the average, all of it without smoothing
+                assert_eq!(smoothed.volatility_ema, 4100);
+                assert_eq!(unsmoothed.volatility_ema, 9000);
+                assert!(smoothed.performance_score > unsmoothed.performance_score);
+            }
+        }
+
+        // One reading later the average has mostly recovered
+        assert_eq!(smoothed.volatility_ema, 3470);
+        assert_eq!(unsmoothed.volatility_ema, 2000);
+        assert_eq!(smoothed.performance_score, compute_performance_score(1000, 3470, 300));
+    }
+
+    #[test]
+    fn test_smoothed_volatility_bounds() {
+        assert_eq!(Strategy::smoothed_volatility(2000, 9000, 10000), 9000);
+        assert_eq!(Strategy::smoothed_volatility(2000, 9000, 1), 2000);
+        assert_eq!(Strategy::smoothed_volatility(10000, 10000, 3000), 10000);
+
+        assert!(Portfolio::validate_volatility_smoothing(0).is_err());
+        assert!(Portfolio::validate_volatility_smoothing(1).is_ok());
+        assert!(Portfolio::validate_volatility_smoothing(10000).is_ok());
+        assert!(Portfolio::validate_volatility_smoothing(10001).is_err());
+    }
+}
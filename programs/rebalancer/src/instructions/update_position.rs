@@ -0,0 +1,125 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::*;
+use crate::core_math::compute_impermanent_loss;
+
+#[derive(Accounts)]
+#[instruction(strategy_id: Pubkey)]
+pub struct UpdatePosition<'info> {
+    #[account(
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+        has_one = manager @ RebalancerErrorCode::UnauthorizedManager
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+    
+    #[account(
+        seeds = [b"strategy", portfolio.key().as_ref(), strategy_id.as_ref()],
+        bump = strategy.bump,
+        constraint = strategy.strategy_id == strategy_id @ RebalancerErrorCode::StrategyNotFound
+    )]
+    pub strategy: Account<'info, Strategy>,
+    
+    #[account(
+        mut,
+        seeds = [b"position", strategy_id.as_ref()],
+        bump = position.bump,
+        constraint = position.strategy_id == strategy_id @ RebalancerErrorCode::StrategyNotFound
+    )]
+    pub position: Account<'info, CapitalPosition>,
+    
+    pub manager: Signer<'info>,
+}
+
+pub fn update_position(
+    ctx: Context<UpdatePosition>,
+    _strategy_id: Pubkey,
+    current_price_a: u64,
+    current_price_b: u64,
+    accrued_fees: u64,
+) -> Result<()> {
+    let position = &mut ctx.accounts.position;
+    let current_time = Clock::get()?.unix_timestamp;
+    
+    // PRICE VALIDATIONS
+    require!(current_price_a > 0, RebalancerErrorCode::InvalidPrice);
+    require!(
+        current_price_b > 0 || position.position_type != PositionType::LiquidityPair,
+        RebalancerErrorCode::InvalidPrice
+    );
+    
+    // REFRESH POSITION METRICS
+    position.accrued_fees = accrued_fees;
+    position.impermanent_loss = calculate_position_il(position, current_price_a, current_price_b);
+    position.last_price_update = current_time;
+    
+    msg!("Position updated: strategy={}, price_a={}, price_b={}, fees={}, il={}",
+         position.strategy_id, current_price_a, current_price_b, accrued_fees, position.impermanent_loss);
+    
+    Ok(())
+}
+
+// ONLY LIQUIDITY PAIRS ARE EXPOSED TO IMPERMANENT LOSS
+pub fn calculate_position_il(position: &CapitalPosition, current_price_a: u64, current_price_b: u64) -> i64 {
+    if position.position_type != PositionType::LiquidityPair {
+        return 0;
+    }
+    
+    compute_impermanent_loss(
+        position.entry_price_a,
+        position.entry_price_b,
+        current_price_a,
+        current_price_b,
+        position.token_a_amount,
+        position.token_b_amount,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn position(position_type: PositionType, entry_price_b: u64) -> CapitalPosition {
+        CapitalPosition {
+            strategy_id: Pubkey::new_unique(),
+            token_a_amount: 1_000_000_000,
+            token_b_amount: 1_000_000_000,
+            lp_tokens: 0,
+            platform_controlled_lp: 0,
+            entry_price_a: 1_000_000,
+            entry_price_b,
+            last_rebalance: 0,
+            accrued_fees: 0,
+            impermanent_loss: 0,
+            position_type,
+            bump: 255,
+            last_price_update: 0,
+            reserved: [0u8; 6],
+        }
+    }
+    
+    #[test]
+    fn test_liquidity_pair_tracks_impermanent_loss() {
+        let pair = position(PositionType::LiquidityPair, 1_000_000);
+        assert_eq!(calculate_position_il(&pair, 2_000_000, 1_000_000), -171_572_875);
+    }
+    
+    #[test]
+    fn test_non_pair_positions_have_no_impermanent_loss() {
+        for position_type in [PositionType::SingleAsset, PositionType::StakedPosition] {
+            assert_eq!(calculate_position_il(&position(position_type, 1_000_000), 2_000_000, 1_000_000), 0);
+        }
+    }
+    
+    #[test]
+    fn test_pair_with_unequal_entry_prices_tracks_impermanent_loss() {
+        // Equal value legs at entry: 1 SOL of A at 1.0 and 0.25 SOL of B at 4.0
+        let mut pair = position(PositionType::LiquidityPair, 4_000_000);
+        pair.token_b_amount = 250_000_000;
+        
+        // A doubles against B, as in the equal-price case, so the loss is the same ~5.72% of 3 SOL
+        assert_eq!(calculate_position_il(&pair, 2_000_000, 4_000_000), -171_572_875);
+        // Both legs move together: the ratio holds and nothing is lost
+        assert_eq!(calculate_position_il(&pair, 2_000_000, 8_000_000), 0);
+    }
+}
@@ -0,0 +1,79 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::*;
+
+#[derive(Accounts)]
+#[instruction(strategy_id: Pubkey)]
+pub struct UpdateStrategyStatus<'info> {
+    #[account(
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+        has_one = manager @ RebalancerErrorCode::UnauthorizedManager
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+    
+    #[account(
+        mut,
+        seeds = [b"strategy", portfolio.key().as_ref(), strategy_id.as_ref()],
+        bump = strategy.bump,
+        constraint = strategy.strategy_id == strategy_id @ RebalancerErrorCode::StrategyNotFound
+    )]
+    pub strategy: Account<'info, Strategy>,
+    
+    pub manager: Signer<'info>,
+}
+
+pub fn update_strategy_status(
+    ctx: Context<UpdateStrategyStatus>,
+    strategy_id: Pubkey,
+    new_status: StrategyStatus,
+) -> Result<()> {
+    let strategy = &mut ctx.accounts.strategy;
+    let previous_status = strategy.status;
+    
+    // STATE MACHINE VALIDATION
+    require!(
+        previous_status.can_transition_to(new_status),
+        RebalancerErrorCode::InvalidStatusTransition
+    );
+    
+    strategy.status = new_status;
+    strategy.last_updated = Clock::get()?.unix_timestamp;
+    
+    msg!("Strategy status updated: strategy={}, {:?} -> {:?}", strategy_id, previous_status, new_status);
+    
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const ALL_STATUSES: [StrategyStatus; 3] = [
+        StrategyStatus::Active,
+        StrategyStatus::Paused,
+        StrategyStatus::Deprecated,
+    ];
+    
+    #[test]
+    fn test_legal_transitions() {
+        assert!(StrategyStatus::Active.can_transition_to(StrategyStatus::Paused));
+        assert!(StrategyStatus::Paused.can_transition_to(StrategyStatus::Active));
+        assert!(StrategyStatus::Active.can_transition_to(StrategyStatus::Deprecated));
+        assert!(StrategyStatus::Paused.can_transition_to(StrategyStatus::Deprecated));
+    }
+    
+    #[test]
+    fn test_deprecated_is_terminal() {
+        for next in ALL_STATUSES {
+            assert!(!StrategyStatus::Deprecated.can_transition_to(next));
+        }
+    }
+    
+    #[test]
+    fn test_self_transitions_rejected() {
+        for status in ALL_STATUSES {
+            assert!(!status.can_transition_to(status));
+        }
+    }
+}
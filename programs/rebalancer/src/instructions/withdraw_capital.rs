@@ -0,0 +1,215 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::*;
+use crate::events::CapitalWithdrawn;
+use crate::instructions::redistribute_capital::RiskLimits;
+use crate::utils::transfer_from_vault;
+
+#[derive(Accounts)]
+#[instruction(strategy_id: Pubkey)]
+pub struct WithdrawCapital<'info> {
+    #[account(
+        mut,
+        seeds = [b"portfolio", portfolio.seed_manager.as_ref()],
+        bump = portfolio.bump,
+        has_one = manager @ RebalancerErrorCode::UnauthorizedManager
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+    
+    #[account(
+        mut,
+        seeds = [b"strategy", portfolio.key().as_ref(), strategy_id.as_ref()],
+        bump = strategy.bump,
+        constraint = strategy.strategy_id == strategy_id @ RebalancerErrorCode::StrategyNotFound
+    )]
+    pub strategy: Account<'info, Strategy>,
+    
+    // Optional: protocol minimums fall back to the defaults when absent
+    #[account(
+        seeds = [b"risk_config", portfolio.key().as_ref()],
+        bump = risk_config.bump,
+    )]
+    pub risk_config: Option<Account<'info, RiskConfig>>,
+    
+    // Optional: native SOL escrow. Required once the withdrawal reaches the
+    // strategy's verified balance; escrowed lamports are paid to the manager
+    #[account(
+        mut,
+        seeds = [b"vault", strategy.key().as_ref()],
+        bump,
+    )]
+    pub vault: Option<SystemAccount<'info>>,
+    
+    #[account(mut)]
+    pub manager: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+pub fn withdraw_capital(
+    ctx: Context<WithdrawCapital>,
+    strategy_id: Pubkey,
+    amount: u64,
+) -> Result<()> {
+    let portfolio = &mut ctx.accounts.portfolio;
+    let strategy = &mut ctx.accounts.strategy;
+    
+    require!(!portfolio.emergency_pause, RebalancerErrorCode::EmergencyPaused);
+    
+    let risk_limits = ctx.accounts.risk_config
+        .as_ref()
+        .map(|config| config.limits.clone())
+        .unwrap_or_default();
+    let previous_balance = strategy.current_balance;
+    apply_withdrawal(strategy, amount, &risk_limits)?;
+    portfolio.apply_balance_change(&strategy.mint, previous_balance, strategy.current_balance)?;
+    
+    // VAULT SETTLEMENT: the verified balance never exceeds the reported one
+    match &ctx.accounts.vault {
+        Some(vault) => {
+            let (lamports, verified_balance) = vault_withdrawal(strategy.verified_balance, vault.lamports(), amount);
+            let bump = ctx.bumps.vault.ok_or(RebalancerErrorCode::VaultAccountRequired)?;
+            transfer_from_vault(
+                vault,
+                ctx.accounts.manager.to_account_info(),
+                &ctx.accounts.system_program,
+                &strategy.key(),
+                bump,
+                lamports,
+            )?;
+            strategy.verified_balance = verified_balance;
+        }
+        None => require!(
+            strategy.current_balance >= strategy.verified_balance,
+            RebalancerErrorCode::VaultAccountRequired
+        ),
+    }
+    
+    msg!("Capital withdrawn: strategy={}, amount={}, remaining={}",
+         strategy_id, amount, strategy.current_balance);
+    
+    emit!(CapitalWithdrawn {
+        portfolio: portfolio.key(),
+        strategy_id,
+        amount,
+        remaining_balance: strategy.current_balance,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    
+    Ok(())
+}
+
+// WITHDRAWAL ACCOUNTING WITH PROTOCOL MINIMUM RESIDUAL
+pub fn apply_withdrawal(strategy: &mut Strategy, amount: u64, risk_limits: &RiskLimits) -> Result<()> {
+    require!(amount > 0, RebalancerErrorCode::InsufficientBalance);
+    require!(amount <= strategy.current_balance, RebalancerErrorCode::InsufficientBalance);
+    
+    let remaining_balance = strategy.current_balance
+        .checked_sub(amount)
+        .ok_or(RebalancerErrorCode::InsufficientBalance)?;
+    
+    // Deprecated strategies may be fully drained; all others must keep the protocol minimum
+    if strategy.status != StrategyStatus::Deprecated {
+        risk_limits.validate_balance_constraints(&strategy.protocol_type, remaining_balance, strategy.decimals)?;
+    }
+    
+    strategy.current_balance = remaining_balance;
+    strategy.total_withdrawals = strategy.total_withdrawals
+        .checked_add(amount)
+        .ok_or(RebalancerErrorCode::BalanceOverflow)?;
+    
+    Ok(())
+}
+
+// VAULT LAMPORTS PAID OUT FOR A WITHDRAWAL, AND THE VERIFIED BALANCE LEFT
+// Escrowed capital covers the withdrawal first; whatever exceeds it was only
+// reported. Once nothing is escrowed the vault is swept, rent and stray
+// lamports included, so nothing is stranded behind the strategy.
+pub fn vault_withdrawal(verified_balance: u64, vault_lamports: u64, amount: u64) -> (u64, u64) {
+    let from_escrow = amount.min(verified_balance);
+    let remaining = verified_balance - from_escrow;
+    let lamports = if remaining == 0 { vault_lamports } else { from_escrow.min(vault_lamports) };
+    (lamports, remaining)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+    
+    fn lending_strategy(current_balance: u64, status: StrategyStatus) -> Strategy {
+        Strategy {
+            current_balance,
+            total_deposits: current_balance,
+            status,
+            ..test_utils::strategy()
+        }
+    }
+    
+    #[test]
+    fn test_partial_withdrawal() {
+        let mut strategy = lending_strategy(1_000_000_000, StrategyStatus::Active);
+        
+        apply_withdrawal(&mut strategy, 400_000_000, &RiskLimits::default()).unwrap();
+        
+        assert_eq!(strategy.current_balance, 600_000_000);
+        assert_eq!(strategy.total_withdrawals, 400_000_000);
+    }
+    
+    #[test]
+    fn test_over_withdrawal_rejected() {
+        let mut strategy = lending_strategy(1_000_000_000, StrategyStatus::Active);
+        
+        assert_eq!(
+            apply_withdrawal(&mut strategy, 1_000_000_001, &RiskLimits::default()).unwrap_err(),
+            RebalancerErrorCode::InsufficientBalance.into()
+        );
+        assert_eq!(strategy.current_balance, 1_000_000_000);
+    }
+    
+    #[test]
+    fn test_active_strategy_keeps_protocol_minimum() {
+        let mut strategy = lending_strategy(1_000_000_000, StrategyStatus::Active);
+        
+        // Stable lending requires 0.1 SOL to remain
+        assert!(apply_withdrawal(&mut strategy, 950_000_000, &RiskLimits::default()).is_err());
+        assert!(apply_withdrawal(&mut strategy, 1_000_000_000, &RiskLimits::default()).is_err());
+        assert!(apply_withdrawal(&mut strategy, 900_000_000, &RiskLimits::default()).is_ok());
+        assert_eq!(strategy.current_balance, 100_000_000);
+    }
+    
+    #[test]
+    fn test_withdrawal_honours_protocol_minimum_override() {
+        let mut strategy = lending_strategy(1_000_000_000, StrategyStatus::Active);
+        let small_scale = RiskLimits {
+            stable_lending_min_lamports: 10_000_000,
+            ..RiskLimits::default()
+        };
+        
+        // Below the default 0.1 SOL residual, but above the configured 0.01 SOL
+        apply_withdrawal(&mut strategy, 950_000_000, &small_scale).unwrap();
+        assert_eq!(strategy.current_balance, 50_000_000);
+        assert!(apply_withdrawal(&mut strategy, 45_000_000, &small_scale).is_err());
+    }
+    
+    #[test]
+    fn test_deprecated_strategy_full_drain() {
+        let mut strategy = lending_strategy(1_000_000_000, StrategyStatus::Deprecated);
+        
+        apply_withdrawal(&mut strategy, 1_000_000_000, &RiskLimits::default()).unwrap();
+        
+        assert_eq!(strategy.current_balance, 0);
+        assert_eq!(strategy.total_withdrawals, 1_000_000_000);
+    }
+    
+    #[test]
+    fn test_vault_withdrawal_pays_out_escrow() {
+        // Partial: only the withdrawn amount leaves the vault
+        assert_eq!(vault_withdrawal(1_000_000_000, 1_000_000_000, 400_000_000), (400_000_000, 600_000_000));
+        // Beyond the escrow: the rest was reported only, and the vault is swept
+        assert_eq!(vault_withdrawal(600_000_000, 600_890_880, 900_000_000), (600_890_880, 0));
+        // Nothing escrowed: stray lamports are swept
+        assert_eq!(vault_withdrawal(0, 890_880, 100_000_000), (890_880, 0));
+        assert_eq!(vault_withdrawal(0, 0, 100_000_000), (0, 0));
+    }
+}
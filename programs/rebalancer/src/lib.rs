@@ -1,5 +1,8 @@
+// Anchor's #[program] expansion still calls the deprecated `AccountInfo::realloc`
+#![allow(deprecated)]
+
 use anchor_lang::prelude::*;
-use crate::state::{ProtocolType, CapitalAllocation};
+use crate::state::{ProtocolType, CapitalAllocation, PositionType, StrategyStatus, GovernanceAction};
 
 declare_id!("H5sewgM4P61yo75GtnbsVcevhEAVKpoRxJjsHWXoNYV7");
 
@@ -7,6 +10,11 @@ pub mod state;
 pub mod instructions;
 pub mod errors;
 pub mod utils;
+pub mod events;
+pub mod core_math;
+pub mod adapters;
+#[cfg(test)]
+mod test_utils;
 
 use instructions::*;
 
@@ -20,8 +28,9 @@ pub mod rebalancer {
         manager: Pubkey,
         base_threshold: u8,
         min_rebalance_interval: i64,
+        max_strategies: Option<u32>,
     ) -> Result<()> {
-        instructions::initialize_portfolio(ctx, manager, base_threshold, min_rebalance_interval)
+        instructions::initialize_portfolio(ctx, manager, base_threshold, min_rebalance_interval, max_strategies)
     }
     
     pub fn register_strategy(
@@ -29,8 +38,9 @@ pub mod rebalancer {
         strategy_id: Pubkey,
         protocol_type: ProtocolType,
         initial_balance: u64,
+        max_capacity: Option<u64>,
     ) -> Result<()> {
-        instructions::register_strategy(ctx, strategy_id, protocol_type, initial_balance)
+        instructions::register_strategy(ctx, strategy_id, protocol_type, initial_balance, max_capacity)
     }
 
     pub fn update_performance(
@@ -43,8 +53,8 @@ pub mod rebalancer {
         instructions::update_performance(ctx, strategy_id, yield_rate, volatility_score, current_balance)
     }
 
-    pub fn execute_ranking_cycle(
-        ctx: Context<ExecuteRankingCycle>,
+    pub fn execute_ranking_cycle<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ExecuteRankingCycle<'info>>,
     ) -> Result<()> {
         instructions::execute_ranking_cycle(ctx)
     }
@@ -56,12 +66,287 @@ pub mod rebalancer {
         instructions::extract_capital(ctx, strategy_ids)
     }
     
-    pub fn redistribute_capital(
-        ctx: Context<RedistributeCapital>, 
+    pub fn redistribute_capital<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RedistributeCapital<'info>>, 
         allocations: Vec<CapitalAllocation>,
     ) -> Result<()> {
         instructions::redistribute_capital(ctx, allocations)
     }
     
+    pub fn preview_rebalancing<'info>(
+        ctx: Context<'_, '_, 'info, 'info, PreviewRebalancing<'info>>,
+    ) -> Result<RebalancingPlan> {
+        instructions::preview_rebalancing(ctx)
+    }
+    
+    pub fn set_capacity_limits(
+        ctx: Context<SetCapacityLimits>,
+        max_strategies: u32,
+        max_capital: u64,
+    ) -> Result<()> {
+        instructions::set_capacity_limits(ctx, max_strategies, max_capital)
+    }
+    
+    pub fn redistribute_scoped_capital<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RedistributeScopedCapital<'info>>,
+        scope: Vec<Pubkey>,
+    ) -> Result<()> {
+        instructions::redistribute_scoped_capital(ctx, scope)
+    }
+    
+    pub fn set_emergency_pause(
+        ctx: Context<SetEmergencyPause>,
+        paused: bool,
+    ) -> Result<()> {
+        instructions::set_emergency_pause(ctx, paused)
+    }
+    
+    pub fn set_guardian(
+        ctx: Context<SetGuardian>,
+        guardian: Pubkey,
+    ) -> Result<()> {
+        instructions::set_guardian(ctx, guardian)
+    }
+    
+    pub fn open_capital_position(
+        ctx: Context<OpenCapitalPosition>,
+        strategy_id: Pubkey,
+        position_type: PositionType,
+        token_a_amount: u64,
+        entry_price_a: u64,
+        token_b_amount: u64,
+        entry_price_b: u64,
+    ) -> Result<()> {
+        instructions::open_capital_position(
+            ctx, strategy_id, position_type, token_a_amount, entry_price_a, token_b_amount, entry_price_b,
+        )
+    }
+    
+    pub fn set_risk_config(
+        ctx: Context<SetRiskConfig>,
+        limits: RiskLimits,
+    ) -> Result<()> {
+        instructions::set_risk_config(ctx, limits)
+    }
+    
+    pub fn withdraw_capital(
+        ctx: Context<WithdrawCapital>,
+        strategy_id: Pubkey,
+        amount: u64,
+    ) -> Result<()> {
+        instructions::withdraw_capital(ctx, strategy_id, amount)
+    }
+    
+    pub fn update_strategy_status(
+        ctx: Context<UpdateStrategyStatus>,
+        strategy_id: Pubkey,
+        new_status: StrategyStatus,
+    ) -> Result<()> {
+        instructions::update_strategy_status(ctx, strategy_id, new_status)
+    }
+    
+    pub fn emergency_rebalance<'info>(
+        ctx: Context<'_, '_, 'info, 'info, EmergencyRebalance<'info>>,
+    ) -> Result<()> {
+        instructions::emergency_rebalance(ctx)
+    }
+    
+    pub fn update_position(
+        ctx: Context<UpdatePosition>,
+        strategy_id: Pubkey,
+        current_price_a: u64,
+        current_price_b: u64,
+        accrued_fees: u64,
+    ) -> Result<()> {
+        instructions::update_position(ctx, strategy_id, current_price_a, current_price_b, accrued_fees)
+    }
+    
+    pub fn close_strategy(
+        ctx: Context<CloseStrategy>,
+        strategy_id: Pubkey,
+    ) -> Result<()> {
+        instructions::close_strategy(ctx, strategy_id)
+    }
+    
+    pub fn close_portfolio(ctx: Context<ClosePortfolio>) -> Result<()> {
+        instructions::close_portfolio(ctx)
+    }
+    
+    pub fn propose_manager_transfer(
+        ctx: Context<ProposeManagerTransfer>,
+        new_manager: Pubkey,
+    ) -> Result<()> {
+        instructions::propose_manager_transfer(ctx, new_manager)
+    }
+    
+    pub fn accept_manager_transfer(ctx: Context<AcceptManagerTransfer>) -> Result<()> {
+        instructions::accept_manager_transfer(ctx)
+    }
+    
+    pub fn get_portfolio_summary<'info>(
+        ctx: Context<'_, '_, 'info, 'info, GetPortfolioSummary<'info>>,
+    ) -> Result<PortfolioSummary> {
+        instructions::get_portfolio_summary(ctx)
+    }
+    
+    pub fn batch_update_performance<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BatchUpdatePerformance<'info>>,
+        updates: Vec<PerformanceUpdate>,
+    ) -> Result<()> {
+        instructions::batch_update_performance(ctx, updates)
+    }
+    
+    pub fn set_metric_staleness(
+        ctx: Context<SetMetricStaleness>,
+        max_metric_staleness: i64,
+    ) -> Result<()> {
+        instructions::set_metric_staleness(ctx, max_metric_staleness)
+    }
+    
+    pub fn simulate_rebalance<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SimulateRebalance<'info>>,
+    ) -> Result<RebalancingPlan> {
+        instructions::simulate_rebalance(ctx)
+    }
+    
+    pub fn begin_ranking_cycle(ctx: Context<BeginRankingCycle>) -> Result<()> {
+        instructions::begin_ranking_cycle(ctx)
+    }
+    
+    pub fn submit_ranking_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SubmitRankingBatch<'info>>,
+    ) -> Result<()> {
+        instructions::submit_ranking_batch(ctx)
+    }
+    
+    pub fn finalize_ranking_cycle(ctx: Context<FinalizeRankingCycle>) -> Result<()> {
+        instructions::finalize_ranking_cycle(ctx)
+    }
+    
+    pub fn update_base_threshold(
+        ctx: Context<UpdateBaseThreshold>,
+        base_threshold: u8,
+    ) -> Result<()> {
+        instructions::update_base_threshold(ctx, base_threshold)
+    }
+    
+    pub fn record_performance_fee<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RecordPerformanceFee<'info>>,
+    ) -> Result<u64> {
+        instructions::record_performance_fee(ctx)
+    }
+    
+    pub fn initialize_allocation_log(
+        ctx: Context<InitializeAllocationLog>,
+        strategy_id: Pubkey,
+    ) -> Result<()> {
+        instructions::initialize_allocation_log(ctx, strategy_id)
+    }
+    
+    pub fn set_volatility_smoothing(
+        ctx: Context<SetVolatilitySmoothing>,
+        smoothing_bps: u16,
+    ) -> Result<()> {
+        instructions::set_volatility_smoothing(ctx, smoothing_bps)
+    }
+    
+    pub fn set_oracle_authority(
+        ctx: Context<SetOracleAuthority>,
+        oracle_authority: Pubkey,
+    ) -> Result<()> {
+        instructions::set_oracle_authority(ctx, oracle_authority)
+    }
+    
+    pub fn reconcile_strategy_count<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ReconcileStrategyCount<'info>>,
+        closed_strategy_ids: Vec<Pubkey>,
+    ) -> Result<()> {
+        instructions::reconcile_strategy_count(ctx, closed_strategy_ids)
+    }
+    
+    pub fn set_governance_config(
+        ctx: Context<SetGovernanceConfig>,
+        managers: Vec<Pubkey>,
+        threshold: u8,
+        approval_window: i64,
+    ) -> Result<()> {
+        instructions::set_governance_config(ctx, managers, threshold, approval_window)
+    }
+    
+    pub fn approve_governance_action(
+        ctx: Context<ApproveGovernanceAction>,
+        action: GovernanceAction,
+    ) -> Result<()> {
+        instructions::approve_governance_action(ctx, action)
+    }
+    
+    pub fn simulate_rebalance_deltas<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SimulateRebalanceDeltas<'info>>,
+        offset: u32,
+        limit: u8,
+    ) -> Result<Vec<StrategyDelta>> {
+        instructions::simulate_rebalance_deltas(ctx, offset, limit)
+    }
+    
+    pub fn transfer_strategy(
+        ctx: Context<TransferStrategy>,
+        strategy_id: Pubkey,
+    ) -> Result<()> {
+        instructions::transfer_strategy(ctx, strategy_id)
+    }
+    
+    pub fn initialize_redistribution_execution(
+        ctx: Context<InitializeRedistributionExecution>,
+        execution_id: u64,
+    ) -> Result<()> {
+        instructions::initialize_redistribution_execution(ctx, execution_id)
+    }
+    
+    pub fn rebalance_status(ctx: Context<RebalanceStatus>) -> Result<RebalanceStatusView> {
+        instructions::rebalance_status(ctx)
+    }
+    
+    pub fn pause_strategy(ctx: Context<PauseStrategy>, strategy_id: Pubkey) -> Result<()> {
+        instructions::pause_strategy(ctx, strategy_id)
+    }
+    
+    pub fn resume_strategy(ctx: Context<PauseStrategy>, strategy_id: Pubkey) -> Result<()> {
+        instructions::resume_strategy(ctx, strategy_id)
+    }
+    
+    pub fn snapshot_metrics<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SnapshotMetrics<'info>>,
+    ) -> Result<()> {
+        instructions::snapshot_metrics(ctx)
+    }
+    
+    pub fn simulate_target_allocation<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SimulateTargetAllocation<'info>>,
+        offset: u32,
+        limit: u8,
+    ) -> Result<Vec<StrategyDelta>> {
+        instructions::simulate_target_allocation(ctx, offset, limit)
+    }
+    
+    pub fn close_redistribution_execution(ctx: Context<CloseRedistributionExecution>) -> Result<()> {
+        instructions::close_redistribution_execution(ctx)
+    }
+    
+    pub fn migrate_portfolio<'info>(
+        ctx: Context<'_, '_, 'info, 'info, MigratePortfolio<'info>>,
+    ) -> Result<()> {
+        instructions::migrate_portfolio(ctx)
+    }
+    
+    pub fn migrate_strategy(ctx: Context<MigrateStrategy>, strategy_id: Pubkey) -> Result<()> {
+        instructions::migrate_strategy(ctx, strategy_id)
+    }
+    
+    pub fn apply_ranking_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ApplyRankingBatch<'info>>,
+    ) -> Result<()> {
+        instructions::apply_ranking_batch(ctx)
+    }
+    
 }
 
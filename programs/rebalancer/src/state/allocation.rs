@@ -0,0 +1,140 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::RebalancerErrorCode;
+
+/// One destination for redistributed capital.
+///
+/// `strategy_id` only names a registered strategy for strategy allocations
+/// (see `AllocationType::is_strategy_allocation`). Fee entries carry the
+/// treasury that receives the fee, `SafeHaven` carries the risk config's safe
+/// haven and `Unallocated` carries `Pubkey::default()`, so consumers must filter
+/// on the type before treating it as a strategy.
+///
+/// `min_acceptable_amount` is the least the destination may end up with once
+/// the capital actually moves; plans computed on-chain leave it at zero and
+/// callers tighten it before submitting the allocation.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct CapitalAllocation {
+    pub strategy_id: Pubkey,
+    pub amount: u64,
+    pub min_acceptable_amount: u64,
+    pub allocation_type: AllocationType,
+}
+
+impl CapitalAllocation {
+    pub const SIZE: usize = 32 + 8 + 8 + 1;
+    
+    /// Reject a fill where the destination received less than the caller's bound.
+    pub fn validate_received(&self, received: u64) -> Result<()> {
+        require!(
+            received >= self.min_acceptable_amount,
+            RebalancerErrorCode::SlippageExceeded
+        );
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum AllocationType {
+    TopPerformer,
+    RiskDiversification,
+    ManagerIncentive,
+    PlatformFee,
+    Unallocated,    // Capital no strategy could absorb; stays with the portfolio
+    SafeHaven,      // Extract-only mode: capital parked with the configured safe haven
+}
+
+impl AllocationType {
+    /// Whether the allocation moves capital into a strategy, as opposed to a
+    /// treasury fee or capital left with the portfolio.
+    pub fn is_strategy_allocation(&self) -> bool {
+        matches!(self, AllocationType::TopPerformer | AllocationType::RiskDiversification)
+    }
+    
+    /// Whether the allocation counts toward `Portfolio::total_capital_moved`:
+    /// capital redeployed into a strategy or parked with the safe haven. Fees
+    /// leave the portfolio but are not rebalanced capital, and unallocated
+    /// capital never moves.
+    pub fn counts_as_capital_moved(&self) -> bool {
+        self.is_strategy_allocation() || matches!(self, AllocationType::SafeHaven)
+    }
+}
+
+/// How extracted capital is split among the top performers before
+/// diversification caps and protocol minimums are applied.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum AllocationMode {
+    #[default]
+    PerformanceWeighted,    // Proportional to performance score
+    BalanceWeighted,        // Proportional to current balance, minimizing turnover
+    EqualWeight,            // Same share for every top performer
+    TargetAllocation,       // Per-protocol target weights from the risk config
+}
+
+impl AllocationMode {
+    pub fn weight(&self, performance_score: u64, current_balance: u64) -> u128 {
+        match self {
+            AllocationMode::PerformanceWeighted => performance_score as u128,
+            AllocationMode::BalanceWeighted => current_balance as u128,
+            AllocationMode::EqualWeight => 1,
+            // Target plans follow `plan_target_allocation` rather than weights
+            AllocationMode::TargetAllocation => 1,
+        }
+    }
+}
+
+/// Where capital left over after the weighted split (rounding and skipped
+/// strategies) goes, within each strategy's single and group caps.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum RemainderPolicy {
+    #[default]
+    TopPerformer,   // All of it tops up the first top performer
+    RoundRobin,     // Spread evenly across every funded strategy, in ranking order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_capital_allocation_round_trip() {
+        let allocation = CapitalAllocation {
+            strategy_id: Pubkey::new_unique(),
+            amount: 1_234_567_890,
+            min_acceptable_amount: 0,
+            allocation_type: AllocationType::RiskDiversification,
+        };
+        
+        let bytes = borsh::to_vec(&allocation).unwrap();
+        assert_eq!(bytes.len(), 32 + 8 + 8 + 1);
+        
+        let decoded = CapitalAllocation::try_from_slice(&bytes).unwrap();
+        assert_eq!(decoded, allocation);
+    }
+
+    #[test]
+    fn test_received_amount_meets_minimum() {
+        let allocation = CapitalAllocation {
+            strategy_id: Pubkey::new_unique(),
+            amount: 1_000_000_000,
+            min_acceptable_amount: 995_000_000,
+            allocation_type: AllocationType::TopPerformer,
+        };
+
+        assert!(allocation.validate_received(1_000_000_000).is_ok());
+        assert!(allocation.validate_received(995_000_000).is_ok());
+    }
+
+    #[test]
+    fn test_received_amount_below_minimum_fails() {
+        let allocation = CapitalAllocation {
+            strategy_id: Pubkey::new_unique(),
+            amount: 1_000_000_000,
+            min_acceptable_amount: 995_000_000,
+            allocation_type: AllocationType::TopPerformer,
+        };
+
+        let err = allocation.validate_received(994_999_999).unwrap_err();
+        assert_eq!(err, RebalancerErrorCode::SlippageExceeded.into());
+    }
+}
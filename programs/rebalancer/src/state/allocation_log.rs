@@ -0,0 +1,123 @@
+use anchor_lang::prelude::*;
+
+use crate::state::AllocationType;
+
+pub const ALLOCATION_LOG_CAPACITY: usize = 16; // Allocations kept per strategy before the oldest is overwritten
+
+/// Recent allocations `redistribute_capital` made to one strategy.
+///
+/// A ring buffer seeded by `[b"allocation_log", portfolio, strategy_id]`. Space
+/// is allocated for `ALLOCATION_LOG_CAPACITY` entries up front, so rent is
+/// fixed; once full, each new entry overwrites the oldest one.
+#[account]
+#[derive(Debug)]
+pub struct StrategyAllocationLog {
+    pub portfolio: Pubkey,                  // 32 bytes - Portfolio the strategy belongs to
+    pub strategy_id: Pubkey,                // 32 bytes - Strategy whose allocations are logged
+    pub head: u8,                           // 1 byte - Slot the next entry is written to once full
+    pub entries: Vec<AllocationLogEntry>,   // Variable size - Up to ALLOCATION_LOG_CAPACITY entries
+    pub bump: u8,                           // 1 byte - PDA bump seed
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub struct AllocationLogEntry {
+    pub timestamp: i64,
+    pub amount: u64,
+    pub allocation_type: AllocationType,
+}
+
+impl AllocationLogEntry {
+    pub const SIZE: usize = 8 + 8 + 1;
+}
+
+impl StrategyAllocationLog {
+    pub const MAX_SIZE: usize = 8
+    + 32 // portfolio
+    + 32 // strategy_id
+    + 1 // head
+    + 4 + AllocationLogEntry::SIZE * ALLOCATION_LOG_CAPACITY // entries
+    + 1; // bump
+
+    pub fn record(&mut self, entry: AllocationLogEntry) {
+        if self.entries.len() < ALLOCATION_LOG_CAPACITY {
+            self.entries.push(entry);
+        } else {
+            self.entries[self.head as usize] = entry;
+            self.head = ((self.head as usize + 1) % ALLOCATION_LOG_CAPACITY) as u8;
+        }
+    }
+
+    /// Logged allocations, oldest first.
+    pub fn history(&self) -> Vec<AllocationLogEntry> {
+        let (newer, older) = self.entries.split_at(self.head as usize);
+        older.iter().chain(newer).copied().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log() -> StrategyAllocationLog {
+        StrategyAllocationLog {
+            portfolio: Pubkey::new_unique(),
+            strategy_id: Pubkey::new_unique(),
+            head: 0,
+            entries: vec![],
+            bump: 255,
+        }
+    }
+
+    fn entry(timestamp: i64) -> AllocationLogEntry {
+        AllocationLogEntry {
+            timestamp,
+            amount: timestamp as u64 * 1_000_000,
+            allocation_type: AllocationType::TopPerformer,
+        }
+    }
+
+    #[test]
+    fn test_history_in_order_before_wrapping() {
+        let mut log = log();
+        for timestamp in 1..=3 {
+            log.record(entry(timestamp));
+        }
+
+        assert_eq!(log.history(), vec![entry(1), entry(2), entry(3)]);
+    }
+
+    #[test]
+    fn test_ring_buffer_keeps_most_recent_entries() {
+        let mut log = log();
+        let total = ALLOCATION_LOG_CAPACITY as i64 + 5;
+        for timestamp in 1..=total {
+            log.record(entry(timestamp));
+        }
+
+        // The five oldest were overwritten; the rest come back oldest first
+        let history = log.history();
+        assert_eq!(history.len(), ALLOCATION_LOG_CAPACITY);
+        assert_eq!(history.first(), Some(&entry(6)));
+        assert_eq!(history.last(), Some(&entry(total)));
+        assert!(history.windows(2).all(|pair| pair[0].timestamp + 1 == pair[1].timestamp));
+
+        // Wrapping a full lap more still keeps exactly the latest entries
+        for timestamp in total + 1..=total + ALLOCATION_LOG_CAPACITY as i64 {
+            log.record(entry(timestamp));
+        }
+        let history = log.history();
+        assert_eq!(history.first(), Some(&entry(total + 1)));
+        assert_eq!(history.last(), Some(&entry(total + ALLOCATION_LOG_CAPACITY as i64)));
+    }
+
+    #[test]
+    fn test_max_size_fits_full_log() {
+        let mut log = log();
+        for timestamp in 1..=ALLOCATION_LOG_CAPACITY as i64 {
+            log.record(entry(timestamp));
+        }
+
+        let serialized = log.try_to_vec().unwrap();
+        assert_eq!(StrategyAllocationLog::DISCRIMINATOR.len() + serialized.len(), StrategyAllocationLog::MAX_SIZE);
+    }
+}
@@ -0,0 +1,311 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+
+use crate::errors::RebalancerErrorCode;
+use crate::instructions::redistribute_capital::RiskLimits;
+
+// Governance bounds
+pub const MAX_GOVERNANCE_MANAGERS: usize = 8;           // Approvals are tracked in a u8 bitmap
+pub const MAX_GOVERNANCE_APPROVAL_WINDOW: i64 = 604800; // 7 days
+
+/// Multi-manager approval for sensitive portfolio changes.
+///
+/// Once enabled, `set_emergency_pause`, `set_risk_config`,
+/// `update_base_threshold` and `set_governance_config` only take effect after
+/// `threshold` distinct listed managers have called `approve_governance_action`
+/// for that exact action within `approval_window` seconds. Approvals are kept
+/// per action hash. A proposal lives in the slot of the manager who opened
+/// it, so each manager has at most one open proposal and a new one replaces
+/// only their own; nobody can reset another manager's proposal.
+#[account]
+#[derive(Debug)]
+pub struct GovernanceConfig {
+    pub portfolio: Pubkey,                              // 32 bytes - Portfolio this governance applies to
+    pub managers: [Pubkey; MAX_GOVERNANCE_MANAGERS],    // 256 bytes - Approver keys, first manager_count used
+    pub manager_count: u8,                              // 1 byte - Listed managers
+    pub threshold: u8,                                  // 1 byte - Approvals required (M of manager_count)
+    pub approval_window: i64,                           // 8 bytes - Seconds a proposal collects approvals
+    pub proposals: [GovernanceProposal; MAX_GOVERNANCE_MANAGERS], // 328 bytes - Open proposal per proposing manager
+    pub bump: u8,                                       // 1 byte - PDA bump seed
+    pub reserved: [u8; 16],                             // 16 bytes - Future expansion
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct GovernanceProposal {
+    pub action_hash: [u8; 32],                          // 32 bytes - Hash of the action being approved
+    pub proposed_at: i64,                               // 8 bytes - Unix timestamp of the first approval
+    pub approvals: u8,                                  // 1 byte - Bitmap of approving manager indices (0 = empty slot)
+}
+
+impl GovernanceProposal {
+    pub const SIZE: usize = 32 + 8 + 1;
+
+    fn is_live(&self, current_time: i64, approval_window: i64) -> bool {
+        self.approvals != 0 && current_time.saturating_sub(self.proposed_at) <= approval_window
+    }
+
+    pub fn approval_count(&self) -> u8 {
+        self.approvals.count_ones() as u8
+    }
+}
+
+/// A governed change, hashed so approvals are bound to its exact arguments.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub enum GovernanceAction {
+    SetEmergencyPause { paused: bool },
+    SetRiskConfig { limits: Box<RiskLimits> }, // Boxed to keep the enum small; serializes as the bare limits
+    UpdateBaseThreshold { base_threshold: u8 },
+    SetGovernanceConfig { managers: Vec<Pubkey>, threshold: u8, approval_window: i64 },
+    TransferStrategy { strategy_id: Pubkey, destination_portfolio: Pubkey },
+}
+
+impl GovernanceAction {
+    pub fn hash(&self) -> [u8; 32] {
+        hash(&self.try_to_vec().unwrap_or_default()).to_bytes()
+    }
+}
+
+impl GovernanceConfig {
+    pub const MAX_SIZE: usize = 8
+    + 32 // portfolio
+    + 32 * MAX_GOVERNANCE_MANAGERS // managers
+    + 1 // manager_count
+    + 1 // threshold
+    + 8 // approval_window
+    + GovernanceProposal::SIZE * MAX_GOVERNANCE_MANAGERS // proposals
+    + 1 // bump
+    + 16; // reserved
+
+    /// An empty manager list with a zero threshold turns governance off;
+    /// anything else must be a distinct, non-empty M-of-N set.
+    pub fn validate_settings(managers: &[Pubkey], threshold: u8, approval_window: i64) -> Result<()> {
+        if managers.is_empty() && threshold == 0 {
+            return Ok(());
+        }
+        require!(
+            managers.len() <= MAX_GOVERNANCE_MANAGERS
+                && (1..=managers.len()).contains(&(threshold as usize))
+                && (1..=MAX_GOVERNANCE_APPROVAL_WINDOW).contains(&approval_window),
+            RebalancerErrorCode::InvalidGovernanceConfig
+        );
+        require!(
+            managers.iter().enumerate().all(|(i, m)| *m != Pubkey::default() && !managers[..i].contains(m)),
+            RebalancerErrorCode::InvalidGovernanceConfig
+        );
+        Ok(())
+    }
+
+    /// Replace the manager set. Every open proposal is dropped, since its
+    /// approvals were counted against the old set.
+    pub fn apply_settings(&mut self, managers: &[Pubkey], threshold: u8, approval_window: i64) {
+        self.managers = [Pubkey::default(); MAX_GOVERNANCE_MANAGERS];
+        self.managers[..managers.len()].copy_from_slice(managers);
+        self.manager_count = managers.len() as u8;
+        self.threshold = threshold;
+        self.approval_window = approval_window;
+        self.proposals = [GovernanceProposal::default(); MAX_GOVERNANCE_MANAGERS];
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.threshold > 0
+    }
+
+    fn manager_index(&self, approver: &Pubkey) -> Option<usize> {
+        self.managers[..self.manager_count as usize]
+            .iter()
+            .position(|m| m == approver)
+    }
+
+    /// Slot of the open proposal for `action_hash`, if one is still within
+    /// its approval window.
+    fn live_proposal(&self, action_hash: &[u8; 32], current_time: i64) -> Option<usize> {
+        self.proposals
+            .iter()
+            .position(|p| p.is_live(current_time, self.approval_window) && p.action_hash == *action_hash)
+    }
+
+    /// Approvals `action_hash` currently holds (0 when it has no open proposal).
+    pub fn approval_count(&self, action_hash: &[u8; 32], current_time: i64) -> u8 {
+        self.live_proposal(action_hash, current_time)
+            .map_or(0, |slot| self.proposals[slot].approval_count())
+    }
+
+    /// Record `approver`'s approval of `action_hash`. Returns the number of
+    /// distinct approvals the action now has.
+    pub fn approve(&mut self, approver: &Pubkey, action_hash: [u8; 32], current_time: i64) -> Result<u8> {
+        let index = self.manager_index(approver).ok_or(RebalancerErrorCode::NotGovernanceMember)?;
+
+        // An action without an open proposal starts one in the approver's own
+        // slot, replacing only a proposal they opened themselves
+        let slot = match self.live_proposal(&action_hash, current_time) {
+            Some(slot) => slot,
+            None => {
+                self.proposals[index] = GovernanceProposal {
+                    action_hash,
+                    proposed_at: current_time,
+                    approvals: 0,
+                };
+                index
+            }
+        };
+        self.proposals[slot].approvals |= 1 << index;
+
+        Ok(self.proposals[slot].approval_count())
+    }
+
+    /// Spend the open proposal for `action`. Fails unless that exact action
+    /// reached the threshold within the approval window.
+    pub fn consume_approval(&mut self, action: &GovernanceAction, current_time: i64) -> Result<()> {
+        let slot = self.live_proposal(&action.hash(), current_time)
+            .filter(|slot| self.proposals[*slot].approval_count() >= self.threshold)
+            .ok_or(RebalancerErrorCode::GovernanceApprovalRequired)?;
+        self.proposals[slot] = GovernanceProposal::default();
+        Ok(())
+    }
+}
+
+/// Gate a sensitive change on governance approval when the portfolio has it
+/// enabled. Single-manager portfolios pass straight through.
+pub fn require_governance_approval(
+    governance_enabled: bool,
+    governance: Option<&mut GovernanceConfig>,
+    action: &GovernanceAction,
+    current_time: i64,
+) -> Result<()> {
+    if !governance_enabled {
+        return Ok(());
+    }
+    governance
+        .ok_or(RebalancerErrorCode::GovernanceApprovalRequired)?
+        .consume_approval(action, current_time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn governance(managers: &[Pubkey], threshold: u8) -> GovernanceConfig {
+        let mut config = GovernanceConfig {
+            portfolio: Pubkey::new_unique(),
+            managers: [Pubkey::default(); MAX_GOVERNANCE_MANAGERS],
+            manager_count: 0,
+            threshold: 0,
+            approval_window: 0,
+            proposals: [GovernanceProposal::default(); MAX_GOVERNANCE_MANAGERS],
+            bump: 255,
+            reserved: [0u8; 16],
+        };
+        GovernanceConfig::validate_settings(managers, threshold, 3600).unwrap();
+        config.apply_settings(managers, threshold, 3600);
+        config
+    }
+
+    #[test]
+    fn test_two_of_three_approval() {
+        let managers = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+        let mut config = governance(&managers, 2);
+        let action = GovernanceAction::UpdateBaseThreshold { base_threshold: 20 };
+
+        assert_eq!(config.approve(&managers[0], action.hash(), 100).unwrap(), 1);
+        // Approving twice does not count twice
+        assert_eq!(config.approve(&managers[0], action.hash(), 150).unwrap(), 1);
+        assert_eq!(config.approve(&managers[2], action.hash(), 200).unwrap(), 2);
+
+        assert!(require_governance_approval(true, Some(&mut config), &action, 300).is_ok());
+        // The approval is spent once used
+        assert_eq!(
+            require_governance_approval(true, Some(&mut config), &action, 300).unwrap_err(),
+            RebalancerErrorCode::GovernanceApprovalRequired.into()
+        );
+    }
+
+    #[test]
+    fn test_under_threshold_rejected() {
+        let managers = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+        let mut config = governance(&managers, 2);
+        let action = GovernanceAction::SetEmergencyPause { paused: false };
+
+        config.approve(&managers[1], action.hash(), 100).unwrap();
+        assert_eq!(
+            require_governance_approval(true, Some(&mut config), &action, 100).unwrap_err(),
+            RebalancerErrorCode::GovernanceApprovalRequired.into()
+        );
+
+        // Approvals for different arguments do not carry over
+        let other = GovernanceAction::SetEmergencyPause { paused: true };
+        config.approve(&managers[0], other.hash(), 110).unwrap();
+        assert_eq!(config.approval_count(&other.hash(), 110), 1);
+
+        // Nor do approvals gathered outside the window
+        config.approve(&managers[1], other.hash(), 110 + 3600 + 1).unwrap();
+        assert_eq!(config.approval_count(&other.hash(), 110 + 3600 + 1), 1);
+        assert!(config.consume_approval(&other, 110 + 3600 + 1).is_err());
+
+        // The governance account cannot be left out once governance is on
+        assert_eq!(
+            require_governance_approval(true, None, &action, 100).unwrap_err(),
+            RebalancerErrorCode::GovernanceApprovalRequired.into()
+        );
+        assert!(require_governance_approval(false, None, &action, 100).is_ok());
+    }
+
+    #[test]
+    fn test_competing_proposal_keeps_existing_approvals() {
+        let managers = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+        let mut config = governance(&managers, 2);
+        let action = GovernanceAction::UpdateBaseThreshold { base_threshold: 20 };
+        let spam = |n| GovernanceAction::UpdateBaseThreshold { base_threshold: n };
+
+        config.approve(&managers[0], action.hash(), 100).unwrap();
+
+        // Another member opening other proposals only ever replaces their own
+        for (offset, n) in [(10, 30), (20, 31), (30, 32)] {
+            config.approve(&managers[2], spam(n).hash(), 100 + offset).unwrap();
+            assert_eq!(config.approval_count(&action.hash(), 100 + offset), 1);
+        }
+        assert_eq!(config.approval_count(&spam(31).hash(), 140), 0);
+        assert_eq!(config.approval_count(&spam(32).hash(), 140), 1);
+
+        // The first proposal still reaches its threshold
+        assert_eq!(config.approve(&managers[1], action.hash(), 150).unwrap(), 2);
+        assert!(config.consume_approval(&action, 160).is_ok());
+        assert_eq!(config.approval_count(&spam(32).hash(), 160), 1);
+    }
+
+    #[test]
+    fn test_non_member_cannot_approve() {
+        let managers = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let mut config = governance(&managers, 2);
+        let action = GovernanceAction::UpdateBaseThreshold { base_threshold: 20 };
+
+        assert_eq!(
+            config.approve(&Pubkey::new_unique(), action.hash(), 100).unwrap_err(),
+            RebalancerErrorCode::NotGovernanceMember.into()
+        );
+    }
+
+    #[test]
+    fn test_settings_validation() {
+        let a = Pubkey::new_unique();
+        let b = Pubkey::new_unique();
+
+        assert!(GovernanceConfig::validate_settings(&[], 0, 0).is_ok());
+        assert!(GovernanceConfig::validate_settings(&[a, b], 2, 3600).is_ok());
+        assert!(GovernanceConfig::validate_settings(&[a, b], 3, 3600).is_err());
+        assert!(GovernanceConfig::validate_settings(&[a, b], 0, 3600).is_err());
+        assert!(GovernanceConfig::validate_settings(&[a, a], 1, 3600).is_err());
+        assert!(GovernanceConfig::validate_settings(&[a, b], 1, 0).is_err());
+        assert!(GovernanceConfig::validate_settings(&[a, b], 1, MAX_GOVERNANCE_APPROVAL_WINDOW + 1).is_err());
+        let too_many: Vec<Pubkey> = (0..=MAX_GOVERNANCE_MANAGERS).map(|_| Pubkey::new_unique()).collect();
+        assert!(GovernanceConfig::validate_settings(&too_many, 1, 3600).is_err());
+    }
+
+    #[test]
+    fn test_max_size_matches_serialized_layout() {
+        let managers: Vec<Pubkey> = (0..MAX_GOVERNANCE_MANAGERS).map(|_| Pubkey::new_unique()).collect();
+        let config = governance(&managers, MAX_GOVERNANCE_MANAGERS as u8);
+
+        let serialized = config.try_to_vec().unwrap();
+        assert_eq!(GovernanceConfig::DISCRIMINATOR.len() + serialized.len(), GovernanceConfig::MAX_SIZE);
+    }
+}
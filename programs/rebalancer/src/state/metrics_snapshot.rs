@@ -0,0 +1,115 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+
+use crate::instructions::execute_ranking::StrategyData;
+
+/// Tamper-evident digest of the ranking inputs.
+///
+/// Snapshots are PDAs seeded by `[b"metrics_snapshot", portfolio, sequence]`,
+/// where `sequence` is the portfolio's `rebalance_sequence` when the snapshot
+/// is taken, so it pairs with the `RebalanceRecord` the next ranking cycle
+/// writes. Off-chain tools rebuild each strategy's `StrategyData`, serialize
+/// them sorted by `strategy_id` and compare the SHA-256 digest, so the order
+/// the accounts were passed in does not matter.
+#[account]
+#[derive(Debug)]
+pub struct MetricsSnapshot {
+    pub portfolio: Pubkey,                  // 32 bytes - Portfolio whose strategies were hashed
+    pub sequence: u64,                      // 8 bytes - Portfolio rebalance_sequence at snapshot time
+    pub metrics_hash: [u8; 32],             // 32 bytes - SHA-256 of the serialized StrategyData, sorted by strategy_id
+    pub strategy_count: u32,                // 4 bytes - Strategies covered by the hash
+    pub timestamp: i64,                     // 8 bytes - Unix timestamp of the snapshot
+    pub bump: u8,                           // 1 byte - PDA bump seed
+}
+
+impl MetricsSnapshot {
+    pub const MAX_SIZE: usize = 8
+    + 32 // portfolio
+    + 8 // sequence
+    + 32 // metrics_hash
+    + 4 // strategy_count
+    + 8 // timestamp
+    + 1; // bump
+
+    pub fn hash_metrics(strategies: &[StrategyData]) -> Result<[u8; 32]> {
+        let mut sorted: Vec<&StrategyData> = strategies.iter().collect();
+        sorted.sort_by_key(|strategy| strategy.strategy_id);
+        
+        let mut bytes = Vec::with_capacity(strategies.len() * StrategyData::SIZE);
+        for strategy in sorted {
+            strategy.serialize(&mut bytes)?;
+        }
+        Ok(hash(&bytes).to_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::solana_program::hash::hashv;
+
+    fn strategy_data(seed: u8, performance_score: u64, net_return_bps: i64) -> StrategyData {
+        StrategyData {
+            strategy_id: Pubkey::new_from_array([seed; 32]),
+            performance_score,
+            current_balance: 1_000_000_000 * seed as u64,
+            volatility_score: 2500,
+            percentile_rank: 50,
+            protocol_weight_bps: 9500,
+            net_return_bps,
+        }
+    }
+
+    #[test]
+    fn test_hash_matches_independent_computation() {
+        let strategies = vec![strategy_data(1, 8000, 120), strategy_data(2, 3000, -45)];
+
+        // Borsh layout written out by hand: fields in declaration order, little endian
+        let encoded: Vec<Vec<u8>> = strategies
+            .iter()
+            .map(|s| {
+                let mut bytes = s.strategy_id.to_bytes().to_vec();
+                bytes.extend_from_slice(&s.performance_score.to_le_bytes());
+                bytes.extend_from_slice(&s.current_balance.to_le_bytes());
+                bytes.extend_from_slice(&s.volatility_score.to_le_bytes());
+                bytes.push(s.percentile_rank);
+                bytes.extend_from_slice(&s.protocol_weight_bps.to_le_bytes());
+                bytes.extend_from_slice(&s.net_return_bps.to_le_bytes());
+                assert_eq!(bytes.len(), StrategyData::SIZE);
+                bytes
+            })
+            .collect();
+        let expected = hashv(&encoded.iter().map(|b| b.as_slice()).collect::<Vec<_>>()).to_bytes();
+
+        assert_eq!(MetricsSnapshot::hash_metrics(&strategies).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_hash_changes_with_any_metric_not_order() {
+        let strategies = vec![strategy_data(1, 8000, 120), strategy_data(2, 3000, -45)];
+        let original = MetricsSnapshot::hash_metrics(&strategies).unwrap();
+
+        let mut tampered = strategies.clone();
+        tampered[1].performance_score += 1;
+        assert_ne!(MetricsSnapshot::hash_metrics(&tampered).unwrap(), original);
+
+        // Account order is the caller's choice, the digest is over strategy_id order
+        let reordered = vec![strategies[1].clone(), strategies[0].clone()];
+        assert_eq!(MetricsSnapshot::hash_metrics(&reordered).unwrap(), original);
+    }
+
+    #[test]
+    fn test_max_size_matches_serialized_layout() {
+        let snapshot = MetricsSnapshot {
+            portfolio: Pubkey::new_unique(),
+            sequence: u64::MAX,
+            metrics_hash: [7u8; 32],
+            strategy_count: u32::MAX,
+            timestamp: i64::MAX,
+            bump: 255,
+        };
+
+        let serialized = snapshot.try_to_vec().unwrap();
+        assert_eq!(MetricsSnapshot::DISCRIMINATOR.len() + serialized.len(), MetricsSnapshot::MAX_SIZE);
+    }
+}
@@ -1,7 +1,25 @@
 pub mod portfolio;
 pub mod strategy;
 pub mod capital_position;
+pub mod allocation;
+pub mod preview_cache;
+pub mod risk_config;
+pub mod rebalance_record;
+pub mod ranking_session;
+pub mod allocation_log;
+pub mod governance_config;
+pub mod redistribution_execution;
+pub mod metrics_snapshot;
 
 pub use portfolio::*;
 pub use strategy::*;
 pub use capital_position::*;
+pub use allocation::*;
+pub use preview_cache::*;
+pub use risk_config::*;
+pub use rebalance_record::*;
+pub use ranking_session::*;
+pub use allocation_log::*;
+pub use governance_config::*;
+pub use redistribution_execution::*;
+pub use metrics_snapshot::*;
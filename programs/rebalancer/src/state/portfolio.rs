@@ -1,12 +1,35 @@
 use anchor_lang::prelude::*;
 
 use crate::errors::RebalancerErrorCode;
+use crate::state::{Strategy, StrategyStatus, WRAPPED_SOL_MINT};
+use crate::utils::apply_bps;
 
+// Default age (seconds) after which strategy metrics are too stale to act on
+pub const DEFAULT_MAX_METRIC_STALENESS: i64 = 86400; // 24 hours
+
+// Allowed range (seconds) for min_rebalance_interval
+pub const MIN_REBALANCE_INTERVAL: i64 = 3600; // 1 hour
+pub const MAX_REBALANCE_INTERVAL: i64 = 86400; // 1 day
+
+// Default strategy slot limit; keeps ranking a whole portfolio within one transaction
+pub const DEFAULT_MAX_STRATEGIES: u32 = 50;
+
+// Default weight (bps) of a new volatility reading in the strategy volatility EMA
+pub const DEFAULT_VOLATILITY_SMOOTHING_BPS: u16 = 3000; // 30%
+
+// Current portfolio account layout. Portfolios written before the layout was
+// versioned are 112 bytes and are brought up to date by migrate_portfolio
+pub const PORTFOLIO_LAYOUT_VERSION: u8 = 1;
+pub const LEGACY_PORTFOLIO_SIZE: usize = 112;
+
+/// The portfolio PDA is derived from `seed_manager`, the manager key at creation.
+/// It never changes, so the portfolio (and every strategy PDA seeded from it)
+/// keeps its address when `manager` is handed off via a manager transfer.
 #[account]
 #[derive(Debug)]
 pub struct Portfolio {
     pub manager: Pubkey,                    // 32 bytes - Portfolio manager authority
-    pub total_capital_moved: u64,           // 8 bytes - Lifetime capital rebalanced (lamports)
+    pub total_capital_moved: u64,           // 8 bytes - Lifetime capital rebalanced, fees excluded (lamports)
     pub last_rebalance: i64,                // 8 bytes - Unix timestamp of last rebalance
     pub min_rebalance_interval: i64,        // 8 bytes - Minimum seconds between rebalances
     pub portfolio_creation: i64,            // 8 bytes - Portfolio creation timestamp
@@ -15,9 +38,23 @@ pub struct Portfolio {
     pub base_threshold: u8,                 // 1 byte - Base threshold for dynamic calculation (1-50)
     pub emergency_pause: bool,              // 1 byte - Emergency stop flag
     pub bump: u8,                           // 1 byte - PDA bump seed
-    pub reserved: [u8; 31],                 // 31 bytes - Future expansion buffer
+    pub max_strategies: u32,                // 4 bytes - Strategy slot limit (0 = uncapped)
+    pub max_capital: u64,                   // 8 bytes - Capital cap in lamports (0 = uncapped)
+    pub guardian: Pubkey,                   // 32 bytes - Incident-response key that can only pause (default = none)
+    pub emergency_rebalance_count: u32,     // 4 bytes - Rebalances that bypassed the interval
+    pub seed_manager: Pubkey,               // 32 bytes - Original manager key the PDA is derived from
+    pub pending_manager: Pubkey,            // 32 bytes - Proposed new manager awaiting acceptance (default = none)
+    pub max_metric_staleness: i64,          // 8 bytes - Max age of strategy metrics in seconds (0 = unchecked)
+    pub rebalance_sequence: u64,            // 8 bytes - Sequence number of the next RebalanceRecord
+    pub high_water_mark: u64,               // 8 bytes - Cumulative profit already charged a performance fee (lamports)
+    pub total_value_locked: u64,            // 8 bytes - Sum of native SOL strategy balances (lamports)
+    pub volatility_smoothing_bps: u16,      // 2 bytes - Weight of a new volatility reading in the EMA (1-10000)
+    pub oracle_authority: Pubkey,           // 32 bytes - Keeper key that may push performance updates (default = none)
+    pub governance_enabled: bool,           // 1 byte - Sensitive changes need GovernanceConfig approval
+    pub layout_version: u8,                 // 1 byte - Account layout written (0 = legacy, see migrate_portfolio)
+    pub reserved: [u8; 3],                  // 3 bytes - Future expansion buffer
 }
-// Total: 136 bytes
+// Total: 256 bytes + 8 byte discriminator
 
 impl Portfolio {
     pub const MAX_SIZE: usize = 8 
@@ -28,23 +65,686 @@ impl Portfolio {
     + 8 // portfolio_creation
     + 4 // total_strategies
     + 2 // performance_fee_bps
-    + 1 // rebalance_threshold
+    + 1 // base_threshold
     + 1 // emergency_pause
     + 1 // bump
-    + 31; // reserved
-    // 112 bytes
+    + 4 // max_strategies
+    + 8 // max_capital
+    + 32 // guardian
+    + 4 // emergency_rebalance_count
+    + 32 // seed_manager
+    + 32 // pending_manager
+    + 8 // max_metric_staleness
+    + 8 // rebalance_sequence
+    + 8 // high_water_mark
+    + 8 // total_value_locked
+    + 2 // volatility_smoothing_bps
+    + 32 // oracle_authority
+    + 1 // governance_enabled
+    + 1 // layout_version
+    + 3; // reserved
+    // 264 bytes
     pub fn validate_base_threshold(threshold: u8) -> Result<()> {
-        require!(threshold >= 1 && threshold <= 50, RebalancerErrorCode::InvalidRebalanceThreshold);
+        require!((1..=50).contains(&threshold), RebalancerErrorCode::InvalidRebalanceThreshold);
         Ok(())
     }
     
+    /// The clock must have moved strictly past the last rebalance as well as
+    /// the interval, so two rebalances never share a timestamp even if the
+    /// interval saturates at the i64 bounds. A negative clock reading is not
+    /// trusted and never allows a rebalance, and neither is an interval that
+    /// is not positive: `validate_min_interval` rules it out, so only a
+    /// corrupted account can hold one.
     pub fn can_rebalance(&self, current_time: i64) -> bool {
-        !self.emergency_pause && 
-        current_time >= self.last_rebalance.saturating_add(self.min_rebalance_interval)
+        !self.emergency_pause
+            && self.min_rebalance_interval > 0
+            && current_time >= 0
+            && current_time > self.last_rebalance
+            && current_time >= self.last_rebalance.saturating_add(self.min_rebalance_interval)
+    }
+    
+    /// Seconds until the interval gate in `can_rebalance` opens, or 0 once it
+    /// has. Ignores the emergency pause, which callers report separately. A
+    /// corrupted (non-positive) interval never opens the gate, so it reports
+    /// `i64::MAX`.
+    pub fn seconds_until_rebalance(&self, current_time: i64) -> i64 {
+        // A last rebalance at i64::MAX leaves no later timestamp to rebalance at
+        if self.min_rebalance_interval <= 0 || self.last_rebalance == i64::MAX {
+            return i64::MAX;
+        }
+        let eligible_at = self.last_rebalance
+            .saturating_add(self.min_rebalance_interval)
+            .max(0);
+        eligible_at.saturating_sub(current_time).max(0)
+    }
+    
+    /// Metrics last refreshed at `last_updated` are stale once they are older
+    /// than `max_metric_staleness` seconds. A limit of 0 disables the check.
+    pub fn is_metric_stale(&self, last_updated: i64, current_time: i64) -> bool {
+        self.max_metric_staleness > 0
+            && last_updated < current_time.saturating_sub(self.max_metric_staleness)
+    }
+    
+    /// Reject acting on any active strategy whose metrics are stale. Paused and
+    /// deprecated strategies cannot receive performance updates, so they are
+    /// not held to the freshness limit.
+    pub fn validate_metric_freshness<'a>(
+        &self,
+        strategies: impl IntoIterator<Item = &'a Strategy>,
+        current_time: i64,
+    ) -> Result<()> {
+        for strategy in strategies {
+            require!(
+                strategy.status != StrategyStatus::Active
+                    || !self.is_metric_stale(strategy.last_updated, current_time),
+                RebalancerErrorCode::StaleStrategyData
+            );
+        }
+        Ok(())
+    }
+    
+    pub fn validate_max_metric_staleness(max_metric_staleness: i64) -> Result<()> {
+        require!(max_metric_staleness >= 0, RebalancerErrorCode::InvalidMetricStaleness);
+        Ok(())
+    }
+    
+    pub fn validate_volatility_smoothing(smoothing_bps: u16) -> Result<()> {
+        require!((1..=10000).contains(&smoothing_bps), RebalancerErrorCode::InvalidVolatilitySmoothing);
+        Ok(())
+    }
+    
+    /// Claim the sequence number for a new `RebalanceRecord`. Returns the
+    /// number the record's PDA was derived from and advances the counter.
+    pub fn next_rebalance_sequence(&mut self) -> Result<u64> {
+        let sequence = self.rebalance_sequence;
+        self.rebalance_sequence = sequence
+            .checked_add(1)
+            .ok_or(RebalancerErrorCode::BalanceOverflow)?;
+        Ok(sequence)
+    }
+    
+    /// Charge the performance fee on cumulative profit above the high-water
+    /// mark and raise the mark to it. Profit lost and then recovered is below
+    /// the mark again, so it is never charged twice. Returns the fee; moving
+    /// it is left to the caller.
+    pub fn record_performance_fee(&mut self, cumulative_profit: i128) -> Result<u64> {
+        let high_water_mark = self.high_water_mark as i128;
+        require!(cumulative_profit > high_water_mark, RebalancerErrorCode::NoPerformanceGains);
+        
+        let gains = u64::try_from(cumulative_profit - high_water_mark)
+            .map_err(|_| RebalancerErrorCode::BalanceOverflow)?;
+        let fee = apply_bps(gains, self.performance_fee_bps as u64)?;
+        require!(fee > 0, RebalancerErrorCode::NoPerformanceGains);
+        
+        self.high_water_mark = u64::try_from(cumulative_profit)
+            .map_err(|_| RebalancerErrorCode::BalanceOverflow)?;
+        Ok(fee)
+    }
+    
+    /// Keep `total_value_locked` in step with one strategy's balance moving
+    /// from `previous` to `current`. Only native SOL strategies are counted;
+    /// balances of other mints are not lamports.
+    pub fn apply_balance_change(&mut self, mint: &Pubkey, previous: u64, current: u64) -> Result<()> {
+        if *mint != WRAPPED_SOL_MINT {
+            return Ok(());
+        }
+        self.total_value_locked = if current >= previous {
+            self.total_value_locked
+                .checked_add(current - previous)
+                .ok_or(RebalancerErrorCode::BalanceOverflow)?
+        } else {
+            self.total_value_locked
+                .checked_sub(previous - current)
+                .ok_or(RebalancerErrorCode::InsufficientBalance)?
+        };
+        Ok(())
+    }
+    
+    pub fn has_guardian(&self) -> bool {
+        self.guardian != Pubkey::default()
+    }
+    
+    /// Whole-portfolio operations must see every registered strategy; a short
+    /// account list is a client bug, not a smaller portfolio.
+    pub fn validate_strategy_account_count(&self, passed: usize) -> Result<()> {
+        require!(
+            passed == self.total_strategies as usize,
+            RebalancerErrorCode::MissingStrategyAccounts
+        );
+        Ok(())
+    }
+    
+    /// Lower `total_strategies` to a verified count of live strategy
+    /// accounts. Returns the count it replaced. Reconciliation never raises
+    /// the count: only registration adds strategies.
+    pub fn reconcile_strategy_count(&mut self, verified: usize) -> Result<u32> {
+        let verified = u32::try_from(verified).map_err(|_| RebalancerErrorCode::BalanceOverflow)?;
+        require!(verified <= self.total_strategies, RebalancerErrorCode::InvalidStrategyCount);
+        
+        let previous_count = self.total_strategies;
+        self.total_strategies = verified;
+        Ok(previous_count)
+    }
+    
+    /// Fields appended after the legacy layout are only meaningful on an
+    /// account written (or migrated) in the current layout.
+    pub fn validate_layout(&self) -> Result<()> {
+        require!(self.layout_version == PORTFOLIO_LAYOUT_VERSION, RebalancerErrorCode::UnsupportedLayoutVersion);
+        Ok(())
+    }
+    
+    pub fn is_governance_enabled(&self) -> Result<bool> {
+        self.validate_layout()?;
+        Ok(self.governance_enabled)
+    }
+    
+    pub fn has_oracle_authority(&self) -> bool {
+        self.oracle_authority != Pubkey::default()
+    }
+    
+    /// Performance metrics may be pushed by the manager or, when one is set,
+    /// the oracle authority. Everything else stays manager-only.
+    pub fn can_update_performance(&self, authority: &Pubkey) -> bool {
+        *authority == self.manager || (self.has_oracle_authority() && *authority == self.oracle_authority)
+    }
+    
+    /// The oracle authority watches the strategies, so it may freeze or thaw
+    /// one alongside the manager.
+    pub fn can_pause_strategy(&self, authority: &Pubkey) -> bool {
+        self.can_update_performance(authority)
+    }
+    
+    pub fn has_pending_manager(&self) -> bool {
+        self.pending_manager != Pubkey::default()
+    }
+    
+    /// First step of a manager transfer. Proposing `Pubkey::default()` cancels
+    /// any pending transfer.
+    pub fn propose_manager(&mut self, new_manager: Pubkey) -> Result<()> {
+        require!(new_manager != self.manager, RebalancerErrorCode::InvalidManager);
+        self.pending_manager = new_manager;
+        Ok(())
+    }
+    
+    /// Second step of a manager transfer, called with the accepting signer.
+    pub fn accept_manager(&mut self, signer: Pubkey) -> Result<()> {
+        require!(
+            self.has_pending_manager() && signer == self.pending_manager,
+            RebalancerErrorCode::UnauthorizedPendingManager
+        );
+        self.manager = signer;
+        self.pending_manager = Pubkey::default();
+        Ok(())
+    }
+    
+    /// Reject registering another strategy once every slot is taken. A limit
+    /// of 0 leaves the portfolio uncapped.
+    pub fn validate_strategy_slot(&self) -> Result<()> {
+        require!(
+            self.max_strategies == 0 || self.total_strategies < self.max_strategies,
+            RebalancerErrorCode::TooManyStrategies
+        );
+        Ok(())
     }
     
     pub fn validate_min_interval(interval: i64) -> Result<()> {
-        require!(interval >= 1 && interval <= 86400, RebalancerErrorCode::InvalidRebalanceInterval);
+        require!(
+            (MIN_REBALANCE_INTERVAL..=MAX_REBALANCE_INTERVAL).contains(&interval),
+            RebalancerErrorCode::InvalidRebalanceInterval
+        );
         Ok(())
     }
-}
\ No newline at end of file
+    
+    /// How full the portfolio is relative to its configured limits, in basis points.
+    /// 
+    /// Strategy slots (`total_strategies / max_strategies`) and capital
+    /// (`total_capital / max_capital`) are measured separately and the tighter of
+    /// the two is reported. An uncapped dimension (limit of 0) contributes 0, and
+    /// the result is clamped to 10000 if the portfolio is over a limit.
+    pub fn capacity_utilization_bps(&self, total_capital: u64) -> u16 {
+        let strategy_utilization = if self.max_strategies == 0 {
+            0u64
+        } else {
+            (self.total_strategies as u64 * 10000) / self.max_strategies as u64
+        };
+        
+        let capital_utilization = if self.max_capital == 0 {
+            0u64
+        } else {
+            ((total_capital as u128 * 10000) / self.max_capital as u128).min(10000) as u64
+        };
+        
+        strategy_utilization.max(capital_utilization).min(10000) as u16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+    use crate::state::WRAPPED_SOL_MINT;
+    
+    fn portfolio_with_limits(total_strategies: u32, max_strategies: u32, max_capital: u64) -> Portfolio {
+        Portfolio {
+            manager: Pubkey::new_unique(),
+            total_capital_moved: 0,
+            last_rebalance: 0,
+            min_rebalance_interval: 3600,
+            portfolio_creation: 0,
+            total_strategies,
+            performance_fee_bps: 200,
+            base_threshold: 15,
+            emergency_pause: false,
+            bump: 255,
+            max_strategies,
+            max_capital,
+            guardian: Pubkey::default(),
+            emergency_rebalance_count: 0,
+            seed_manager: Pubkey::default(),
+            pending_manager: Pubkey::default(),
+            max_metric_staleness: DEFAULT_MAX_METRIC_STALENESS,
+            rebalance_sequence: 0,
+            high_water_mark: 0,
+            total_value_locked: 0,
+            volatility_smoothing_bps: DEFAULT_VOLATILITY_SMOOTHING_BPS,
+            oracle_authority: Pubkey::default(),
+            governance_enabled: false,
+            layout_version: PORTFOLIO_LAYOUT_VERSION,
+            reserved: [0u8; 3],
+        }
+    }
+    
+    fn strategy_updated_at(last_updated: i64, status: StrategyStatus) -> Strategy {
+        Strategy {
+            last_updated,
+            status,
+            ..test_utils::strategy()
+        }
+    }
+    
+    #[test]
+    fn test_metric_freshness_accepts_fresh_strategies() {
+        let portfolio = portfolio_with_limits(2, 0, 0);
+        let now = 100_000;
+        let strategies = [
+            strategy_updated_at(now - 10, StrategyStatus::Active),
+            // Exactly at the limit still counts as fresh
+            strategy_updated_at(now - DEFAULT_MAX_METRIC_STALENESS, StrategyStatus::Active),
+        ];
+        
+        assert!(portfolio.validate_metric_freshness(strategies.iter(), now).is_ok());
+    }
+    
+    #[test]
+    fn test_metric_freshness_rejects_stale_active_strategy() {
+        let portfolio = portfolio_with_limits(2, 0, 0);
+        let now = 100_000;
+        let strategies = [
+            strategy_updated_at(now, StrategyStatus::Active),
+            strategy_updated_at(now - DEFAULT_MAX_METRIC_STALENESS - 1, StrategyStatus::Active),
+        ];
+        
+        assert_eq!(
+            portfolio.validate_metric_freshness(strategies.iter(), now).unwrap_err(),
+            RebalancerErrorCode::StaleStrategyData.into()
+        );
+    }
+    
+    #[test]
+    fn test_metric_freshness_ignores_inactive_and_disabled() {
+        let mut portfolio = portfolio_with_limits(2, 0, 0);
+        let now = 100_000;
+        
+        // Paused strategies cannot be updated, so they never block the gate
+        let paused = [strategy_updated_at(0, StrategyStatus::Paused)];
+        assert!(portfolio.validate_metric_freshness(paused.iter(), now).is_ok());
+        
+        // A limit of 0 turns the check off entirely
+        portfolio.max_metric_staleness = 0;
+        let ancient = [strategy_updated_at(0, StrategyStatus::Active)];
+        assert!(portfolio.validate_metric_freshness(ancient.iter(), now).is_ok());
+        
+        assert!(Portfolio::validate_max_metric_staleness(-1).is_err());
+    }
+    
+    #[test]
+    fn test_rebalance_sequence_increments() {
+        let mut portfolio = portfolio_with_limits(0, 0, 0);
+        
+        assert_eq!(portfolio.next_rebalance_sequence().unwrap(), 0);
+        assert_eq!(portfolio.next_rebalance_sequence().unwrap(), 1);
+        assert_eq!(portfolio.rebalance_sequence, 2);
+        
+        portfolio.rebalance_sequence = u64::MAX;
+        assert!(portfolio.next_rebalance_sequence().is_err());
+    }
+    
+    #[test]
+    fn test_manager_transfer_two_step_flow() {
+        let mut portfolio = portfolio_with_limits(0, 0, 0);
+        let original = portfolio.manager;
+        let successor = Pubkey::new_unique();
+        
+        portfolio.propose_manager(successor).unwrap();
+        assert_eq!(portfolio.manager, original); // Nothing changes until accepted
+        
+        portfolio.accept_manager(successor).unwrap();
+        assert_eq!(portfolio.manager, successor);
+        assert!(!portfolio.has_pending_manager());
+    }
+    
+    #[test]
+    fn test_manager_transfer_rejects_wrong_key() {
+        let mut portfolio = portfolio_with_limits(0, 0, 0);
+        let successor = Pubkey::new_unique();
+        
+        // Nothing pending yet
+        assert_eq!(
+            portfolio.accept_manager(successor).unwrap_err(),
+            RebalancerErrorCode::UnauthorizedPendingManager.into()
+        );
+        
+        portfolio.propose_manager(successor).unwrap();
+        assert_eq!(
+            portfolio.accept_manager(Pubkey::new_unique()).unwrap_err(),
+            RebalancerErrorCode::UnauthorizedPendingManager.into()
+        );
+        
+        // Cancelling leaves nothing to accept
+        portfolio.propose_manager(Pubkey::default()).unwrap();
+        assert!(portfolio.accept_manager(successor).is_err());
+    }
+    
+    #[test]
+    fn test_capacity_utilization_near_full() {
+        // 9 of 10 slots used, 95 of 100 SOL cap used -> capital is the binding limit
+        let portfolio = portfolio_with_limits(9, 10, 100_000_000_000);
+        assert_eq!(portfolio.capacity_utilization_bps(95_000_000_000), 9500);
+        
+        // Slots are the binding limit when capital is low
+        assert_eq!(portfolio.capacity_utilization_bps(1_000_000_000), 9000);
+    }
+    
+    #[test]
+    fn test_capacity_utilization_nearly_empty() {
+        let portfolio = portfolio_with_limits(1, 50, 1_000_000_000_000);
+        assert_eq!(portfolio.capacity_utilization_bps(1_000_000_000), 200);
+    }
+    
+    #[test]
+    fn test_capacity_utilization_uncapped() {
+        // No limits configured at all
+        let uncapped = portfolio_with_limits(25, 0, 0);
+        assert_eq!(uncapped.capacity_utilization_bps(u64::MAX), 0);
+        
+        // Only the strategy limit configured
+        let slots_only = portfolio_with_limits(5, 20, 0);
+        assert_eq!(slots_only.capacity_utilization_bps(u64::MAX), 2500);
+        
+        // Only the capital limit configured, and exceeded
+        let capital_only = portfolio_with_limits(5, 0, 1_000_000_000);
+        assert_eq!(capital_only.capacity_utilization_bps(3_000_000_000), 10000);
+    }
+    
+    #[test]
+    fn test_min_interval_bounds() {
+        let err = Portfolio::validate_min_interval(3599).unwrap_err();
+        assert_eq!(err, RebalancerErrorCode::InvalidRebalanceInterval.into());
+        Portfolio::validate_min_interval(3600).unwrap();
+        Portfolio::validate_min_interval(86400).unwrap();
+        let err = Portfolio::validate_min_interval(86401).unwrap_err();
+        assert_eq!(err, RebalancerErrorCode::InvalidRebalanceInterval.into());
+    }
+    
+    #[test]
+    fn test_base_threshold_bounds() {
+        let err = Portfolio::validate_base_threshold(0).unwrap_err();
+        assert_eq!(err, RebalancerErrorCode::InvalidRebalanceThreshold.into());
+        Portfolio::validate_base_threshold(1).unwrap();
+        Portfolio::validate_base_threshold(50).unwrap();
+        let err = Portfolio::validate_base_threshold(51).unwrap_err();
+        assert_eq!(err, RebalancerErrorCode::InvalidRebalanceThreshold.into());
+    }
+    
+    #[test]
+    fn test_performance_fee_on_gains() {
+        let mut portfolio = Portfolio { performance_fee_bps: 200, ..portfolio_with_limits(3, 0, 0) };
+        
+        // 2% of 10 SOL profit
+        assert_eq!(portfolio.record_performance_fee(10_000_000_000).unwrap(), 200_000_000);
+        assert_eq!(portfolio.high_water_mark, 10_000_000_000);
+        
+        // Nothing new to charge at the same profit
+        let err = portfolio.record_performance_fee(10_000_000_000).unwrap_err();
+        assert_eq!(err, RebalancerErrorCode::NoPerformanceGains.into());
+    }
+    
+    #[test]
+    fn test_performance_fee_not_charged_on_recovered_losses() {
+        let mut portfolio = Portfolio { performance_fee_bps: 200, ..portfolio_with_limits(3, 0, 0) };
+        portfolio.record_performance_fee(10_000_000_000).unwrap();
+        
+        // Drop to a net loss, then recover back to the previous high
+        for profit in [-5_000_000_000, 4_000_000_000, 10_000_000_000] {
+            let err = portfolio.record_performance_fee(profit).unwrap_err();
+            assert_eq!(err, RebalancerErrorCode::NoPerformanceGains.into());
+        }
+        assert_eq!(portfolio.high_water_mark, 10_000_000_000);
+    }
+    
+    #[test]
+    fn test_performance_fee_on_new_high() {
+        let mut portfolio = Portfolio { performance_fee_bps: 200, ..portfolio_with_limits(3, 0, 0) };
+        portfolio.record_performance_fee(10_000_000_000).unwrap();
+        assert!(portfolio.record_performance_fee(6_000_000_000).is_err());
+        
+        // Only the 2 SOL above the previous high is charged
+        assert_eq!(portfolio.record_performance_fee(12_000_000_000).unwrap(), 40_000_000);
+        assert_eq!(portfolio.high_water_mark, 12_000_000_000);
+    }
+    
+    #[test]
+    fn test_total_value_locked_tracks_balance_updates() {
+        let mut portfolio = portfolio_with_limits(0, 0, 0);
+        let mut balances = [0u64; 3];
+        
+        // Register, report gains and losses, then withdraw, checking the running sum each step
+        let changes = [
+            (0, 1_000_000_000),
+            (1, 2_000_000_000),
+            (2, 500_000_000),
+            (0, 1_250_000_000),
+            (1, 1_400_000_000),
+            (2, 500_000_000),
+            (0, 250_000_000),
+            (2, 0),
+        ];
+        for (index, balance) in changes {
+            portfolio.apply_balance_change(&WRAPPED_SOL_MINT, balances[index], balance).unwrap();
+            balances[index] = balance;
+            assert_eq!(portfolio.total_value_locked, balances.iter().sum::<u64>());
+        }
+        assert_eq!(portfolio.total_value_locked, 1_650_000_000);
+        
+        // A decrease larger than what is locked indicates corrupted accounting
+        let err = portfolio.apply_balance_change(&WRAPPED_SOL_MINT, 2_000_000_000, 0).unwrap_err();
+        assert_eq!(err, RebalancerErrorCode::InsufficientBalance.into());
+        assert_eq!(portfolio.total_value_locked, 1_650_000_000);
+        
+        // Balances of other mints are not lamports and stay out of the total
+        portfolio.apply_balance_change(&Pubkey::new_unique(), 0, 5_000_000_000).unwrap();
+        assert_eq!(portfolio.total_value_locked, 1_650_000_000);
+    }
+    
+    #[test]
+    fn test_rebalance_requires_interval_to_elapse() {
+        let portfolio = Portfolio { last_rebalance: 10_000, min_rebalance_interval: 3600, ..portfolio_with_limits(2, 0, 0) };
+        
+        assert!(!portfolio.can_rebalance(13_599));
+        assert!(portfolio.can_rebalance(13_600));
+        assert!(!Portfolio { emergency_pause: true, ..portfolio }.can_rebalance(13_600));
+    }
+    
+    #[test]
+    fn test_rebalance_rejected_at_same_timestamp() {
+        // A one-second interval (or one that saturates) would otherwise allow a
+        // second rebalance in the same second
+        let short_interval = Portfolio { last_rebalance: 10_000, min_rebalance_interval: 1, ..portfolio_with_limits(2, 0, 0) };
+        assert!(!short_interval.can_rebalance(10_000));
+        assert!(short_interval.can_rebalance(10_001));
+        
+        let saturated = Portfolio { last_rebalance: i64::MAX, min_rebalance_interval: 3600, ..portfolio_with_limits(2, 0, 0) };
+        assert!(!saturated.can_rebalance(i64::MAX));
+    }
+    
+    #[test]
+    fn test_rebalance_rejected_on_backwards_or_negative_clock() {
+        let portfolio = Portfolio { last_rebalance: 10_000, min_rebalance_interval: 1, ..portfolio_with_limits(2, 0, 0) };
+        assert!(!portfolio.can_rebalance(9_999));
+        assert!(!portfolio.can_rebalance(-1));
+        
+        // Neither a negative last rebalance nor a negative interval opens the gate early
+        let negative_last = Portfolio { last_rebalance: -5_000, min_rebalance_interval: 3600, ..portfolio_with_limits(2, 0, 0) };
+        assert!(!negative_last.can_rebalance(-1_000));
+        assert!(negative_last.can_rebalance(0));
+        
+    }
+    
+    #[test]
+    fn test_rebalance_blocked_by_corrupted_interval() {
+        // A non-positive interval can only come from a corrupted account; it
+        // blocks rebalancing rather than letting it run early
+        for min_rebalance_interval in [-3600, -1, 0, i64::MIN] {
+            let corrupted = Portfolio { last_rebalance: 10_000, min_rebalance_interval, ..portfolio_with_limits(2, 0, 0) };
+            assert!(!corrupted.can_rebalance(10_000));
+            assert!(!corrupted.can_rebalance(10_001));
+            assert!(!corrupted.can_rebalance(i64::MAX));
+        }
+    }
+
+    #[test]
+    fn test_seconds_until_rebalance_agrees_with_can_rebalance() {
+        let portfolio = Portfolio { last_rebalance: 10_000, min_rebalance_interval: 3600, ..portfolio_with_limits(2, 0, 0) };
+        
+        assert_eq!(portfolio.seconds_until_rebalance(10_000), 3600);
+        assert_eq!(portfolio.seconds_until_rebalance(13_599), 1);
+        assert_eq!(portfolio.seconds_until_rebalance(13_600), 0);
+        assert_eq!(portfolio.seconds_until_rebalance(20_000), 0);
+        
+        // Every clock reading the gate rejects reports a positive wait
+        let negative_last = Portfolio { last_rebalance: -5_000, ..portfolio.clone() };
+        for (portfolio, current_time) in [(&portfolio, 9_999), (&negative_last, -1), (&negative_last, 0)] {
+            assert_eq!(portfolio.can_rebalance(current_time), portfolio.seconds_until_rebalance(current_time) == 0);
+        }
+        
+        let saturated = Portfolio { last_rebalance: i64::MAX, ..portfolio.clone() };
+        assert_eq!(saturated.seconds_until_rebalance(i64::MAX), i64::MAX);
+        let corrupted = Portfolio { min_rebalance_interval: 0, ..portfolio };
+        assert_eq!(corrupted.seconds_until_rebalance(20_000), i64::MAX);
+    }
+    
+    #[test]
+    fn test_governance_flag_rejected_outside_current_layout() {
+        let mut portfolio = portfolio_with_limits(2, 0, 0);
+        portfolio.governance_enabled = true;
+        assert!(portfolio.is_governance_enabled().unwrap());
+        
+        // Unmigrated (0) and unknown future layouts can't vouch for the flag
+        for layout_version in [0, PORTFOLIO_LAYOUT_VERSION + 1] {
+            portfolio.layout_version = layout_version;
+            assert_eq!(
+                portfolio.is_governance_enabled().unwrap_err(),
+                RebalancerErrorCode::UnsupportedLayoutVersion.into()
+            );
+        }
+    }
+    
+    #[test]
+    fn test_oracle_authority_may_update_performance() {
+        let mut portfolio = portfolio_with_limits(0, 0, 0);
+        let manager = portfolio.manager;
+        let oracle = Pubkey::new_unique();
+
+        // Unset: only the manager, and the default key never matches
+        assert!(portfolio.can_update_performance(&manager));
+        assert!(!portfolio.can_update_performance(&oracle));
+        assert!(!portfolio.can_update_performance(&Pubkey::default()));
+
+        portfolio.oracle_authority = oracle;
+        assert!(portfolio.can_update_performance(&manager));
+        assert!(portfolio.can_update_performance(&oracle));
+        assert!(!portfolio.can_update_performance(&Pubkey::new_unique()));
+    }
+
+    #[test]
+    fn test_strategy_account_count_must_match() {
+        let portfolio = portfolio_with_limits(3, 0, 0);
+
+        assert!(portfolio.validate_strategy_account_count(3).is_ok());
+        for passed in [0, 2, 4] {
+            assert_eq!(
+                portfolio.validate_strategy_account_count(passed).unwrap_err(),
+                RebalancerErrorCode::MissingStrategyAccounts.into()
+            );
+        }
+    }
+
+    #[test]
+    fn test_inflated_strategy_count_is_corrected() {
+        // Three strategies recorded, but one was closed out-of-band
+        let mut portfolio = portfolio_with_limits(3, 0, 0);
+        assert_eq!(
+            portfolio.validate_strategy_account_count(2).unwrap_err(),
+            RebalancerErrorCode::MissingStrategyAccounts.into()
+        );
+
+        assert_eq!(portfolio.reconcile_strategy_count(2).unwrap(), 3);
+        assert_eq!(portfolio.total_strategies, 2);
+        assert!(portfolio.validate_strategy_account_count(2).is_ok());
+    }
+
+    #[test]
+    fn test_reconciliation_never_raises_the_count() {
+        let mut portfolio = portfolio_with_limits(2, 0, 0);
+
+        assert_eq!(
+            portfolio.reconcile_strategy_count(3).unwrap_err(),
+            RebalancerErrorCode::InvalidStrategyCount.into()
+        );
+        assert_eq!(portfolio.total_strategies, 2);
+
+        // Confirming the current count is allowed
+        assert_eq!(portfolio.reconcile_strategy_count(2).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_registration_fills_strategy_slots_up_to_cap() {
+        let mut portfolio = portfolio_with_limits(0, DEFAULT_MAX_STRATEGIES, 0);
+
+        for _ in 0..DEFAULT_MAX_STRATEGIES {
+            assert!(portfolio.validate_strategy_slot().is_ok());
+            portfolio.total_strategies += 1;
+        }
+        assert_eq!(
+            portfolio.validate_strategy_slot().unwrap_err(),
+            RebalancerErrorCode::TooManyStrategies.into()
+        );
+
+        // Closing a strategy frees its slot
+        portfolio.total_strategies -= 1;
+        assert!(portfolio.validate_strategy_slot().is_ok());
+
+        // A limit of 0 never caps registration
+        assert!(portfolio_with_limits(u32::MAX - 1, 0, 0).validate_strategy_slot().is_ok());
+    }
+
+    #[test]
+    fn test_max_size_matches_serialized_layout() {
+        let portfolio = portfolio_with_limits(u32::MAX, u32::MAX, u64::MAX);
+
+        // Fixed-size layout: the discriminator plus the borsh encoding fills MAX_SIZE exactly
+        let serialized = portfolio.try_to_vec().unwrap();
+        assert_eq!(serialized.len(), 256);
+        assert_eq!(Portfolio::DISCRIMINATOR.len() + serialized.len(), Portfolio::MAX_SIZE);
+    }
+}
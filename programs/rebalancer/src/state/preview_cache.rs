@@ -0,0 +1,275 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+
+use crate::instructions::redistribute_capital::{RebalancingPlan, RiskLimits};
+use crate::state::Strategy;
+
+// Preview cache bounds
+pub const PREVIEW_CACHE_TTL: i64 = 60;          // Seconds a cached plan stays fresh
+pub const MAX_PREVIEW_TARGETS: usize = RebalancingPlan::MAX_EXTRACTION_TARGETS; // Max extraction targets stored
+pub const MAX_PREVIEW_ALLOCATIONS: usize = RebalancingPlan::MAX_ALLOCATIONS;   // Max allocations stored (fees + top performers)
+
+#[account]
+#[derive(Debug)]
+pub struct PreviewCache {
+    pub portfolio: Pubkey,                  // 32 bytes - Portfolio this cache belongs to
+    pub inputs_hash: [u8; 32],              // 32 bytes - Hash of the inputs the plan was computed from
+    pub computed_at: i64,                   // 8 bytes - Unix timestamp of the cached computation
+    pub plan: RebalancingPlan,              // Variable size - Last computed rebalancing plan
+    pub bump: u8,                           // 1 byte - PDA bump seed
+}
+
+impl PreviewCache {
+    pub const MAX_SIZE: usize = 8
+    + 32 // portfolio
+    + 32 // inputs_hash
+    + 8 // computed_at
+    + RebalancingPlan::MAX_SIZE // plan
+    + 1; // bump
+
+    /// Return the cached plan if it was computed from `inputs_hash` less than
+    /// `PREVIEW_CACHE_TTL` seconds ago.
+    ///
+    /// The inputs hash covers every strategy's `last_updated` timestamp, so any
+    /// `update_performance` call invalidates the cache without touching it.
+    pub fn lookup(&self, inputs_hash: &[u8; 32], current_time: i64) -> Option<&RebalancingPlan> {
+        let is_populated = self.computed_at > 0;
+        let is_fresh = current_time >= self.computed_at
+            && current_time.saturating_sub(self.computed_at) < PREVIEW_CACHE_TTL;
+
+        if is_populated && is_fresh && self.inputs_hash == *inputs_hash {
+            Some(&self.plan)
+        } else {
+            None
+        }
+    }
+
+    pub fn store(&mut self, inputs_hash: [u8; 32], current_time: i64, plan: RebalancingPlan) -> Result<()> {
+        plan.validate_size()?;
+
+        self.inputs_hash = inputs_hash;
+        self.computed_at = current_time;
+        self.plan = plan;
+        Ok(())
+    }
+
+    /// Hash everything `execute_complete_rebalancing` reads (every strategy field
+    /// `StrategyPerformanceData::from_strategy` takes), plus each strategy's
+    /// `last_updated` so that fresh metric updates always miss the cache.
+    pub fn hash_inputs<'a>(
+        base_threshold: u8,
+        risk_limits: &RiskLimits,
+        strategies: impl ExactSizeIterator<Item = &'a Strategy>,
+    ) -> Result<[u8; 32]> {
+        let mut limit_bytes = Vec::with_capacity(259);
+        for value in [
+            risk_limits.max_single_strategy_bps,
+            risk_limits.min_single_strategy_bps,
+            risk_limits.platform_fee_bps,
+            risk_limits.manager_fee_bps,
+            risk_limits.risk_tolerance_bps,
+            risk_limits.min_extraction_per_strategy,
+            risk_limits.stable_lending_min_lamports,
+            risk_limits.yield_farming_min_lamports,
+            risk_limits.liquid_staking_min_lamports,
+            risk_limits.perpetual_funding_min_lamports,
+            risk_limits.perpetual_taker_fee_bps,
+        ] {
+            limit_bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        limit_bytes.extend_from_slice(risk_limits.platform_treasury.as_ref());
+        limit_bytes.extend_from_slice(risk_limits.manager_treasury.as_ref());
+        for weight in [
+            risk_limits.stable_lending_weight_bps,
+            risk_limits.yield_farming_weight_bps,
+            risk_limits.liquid_staking_weight_bps,
+            risk_limits.perpetual_funding_weight_bps,
+        ] {
+            limit_bytes.extend_from_slice(&weight.to_le_bytes());
+        }
+        limit_bytes.push(risk_limits.allocation_mode as u8);
+        limit_bytes.extend_from_slice(&risk_limits.min_net_benefit_bps.to_le_bytes());
+        limit_bytes.extend_from_slice(&risk_limits.max_group_bps.to_le_bytes());
+        limit_bytes.push(risk_limits.top_performer_count);
+        limit_bytes.push(risk_limits.top_performer_percentile);
+        limit_bytes.push(risk_limits.require_protocol_diversity as u8);
+        limit_bytes.extend_from_slice(&risk_limits.fee_grace_period.to_le_bytes());
+        limit_bytes.extend_from_slice(&risk_limits.volatility_weight.to_le_bytes());
+        limit_bytes.extend_from_slice(&risk_limits.crisis_volatility_threshold.to_le_bytes());
+        limit_bytes.push(risk_limits.min_threshold);
+        limit_bytes.push(risk_limits.max_threshold);
+        limit_bytes.push(risk_limits.remainder_policy as u8);
+        limit_bytes.extend_from_slice(&risk_limits.reallocation_cooldown.to_le_bytes());
+        limit_bytes.push(risk_limits.extract_only as u8);
+        limit_bytes.extend_from_slice(risk_limits.safe_haven.as_ref());
+        limit_bytes.push(risk_limits.underperformer_max_percentile);
+        limit_bytes.push(risk_limits.tie_break_policy as u8);
+        limit_bytes.extend_from_slice(&risk_limits.min_rebalance_capital.to_le_bytes());
+        for target in [
+            risk_limits.stable_lending_target_bps,
+            risk_limits.yield_farming_target_bps,
+            risk_limits.liquid_staking_target_bps,
+            risk_limits.perpetual_funding_target_bps,
+        ] {
+            limit_bytes.extend_from_slice(&target.to_le_bytes());
+        }
+
+        // Up to 219 bytes per strategy with the largest protocol_type
+        let mut strategy_bytes = Vec::with_capacity(strategies.len() * 219);
+        for strategy in strategies {
+            strategy_bytes.extend_from_slice(strategy.strategy_id.as_ref());
+            strategy_bytes.extend_from_slice(&strategy.performance_score.to_le_bytes());
+            strategy_bytes.extend_from_slice(&strategy.yield_rate.to_le_bytes());
+            strategy_bytes.extend_from_slice(&strategy.current_balance.to_le_bytes());
+            strategy_bytes.extend_from_slice(&strategy.volatility_ema.to_le_bytes());
+            strategy.protocol_type.serialize(&mut strategy_bytes)?;
+            strategy_bytes.push(strategy.percentile_rank);
+            strategy_bytes.push(strategy.status as u8);
+            strategy_bytes.extend_from_slice(strategy.mint.as_ref());
+            strategy_bytes.push(strategy.decimals);
+            strategy_bytes.extend_from_slice(&strategy.creation_time.to_le_bytes());
+            strategy_bytes.extend_from_slice(&strategy.last_updated.to_le_bytes());
+            strategy_bytes.extend_from_slice(&strategy.last_extracted.to_le_bytes());
+        }
+
+        Ok(hashv(&[&[base_threshold], &limit_bytes, &strategy_bytes]).to_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{AllocationType, CapitalAllocation, ProtocolType, StrategyStatus};
+    use crate::test_utils;
+
+    fn sample_plan() -> RebalancingPlan {
+        RebalancingPlan {
+            extraction_targets: vec![Pubkey::new_unique()],
+            extraction_amounts: vec![1_990_000_000],
+            total_to_extract: 1_990_000_000,
+            redistribution_plan: vec![],
+            estimated_fees: 39_800_000,
+            expected_improvement: 1350,
+        }
+    }
+
+    fn cached(inputs_hash: [u8; 32], computed_at: i64) -> PreviewCache {
+        let mut cache = PreviewCache {
+            portfolio: Pubkey::new_unique(),
+            inputs_hash: [0u8; 32],
+            computed_at: 0,
+            plan: RebalancingPlan {
+                extraction_targets: vec![],
+                extraction_amounts: vec![],
+                total_to_extract: 0,
+                redistribution_plan: vec![],
+                estimated_fees: 0,
+                expected_improvement: 0,
+            },
+            bump: 255,
+        };
+        cache.store(inputs_hash, computed_at, sample_plan()).unwrap();
+        cache
+    }
+
+    #[test]
+    fn test_cache_hit_with_same_inputs() {
+        let cache = cached([7u8; 32], 1_000);
+
+        let plan = cache.lookup(&[7u8; 32], 1_000 + PREVIEW_CACHE_TTL - 1);
+        assert!(plan.is_some());
+        assert_eq!(plan.unwrap().total_to_extract, 1_990_000_000);
+    }
+
+    #[test]
+    fn test_cache_miss_when_stale() {
+        let cache = cached([7u8; 32], 1_000);
+
+        assert!(cache.lookup(&[7u8; 32], 1_000 + PREVIEW_CACHE_TTL).is_none());
+        // A clock that went backwards is treated as stale as well
+        assert!(cache.lookup(&[7u8; 32], 999).is_none());
+    }
+
+    #[test]
+    fn test_cache_miss_when_inputs_change() {
+        let cache = cached([7u8; 32], 1_000);
+
+        assert!(cache.lookup(&[8u8; 32], 1_001).is_none());
+    }
+
+    #[test]
+    fn test_pause_and_resume_miss_the_cache() {
+        let risk_limits = RiskLimits::default();
+        let mut strategies = [test_utils::strategy(), test_utils::strategy()];
+        let hash = |strategies: &[Strategy]| PreviewCache::hash_inputs(15, &risk_limits, strategies.iter()).unwrap();
+        
+        // Pausing leaves last_updated alone, so only the status tells the plans apart
+        let mut cache = cached(hash(&strategies), 1_000);
+        strategies[1].status = StrategyStatus::Paused;
+        assert!(cache.lookup(&hash(&strategies), 1_001).is_none());
+        
+        cache.store(hash(&strategies), 1_001, sample_plan()).unwrap();
+        strategies[1].status = StrategyStatus::Active;
+        assert!(cache.lookup(&hash(&strategies), 1_002).is_none());
+    }
+    
+    #[test]
+    fn test_inputs_hash_covers_every_planning_field() {
+        let risk_limits = RiskLimits::default();
+        let base = test_utils::strategy();
+        let hash = |strategy: &Strategy| PreviewCache::hash_inputs(15, &risk_limits, [strategy].into_iter()).unwrap();
+        
+        let changed = [
+            Strategy { yield_rate: base.yield_rate + 1, ..base },
+            Strategy { mint: Pubkey::new_unique(), ..base },
+            Strategy { decimals: 6, ..base },
+            Strategy { creation_time: 1, ..base },
+            Strategy {
+                protocol_type: ProtocolType::StableLending {
+                    pool_id: Pubkey::new_unique(),
+                    reserve_address: Pubkey::new_unique(),
+                    utilization: 7500,
+                },
+                ..base
+            },
+        ];
+        for strategy in &changed {
+            assert_ne!(hash(strategy), hash(&base));
+        }
+    }
+    
+    #[test]
+    fn test_empty_cache_never_hits() {
+        let cache = PreviewCache {
+            portfolio: Pubkey::new_unique(),
+            inputs_hash: [0u8; 32],
+            computed_at: 0,
+            plan: sample_plan(),
+            bump: 255,
+        };
+
+        assert!(cache.lookup(&[0u8; 32], 10).is_none());
+    }
+
+    #[test]
+    fn test_max_size_fits_largest_plan() {
+        let allocation = CapitalAllocation {
+            strategy_id: Pubkey::new_unique(),
+            amount: u64::MAX,
+            min_acceptable_amount: u64::MAX,
+            allocation_type: AllocationType::RiskDiversification,
+        };
+        let mut cache = cached([7u8; 32], 1_000);
+        cache.store([8u8; 32], 2_000, RebalancingPlan {
+            extraction_targets: vec![Pubkey::new_unique(); MAX_PREVIEW_TARGETS],
+            extraction_amounts: vec![u64::MAX; MAX_PREVIEW_TARGETS],
+            total_to_extract: u64::MAX,
+            redistribution_plan: vec![allocation; MAX_PREVIEW_ALLOCATIONS],
+            estimated_fees: u64::MAX,
+            expected_improvement: u64::MAX,
+        }).unwrap();
+
+        let serialized = cache.try_to_vec().unwrap();
+        assert_eq!(PreviewCache::DISCRIMINATOR.len() + serialized.len(), PreviewCache::MAX_SIZE);
+    }
+}
@@ -0,0 +1,371 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+
+use crate::errors::RebalancerErrorCode;
+use crate::instructions::execute_ranking::{assign_percentile_ranks, ranking_order, StrategyData};
+use crate::instructions::redistribute_capital::RiskLimits;
+use crate::state::{Strategy, StrategyStatus};
+
+pub const MAX_RANKING_SESSION_STRATEGIES: usize = 64; // Strategies one chunked ranking cycle can hold
+
+/// Scratch state for a ranking cycle split across several transactions.
+///
+/// `begin_ranking_cycle` snapshots the strategy count and risk limits, each
+/// `submit_ranking_batch` merges its strategies into `entries` in ranking
+/// order, and `finalize_ranking_cycle` assigns percentiles from that order
+/// without sorting again or loading any strategy. `apply_ranking_batch` then
+/// writes the ranks back a batch at a time and closes the session once every
+/// strategy has its rank.
+#[account]
+#[derive(Debug)]
+pub struct RankingSession {
+    pub portfolio: Pubkey,                  // 32 bytes - Portfolio being ranked
+    pub expected_count: u32,                // 4 bytes - Portfolio total_strategies when the cycle began
+    pub started_at: i64,                    // 8 bytes - Unix timestamp of begin_ranking_cycle
+    pub risk_limits_hash: [u8; 32],         // 32 bytes - Hash of the RiskLimits every batch is ranked under
+    pub entries: Vec<RankingEntry>,         // Variable size - Submitted strategies, best first
+    pub finalized: bool,                    // 1 byte - Percentiles assigned, awaiting write-back
+    pub bump: u8,                           // 1 byte - PDA bump seed
+}
+
+/// One submitted strategy's ranking inputs.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct RankingEntry {
+    pub strategy_id: Pubkey,
+    pub performance_score: u64,
+    pub current_balance: u64,
+    pub volatility_score: u32,
+    pub protocol_weight_bps: u32,
+    pub net_return_bps: i64,
+    pub last_updated: i64,      // Write-back rejects the strategy if metrics changed after submission
+    pub rankable: bool,         // Only Active strategies are ranked (see rankable_strategies)
+    pub percentile_rank: u8,    // Assigned at finalize
+    pub applied: bool,          // Rank written back to the strategy
+}
+
+impl RankingEntry {
+    pub const SIZE: usize = 32 + 8 + 8 + 4 + 4 + 8 + 8 + 1 + 1 + 1;
+
+    pub fn from_strategy(strategy: &Strategy, risk_limits: &RiskLimits) -> Self {
+        RankingEntry {
+            strategy_id: strategy.strategy_id,
+            performance_score: strategy.performance_score,
+            current_balance: strategy.current_balance,
+            volatility_score: strategy.volatility_ema,
+            protocol_weight_bps: risk_limits.protocol_weight(&strategy.protocol_type),
+            net_return_bps: strategy.net_return_bps(),
+            last_updated: strategy.last_updated,
+            rankable: strategy.status == StrategyStatus::Active,
+            percentile_rank: 0,
+            applied: false,
+        }
+    }
+
+    pub fn to_strategy_data(&self) -> StrategyData {
+        StrategyData {
+            strategy_id: self.strategy_id,
+            performance_score: self.performance_score,
+            current_balance: self.current_balance,
+            volatility_score: self.volatility_score,
+            percentile_rank: 0,
+            protocol_weight_bps: self.protocol_weight_bps,
+            net_return_bps: self.net_return_bps,
+        }
+    }
+}
+
+impl RankingSession {
+    pub const MAX_SIZE: usize = 8
+    + 32 // portfolio
+    + 4 // expected_count
+    + 8 // started_at
+    + 32 // risk_limits_hash
+    + 4 + RankingEntry::SIZE * MAX_RANKING_SESSION_STRATEGIES // entries
+    + 1 // finalized
+    + 1; // bump
+
+    pub fn reset(&mut self, portfolio: Pubkey, expected_count: u32, started_at: i64, risk_limits: &RiskLimits) -> Result<()> {
+        self.portfolio = portfolio;
+        self.expected_count = expected_count;
+        self.started_at = started_at;
+        self.risk_limits_hash = Self::hash_risk_limits(risk_limits)?;
+        self.entries.clear();
+        self.finalized = false;
+        Ok(())
+    }
+
+    pub fn hash_risk_limits(risk_limits: &RiskLimits) -> Result<[u8; 32]> {
+        Ok(hash(&risk_limits.try_to_vec()?).to_bytes())
+    }
+
+    /// Every batch and the finalize step must rank under the limits the cycle
+    /// began with, or chunks would be weighted against different configs.
+    pub fn validate_risk_limits(&self, risk_limits: &RiskLimits) -> Result<()> {
+        require!(
+            Self::hash_risk_limits(risk_limits)? == self.risk_limits_hash,
+            RebalancerErrorCode::RankingInputsChanged
+        );
+        Ok(())
+    }
+
+    /// Merge one batch into the session, keeping `entries` in ranking order.
+    ///
+    /// A strategy that was already submitted rejects the whole batch, so a
+    /// batch cannot be counted twice.
+    pub fn submit_batch<'a>(
+        &mut self,
+        strategies: impl IntoIterator<Item = &'a Strategy>,
+        risk_limits: &RiskLimits,
+    ) -> Result<()> {
+        require!(!self.finalized, RebalancerErrorCode::RankingCycleFinalized);
+        
+        for strategy in strategies {
+            require!(
+                self.entries.iter().all(|e| e.strategy_id != strategy.strategy_id),
+                RebalancerErrorCode::RankingBatchAlreadySubmitted
+            );
+            require!(
+                self.entries.len() < self.expected_count as usize,
+                RebalancerErrorCode::TooManyStrategies
+            );
+
+            let entry = RankingEntry::from_strategy(strategy, risk_limits);
+            let candidate = entry.to_strategy_data();
+            // The order is total (ids break the last ties), so this matches a single-transaction sort
+            let position = self.entries.partition_point(|e| {
+                ranking_order(&e.to_strategy_data(), &candidate, risk_limits.tie_break_policy) != std::cmp::Ordering::Greater
+            });
+            self.entries.insert(position, entry);
+        }
+
+        Ok(())
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.expected_count > 0 && self.entries.len() == self.expected_count as usize
+    }
+
+    /// Rankable strategies in merged ranking order, ready for `assign_percentile_ranks`.
+    pub fn ranked_order(&self) -> Vec<StrategyData> {
+        self.entries
+            .iter()
+            .filter(|e| e.rankable)
+            .map(|e| e.to_strategy_data())
+            .collect()
+    }
+
+    /// Assign percentiles from the merged order and store them on the entries.
+    /// Returns the ranked strategies and the underperformers.
+    pub fn finalize(&mut self, base_threshold: u8, risk_limits: &RiskLimits) -> Result<(Vec<StrategyData>, Vec<Pubkey>)> {
+        require!(!self.finalized, RebalancerErrorCode::RankingCycleFinalized);
+        require!(self.is_complete(), RebalancerErrorCode::RankingCycleIncomplete);
+
+        let mut ranking_data = self.ranked_order();
+        let underperformers = assign_percentile_ranks(&mut ranking_data, base_threshold, risk_limits)?;
+
+        for ranked in ranking_data.iter() {
+            if let Some(entry) = self.entries.iter_mut().find(|e| e.strategy_id == ranked.strategy_id) {
+                entry.percentile_rank = ranked.percentile_rank;
+            }
+        }
+        self.finalized = true;
+
+        Ok((ranking_data, underperformers))
+    }
+
+    /// Write this cycle's rank back to `strategy`. The strategy's metrics must
+    /// be the ones it was submitted with; paused and deprecated strategies keep
+    /// their rank but are still marked as handled.
+    pub fn apply_rank(&mut self, strategy: &mut Strategy) -> Result<()> {
+        require!(self.finalized, RebalancerErrorCode::RankingCycleIncomplete);
+
+        let entry = self.entries
+            .iter_mut()
+            .find(|e| e.strategy_id == strategy.strategy_id)
+            .ok_or(RebalancerErrorCode::StrategyNotFound)?;
+        require!(!entry.applied, RebalancerErrorCode::RankingAlreadyApplied);
+        require!(
+            entry.last_updated == strategy.last_updated
+                && entry.current_balance == strategy.current_balance
+                && entry.rankable == (strategy.status == StrategyStatus::Active),
+            RebalancerErrorCode::RankingInputsChanged
+        );
+
+        if entry.rankable {
+            strategy.percentile_rank = entry.percentile_rank;
+        }
+        entry.applied = true;
+        Ok(())
+    }
+
+    pub fn is_fully_applied(&self) -> bool {
+        self.finalized && self.entries.iter().all(|e| e.applied)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+    use crate::instructions::execute_ranking::{calculate_percentile_rankings, rankable_strategies};
+
+    fn strategy(performance_score: u64, current_balance: u64, status: StrategyStatus) -> Strategy {
+        Strategy {
+            performance_score,
+            current_balance,
+            total_deposits: current_balance,
+            last_updated: 100,
+            status,
+            ..test_utils::strategy()
+        }
+    }
+
+    fn session(expected_count: u32) -> RankingSession {
+        RankingSession {
+            portfolio: Pubkey::new_unique(),
+            expected_count,
+            started_at: 100,
+            risk_limits_hash: RankingSession::hash_risk_limits(&RiskLimits::default()).unwrap(),
+            entries: vec![],
+            finalized: false,
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn test_two_batches_rank_like_a_single_cycle() {
+        let strategies = [
+            strategy(4000, 1_000_000_000, StrategyStatus::Active),
+            strategy(9000, 1_000_000_000, StrategyStatus::Active),
+            strategy(6000, 2_000_000_000, StrategyStatus::Active),
+            strategy(6000, 1_000_000_000, StrategyStatus::Active), // Loses the balance tiebreaker
+            strategy(1000, 1_000_000_000, StrategyStatus::Paused),
+            strategy(7000, 1_000_000_000, StrategyStatus::Active),
+        ];
+        let limits = RiskLimits::default();
+
+        let mut chunked = session(strategies.len() as u32);
+        chunked.submit_batch(strategies[..3].iter(), &limits).unwrap();
+        assert!(!chunked.is_complete());
+        chunked.submit_batch(strategies[3..].iter(), &limits).unwrap();
+        assert!(chunked.is_complete());
+
+        let (merged, chunked_underperformers) = chunked.finalize(15, &limits).unwrap();
+
+        let mut single = rankable_strategies(strategies.iter(), &limits);
+        let single_underperformers = calculate_percentile_rankings(&mut single, 15, &limits).unwrap();
+
+        let ranks = |data: &[StrategyData]| data.iter().map(|d| (d.strategy_id, d.percentile_rank)).collect::<Vec<_>>();
+        assert_eq!(ranks(&merged), ranks(&single));
+        assert_eq!(chunked_underperformers, single_underperformers);
+        assert_eq!(merged[0].strategy_id, strategies[1].strategy_id);
+        // The paused strategy was submitted but is not ranked
+        assert_eq!(chunked.entries.len(), 6);
+        assert_eq!(merged.len(), 5);
+    }
+
+    #[test]
+    fn test_ranks_are_written_back_in_batches() {
+        let mut strategies = [
+            strategy(2000, 1_000_000_000, StrategyStatus::Active),
+            strategy(9000, 1_000_000_000, StrategyStatus::Active),
+            strategy(1000, 1_000_000_000, StrategyStatus::Paused),
+        ];
+        let limits = RiskLimits::default();
+
+        let mut chunked = session(3);
+        chunked.submit_batch(strategies.iter(), &limits).unwrap();
+
+        // Nothing can be applied before finalize, and nothing submitted after it
+        assert_eq!(
+            chunked.apply_rank(&mut strategies[0]).unwrap_err(),
+            RebalancerErrorCode::RankingCycleIncomplete.into()
+        );
+        chunked.finalize(15, &limits).unwrap();
+        assert_eq!(
+            chunked.submit_batch(strategies[..1].iter(), &limits).unwrap_err(),
+            RebalancerErrorCode::RankingCycleFinalized.into()
+        );
+
+        let (first, rest) = strategies.split_at_mut(1);
+        chunked.apply_rank(&mut first[0]).unwrap();
+        assert_eq!(first[0].percentile_rank, 0);
+        assert!(!chunked.is_fully_applied());
+        assert_eq!(
+            chunked.apply_rank(&mut first[0]).unwrap_err(),
+            RebalancerErrorCode::RankingAlreadyApplied.into()
+        );
+
+        // A strategy whose metrics moved since submission is not given a stale rank
+        rest[0].last_updated += 1;
+        assert_eq!(
+            chunked.apply_rank(&mut rest[0]).unwrap_err(),
+            RebalancerErrorCode::RankingInputsChanged.into()
+        );
+        rest[0].last_updated -= 1;
+
+        for strategy in rest.iter_mut() {
+            chunked.apply_rank(strategy).unwrap();
+        }
+        assert_eq!(rest[0].percentile_rank, 100);
+        // Paused strategies keep their previous rank
+        assert_eq!(rest[1].percentile_rank, 50);
+        assert!(chunked.is_fully_applied());
+    }
+
+    #[test]
+    fn test_batches_must_use_the_cycle_risk_limits() {
+        let chunked = session(2);
+        assert!(chunked.validate_risk_limits(&RiskLimits::default()).is_ok());
+
+        let changed = RiskLimits { max_single_strategy_bps: 2000, ..RiskLimits::default() };
+        assert_eq!(
+            chunked.validate_risk_limits(&changed).unwrap_err(),
+            RebalancerErrorCode::RankingInputsChanged.into()
+        );
+    }
+
+    #[test]
+    fn test_batch_cannot_be_submitted_twice() {
+        let strategies = [
+            strategy(9000, 1_000_000_000, StrategyStatus::Active),
+            strategy(5000, 1_000_000_000, StrategyStatus::Active),
+            strategy(2000, 1_000_000_000, StrategyStatus::Active),
+        ];
+        let limits = RiskLimits::default();
+
+        let mut chunked = session(3);
+        chunked.submit_batch(strategies[..2].iter(), &limits).unwrap();
+
+        let err = chunked.submit_batch(strategies[..2].iter(), &limits).unwrap_err();
+        assert_eq!(err, RebalancerErrorCode::RankingBatchAlreadySubmitted.into());
+
+        // Overlapping with an earlier batch is rejected as well
+        let err = chunked.submit_batch(strategies[1..].iter(), &limits).unwrap_err();
+        assert_eq!(err, RebalancerErrorCode::RankingBatchAlreadySubmitted.into());
+    }
+
+    #[test]
+    fn test_batch_beyond_snapshot_rejected() {
+        let strategies = [
+            strategy(9000, 1_000_000_000, StrategyStatus::Active),
+            strategy(5000, 1_000_000_000, StrategyStatus::Active),
+            strategy(2000, 1_000_000_000, StrategyStatus::Active),
+        ];
+
+        let mut chunked = session(2);
+        let err = chunked.submit_batch(strategies.iter(), &RiskLimits::default()).unwrap_err();
+        assert_eq!(err, RebalancerErrorCode::TooManyStrategies.into());
+    }
+
+    #[test]
+    fn test_max_size_fits_full_session() {
+        let mut full = session(MAX_RANKING_SESSION_STRATEGIES as u32);
+        full.entries = (0..MAX_RANKING_SESSION_STRATEGIES)
+            .map(|_| RankingEntry::from_strategy(&strategy(5000, 1_000_000_000, StrategyStatus::Active), &RiskLimits::default()))
+            .collect();
+
+        let serialized = full.try_to_vec().unwrap();
+        assert_eq!(RankingSession::DISCRIMINATOR.len() + serialized.len(), RankingSession::MAX_SIZE);
+    }
+}
@@ -0,0 +1,63 @@
+use anchor_lang::prelude::*;
+
+/// Audit entry for one ranking cycle or redistribution.
+///
+/// Records are PDAs seeded by `[b"record", portfolio, sequence]`, where
+/// `sequence` is the portfolio's `rebalance_sequence` at the time of writing,
+/// so the full history can be walked from 0 without an index account.
+#[account]
+#[derive(Debug)]
+pub struct RebalanceRecord {
+    pub portfolio: Pubkey,                  // 32 bytes - Portfolio that rebalanced
+    pub sequence: u64,                      // 8 bytes - Position in the portfolio's rebalance history
+    pub kind: RebalanceKind,                // 1 byte - Which instruction wrote the record
+    pub timestamp: i64,                     // 8 bytes - Unix timestamp of the rebalance
+    pub total_extracted: u64,               // 8 bytes - Capital moved, or flagged for extraction by a ranking cycle (lamports)
+    pub target_count: u32,                  // 4 bytes - Strategies ranked below threshold or targeted
+    pub total_fees: u64,                    // 8 bytes - Fees taken (lamports)
+    pub bump: u8,                           // 1 byte - PDA bump seed
+}
+// Total: 70 bytes + 8 byte discriminator
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum RebalanceKind {
+    RankingCycle,
+    EmergencyRebalance,
+    Redistribution,
+    ScopedRedistribution,
+}
+
+impl RebalanceRecord {
+    pub const MAX_SIZE: usize = 8
+    + 32 // portfolio
+    + 8 // sequence
+    + 1 // kind
+    + 8 // timestamp
+    + 8 // total_extracted
+    + 4 // target_count
+    + 8 // total_fees
+    + 1; // bump
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_max_size_matches_serialized_layout() {
+        let record = RebalanceRecord {
+            portfolio: Pubkey::new_unique(),
+            sequence: u64::MAX,
+            kind: RebalanceKind::ScopedRedistribution,
+            timestamp: i64::MIN,
+            total_extracted: u64::MAX,
+            target_count: u32::MAX,
+            total_fees: u64::MAX,
+            bump: 255,
+        };
+
+        let serialized = record.try_to_vec().unwrap();
+        assert_eq!(serialized.len(), 70);
+        assert_eq!(RebalanceRecord::DISCRIMINATOR.len() + serialized.len(), RebalanceRecord::MAX_SIZE);
+    }
+}
@@ -0,0 +1,141 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+
+use crate::errors::RebalancerErrorCode;
+use crate::state::CapitalAllocation;
+
+/// Progress of one redistribution plan applied over several transactions.
+///
+/// Seeded by `[b"execution", portfolio, execution_id]`. The first
+/// `redistribute_capital` call that passes it binds it to a hash of its
+/// allocation list; later calls must pass the same list and only apply the
+/// allocations whose bit in `executed` is still clear, so resuming after a
+/// partial execution never moves or counts capital twice.
+#[account]
+#[derive(Debug)]
+pub struct RedistributionExecution {
+    pub portfolio: Pubkey,                  // 32 bytes - Portfolio the plan belongs to
+    pub execution_id: u64,                  // 8 bytes - Client-chosen id the PDA is derived from
+    pub allocations_hash: [u8; 32],         // 32 bytes - Hash of the bound allocation list
+    pub allocation_count: u8,               // 1 byte - Allocations in the bound list (0 = unbound)
+    pub executed: u16,                      // 2 bytes - Bitmap of applied allocation indices
+    pub bump: u8,                           // 1 byte - PDA bump seed
+}
+
+impl RedistributionExecution {
+    pub const MAX_SIZE: usize = 8
+    + 32 // portfolio
+    + 8 // execution_id
+    + 32 // allocations_hash
+    + 1 // allocation_count
+    + 2 // executed
+    + 1; // bump
+
+    /// Allocations one execution can track: one bit of `executed` each.
+    pub const MAX_ALLOCATIONS: usize = u16::BITS as usize;
+
+    pub fn hash_allocations(allocations: &[CapitalAllocation]) -> [u8; 32] {
+        hash(&allocations.try_to_vec().unwrap_or_default()).to_bytes()
+    }
+
+    /// Bind an unbound execution to `allocations`, or check that a bound one
+    /// is being resumed with the same list. The list must fit the bitmap.
+    pub fn bind(&mut self, allocations: &[CapitalAllocation]) -> Result<()> {
+        require!(allocations.len() <= Self::MAX_ALLOCATIONS, RebalancerErrorCode::ExecutionTooLarge);
+        let allocations_hash = Self::hash_allocations(allocations);
+
+        if self.allocation_count == 0 {
+            self.allocations_hash = allocations_hash;
+            self.allocation_count = allocations.len() as u8;
+        } else {
+            require!(
+                self.allocations_hash == allocations_hash,
+                RebalancerErrorCode::ExecutionMismatch
+            );
+        }
+        Ok(())
+    }
+
+    pub fn is_executed(&self, index: usize) -> bool {
+        self.executed & (1 << index) != 0
+    }
+
+    pub fn mark_executed(&mut self, index: usize) {
+        self.executed |= 1 << index;
+    }
+
+    /// Every allocation of the bound list has been applied.
+    pub fn is_complete(&self) -> bool {
+        self.allocation_count > 0 && self.executed.count_ones() == self.allocation_count as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::AllocationType;
+
+    fn allocations(count: usize) -> Vec<CapitalAllocation> {
+        (0..count)
+            .map(|i| CapitalAllocation {
+                strategy_id: Pubkey::new_unique(),
+                amount: 1_000_000_000 * (i as u64 + 1),
+                min_acceptable_amount: 0,
+                allocation_type: AllocationType::TopPerformer,
+            })
+            .collect()
+    }
+
+    fn unbound() -> RedistributionExecution {
+        RedistributionExecution {
+            portfolio: Pubkey::new_unique(),
+            execution_id: 1,
+            allocations_hash: [0u8; 32],
+            allocation_count: 0,
+            executed: 0,
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn test_bind_then_resume_with_same_allocations() {
+        let plan = allocations(3);
+        let mut execution = unbound();
+
+        execution.bind(&plan).unwrap();
+        execution.mark_executed(0);
+        execution.mark_executed(2);
+        assert!(!execution.is_complete());
+
+        // A retry with the same list keeps the progress made so far
+        execution.bind(&plan).unwrap();
+        assert!(execution.is_executed(0) && !execution.is_executed(1) && execution.is_executed(2));
+
+        execution.mark_executed(1);
+        assert!(execution.is_complete());
+    }
+
+    #[test]
+    fn test_resume_with_different_allocations_rejected() {
+        let mut execution = unbound();
+        execution.bind(&allocations(2)).unwrap();
+
+        assert_eq!(
+            execution.bind(&allocations(2)).unwrap_err(),
+            RebalancerErrorCode::ExecutionMismatch.into()
+        );
+        assert_eq!(
+            unbound().bind(&allocations(RedistributionExecution::MAX_ALLOCATIONS + 1)).unwrap_err(),
+            RebalancerErrorCode::ExecutionTooLarge.into()
+        );
+    }
+
+    #[test]
+    fn test_max_size_matches_serialized_layout() {
+        let mut execution = unbound();
+        execution.bind(&allocations(2)).unwrap();
+
+        let serialized = execution.try_to_vec().unwrap();
+        assert_eq!(RedistributionExecution::DISCRIMINATOR.len() + serialized.len(), RedistributionExecution::MAX_SIZE);
+    }
+}
@@ -0,0 +1,78 @@
+use anchor_lang::prelude::*;
+
+use crate::instructions::redistribute_capital::RiskLimits;
+
+#[account]
+#[derive(Debug)]
+pub struct RiskConfig {
+    pub portfolio: Pubkey,                  // 32 bytes - Portfolio these limits apply to
+    pub limits: RiskLimits,                 // 266 bytes - Allocation caps, fees, treasuries, protocol weights, minimums, mode, net benefit ratio, group cap, top performer selection, diversity flag, fee grace period, dynamic threshold sensitivity, remainder policy, reallocation cooldown, safe mode, underperformer cutoff, tie-break policy, minimum rebalance capital, protocol targets, perpetual parameters and crisis volatility threshold
+    pub bump: u8,                           // 1 byte - PDA bump seed
+    pub reserved: [u8; 17],                 // 17 bytes - Future expansion
+}
+
+impl RiskConfig {
+    pub const MAX_SIZE: usize = 8
+    + 32 // portfolio
+    + 8 // limits.max_single_strategy_bps
+    + 8 // limits.min_single_strategy_bps
+    + 8 // limits.platform_fee_bps
+    + 8 // limits.manager_fee_bps
+    + 8 // limits.risk_tolerance_bps
+    + 8 // limits.min_extraction_per_strategy
+    + 32 // limits.platform_treasury
+    + 32 // limits.manager_treasury
+    + 4 // limits.stable_lending_weight_bps
+    + 4 // limits.yield_farming_weight_bps
+    + 4 // limits.liquid_staking_weight_bps
+    + 8 // limits.stable_lending_min_lamports
+    + 8 // limits.yield_farming_min_lamports
+    + 8 // limits.liquid_staking_min_lamports
+    + 1 // limits.allocation_mode
+    + 8 // limits.min_net_benefit_bps
+    + 8 // limits.max_group_bps
+    + 1 // limits.top_performer_count
+    + 1 // limits.top_performer_percentile
+    + 1 // limits.require_protocol_diversity
+    + 8 // limits.fee_grace_period
+    + 4 // limits.volatility_weight
+    + 1 // limits.min_threshold
+    + 1 // limits.max_threshold
+    + 1 // limits.remainder_policy
+    + 8 // limits.reallocation_cooldown
+    + 1 // limits.extract_only
+    + 32 // limits.safe_haven
+    + 1 // limits.underperformer_max_percentile
+    + 1 // limits.tie_break_policy
+    + 8 // limits.min_rebalance_capital
+    + 2 // limits.stable_lending_target_bps
+    + 2 // limits.yield_farming_target_bps
+    + 2 // limits.liquid_staking_target_bps
+    + 2 // limits.perpetual_funding_target_bps
+    + 4 // limits.perpetual_funding_weight_bps
+    + 8 // limits.perpetual_funding_min_lamports
+    + 8 // limits.perpetual_taker_fee_bps
+    + 4 // limits.crisis_volatility_threshold
+    + 1 // bump
+    + 17; // reserved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_max_size_matches_serialized_layout() {
+        let config = RiskConfig {
+            portfolio: Pubkey::new_unique(),
+            limits: RiskLimits::default(),
+            bump: 255,
+            reserved: [0u8; 17],
+        };
+
+        // RiskLimits holds no variable-length fields, so every config has the same size
+        assert_eq!(config.limits.try_to_vec().unwrap().len(), 266);
+        let serialized = config.try_to_vec().unwrap();
+        assert_eq!(RiskConfig::DISCRIMINATOR.len() + serialized.len(), RiskConfig::MAX_SIZE);
+    }
+}
@@ -1,25 +1,36 @@
 use anchor_lang::prelude::*;
 use crate::errors::RebalancerErrorCode;
+use crate::state::PositionType;
 
 #[account]
 #[derive(Debug)]
 pub struct Strategy {
     pub strategy_id: Pubkey,                // 32 bytes - Unique strategy identifier
-    pub current_balance: u64,               // 8 bytes - Current capital allocated (lamports)
+    pub current_balance: u64,               // 8 bytes - Current capital allocated (base units of `mint`)
     pub yield_rate: u64,                    // 8 bytes - Annual yield in basis points (0-50000)
     pub performance_score: u64,             // 8 bytes - Calculated composite score
     pub total_deposits: u64,                // 8 bytes - Lifetime deposits tracking
     pub total_withdrawals: u64,             // 8 bytes - Lifetime withdrawals tracking
     pub protocol_type: ProtocolType,        // Variable size - Protocol-specific data
-    pub volatility_score: u32,              // 4 bytes - Risk metric (0-10000, 100.00% max)
+    pub volatility_score: u32,              // 4 bytes - Latest raw risk reading (0-10000, 100.00% max)
     pub last_updated: i64,                  // 8 bytes - Last metric update timestamp
     pub creation_time: i64,                 // 8 bytes - Strategy creation timestamp
     pub status: StrategyStatus,             // 1 byte - Current strategy status
     pub percentile_rank: u8,                // 1 byte - 0-100 ranking position
     pub bump: u8,                           // 1 byte - PDA bump seed
-    pub reserved: [u8; 29],                 // 21 bytes - Future expansion
+    pub max_capacity: u64,                  // 8 bytes - Deposit cap in base units (0 = uncapped)
+    pub volatility_ema: u32,                // 4 bytes - Moving average of volatility_score used for ranking
+    pub mint: Pubkey,                       // 32 bytes - Token the balances are denominated in (wrapped SOL for native)
+    pub decimals: u8,                       // 1 byte - Decimals of `mint`
+    pub verified_balance: u64,              // 8 bytes - Lamports escrowed in the strategy vault at registration (0 = reported only)
+    pub last_extracted: i64,                // 8 bytes - Unix timestamp capital was last extracted (0 = never)
+    pub yield_history: [u16; YIELD_HISTORY_LEN], // 16 bytes - Ring buffer of recent yield readings (bps, clamped)
+    pub yield_history_head: u8,             // 1 byte - Slot the next reading is written to
+    pub yield_history_len: u8,              // 1 byte - Readings held (saturates at YIELD_HISTORY_LEN)
+    pub layout_version: u8,                 // 1 byte - Account layout written (0 = legacy, see migrate_strategy)
+    pub reserved: [u8; 8],                  // 8 bytes - Future expansion
 }
-// Total: ~144 bytes + protocol_type size
+// Total: 183 bytes + protocol_type size (up to 100) + 8 byte discriminator
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
 pub enum ProtocolType {
@@ -41,8 +52,56 @@ pub enum ProtocolType {
         unstake_delay: u32,                 // 4 bytes - Unstaking delay in epochs
         commission: u16,                    // 2 bytes - Validator commission (basis points)
     },  // 70 bytes total
+    PerpetualFunding {
+        market_id: Pubkey,                  // 32 bytes - Perpetual market identifier
+        funding_rate_bps: i32,              // 4 bytes - Current funding rate (negative when shorts pay)
+        max_leverage: u8,                   // 1 byte - Leverage ceiling for the hedge (1-20x)
+    },  // 37 bytes total
 }
 
+// Default volatility (basis points) above which a strategy justifies an emergency rebalance
+pub const CRISIS_VOLATILITY_THRESHOLD: u32 = 9000;
+
+// Balance every extraction leaves behind in a native SOL strategy (lamports)
+pub const STRATEGY_RENT_RESERVE: u64 = 10_000_000; // 0.01 SOL
+
+// Yield readings kept for trend analysis, and the average move (basis points)
+// between the older and newer half of them that counts as a trend
+pub const YIELD_HISTORY_LEN: usize = 8;
+pub const YIELD_TREND_TOLERANCE_BPS: u64 = 25;
+
+// Strategies registered without a mint hold native SOL, tracked as wrapped SOL
+pub const WRAPPED_SOL_MINT: Pubkey = pubkey!("So11111111111111111111111111111111111111112");
+pub const SOL_DECIMALS: u8 = 9;
+
+// Current strategy account layout. Strategies written before the layout was
+// versioned are 232 bytes and are brought up to date by migrate_strategy
+pub const STRATEGY_LAYOUT_VERSION: u8 = 1;
+pub const LEGACY_STRATEGY_SIZE: usize = 232;
+
+// Default minimum allocation per protocol (lamports; scaled for other mints by `scale_to_decimals`)
+pub const STABLE_LENDING_MIN_LAMPORTS: u64 = 100_000_000;    // 0.1 SOL
+pub const YIELD_FARMING_MIN_LAMPORTS: u64 = 500_000_000;     // 0.5 SOL - gas + slippage
+pub const LIQUID_STAKING_MIN_LAMPORTS: u64 = 1_000_000_000;  // 1 SOL - epoch requirements
+pub const PERPETUAL_FUNDING_MIN_LAMPORTS: u64 = 500_000_000; // 0.5 SOL - margin buffer against liquidation
+
+// Default protocol risk weights (basis points)
+pub const STABLE_LENDING_WEIGHT_BPS: u32 = 10000;  // 100% - baseline
+pub const LIQUID_STAKING_WEIGHT_BPS: u32 = 9500;   // 95% - validator and depeg risk
+pub const YIELD_FARMING_WEIGHT_BPS: u32 = 8500;    // 85% - impermanent loss and leverage risk
+pub const PERPETUAL_FUNDING_WEIGHT_BPS: u32 = 8000; // 80% - funding flips and liquidation risk
+
+// Highest plausible yield per protocol (basis points); anything above is treated as bad data
+pub const STABLE_LENDING_MAX_YIELD_BPS: u64 = 2000;   // 20% APY
+pub const LIQUID_STAKING_MAX_YIELD_BPS: u64 = 1500;   // 15% APY
+pub const YIELD_FARMING_MAX_YIELD_BPS: u64 = 50000;   // 500% APY - matches the global cap
+pub const PERPETUAL_FUNDING_MAX_YIELD_BPS: u64 = 10000; // 100% APY
+
+// Perpetual funding configuration bounds
+pub const MAX_PERPETUAL_LEVERAGE: u8 = 20;
+pub const MAX_FUNDING_RATE_BPS: i32 = 10000; // +/-100% either direction
+pub const PERPETUAL_TAKER_FEE_BPS: u64 = 5;  // Default fee for closing a position
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
 pub enum StrategyStatus {
     Active,      // Normal operation, participates in rebalancing
@@ -50,6 +109,62 @@ pub enum StrategyStatus {
     Deprecated,  // Marked for removal, extract capital when possible
 }
 
+/// How quickly volatility erodes a strategy's allocation multiplier, from
+/// `ProtocolType::risk_curve`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RiskCurve {
+    Linear,      // Penalty proportional to volatility
+    Steep,       // Penalty grows quickly; volatility signals real loss risk
+    Lenient,     // Penalty stays small until volatility is high
+}
+
+impl RiskCurve {
+    /// Map a volatility score (basis points, capped at 10000) to the share of
+    /// the multiplier range the strategy keeps: 10000 at zero volatility, 0 at
+    /// 100% on every curve.
+    pub fn inverse_volatility(&self, volatility_score: u32) -> u32 {
+        let volatility = volatility_score.min(10000) as u64;
+        let inverse = 10000 - volatility;
+        let shaped = match self {
+            RiskCurve::Linear => inverse,
+            RiskCurve::Steep => inverse * inverse / 10000,
+            RiskCurve::Lenient => 10000 - volatility * volatility / 10000,
+        };
+        shaped as u32
+    }
+}
+
+/// Which tiebreaker decides between strategies with the same weighted score
+/// when ranking. The strategy id always breaks any tie left after both.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum TieBreakPolicy {
+    #[default]
+    BalanceFirst,       // Higher balance wins, then lower volatility
+    VolatilityFirst,    // Lower volatility wins, then higher balance
+}
+
+/// Direction of a strategy's recent yields, from `Strategy::yield_trend`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum YieldTrend {
+    Rising,
+    Falling,
+    Stable,      // Within tolerance, or too few readings to tell
+}
+
+impl StrategyStatus {
+    /// Legal transitions: Active <-> Paused, and Active/Paused -> Deprecated.
+    /// Deprecated is terminal.
+    pub fn can_transition_to(&self, next: StrategyStatus) -> bool {
+        matches!(
+            (self, next),
+            (StrategyStatus::Active, StrategyStatus::Paused)
+                | (StrategyStatus::Paused, StrategyStatus::Active)
+                | (StrategyStatus::Active, StrategyStatus::Deprecated)
+                | (StrategyStatus::Paused, StrategyStatus::Deprecated)
+        )
+    }
+}
+
 impl Strategy {
     pub const MAX_SIZE: usize = 8 
     + 32 // strategy_id
@@ -58,15 +173,25 @@ impl Strategy {
     + 8 // performance_score
     + 8 // total_deposits
     + 8 // total_withdrawals
-    + 100 // protocol_type
+    + 100 // protocol_type (1 byte tag + largest variant, YieldFarming at 99 bytes)
     + 4 // volatility_score
     + 8 // last_updated
     + 8 // creation_time
     + 1 // status
     + 1 // percentile_rank
     + 1 // bump
-    + 29; // reserved
-    // 232 bytes
+    + 8 // max_capacity
+    + 4 // volatility_ema
+    + 32 // mint
+    + 1 // decimals
+    + 8 // verified_balance
+    + 8 // last_extracted
+    + 2 * YIELD_HISTORY_LEN // yield_history
+    + 1 // yield_history_head
+    + 1 // yield_history_len
+    + 1 // layout_version
+    + 8; // reserved
+    // 291 bytes
     
     pub fn validate_yield_rate(rate: u64) -> Result<()> {
         require!(rate <= 50000, RebalancerErrorCode::ExcessiveYieldRate);
@@ -78,10 +203,101 @@ impl Strategy {
         Ok(())
     }
     
+    /// Reject a balance above the strategy's deposit cap. Zero means uncapped,
+    /// matching `Portfolio::max_capital`.
+    pub fn validate_capacity(&self, balance: u64) -> Result<()> {
+        require!(
+            self.max_capacity == 0 || balance <= self.max_capacity,
+            RebalancerErrorCode::StrategyAtCapacity
+        );
+        Ok(())
+    }
+    
+    /// Blend a new volatility reading into the moving average. `smoothing_bps`
+    /// is the weight given to the new reading: 10000 follows it exactly, lower
+    /// values damp single spikes.
+    pub fn smoothed_volatility(previous_ema: u32, reading: u32, smoothing_bps: u16) -> u32 {
+        let weight = smoothing_bps.min(10000) as u64;
+        ((reading as u64 * weight + previous_ema as u64 * (10000 - weight)) / 10000) as u32
+    }
+    
     pub fn validate_volatility_score(score: u32) -> Result<()> {
         require!(score <= 10000, RebalancerErrorCode::InvalidVolatilityScore);
         Ok(())
     }
+    
+    /// Lifetime profit: what the strategy holds or has paid out beyond what
+    /// was put in. Deposits, withdrawals and redistributions leave it unchanged.
+    pub fn net_profit(&self) -> i128 {
+        self.current_balance as i128 + self.total_withdrawals as i128 - self.total_deposits as i128
+    }
+    
+    /// Lifetime return on deposits in basis points; negative once the strategy
+    /// has lost money. A strategy with no recorded deposits is treated as flat.
+    pub fn net_return_bps(&self) -> i64 {
+        if self.total_deposits == 0 {
+            return 0;
+        }
+        let bps = self.net_profit().saturating_mul(10000) / self.total_deposits as i128;
+        bps.clamp(i64::MIN as i128, i64::MAX as i128) as i64
+    }
+    
+    /// Append a yield reading to the history, overwriting the oldest once the
+    /// buffer is full. Readings above `u16::MAX` are clamped.
+    pub fn record_yield(&mut self, yield_rate: u64) {
+        let head = self.yield_history_head as usize % YIELD_HISTORY_LEN;
+        self.yield_history[head] = yield_rate.min(u16::MAX as u64) as u16;
+        self.yield_history_head = ((head + 1) % YIELD_HISTORY_LEN) as u8;
+        self.yield_history_len = (self.yield_history_len as usize + 1).min(YIELD_HISTORY_LEN) as u8;
+    }
+    
+    /// Recorded yields, oldest first.
+    pub fn yield_samples(&self) -> Vec<u16> {
+        let len = (self.yield_history_len as usize).min(YIELD_HISTORY_LEN);
+        let start = (self.yield_history_head as usize + YIELD_HISTORY_LEN - len) % YIELD_HISTORY_LEN;
+        (0..len)
+            .map(|i| self.yield_history[(start + i) % YIELD_HISTORY_LEN])
+            .collect()
+    }
+    
+    /// Compare the average of the older half of the recorded yields with the
+    /// newer half. Needs at least four readings; a move of no more than
+    /// `YIELD_TREND_TOLERANCE_BPS` is treated as stable.
+    pub fn yield_trend(&self) -> YieldTrend {
+        let samples = self.yield_samples();
+        if samples.len() < 4 {
+            return YieldTrend::Stable;
+        }
+        let half = samples.len() / 2;
+        let average = |slice: &[u16]| slice.iter().map(|&y| y as u64).sum::<u64>() / slice.len() as u64;
+        let older = average(&samples[..half]);
+        let newer = average(&samples[samples.len() - half..]);
+        
+        if newer > older.saturating_add(YIELD_TREND_TOLERANCE_BPS) {
+            YieldTrend::Rising
+        } else if older > newer.saturating_add(YIELD_TREND_TOLERANCE_BPS) {
+            YieldTrend::Falling
+        } else {
+            YieldTrend::Stable
+        }
+    }
+    
+    /// Fields appended after the legacy layout are only meaningful on an
+    /// account written (or migrated) in the current layout.
+    pub fn validate_layout(&self) -> Result<()> {
+        require!(self.layout_version == STRATEGY_LAYOUT_VERSION, RebalancerErrorCode::UnsupportedLayoutVersion);
+        Ok(())
+    }
+    
+    /// Balances of native SOL strategies are lamports, the unit portfolio
+    /// totals are kept in.
+    pub fn is_native_sol(&self) -> bool {
+        self.mint == WRAPPED_SOL_MINT
+    }
+    
+    pub fn is_in_crisis(&self, crisis_volatility_threshold: u32) -> bool {
+        self.status == StrategyStatus::Active && self.volatility_score > crisis_volatility_threshold
+    }
 }
 
 impl ProtocolType {
@@ -113,6 +329,19 @@ impl ProtocolType {
                 require!(*unstake_delay <= 50, RebalancerErrorCode::InvalidUnstakeDelay);
                 Ok(())
             },
+            ProtocolType::PerpetualFunding { market_id, funding_rate_bps, max_leverage } => {
+                require!(*market_id != Pubkey::default(), RebalancerErrorCode::InvalidMarketId);
+                // Negative funding is legitimate; only the magnitude is bounded
+                require!(
+                    funding_rate_bps.unsigned_abs() <= MAX_FUNDING_RATE_BPS as u32,
+                    RebalancerErrorCode::InvalidFundingRate
+                );
+                require!(
+                    (1..=MAX_PERPETUAL_LEVERAGE).contains(max_leverage),
+                    RebalancerErrorCode::InvalidLeverage
+                );
+                Ok(())
+            },
         }
     }
     
@@ -121,6 +350,42 @@ impl ProtocolType {
             ProtocolType::StableLending { .. } => "Stable Lending",
             ProtocolType::YieldFarming { .. } => "Yield Farming",
             ProtocolType::LiquidStaking { .. } => "Liquid Staking",
+            ProtocolType::PerpetualFunding { .. } => "Perpetual Funding",
+        }
+    }
+
+    /// Default risk weight (basis points) applied to performance scores when
+    /// ranking, so riskier protocols need a higher raw score to rank equally.
+    /// Managers can override these through `RiskConfig`.
+    pub fn protocol_weight(&self) -> u32 {
+        match self {
+            ProtocolType::StableLending { .. } => STABLE_LENDING_WEIGHT_BPS,
+            ProtocolType::YieldFarming { .. } => YIELD_FARMING_WEIGHT_BPS,
+            ProtocolType::LiquidStaking { .. } => LIQUID_STAKING_WEIGHT_BPS,
+            ProtocolType::PerpetualFunding { .. } => PERPETUAL_FUNDING_WEIGHT_BPS,
+        }
+    }
+
+    /// Volatility-to-multiplier curve for allocation sizing. Farming and
+    /// perpetual volatility comes with impermanent loss or liquidation risk, so
+    /// it is penalised steeply; staking volatility mostly tracks the staked
+    /// asset's price and is penalised lightly.
+    pub fn risk_curve(&self) -> RiskCurve {
+        match self {
+            ProtocolType::StableLending { .. } => RiskCurve::Linear,
+            ProtocolType::YieldFarming { .. } => RiskCurve::Steep,
+            ProtocolType::LiquidStaking { .. } => RiskCurve::Lenient,
+            ProtocolType::PerpetualFunding { .. } => RiskCurve::Steep,
+        }
+    }
+
+    pub fn get_position_type(&self) -> PositionType {
+        match self {
+            ProtocolType::StableLending { .. } => PositionType::SingleAsset,
+            ProtocolType::YieldFarming { .. } => PositionType::LiquidityPair,
+            ProtocolType::LiquidStaking { .. } => PositionType::StakedPosition,
+            // Delta-neutral: collateral is held in one asset, the hedge lives on the market
+            ProtocolType::PerpetualFunding { .. } => PositionType::SingleAsset,
         }
     }
 
@@ -135,24 +400,264 @@ impl ProtocolType {
             ProtocolType::LiquidStaking { stake_pool, .. } => {
                 vec![*stake_pool]
             },
+            ProtocolType::PerpetualFunding { market_id, .. } => {
+                vec![*market_id]
+            },
         }
     }
     
-    pub fn validate_balance_constraints(&self, balance: u64) -> Result<()> {
+    /// Venue shared by strategies that move together: the lending pool, the
+    /// farming pair, the staking validator or the perpetual market. Strategies
+    /// with the same key are one bet for diversification purposes.
+    pub fn correlation_key(&self) -> Pubkey {
         match self {
-            ProtocolType::StableLending { .. } => {
-                // Minimum 0.1 SOL for lending protocols
-                require!(balance >= 100_000_000, RebalancerErrorCode::InsufficientBalance);
-            },
-            ProtocolType::YieldFarming { .. } => {
-                // Minimum 0.5 SOL for LP positions (gas + slippage)
-                require!(balance >= 500_000_000, RebalancerErrorCode::InsufficientBalance);
-            },
-            ProtocolType::LiquidStaking { .. } => {
-                // Minimum 1 SOL for staking (epoch requirements)
-                require!(balance >= 1_000_000_000, RebalancerErrorCode::InsufficientBalance);
-            },
+            ProtocolType::StableLending { pool_id, .. } => *pool_id,
+            ProtocolType::YieldFarming { pair_id, .. } => *pair_id,
+            ProtocolType::LiquidStaking { validator_id, .. } => *validator_id,
+            ProtocolType::PerpetualFunding { market_id, .. } => *market_id,
+        }
+    }
+    
+    /// Highest yield (basis points) considered plausible for this protocol.
+    /// Reported rates above it indicate a misconfiguration or oracle error.
+    pub fn max_reasonable_yield_bps(&self) -> u64 {
+        match self {
+            ProtocolType::StableLending { .. } => STABLE_LENDING_MAX_YIELD_BPS,
+            ProtocolType::YieldFarming { .. } => YIELD_FARMING_MAX_YIELD_BPS,
+            ProtocolType::LiquidStaking { .. } => LIQUID_STAKING_MAX_YIELD_BPS,
+            ProtocolType::PerpetualFunding { .. } => PERPETUAL_FUNDING_MAX_YIELD_BPS,
         }
+    }
+
+    pub fn validate_yield_for_protocol(&self, rate: u64) -> Result<()> {
+        require!(rate <= self.max_reasonable_yield_bps(), RebalancerErrorCode::UnreasonableYieldForProtocol);
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// Convert an amount expressed with SOL's 9 decimals into base units of a
+/// mint with `decimals` decimals, so 0.1 SOL of minimum becomes 0.1 USDC
+/// (100_000) for a 6-decimal mint. Rounds down; saturates for very large
+/// decimal counts.
+pub fn scale_to_decimals(lamports: u64, decimals: u8) -> u64 {
+    if decimals >= SOL_DECIMALS {
+        lamports.saturating_mul(10u64.saturating_pow((decimals - SOL_DECIMALS) as u32))
+    } else {
+        lamports / 10u64.pow((SOL_DECIMALS - decimals) as u32)
+    }
+}
+
+/// Capital that can be pulled out of a strategy holding `current_balance` of
+/// `mint`. Native SOL strategies keep `STRATEGY_RENT_RESERVE` in place; the
+/// reserve is lamports and means nothing to balances of other mints.
+pub fn extractable_balance(current_balance: u64, mint: &Pubkey) -> u64 {
+    if *mint == WRAPPED_SOL_MINT {
+        current_balance.saturating_sub(STRATEGY_RENT_RESERVE)
+    } else {
+        current_balance
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+    use crate::instructions::redistribute_capital::RiskLimits;
+
+    fn perpetual(funding_rate_bps: i32, max_leverage: u8) -> ProtocolType {
+        ProtocolType::PerpetualFunding {
+            market_id: Pubkey::new_unique(),
+            funding_rate_bps,
+            max_leverage,
+        }
+    }
+
+    #[test]
+    fn test_valid_perpetual_configs() {
+        // Positive, zero and negative funding are all legitimate
+        for funding_rate_bps in [1200, 0, -850, MAX_FUNDING_RATE_BPS, -MAX_FUNDING_RATE_BPS] {
+            assert!(perpetual(funding_rate_bps, 3).validate().is_ok());
+        }
+        assert!(perpetual(100, 1).validate().is_ok());
+        assert!(perpetual(100, MAX_PERPETUAL_LEVERAGE).validate().is_ok());
+
+        let protocol = perpetual(-300, 5);
+        assert_eq!(protocol.get_protocol_name(), "Perpetual Funding");
+        assert_eq!(protocol.get_expected_tokens().len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_perpetual_configs() {
+        let default_market = ProtocolType::PerpetualFunding {
+            market_id: Pubkey::default(),
+            funding_rate_bps: 100,
+            max_leverage: 3,
+        };
+        assert_eq!(default_market.validate().unwrap_err(), RebalancerErrorCode::InvalidMarketId.into());
+
+        for funding_rate_bps in [MAX_FUNDING_RATE_BPS + 1, -MAX_FUNDING_RATE_BPS - 1, i32::MIN] {
+            assert_eq!(
+                perpetual(funding_rate_bps, 3).validate().unwrap_err(),
+                RebalancerErrorCode::InvalidFundingRate.into()
+            );
+        }
+
+        for max_leverage in [0, MAX_PERPETUAL_LEVERAGE + 1] {
+            assert_eq!(
+                perpetual(100, max_leverage).validate().unwrap_err(),
+                RebalancerErrorCode::InvalidLeverage.into()
+            );
+        }
+    }
+
+    #[test]
+    fn test_usdc_minimums_scale_with_decimals() {
+        const USDC_DECIMALS: u8 = 6;
+        let lending = ProtocolType::StableLending {
+            pool_id: Pubkey::new_unique(),
+            reserve_address: Pubkey::new_unique(),
+            utilization: 7500,
+        };
+
+        // 0.1 SOL of minimum is 0.1 USDC, not 100 USDC
+        assert_eq!(scale_to_decimals(STABLE_LENDING_MIN_LAMPORTS, USDC_DECIMALS), 100_000);
+        let limits = RiskLimits::default();
+        assert!(limits.validate_balance_constraints(&lending, 100_000, USDC_DECIMALS).is_ok());
+        assert_eq!(
+            limits.validate_balance_constraints(&lending, 99_999, USDC_DECIMALS).unwrap_err(),
+            RebalancerErrorCode::InsufficientBalance.into()
+        );
+        // The same raw amount is far below the minimum for a 9-decimal mint
+        assert!(limits.validate_balance_constraints(&lending, 100_000, SOL_DECIMALS).is_err());
+
+        assert_eq!(scale_to_decimals(STABLE_LENDING_MIN_LAMPORTS, SOL_DECIMALS), STABLE_LENDING_MIN_LAMPORTS);
+        assert_eq!(scale_to_decimals(STABLE_LENDING_MIN_LAMPORTS, 18), STABLE_LENDING_MIN_LAMPORTS * 1_000_000_000);
+        assert_eq!(scale_to_decimals(STABLE_LENDING_MIN_LAMPORTS, u8::MAX), u64::MAX);
+    }
+
+    fn sample_strategy() -> Strategy {
+        Strategy {
+            current_balance: 0,
+            yield_rate: 0,
+            performance_score: 0,
+            total_deposits: 0,
+            volatility_score: 0,
+            percentile_rank: 0,
+            volatility_ema: 0,
+            ..test_utils::strategy()
+        }
+    }
+
+    #[test]
+    fn test_net_return_bps() {
+        let strategy = |total_deposits, total_withdrawals, current_balance| Strategy {
+            current_balance,
+            total_deposits,
+            total_withdrawals,
+            ..sample_strategy()
+        };
+
+        assert_eq!(strategy(1_000_000_000, 0, 900_000_000).net_return_bps(), -1000);
+        assert_eq!(strategy(1_000_000_000, 0, 1_000_000_000).net_return_bps(), 0);
+        // Withdrawals count toward the return
+        assert_eq!(strategy(1_000_000_000, 200_000_000, 850_000_000).net_return_bps(), 500);
+        // No deposits recorded: flat rather than a division by zero
+        assert_eq!(strategy(0, 0, 1_000_000_000).net_return_bps(), 0);
+        assert_eq!(strategy(1, 0, u64::MAX).net_return_bps(), i64::MAX);
+    }
+
+    #[test]
+    fn test_yield_history_wraps_around() {
+        let mut strategy = sample_strategy();
+        assert!(strategy.yield_samples().is_empty());
+
+        for yield_rate in 1..=3 {
+            strategy.record_yield(yield_rate * 100);
+        }
+        assert_eq!(strategy.yield_samples(), vec![100, 200, 300]);
+
+        // Ten readings into eight slots: the two oldest are overwritten
+        for yield_rate in 4..=10 {
+            strategy.record_yield(yield_rate * 100);
+        }
+        assert_eq!(strategy.yield_history_len as usize, YIELD_HISTORY_LEN);
+        assert_eq!(strategy.yield_history_head, 2);
+        assert_eq!(strategy.yield_samples(), vec![300, 400, 500, 600, 700, 800, 900, 1000]);
+
+        // Out-of-range readings are clamped rather than truncated
+        strategy.record_yield(u64::MAX);
+        assert_eq!(*strategy.yield_samples().last().unwrap(), u16::MAX);
+    }
+
+    #[test]
+    fn test_yield_trend_detection() {
+        let with_yields = |yields: &[u64]| {
+            let mut strategy = sample_strategy();
+            yields.iter().for_each(|&y| strategy.record_yield(y));
+            strategy
+        };
+
+        assert_eq!(with_yields(&[800, 850, 900, 1000, 1100, 1200]).yield_trend(), YieldTrend::Rising);
+        assert_eq!(with_yields(&[1200, 1100, 1000, 900]).yield_trend(), YieldTrend::Falling);
+        // Small wobbles stay within tolerance
+        assert_eq!(with_yields(&[1000, 1010, 990, 1020]).yield_trend(), YieldTrend::Stable);
+        // Too few readings to call a direction
+        assert_eq!(with_yields(&[100, 5000, 9000]).yield_trend(), YieldTrend::Stable);
+
+        // Only the readings still in the buffer count: an old decline followed
+        // by a full window of growth reads as rising
+        let mut strategy = with_yields(&[5000, 4000, 3000, 2000]);
+        for yield_rate in [500, 600, 700, 800, 900, 1000, 1100, 1200] {
+            strategy.record_yield(yield_rate);
+        }
+        assert_eq!(strategy.yield_trend(), YieldTrend::Rising);
+    }
+
+    #[test]
+    fn test_max_size_covers_largest_protocol_type() {
+        let farming = Strategy {
+            protocol_type: ProtocolType::YieldFarming {
+                pair_id: Pubkey::new_unique(),
+                token_a_mint: Pubkey::new_unique(),
+                token_b_mint: Pubkey::new_unique(),
+                fee_tier: 1000,
+                reward_multiplier: 10,
+            },
+            ..sample_strategy()
+        };
+
+        // YieldFarming is the largest variant, so it fills MAX_SIZE exactly
+        let serialized = farming.try_to_vec().unwrap();
+        assert_eq!(serialized.len(), 283);
+        assert_eq!(Strategy::DISCRIMINATOR.len() + serialized.len(), Strategy::MAX_SIZE);
+
+        for protocol_type in [sample_strategy().protocol_type, perpetual(100, 3)] {
+            let strategy = Strategy { protocol_type, ..sample_strategy() };
+            assert!(Strategy::DISCRIMINATOR.len() + strategy.try_to_vec().unwrap().len() < Strategy::MAX_SIZE);
+        }
+    }
+
+    #[test]
+    fn test_layout_version_must_be_current() {
+        assert!(sample_strategy().validate_layout().is_ok());
+        
+        let legacy = Strategy { layout_version: 0, ..sample_strategy() };
+        assert_eq!(legacy.validate_layout().unwrap_err(), RebalancerErrorCode::UnsupportedLayoutVersion.into());
+    }
+
+    #[test]
+    fn test_reserved_bytes_close_the_layout() {
+        let strategy = Strategy {
+            yield_history: [u16::MAX; YIELD_HISTORY_LEN],
+            yield_history_head: 0xAA,
+            yield_history_len: 0xBB,
+            layout_version: 0xDD,
+            reserved: [0xCC; 8],
+            ..sample_strategy()
+        };
+
+        // New fields are carved from the front of `reserved`, so nothing may follow it
+        let serialized = strategy.try_to_vec().unwrap();
+        let tail = &serialized[serialized.len() - 12..];
+        assert_eq!(tail, &[0xFF, 0xAA, 0xBB, 0xDD, 0xCC, 0xCC, 0xCC, 0xCC, 0xCC, 0xCC, 0xCC, 0xCC]);
+    }
+}
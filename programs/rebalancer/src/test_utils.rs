@@ -0,0 +1,42 @@
+use anchor_lang::prelude::*;
+use crate::state::{
+    ProtocolType, Strategy, StrategyStatus, SOL_DECIMALS, STRATEGY_LAYOUT_VERSION, WRAPPED_SOL_MINT,
+    YIELD_HISTORY_LEN,
+};
+
+/// Active 1 SOL stable lending strategy with mid-range scores.
+///
+/// Tests override the fields they exercise with struct update syntax, so a new
+/// `Strategy` field only needs a default here.
+pub fn strategy() -> Strategy {
+    Strategy {
+        strategy_id: Pubkey::new_unique(),
+        current_balance: 1_000_000_000,
+        yield_rate: 1000,
+        performance_score: 5000,
+        total_deposits: 1_000_000_000,
+        total_withdrawals: 0,
+        protocol_type: ProtocolType::StableLending {
+            pool_id: Pubkey::new_unique(),
+            reserve_address: Pubkey::new_unique(),
+            utilization: 7500,
+        },
+        volatility_score: 3000,
+        last_updated: 0,
+        creation_time: 0,
+        status: StrategyStatus::Active,
+        percentile_rank: 50,
+        bump: 255,
+        max_capacity: 0,
+        volatility_ema: 3000,
+        mint: WRAPPED_SOL_MINT,
+        decimals: SOL_DECIMALS,
+        verified_balance: 0,
+        yield_history: [0; YIELD_HISTORY_LEN],
+        yield_history_head: 0,
+        yield_history_len: 0,
+        last_extracted: 0,
+        layout_version: STRATEGY_LAYOUT_VERSION,
+        reserved: [0u8; 8],
+    }
+}
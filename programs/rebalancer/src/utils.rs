@@ -1,6 +1,23 @@
 use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
 use crate::errors::RebalancerErrorCode;
 use crate::instructions::execute_ranking::StrategyData;
+use crate::instructions::redistribute_capital::RiskLimits;
+use crate::state::{Portfolio, RebalanceKind, RebalanceRecord, Strategy, StrategyAllocationLog};
+
+/// Log the remaining compute units, tagged with `$label`, when built with the
+/// `trace` feature. Expands to nothing otherwise, so production builds carry
+/// neither the syscall nor the label string.
+macro_rules! trace_compute_units {
+    ($label:expr) => {
+        #[cfg(feature = "trace")]
+        {
+            anchor_lang::prelude::msg!($label);
+            anchor_lang::solana_program::log::sol_log_compute_units();
+        }
+    };
+}
+pub(crate) use trace_compute_units;
 
 /// Calculate the average volatility across all strategies
 /// 
@@ -38,48 +55,314 @@ pub fn calculate_average_volatility(strategies: &[StrategyData]) -> Result<u32>
 /// 
 /// This function implements the dynamic threshold formula:
 /// Dynamic Threshold = Base Threshold + Volatility Adjustment
-/// where Volatility Adjustment = (Average Volatility / 100) × 20%
+/// where Volatility Adjustment = (Average Volatility / 100) × volatility_weight%
 /// 
-/// The final threshold is clamped to the range 10% - 40%.
+/// The final threshold is clamped to `min_threshold` - `max_threshold`. With the
+/// default risk limits the weight is 20 and the range is 10% - 40%.
 /// 
 /// # Arguments
 /// * `base_threshold` - The base threshold percentage (e.g., 15 for 15%)
 /// * `average_volatility` - The average volatility score across strategies
+/// * `risk_limits` - Supplies `volatility_weight`, `min_threshold` and `max_threshold`
 /// 
 /// # Returns
-/// * `Result<u8>` - The dynamic threshold percentage (10-40), or an error if:
+/// * `Result<u8>` - The dynamic threshold percentage within the clamp, or an error if:
 ///   - Base threshold is invalid (> 100)
 ///   - Mathematical overflow occurs
 /// 
 /// # Formula
 /// Dynamic Threshold = Base Threshold + ((Average Volatility / 100) × 20)
-/// Final Range: 10% minimum to 40% maximum
+/// Final Range: 10% minimum to 40% maximum (defaults)
 /// 
 /// # Example
-/// If base_threshold = 15 and average_volatility = 3000:
+/// If base_threshold = 15 and average_volatility = 3000, with default limits:
 /// Volatility Adjustment = (3000 / 100) × 20 = 30 × 20 = 600
 /// Dynamic Threshold = 15 + 6 = 21%
 /// Final Threshold = 21% (within 10-40% range)
 pub fn calculate_dynamic_threshold(
     base_threshold: u8, 
-    average_volatility: u32
+    average_volatility: u32,
+    risk_limits: &RiskLimits,
 ) -> Result<u8> {
     // Base validation
     require!(base_threshold <= 100, RebalancerErrorCode::InvalidRebalanceThreshold);
     
-    // Calculate volatility adjustment: (Average Volatility / 100) × 20%
+    // Calculate volatility adjustment: (Average Volatility / 100) × volatility_weight%
     // Note: average_volatility is expressed in basis points (0-10000 for 0-100%).
-    // Therefore: adjustment = (average_volatility * 20) / 10000 → integer percent points
+    // Therefore: adjustment = (average_volatility * weight) / 10000 → integer percent points
     // Use u64 for intermediate calculations to prevent overflow
-    let volatility_adjustment = (average_volatility as u64 * 20) / 10_000;
+    let volatility_adjustment = (average_volatility as u64 * risk_limits.volatility_weight as u64) / 10_000;
     
     // Calculate dynamic threshold: Base + Volatility Adjustment (both in percent points)
     let dynamic_threshold = (base_threshold as u64)
         .checked_add(volatility_adjustment)
-        .ok_or(RebalancerErrorCode::BalanceOverflow)? as u8;
+        .ok_or(RebalancerErrorCode::BalanceOverflow)?;
+    
+    // Clamp to the configured range before narrowing, so large adjustments cannot wrap.
+    // max/min rather than clamp: unvalidated limits must not panic.
+    Ok(dynamic_threshold
+        .max(risk_limits.min_threshold as u64)
+        .min(risk_limits.max_threshold as u64) as u8)
+}
+
+// Performance score weights (basis points, sum to 10000)
+pub const SCORE_YIELD_WEIGHT_BPS: u64 = 6000;      // 60% - normalized yield
+pub const SCORE_STABILITY_WEIGHT_BPS: u64 = 3500;  // 35% - inverse volatility
+pub const SCORE_MATURITY_WEIGHT_BPS: u64 = 500;    // 5% - strategy age bonus
+pub const SCORE_MATURITY_PERIOD: i64 = 90 * 86400; // Age at which the maturity bonus is fully earned
+const SCORE_MAX_YIELD_BPS: u64 = 50000;            // Yield that normalizes to a full yield component
+
+/// Compute a strategy's performance score (0-10000) from its on-chain metrics
+/// 
+/// The score is derived entirely from validated inputs, so a manager cannot
+/// push a strategy up the rankings by supplying a score directly.
+/// 
+/// # Weighting
+/// * Yield (60%): `yield_rate` normalized from 0-50000 bps to 0-10000
+/// * Stability (35%): `10000 - volatility_score`, so volatility is penalized
+/// * Maturity (5%): ramps linearly from 0 at creation to 10000 after 90 days
+/// 
+/// Inputs above their range are clamped and a negative age earns no bonus.
+/// 
+/// # Example
+/// yield_rate = 15000, volatility_score = 2000, age_seconds = 45 days:
+/// (3000 × 60%) + (8000 × 35%) + (5000 × 5%) = 1800 + 2800 + 250 = 4850
+pub fn compute_performance_score(yield_rate: u64, volatility_score: u32, age_seconds: i64) -> u64 {
+    let normalized_yield = yield_rate.min(SCORE_MAX_YIELD_BPS) * 10000 / SCORE_MAX_YIELD_BPS;
+    let stability = 10000u64.saturating_sub(volatility_score as u64);
+    let maturity = (age_seconds.clamp(0, SCORE_MATURITY_PERIOD) as u64) * 10000 / SCORE_MATURITY_PERIOD as u64;
+    
+    normalized_yield
+        .checked_mul(SCORE_YIELD_WEIGHT_BPS)
+        .and_then(|total| total.checked_add(stability.checked_mul(SCORE_STABILITY_WEIGHT_BPS)?))
+        .and_then(|total| total.checked_add(maturity.checked_mul(SCORE_MATURITY_WEIGHT_BPS)?))
+        .map(|total| total / 10000)
+        .unwrap_or(0)
+}
+
+/// Load the strategy accounts passed through `remaining_accounts`
+/// 
+/// Each account must be a program-owned `Strategy` whose address is the PDA
+/// derived from `[b"strategy", portfolio, strategy_id]`, so strategies belonging
+/// to another portfolio (or spoofed accounts) are rejected.
+/// 
+/// # Returns
+/// * `Result<Vec<Account<Strategy>>>` - The loaded strategies, or an error if:
+///   - An account is not a valid `Strategy` owned by this program
+///   - An account does not belong to the given portfolio (`StrategyNotFound`)
+///   - The same strategy is passed twice (`DuplicateStrategy`)
+///   - A strategy has not been migrated to the current layout (`UnsupportedLayoutVersion`)
+pub fn load_portfolio_strategies<'info>(
+    portfolio: &Pubkey,
+    accounts: &'info [AccountInfo<'info>],
+) -> Result<Vec<Account<'info, Strategy>>> {
+    let mut strategies: Vec<Account<'info, Strategy>> = Vec::with_capacity(accounts.len());
+    
+    for info in accounts {
+        let strategy = Account::<Strategy>::try_from(info)?;
+        
+        let expected_address = Pubkey::create_program_address(
+            &[
+                b"strategy",
+                portfolio.as_ref(),
+                strategy.strategy_id.as_ref(),
+                &[strategy.bump],
+            ],
+            &crate::ID,
+        ).map_err(|_| RebalancerErrorCode::StrategyNotFound)?;
+        require_keys_eq!(expected_address, info.key(), RebalancerErrorCode::StrategyNotFound);
+        
+        require!(
+            strategies.iter().all(|s| s.key() != info.key()),
+            RebalancerErrorCode::DuplicateStrategy
+        );
+        strategy.validate_layout()?;
+        
+        strategies.push(strategy);
+    }
     
-    // Clamp to range: 10% minimum, 40% maximum
-    Ok(dynamic_threshold.clamp(10, 40))
+    Ok(strategies)
+}
+
+/// Basis point share of an amount, with a u128 intermediate and an
+/// overflow-checked result.
+pub fn apply_bps(amount: u64, bps: u64) -> Result<u64> {
+    let share = (amount as u128)
+        .checked_mul(bps as u128)
+        .ok_or(RebalancerErrorCode::BalanceOverflow)?
+        .checked_div(10000u128)
+        .ok_or(RebalancerErrorCode::BalanceOverflow)?;
+    
+    u64::try_from(share).map_err(|_| RebalancerErrorCode::BalanceOverflow.into())
+}
+
+/// Move `lamports` out of a strategy's native SOL vault. The vault is a
+/// system-owned PDA seeded by `[b"vault", strategy]`, so the program signs
+/// the transfer with those seeds.
+pub fn transfer_from_vault<'info>(
+    vault: &SystemAccount<'info>,
+    to: AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    strategy: &Pubkey,
+    bump: u8,
+    lamports: u64,
+) -> Result<()> {
+    if lamports == 0 {
+        return Ok(());
+    }
+    
+    transfer(
+        CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            Transfer {
+                from: vault.to_account_info(),
+                to,
+            },
+            &[&[b"vault", strategy.as_ref(), &[bump]]],
+        ),
+        lamports,
+    )
+}
+
+/// Grow a legacy program account to `new_len` zero-filled bytes, with `payer`
+/// topping up the rent the extra space needs.
+pub fn grow_legacy_account<'info>(
+    account: &AccountInfo<'info>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    new_len: usize,
+) -> Result<()> {
+    let rent_due = Rent::get()?
+        .minimum_balance(new_len)
+        .saturating_sub(account.lamports());
+    if rent_due > 0 {
+        transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                Transfer {
+                    from: payer.to_account_info(),
+                    to: account.clone(),
+                },
+            ),
+            rent_due,
+        )?;
+    }
+    
+    account.resize(new_len)?;
+    Ok(())
+}
+
+/// Close a program account that is not loaded as an `Account`, sending its
+/// lamports to `destination`.
+pub fn close_program_account<'info>(account: &AccountInfo<'info>, destination: &AccountInfo<'info>) -> Result<()> {
+    let lamports = account.lamports();
+    **destination.try_borrow_mut_lamports()? = destination
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(RebalancerErrorCode::BalanceOverflow)?;
+    **account.try_borrow_mut_lamports()? = 0;
+    
+    account.assign(&System::id());
+    account.resize(0)?;
+    Ok(())
+}
+
+/// Split `remaining_accounts` into the strategy accounts and the
+/// `StrategyAllocationLog` accounts passed after them.
+pub fn split_allocation_logs<'info>(
+    accounts: &'info [AccountInfo<'info>],
+) -> (&'info [AccountInfo<'info>], &'info [AccountInfo<'info>]) {
+    let logs_start = accounts
+        .iter()
+        .position(|info| {
+            info.owner == &crate::ID
+                && info.try_borrow_data()
+                    .map(|data| data.starts_with(StrategyAllocationLog::DISCRIMINATOR))
+                    .unwrap_or(false)
+        })
+        .unwrap_or(accounts.len());
+    accounts.split_at(logs_start)
+}
+
+/// Load allocation logs passed through `remaining_accounts`
+/// 
+/// Each account must be a `StrategyAllocationLog` at the PDA derived from
+/// `[b"allocation_log", portfolio, strategy_id]`; logs of another portfolio are
+/// rejected with `StrategyNotFound`.
+pub fn load_allocation_logs<'info>(
+    portfolio: &Pubkey,
+    accounts: &'info [AccountInfo<'info>],
+) -> Result<Vec<Account<'info, StrategyAllocationLog>>> {
+    let mut logs: Vec<Account<'info, StrategyAllocationLog>> = Vec::with_capacity(accounts.len());
+    
+    for info in accounts {
+        let log = Account::<StrategyAllocationLog>::try_from(info)?;
+        
+        let expected_address = Pubkey::create_program_address(
+            &[
+                b"allocation_log",
+                portfolio.as_ref(),
+                log.strategy_id.as_ref(),
+                &[log.bump],
+            ],
+            &crate::ID,
+        ).map_err(|_| RebalancerErrorCode::StrategyNotFound)?;
+        require_keys_eq!(expected_address, info.key(), RebalancerErrorCode::StrategyNotFound);
+        
+        require!(
+            logs.iter().all(|l| l.key() != info.key()),
+            RebalancerErrorCode::DuplicateStrategy
+        );
+        
+        logs.push(log);
+    }
+    
+    Ok(logs)
+}
+
+/// Reject a legacy account whose `reserved` region holds non-zero bytes. The
+/// layout migrations decode newer fields from that region, so an account
+/// written before its reserved bytes were zeroed would hand them garbage.
+pub fn validate_reserved_zeroed(reserved: &[u8]) -> Result<()> {
+    require!(reserved.iter().all(|byte| *byte == 0), RebalancerErrorCode::ReservedBytesNotZeroed);
+    Ok(())
+}
+
+/// Write modified strategy accounts loaded by `load_portfolio_strategies` back to
+/// account storage. Each account must have been passed as writable.
+pub fn persist_strategies(strategies: &[Account<Strategy>]) -> Result<()> {
+    for strategy in strategies {
+        require!(strategy.to_account_info().is_writable, ErrorCode::ConstraintMut);
+        strategy.exit(&crate::ID)?;
+    }
+    
+    Ok(())
+}
+
+/// Fill a freshly initialized `RebalanceRecord` and advance the portfolio's
+/// sequence. The record must have been derived from the current
+/// `rebalance_sequence`; its bump is set by the caller.
+pub fn write_rebalance_record(
+    record: &mut RebalanceRecord,
+    portfolio: &mut Account<Portfolio>,
+    kind: RebalanceKind,
+    total_extracted: u64,
+    target_count: u32,
+    total_fees: u64,
+    timestamp: i64,
+) -> Result<()> {
+    record.portfolio = portfolio.key();
+    record.sequence = portfolio.next_rebalance_sequence()?;
+    record.kind = kind;
+    record.timestamp = timestamp;
+    record.total_extracted = total_extracted;
+    record.target_count = target_count;
+    record.total_fees = total_fees;
+    
+    msg!("Rebalance record #{} written: kind={:?}, extracted={}, targets={}, fees={}",
+         record.sequence, kind, total_extracted, target_count, total_fees);
+    
+    Ok(())
 }
 
 #[cfg(test)]
@@ -87,6 +370,29 @@ mod tests {
     use super::*;
     use anchor_lang::prelude::Pubkey;
     
+    // Only built by `cargo test --features trace`, to keep the traced build compiling
+    #[cfg(feature = "trace")]
+    #[test]
+    fn test_trace_compute_units_builds_with_feature() {
+        trace_compute_units!("trace: test");
+    }
+    
+    #[test]
+    fn test_apply_bps_overflow_is_an_error() {
+        assert_eq!(apply_bps(u64::MAX, 10000).unwrap(), u64::MAX);
+        assert_eq!(apply_bps(u64::MAX, 10001).unwrap_err(), RebalancerErrorCode::BalanceOverflow.into());
+    }
+    
+    #[test]
+    fn test_validate_reserved_zeroed() {
+        assert!(validate_reserved_zeroed(&[0u8; 4]).is_ok());
+        assert!(validate_reserved_zeroed(&[]).is_ok());
+        assert_eq!(
+            validate_reserved_zeroed(&[0, 0, 7, 0]).unwrap_err(),
+            RebalancerErrorCode::ReservedBytesNotZeroed.into()
+        );
+    }
+    
     #[test]
     fn test_calculate_average_volatility_normal() {
         let strategies = vec![
@@ -96,6 +402,8 @@ mod tests {
                 current_balance: 1_000_000_000,
                 volatility_score: 2000,
                 percentile_rank: 0,
+                protocol_weight_bps: 10000,
+                net_return_bps: 0,
             },
             StrategyData {
                 strategy_id: Pubkey::new_unique(),
@@ -103,6 +411,8 @@ mod tests {
                 current_balance: 2_000_000_000,
                 volatility_score: 3000,
                 percentile_rank: 0,
+                protocol_weight_bps: 10000,
+                net_return_bps: 0,
             },
             StrategyData {
                 strategy_id: Pubkey::new_unique(),
@@ -110,6 +420,8 @@ mod tests {
                 current_balance: 3_000_000_000,
                 volatility_score: 4000,
                 percentile_rank: 0,
+                protocol_weight_bps: 10000,
+                net_return_bps: 0,
             },
         ];
         
@@ -135,6 +447,8 @@ mod tests {
                 current_balance: 1_000_000_000,
                 volatility_score: 5000,
                 percentile_rank: 0,
+                protocol_weight_bps: 10000,
+                net_return_bps: 0,
             },
         ];
         
@@ -147,7 +461,7 @@ mod tests {
         let base_threshold = 15;
         let average_volatility = 3000;
         
-        let dynamic_threshold = calculate_dynamic_threshold(base_threshold, average_volatility).unwrap();
+        let dynamic_threshold = calculate_dynamic_threshold(base_threshold, average_volatility, &RiskLimits::default()).unwrap();
         
         // Volatility adjustment = (3000 / 100) × 20 = 600
         // Dynamic threshold = 15 + 6 = 21
@@ -159,7 +473,7 @@ mod tests {
         let base_threshold = 15;
         let average_volatility = 500;
         
-        let dynamic_threshold = calculate_dynamic_threshold(base_threshold, average_volatility).unwrap();
+        let dynamic_threshold = calculate_dynamic_threshold(base_threshold, average_volatility, &RiskLimits::default()).unwrap();
         
         // Volatility adjustment = (500 / 100) × 20 = 100
         // Dynamic threshold = 15 + 1 = 16
@@ -171,7 +485,7 @@ mod tests {
         let base_threshold = 15;
         let average_volatility = 10000;
         
-        let dynamic_threshold = calculate_dynamic_threshold(base_threshold, average_volatility).unwrap();
+        let dynamic_threshold = calculate_dynamic_threshold(base_threshold, average_volatility, &RiskLimits::default()).unwrap();
         
         // Volatility adjustment = (10000 / 100) × 20 = 2000
         // Dynamic threshold = 15 + 20 = 35
@@ -183,7 +497,7 @@ mod tests {
         let base_threshold = 15;
         let average_volatility = 100;
         
-        let dynamic_threshold = calculate_dynamic_threshold(base_threshold, average_volatility).unwrap();
+        let dynamic_threshold = calculate_dynamic_threshold(base_threshold, average_volatility, &RiskLimits::default()).unwrap();
         
         // Volatility adjustment = (100 / 100) × 20 = 20
         // Dynamic threshold = 15 + 0 = 15
@@ -196,7 +510,7 @@ mod tests {
         let base_threshold = 15;
         let average_volatility = 15000;
         
-        let dynamic_threshold = calculate_dynamic_threshold(base_threshold, average_volatility).unwrap();
+        let dynamic_threshold = calculate_dynamic_threshold(base_threshold, average_volatility, &RiskLimits::default()).unwrap();
         
         // Volatility adjustment = (15000 / 100) × 20 = 3000
         // Dynamic threshold = 15 + 30 = 45
@@ -209,7 +523,7 @@ mod tests {
         let base_threshold = 5;
         let average_volatility = 100;
         
-        let dynamic_threshold = calculate_dynamic_threshold(base_threshold, average_volatility).unwrap();
+        let dynamic_threshold = calculate_dynamic_threshold(base_threshold, average_volatility, &RiskLimits::default()).unwrap();
         
         // Volatility adjustment = (100 / 100) × 20 = 20
         // Dynamic threshold = 5 + 0 = 5
@@ -217,13 +531,71 @@ mod tests {
         assert_eq!(dynamic_threshold, 10);
     }
     
+    #[test]
+    fn test_calculate_dynamic_threshold_custom_sensitivity() {
+        // A more sensitive weight and a wider range
+        let limits = RiskLimits { volatility_weight: 50, min_threshold: 5, max_threshold: 60, ..RiskLimits::default() };
+        
+        // Volatility adjustment = (3000 / 100) × 50 = 1500 → 15 points (default weight gives 6)
+        assert_eq!(calculate_dynamic_threshold(15, 3000, &limits).unwrap(), 30);
+        // Below the default 10% floor, above the custom 5% floor
+        assert_eq!(calculate_dynamic_threshold(5, 100, &limits).unwrap(), 5);
+        // Above the default 40% ceiling, capped at the custom 60%
+        assert_eq!(calculate_dynamic_threshold(15, 8000, &limits).unwrap(), 55);
+        assert_eq!(calculate_dynamic_threshold(15, 15000, &limits).unwrap(), 60);
+        
+        // A zero weight ignores volatility entirely
+        let flat = RiskLimits { volatility_weight: 0, ..RiskLimits::default() };
+        assert_eq!(calculate_dynamic_threshold(15, 10000, &flat).unwrap(), 15);
+    }
+    
     #[test]
     fn test_calculate_dynamic_threshold_invalid_base() {
         let base_threshold = 150; // Invalid: > 100
         let average_volatility = 3000;
         
-        let result = calculate_dynamic_threshold(base_threshold, average_volatility);
+        let result = calculate_dynamic_threshold(base_threshold, average_volatility, &RiskLimits::default());
         assert!(result.is_err());
         assert_eq!(result.unwrap_err(), RebalancerErrorCode::InvalidRebalanceThreshold.into());
     }
+    
+    #[test]
+    fn test_compute_performance_score_weighting() {
+        // Worked example from the doc comment
+        assert_eq!(compute_performance_score(15000, 2000, SCORE_MATURITY_PERIOD / 2), 4850);
+        
+        // Best and worst possible inputs
+        assert_eq!(compute_performance_score(50000, 0, SCORE_MATURITY_PERIOD), 10000);
+        assert_eq!(compute_performance_score(0, 10000, 0), 0);
+    }
+    
+    #[test]
+    fn test_compute_performance_score_penalizes_volatility() {
+        // High yield bought with high volatility loses to moderate, stable yield
+        let high_yield_volatile = compute_performance_score(30000, 8000, 0);
+        let moderate_yield_stable = compute_performance_score(15000, 1000, 0);
+        
+        assert_eq!(high_yield_volatile, 4300);
+        assert_eq!(moderate_yield_stable, 4950);
+        assert!(moderate_yield_stable > high_yield_volatile);
+    }
+    
+    #[test]
+    fn test_compute_performance_score_maturity_bonus() {
+        let new_strategy = compute_performance_score(10000, 3000, 0);
+        let mature_strategy = compute_performance_score(10000, 3000, SCORE_MATURITY_PERIOD);
+        let very_old_strategy = compute_performance_score(10000, 3000, SCORE_MATURITY_PERIOD * 10);
+        
+        // Bonus is small and capped once the maturity period has elapsed
+        assert_eq!(mature_strategy - new_strategy, 500);
+        assert_eq!(very_old_strategy, mature_strategy);
+        
+        // A clock skewed before creation earns no bonus
+        assert_eq!(compute_performance_score(10000, 3000, -3600), new_strategy);
+    }
+    
+    #[test]
+    fn test_compute_performance_score_clamps_out_of_range_inputs() {
+        assert_eq!(compute_performance_score(u64::MAX, u32::MAX, i64::MAX), 6500);
+    }
 }
\ No newline at end of file